@@ -0,0 +1,112 @@
+use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+pub struct ActionPlugin;
+
+impl Plugin for ActionPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<ActionCooldownConfig>()
+            .init_resource::<ActionCooldownConfig>();
+    }
+}
+
+/// Gameplay actions a player can trigger, independent of the key they are bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Reflect)]
+pub enum GameAction {
+    Till,
+    UseItem,
+    Attack,
+    Interact,
+    /// Not bound through [`GameAction::from_key`], emotes are sent as their own event and only
+    /// share the cooldown.
+    Emote,
+    /// Sent as its own event like emotes, see [`crate::projectile`].
+    Shoot,
+}
+
+impl GameAction {
+    /// Actions that are triggered by pressing the given key.
+    pub fn from_key(key: KeyCode) -> &'static [GameAction] {
+        match key {
+            KeyCode::Space => &[GameAction::Till, GameAction::UseItem],
+            KeyCode::KeyF => &[GameAction::Attack],
+            KeyCode::KeyE => &[GameAction::Interact],
+            _ => &[],
+        }
+    }
+
+    /// Whether the player swings its tool when performing the action.
+    pub fn swings_tool(self) -> bool {
+        !matches!(self, GameAction::Interact | GameAction::Emote)
+    }
+}
+
+/// Cooldown in seconds per action.
+#[derive(Resource, Debug, Reflect)]
+#[reflect(Resource)]
+pub struct ActionCooldownConfig {
+    pub till: f32,
+    pub use_item: f32,
+    pub attack: f32,
+    pub interact: f32,
+    pub emote: f32,
+    pub shoot: f32,
+}
+
+impl Default for ActionCooldownConfig {
+    fn default() -> Self {
+        Self {
+            till: 0.3,
+            use_item: 0.5,
+            attack: 0.4,
+            interact: 0.2,
+            emote: 1.5,
+            shoot: 0.6,
+        }
+    }
+}
+
+impl ActionCooldownConfig {
+    pub fn get(&self, action: GameAction) -> f32 {
+        match action {
+            GameAction::Till => self.till,
+            GameAction::UseItem => self.use_item,
+            GameAction::Attack => self.attack,
+            GameAction::Interact => self.interact,
+            GameAction::Emote => self.emote,
+            GameAction::Shoot => self.shoot,
+        }
+    }
+}
+
+/// Last time each action was accepted for a player. The server uses it to reject spammed actions,
+/// the owning client mirrors it so it doesn't send actions the server would reject anyway.
+#[derive(Component, Debug, Default)]
+pub struct ActionCooldowns {
+    last_used: HashMap<GameAction, f32>,
+    /// Set for creative players, every action is ready right away.
+    pub instant: bool,
+}
+
+impl ActionCooldowns {
+    pub fn ready(&self, action: GameAction, now: f32, config: &ActionCooldownConfig) -> bool {
+        self.instant
+            || self
+                .last_used
+                .get(&action)
+                .map_or(true, |last| now - last >= config.get(action))
+    }
+
+    /// Actions that were triggered at exactly the given time.
+    pub fn triggered_at(&self, now: f32) -> impl Iterator<Item = GameAction> + '_ {
+        self.last_used
+            .iter()
+            .filter(move |(_, last)| **last == now)
+            .map(|(action, _)| *action)
+    }
+
+    /// Marks the action as used. Call this only after the action was actually applied.
+    pub fn trigger(&mut self, action: GameAction, now: f32) {
+        self.last_used.insert(action, now);
+    }
+}
@@ -0,0 +1,102 @@
+use bevy::{prelude::*, utils::HashSet};
+
+pub const NORTH: u8 = 1;
+pub const EAST: u8 = 2;
+pub const SOUTH: u8 = 4;
+pub const WEST: u8 = 8;
+
+/// Bit and global tile offset of the four direct neighbors, clockwise from north.
+pub const NEIGHBORS: [(u8, IVec2); 4] = [
+    (NORTH, IVec2::Y),
+    (EAST, IVec2::X),
+    (SOUTH, IVec2::NEG_Y),
+    (WEST, IVec2::NEG_X),
+];
+
+pub struct AutotilePlugin;
+
+impl Plugin for AutotilePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NeighborUpdates>();
+    }
+}
+
+/// Shape of a tile that connects to equal neighbors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectedVariant {
+    Single,
+    End,
+    Straight,
+    Corner,
+    T,
+    Cross,
+}
+
+impl ConnectedVariant {
+    /// Connected sides of the unrotated variant.
+    pub fn base_mask(&self) -> u8 {
+        match self {
+            ConnectedVariant::Single => 0,
+            ConnectedVariant::End => NORTH,
+            ConnectedVariant::Straight => NORTH | SOUTH,
+            ConnectedVariant::Corner => NORTH | EAST,
+            ConnectedVariant::T => NORTH | EAST | SOUTH,
+            ConnectedVariant::Cross => NORTH | EAST | SOUTH | WEST,
+        }
+    }
+}
+
+/// Variant and clockwise quarter turns for every neighbor mask.
+const CONNECTED_VARIANTS: [(ConnectedVariant, u8); 16] = [
+    (ConnectedVariant::Single, 0),   // none
+    (ConnectedVariant::End, 0),      // N
+    (ConnectedVariant::End, 1),      // E
+    (ConnectedVariant::Corner, 0),   // N E
+    (ConnectedVariant::End, 2),      // S
+    (ConnectedVariant::Straight, 0), // N S
+    (ConnectedVariant::Corner, 1),   // E S
+    (ConnectedVariant::T, 0),        // N E S
+    (ConnectedVariant::End, 3),      // W
+    (ConnectedVariant::Corner, 3),   // N W
+    (ConnectedVariant::Straight, 1), // E W
+    (ConnectedVariant::T, 3),        // N E W
+    (ConnectedVariant::Corner, 2),   // S W
+    (ConnectedVariant::T, 2),        // N S W
+    (ConnectedVariant::T, 1),        // E S W
+    (ConnectedVariant::Cross, 0),    // N E S W
+];
+
+pub fn connected_variant(mask: u8) -> (ConnectedVariant, u8) {
+    CONNECTED_VARIANTS[(mask & 0b1111) as usize]
+}
+
+/// Rotates a neighbor mask clockwise by quarter turns.
+pub fn rotate_mask(mask: u8, quarter_turns: u8) -> u8 {
+    let turns = quarter_turns % 4;
+    ((mask << turns) | (mask >> (4 - turns))) & 0b1111
+}
+
+/// Mask of the neighbors of `tile` for which `connects` holds.
+pub fn neighbor_mask(tile: IVec2, connects: impl Fn(IVec2) -> bool) -> u8 {
+    NEIGHBORS
+        .iter()
+        .filter(|(_, offset)| connects(tile + *offset))
+        .fold(0, |mask, (bit, _)| mask | bit)
+}
+
+/// Global tiles whose connected appearance has to be re-evaluated, because they or one of their
+/// neighbors changed. Works across chunk borders since it only deals in global tiles.
+#[derive(Resource, Debug, Default)]
+pub struct NeighborUpdates(HashSet<IVec2>);
+
+impl NeighborUpdates {
+    /// Queues the tile and its four direct neighbors.
+    pub fn notify(&mut self, tile: IVec2) {
+        self.0.insert(tile);
+        self.0.extend(NEIGHBORS.iter().map(|(_, offset)| tile + *offset));
+    }
+
+    pub fn drain(&mut self) -> impl Iterator<Item = IVec2> + '_ {
+        self.0.drain()
+    }
+}
@@ -0,0 +1,82 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chunk::{WorldSeed, TILES_PER_CHUNK},
+    terrain::{climate_at, Climate},
+};
+
+/// Climate regions of the world. Tiles get theirs from the climate noise, a chunk keeps the one
+/// of its center tile in [`crate::chunk::Chunk`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub enum Biome {
+    Forest,
+    #[default]
+    Plains,
+    Desert,
+    Swamp,
+}
+
+impl Biome {
+    pub const ALL: [Biome; 4] = [Biome::Forest, Biome::Plains, Biome::Desert, Biome::Swamp];
+
+    pub fn from_climate(climate: Climate) -> Self {
+        if climate.temperature > 0.6 && climate.moisture < 0.45 {
+            Biome::Desert
+        } else if climate.moisture > 0.6 && climate.elevation < 0.5 {
+            Biome::Swamp
+        } else if climate.moisture > 0.5 {
+            Biome::Forest
+        } else {
+            Biome::Plains
+        }
+    }
+
+    /// Elevation below which land is flooded. Swamps are wetter than the rest.
+    pub fn water_level(self) -> f32 {
+        match self {
+            Biome::Swamp => 0.45,
+            _ => 0.38,
+        }
+    }
+
+    /// Color ground tiles of the biome are multiplied with.
+    pub fn tint(self) -> Color {
+        match self {
+            Biome::Forest => Color::srgb(0.85, 0.95, 0.85),
+            Biome::Plains => Color::WHITE,
+            Biome::Desert => Color::srgb(1.0, 0.95, 0.75),
+            Biome::Swamp => Color::srgb(0.8, 0.88, 0.75),
+        }
+    }
+
+    /// Decoration objects of the biome with the chance of a tile to get each of them.
+    pub fn decorations(self) -> &'static [(&'static str, f32)] {
+        match self {
+            Biome::Forest => &[("tree", 0.2), ("bush", 0.06), ("rock", 0.01)],
+            Biome::Plains => &[("tree", 0.02), ("bush", 0.04), ("rock", 0.02)],
+            Biome::Desert => &[("bush", 0.01), ("rock", 0.05)],
+            Biome::Swamp => &[("tree", 0.06), ("bush", 0.1)],
+        }
+    }
+
+    /// Factor on the chance of hostile mobs to spawn in a chunk of the biome.
+    pub fn mob_spawn_factor(self) -> f32 {
+        match self {
+            Biome::Forest => 1.0,
+            Biome::Plains => 0.5,
+            Biome::Desert => 1.0,
+            Biome::Swamp => 2.0,
+        }
+    }
+}
+
+pub fn biome_at(seed: WorldSeed, tile: IVec2) -> Biome {
+    Biome::from_climate(climate_at(seed, tile))
+}
+
+/// Biome of a whole chunk, the one of its center tile.
+pub fn chunk_biome(seed: WorldSeed, chunk_index: IVec2) -> Biome {
+    let tiles_per_chunk = TILES_PER_CHUNK as i32;
+    biome_at(seed, chunk_index * tiles_per_chunk + IVec2::splat(tiles_per_chunk / 2))
+}
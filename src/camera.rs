@@ -1,13 +1,47 @@
-use bevy::{color::palettes::css::GREEN, prelude::*};
+use bevy::{
+    color::palettes::css::GREEN,
+    input::mouse::{MouseScrollUnit, MouseWheel},
+    prelude::*,
+    window::PrimaryWindow,
+};
 use bevy_replicon::{
     core::ClientId,
     prelude::{client_connected, RepliconClient},
 };
+use bevy_rapier2d::prelude::CollisionEvent;
 use bevy_replicon_snap::NetworkOwner;
 
+use bevy_ecs_tilemap::tiles::TilePos;
+use bevy_mod_picking::picking_core::PickSet;
+
+use crate::{
+    chunk::Chunk,
+    debug_overlay::debug_flag,
+    health::Health,
+    loading_screen::GameState,
+    world::world_pos_to_chunk_tile,
+};
+
 pub struct CameraPlugin;
 
 const CAMERA_HEIGHT: f32 = 10.0;
+const DEFAULT_ZOOM: f32 = 0.5;
+const MIN_ZOOM: f32 = 0.2;
+const MAX_ZOOM: f32 = 2.0;
+/// Zoom change per scrolled line, applied multiplicatively.
+const ZOOM_PER_LINE: f32 = 0.1;
+/// Pixel scroll deltas (trackpads) are converted to lines with this factor.
+const PIXELS_PER_LINE: f32 = 20.0;
+const ZOOM_SMOOTHING: f32 = 12.0;
+/// Player movement between two frames larger than this is treated as a teleport.
+const TELEPORT_THRESHOLD: f32 = 256.0;
+const SHAKE_MAX_OFFSET: f32 = 12.0;
+/// Trauma lost per second.
+const SHAKE_DECAY: f32 = 1.5;
+const SHAKE_FREQUENCY: f32 = 25.0;
+pub const TRAUMA_HIT: f32 = 0.25;
+pub const TRAUMA_DEATH: f32 = 0.8;
+const SPECTATOR_TOGGLE_KEY: KeyCode = KeyCode::F4;
 
 
 #[derive(Debug, Component)]
@@ -17,8 +51,41 @@ impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, setup_camera)
             .insert_resource(CameraView::default())
-            .add_systems(Update, (update_camera.run_if(client_connected), update_camera_view).chain())
-            .add_systems(Update, draw_camera_gizmo);
+            .insert_resource(CameraZoom::default())
+            .register_type::<CameraSettings>()
+            .init_resource::<CameraSettings>()
+            .init_resource::<CameraFollowState>()
+            .init_resource::<CameraShake>()
+            .init_resource::<PixelSnapOffset>()
+            .init_resource::<CursorWorldPos>()
+            .init_resource::<CursorTile>()
+            .register_type::<SpectatorSettings>()
+            .init_resource::<SpectatorSettings>()
+            .init_resource::<Spectator>()
+            .add_systems(Update, toggle_spectator.before(remove_pixel_snap))
+            .add_systems(PreUpdate, update_cursor_pos.before(PickSet::Backend))
+            .add_systems(
+                Update,
+                (
+                    remove_pixel_snap,
+                    remove_camera_shake,
+                    update_camera
+                        .run_if(client_connected)
+                        .run_if(in_state(GameState::InGame))
+                        .run_if(not(spectating)),
+                    fly_spectator_camera.run_if(spectating),
+                    zoom_camera,
+                    update_camera_view,
+                    apply_camera_shake,
+                    apply_pixel_snap,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                Update,
+                (shake_on_collision, shake_on_damage).run_if(client_connected),
+            )
+            .add_systems(Update, draw_camera_gizmo.run_if(debug_flag(|f| f.camera_view)));
     }
 }
 
@@ -26,7 +93,7 @@ fn setup_camera(mut commands: Commands) {
     commands.spawn((Camera2dBundle {
         transform: Transform::from_translation(Vec3::new(0.0, 0.0, CAMERA_HEIGHT)),
         projection: OrthographicProjection {
-            scale: 0.5,
+            scale: DEFAULT_ZOOM,
             near: -1000.0,
             far: 1000.0,
             ..Default::default()
@@ -39,6 +106,119 @@ fn setup_camera(mut commands: Commands) {
 #[derive(Debug, Resource, Default)]
 pub struct CameraView(pub Rect);
 
+/// World position under the mouse cursor, `None` if the cursor is outside the primary window
+/// or over interactive UI.
+#[derive(Debug, Resource, Default, Clone, Copy, PartialEq, Deref)]
+pub struct CursorWorldPos(pub Option<Vec2>);
+
+/// Chunk index and tile position under the mouse cursor, derived from [`CursorWorldPos`].
+#[derive(Debug, Resource, Default, Clone, Copy, PartialEq, Deref)]
+pub struct CursorTile(pub Option<(IVec2, TilePos)>);
+
+/// Free flying debug camera. While active the camera is detached from the local player and no
+/// MoveEvents are sent. On the server the spectated position also keeps chunks loaded.
+#[derive(Debug, Resource, Default)]
+pub struct Spectator {
+    pub active: bool,
+    /// Camera center without screen shake, updated while active.
+    pub position: Vec2,
+}
+
+#[derive(Debug, Resource, Reflect)]
+#[reflect(Resource)]
+pub struct SpectatorSettings {
+    /// Flying speed in screen units per second, scaled with the zoom.
+    pub speed: f32,
+    /// View distance in tiles the server keeps loaded around the spectated position.
+    pub view_distance: f32,
+}
+
+impl Default for SpectatorSettings {
+    fn default() -> Self {
+        Self {
+            speed: 600.0,
+            view_distance: 40.0,
+        }
+    }
+}
+
+pub fn spectating(spectator: Res<Spectator>) -> bool {
+    spectator.active
+}
+
+#[derive(Debug, Resource, Reflect)]
+#[reflect(Resource)]
+pub struct CameraSettings {
+    /// Half extents of the rect around the camera center the player can move in without the camera following.
+    pub deadzone: Vec2,
+    /// Time in seconds the camera needs to cover ~63% of the distance to its target.
+    pub follow_time_constant: f32,
+    /// Distance below which the camera snaps onto its target.
+    pub settle_distance: f32,
+    /// Keep the camera view inside the bounds of the loaded chunks.
+    pub clamp_to_world: bool,
+    pub screen_shake: bool,
+    /// Snap the final camera position to the screen pixel grid at the current zoom, so chunk
+    /// tilemaps never land between pixels and show seams.
+    pub pixel_snap: bool,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self {
+            deadzone: Vec2::new(24.0, 16.0),
+            follow_time_constant: 0.15,
+            settle_distance: 0.1,
+            clamp_to_world: true,
+            screen_shake: true,
+            pixel_snap: true,
+        }
+    }
+}
+
+/// Tracks the followed player position so the camera can snap instead of lerp after spawns and teleports.
+#[derive(Debug, Resource, Default)]
+pub struct CameraFollowState {
+    pub last_target: Option<Vec2>,
+}
+
+/// Offset added by [`apply_pixel_snap`], removed again before the camera moves the next frame
+/// so snapping never holds back the follow.
+#[derive(Debug, Resource, Default)]
+struct PixelSnapOffset(Vec2);
+
+/// Trauma based screen shake. The offset is applied on top of the followed camera position
+/// and removed again before the next follow update.
+#[derive(Debug, Resource, Default)]
+pub struct CameraShake {
+    trauma: f32,
+    offset: Vec2,
+    elapsed: f32,
+}
+
+impl CameraShake {
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).clamp(0.0, 1.0);
+    }
+}
+
+#[derive(Debug, Resource)]
+pub struct CameraZoom {
+    pub target: f32,
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Default for CameraZoom {
+    fn default() -> Self {
+        Self {
+            target: DEFAULT_ZOOM,
+            min: MIN_ZOOM,
+            max: MAX_ZOOM,
+        }
+    }
+}
+
 
 fn get_camera_view(
     camera_transform: &GlobalTransform,
@@ -61,30 +241,313 @@ fn draw_camera_gizmo(
 
 fn update_camera(
     player_query: Query<(&Transform, &NetworkOwner)>,
-    mut camera: Query<&mut Transform, (With<MainCamera>, Without<NetworkOwner>)>,
+    mut camera: Query<(&mut Transform, &OrthographicProjection), (With<MainCamera>, Without<NetworkOwner>)>,
+    chunk_query: Query<&Chunk>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
     client: Res<RepliconClient>,
+    settings: Res<CameraSettings>,
+    mut follow_state: ResMut<CameraFollowState>,
+    time: Res<Time>,
 ) {
     if let Some(client_id) = client.id() {
         if let Some((t, _)) = player_query
             .iter()
             .find(|(_, nw)| ClientId::new(nw.0) == client_id)
         {
-            let mut camera_transform = camera.single_mut();
-            camera_transform.translation = camera_transform.translation.lerp(t.translation.xy().extend(CAMERA_HEIGHT), 0.01);
+            let (mut camera_transform, projection) = camera.single_mut();
+            let player_pos = t.translation.xy();
+            let bounds = match (settings.clamp_to_world, window_query.get_single()) {
+                (true, Ok(window)) => loaded_world_bounds(&chunk_query)
+                    .map(|bounds| (bounds, window.size() * projection.scale * 0.5)),
+                _ => None,
+            };
+            let teleported = follow_state
+                .last_target
+                .map_or(true, |last| last.distance(player_pos) > TELEPORT_THRESHOLD);
+            follow_state.last_target = Some(player_pos);
+            if teleported {
+                let pos = clamp_camera(player_pos, player_pos, bounds);
+                camera_transform.translation = pos.extend(CAMERA_HEIGHT);
+                return;
+            }
+
+            let camera_pos = camera_transform.translation.xy();
+            let target = follow_target(camera_pos, player_pos, settings.deadzone);
+            let target = clamp_camera(target, player_pos, bounds);
+            let new_pos = smooth_follow(camera_pos, target, &settings, time.delta_seconds());
+            camera_transform.translation = new_pos.extend(CAMERA_HEIGHT);
+            return;
+        }
+    }
+    follow_state.last_target = None;
+}
+
+/// Returns the camera position that just keeps `player_pos` inside the deadzone around `camera_pos`.
+fn follow_target(camera_pos: Vec2, player_pos: Vec2, deadzone: Vec2) -> Vec2 {
+    let offset = player_pos - camera_pos;
+    let outside = offset - offset.clamp(-deadzone, deadzone);
+    camera_pos + outside
+}
+
+/// Bounding rect of all currently loaded chunks.
+fn loaded_world_bounds(chunk_query: &Query<&Chunk>) -> Option<Rect> {
+    chunk_query
+        .iter()
+        .map(|chunk| Rect::from_corners(chunk.get_world_coords(), chunk.get_world_coords() + chunk.get_size()))
+        .reduce(|a, b| a.union(b))
+}
+
+/// Restricts the camera center so a view with the given half size stays inside the bounds.
+/// Axes where the loaded world is smaller than the view fall back to centering on the player.
+fn clamp_camera(target: Vec2, player_pos: Vec2, bounds: Option<(Rect, Vec2)>) -> Vec2 {
+    let Some((bounds, half_view)) = bounds else {
+        return target;
+    };
+    let min = bounds.min + half_view;
+    let max = bounds.max - half_view;
+    let clamp_axis = |value: f32, player: f32, min: f32, max: f32| {
+        if min > max {
+            player
+        } else {
+            value.clamp(min, max)
+        }
+    };
+    Vec2::new(
+        clamp_axis(target.x, player_pos.x, min.x, max.x),
+        clamp_axis(target.y, player_pos.y, min.y, max.y),
+    )
+}
+
+fn smooth_follow(current: Vec2, target: Vec2, settings: &CameraSettings, delta: f32) -> Vec2 {
+    if current.distance(target) <= settings.settle_distance {
+        return target;
+    }
+    let factor = if settings.follow_time_constant > 0.0 {
+        1.0 - (-delta / settings.follow_time_constant).exp()
+    } else {
+        1.0
+    };
+    current.lerp(target, factor)
+}
+
+fn toggle_spectator(
+    input: Res<ButtonInput<KeyCode>>,
+    mut spectator: ResMut<Spectator>,
+    shake: Res<CameraShake>,
+    camera: Query<&Transform, With<MainCamera>>,
+) {
+    if !input.just_pressed(SPECTATOR_TOGGLE_KEY) {
+        return;
+    }
+    spectator.active = !spectator.active;
+    if let Ok(transform) = camera.get_single() {
+        spectator.position = transform.translation.xy() - shake.offset;
+    }
+    info!("Spectator mode {}", if spectator.active { "on" } else { "off" });
+}
+
+/// Moves the detached camera with WASD. Leaving spectator mode hands the camera back to
+/// [`update_camera`], which glides back to the player because the player didn't move.
+fn fly_spectator_camera(
+    input: Res<ButtonInput<KeyCode>>,
+    mut spectator: ResMut<Spectator>,
+    settings: Res<SpectatorSettings>,
+    mut camera: Query<(&mut Transform, &OrthographicProjection), With<MainCamera>>,
+    time: Res<Time>,
+) {
+    let mut direction = Vec2::ZERO;
+    if input.pressed(KeyCode::KeyW) {
+        direction.y += 1.0;
+    }
+    if input.pressed(KeyCode::KeyS) {
+        direction.y -= 1.0;
+    }
+    if input.pressed(KeyCode::KeyA) {
+        direction.x -= 1.0;
+    }
+    if input.pressed(KeyCode::KeyD) {
+        direction.x += 1.0;
+    }
+    let (mut transform, projection) = camera.single_mut();
+    spectator.position +=
+        direction.normalize_or_zero() * settings.speed * projection.scale * time.delta_seconds();
+    transform.translation = spectator.position.extend(CAMERA_HEIGHT);
+}
+
+fn zoom_camera(
+    mut wheel_events: EventReader<MouseWheel>,
+    mut zoom: ResMut<CameraZoom>,
+    mut camera: Query<&mut OrthographicProjection, With<MainCamera>>,
+    time: Res<Time>,
+) {
+    for event in wheel_events.read() {
+        let lines = match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / PIXELS_PER_LINE,
+        };
+        // scrolling up zooms in, which means a smaller projection scale
+        zoom.target = (zoom.target * (1.0 - ZOOM_PER_LINE).powf(lines)).clamp(zoom.min, zoom.max);
+    }
+
+    let mut projection = camera.single_mut();
+    let diff = zoom.target - projection.scale;
+    if diff.abs() < 0.0001 {
+        if diff != 0.0 {
+            projection.scale = zoom.target;
         }
+        return;
     }
+    projection.scale += diff * (1.0 - (-ZOOM_SMOOTHING * time.delta_seconds()).exp());
+}
+
+fn remove_camera_shake(
+    shake: Res<CameraShake>,
+    mut camera: Query<&mut Transform, With<MainCamera>>,
+) {
+    if shake.offset != Vec2::ZERO {
+        camera.single_mut().translation -= shake.offset.extend(0.0);
+    }
+}
+
+fn apply_camera_shake(
+    mut shake: ResMut<CameraShake>,
+    settings: Res<CameraSettings>,
+    mut camera: Query<&mut Transform, With<MainCamera>>,
+    time: Res<Time>,
+) {
+    shake.trauma = (shake.trauma - SHAKE_DECAY * time.delta_seconds()).max(0.0);
+    if !settings.screen_shake || shake.trauma == 0.0 {
+        shake.trauma = 0.0;
+        shake.offset = Vec2::ZERO;
+        return;
+    }
+    shake.elapsed += time.delta_seconds();
+    let t = shake.elapsed * SHAKE_FREQUENCY;
+    // sum of incommensurable sines as a cheap smooth noise
+    let noise = Vec2::new(
+        (t * 1.0).sin() * 0.6 + (t * 2.3 + 1.7).sin() * 0.4,
+        (t * 1.3 + 4.1).sin() * 0.6 + (t * 2.9 + 0.3).sin() * 0.4,
+    );
+    shake.offset = noise * SHAKE_MAX_OFFSET * shake.trauma * shake.trauma;
+    camera.single_mut().translation += shake.offset.extend(0.0);
+}
+
+fn remove_pixel_snap(
+    mut snap: ResMut<PixelSnapOffset>,
+    mut camera: Query<&mut Transform, With<MainCamera>>,
+) {
+    if snap.0 != Vec2::ZERO {
+        camera.single_mut().translation -= snap.0.extend(0.0);
+        snap.0 = Vec2::ZERO;
+    }
+}
+
+fn apply_pixel_snap(
+    mut snap: ResMut<PixelSnapOffset>,
+    settings: Res<CameraSettings>,
+    mut camera: Query<(&mut Transform, &OrthographicProjection), With<MainCamera>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+) {
+    if !settings.pixel_snap {
+        return;
+    }
+    let (mut transform, projection) = camera.single_mut();
+    let scale_factor = window_query.get_single().map_or(1.0, |window| window.scale_factor());
+    // world units covered by one physical pixel
+    let pixel = projection.scale / scale_factor;
+    let position = transform.translation.xy();
+    let snapped = (position / pixel).round() * pixel;
+    snap.0 = snapped - position;
+    transform.translation = snapped.extend(CAMERA_HEIGHT);
+}
+
+fn shake_on_collision(
+    mut collision_events: EventReader<CollisionEvent>,
+    player_query: Query<(Entity, &NetworkOwner)>,
+    client: Res<RepliconClient>,
+    mut shake: ResMut<CameraShake>,
+) {
+    let Some(client_id) = client.id() else {
+        return;
+    };
+    let Some((local_player, _)) = player_query
+        .iter()
+        .find(|(_, nw)| ClientId::new(nw.0) == client_id)
+    else {
+        return;
+    };
+    for event in collision_events.read() {
+        if let CollisionEvent::Started(a, b, _) = event {
+            if *a == local_player || *b == local_player {
+                shake.add_trauma(TRAUMA_HIT);
+            }
+        }
+    }
+}
+
+/// Shakes the camera when the replicated health of the local player drops, harder when it died.
+fn shake_on_damage(
+    player_query: Query<(Entity, &NetworkOwner, &Health), Changed<Health>>,
+    client: Res<RepliconClient>,
+    mut shake: ResMut<CameraShake>,
+    mut previous: Local<Option<(Entity, f32)>>,
+) {
+    let Some(client_id) = client.id() else {
+        return;
+    };
+    let Some((local_player, _, health)) = player_query
+        .iter()
+        .find(|(_, nw, _)| ClientId::new(nw.0) == client_id)
+    else {
+        return;
+    };
+    let last = previous.replace((local_player, health.current));
+    let Some((last_player, last_health)) = last else {
+        return;
+    };
+    if last_player != local_player || health.current >= last_health {
+        return;
+    }
+    if health.is_dead() {
+        shake.add_trauma(TRAUMA_DEATH);
+    } else {
+        shake.add_trauma(TRAUMA_HIT);
+    }
+}
+
+fn update_cursor_pos(
+    camera_query: Query<(&GlobalTransform, &Camera), With<MainCamera>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    interaction_query: Query<&Interaction>,
+    mut cursor_pos: ResMut<CursorWorldPos>,
+    mut cursor_tile: ResMut<CursorTile>,
+) {
+    let over_ui = interaction_query
+        .iter()
+        .any(|interaction| *interaction != Interaction::None);
+    let world_pos = match (camera_query.get_single(), window_query.get_single()) {
+        (Ok((camera_transform, camera)), Ok(window)) if !over_ui => window
+            .cursor_position()
+            .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor)),
+        _ => None,
+    };
+    cursor_pos.set_if_neq(CursorWorldPos(world_pos));
+    cursor_tile.set_if_neq(CursorTile(world_pos.and_then(world_pos_to_chunk_tile)));
 }
 
 fn update_camera_view(
     camera_query: Query<(&GlobalTransform, &Camera), With<MainCamera>>,
-    window_query: Query<&Window>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    shake: Res<CameraShake>,
     mut camera_view : ResMut<CameraView>,
 ) {
-    let (cam, proj) = camera_query.single();
-    let win = window_query.single();
+    let (Ok((cam, proj)), Ok(win)) = (camera_query.get_single(), window_query.get_single()) else {
+        return;
+    };
 
     match get_camera_view(cam, win, proj) {
-        Some(view) => camera_view.0 = view,
+        // the global transform still contains the shake of the last frame
+        Some(view) => camera_view.0 = Rect::from_center_size(view.center() - shake.offset, view.size()),
         None => {}
     }
     
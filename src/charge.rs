@@ -0,0 +1,285 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use bevy_replicon::{core::ClientId, prelude::*};
+use bevy_replicon_snap::NetworkOwner;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chunk::{ChunkIndexMap, ChunkTiles},
+    fence::facing_tile,
+    health::DamageEvent,
+    loading_screen::GameState,
+    player::{Facing, Player},
+    world::{split_global_tile, Ground},
+    world_object::TileOccupancy,
+};
+
+/// Held to charge a heavy tool use like breaking stone.
+const CHARGE_KEY: KeyCode = KeyCode::KeyR;
+/// Seconds the owning client waits for the server to confirm a started charge.
+const CHARGE_CONFIRM_TIMEOUT: f32 = 0.5;
+const RING_RADIUS: f32 = 10.0;
+const RING_OFFSET: Vec2 = Vec2::new(0.0, 28.0);
+const RING_COLOR: Color = Color::srgb(1.0, 0.9, 0.4);
+
+pub struct ChargePlugin;
+
+impl Plugin for ChargePlugin {
+    fn build(&self, app: &mut App) {
+        app.replicate::<Charging>()
+            .register_type::<ChargeSettings>()
+            .init_resource::<ChargeSettings>()
+            .init_resource::<LocalCharge>()
+            .add_client_event::<StartChargeEvent>(ChannelKind::Ordered)
+            .add_client_event::<ReleaseChargeEvent>(ChannelKind::Ordered)
+            .add_systems(
+                Update,
+                (start_charges, cancel_charges, release_charges)
+                    .chain()
+                    .run_if(server_running),
+            )
+            .add_systems(
+                Update,
+                (send_charge_events, draw_charge_ring)
+                    .chain()
+                    .run_if(client_connected)
+                    .run_if(in_state(GameState::InGame)),
+            );
+    }
+}
+
+#[derive(Resource, Debug, Reflect)]
+#[reflect(Resource)]
+pub struct ChargeSettings {
+    /// Seconds the charge key has to be held to break a stone tile.
+    pub break_time: f32,
+    /// Distance the player may move while charging before it is cancelled.
+    pub move_tolerance: f32,
+}
+
+impl Default for ChargeSettings {
+    fn default() -> Self {
+        Self {
+            break_time: 1.5,
+            move_tolerance: 4.0,
+        }
+    }
+}
+
+/// Sent when the charge key goes down. The server picks the target from the player's Facing.
+#[derive(Event, Debug, Clone, Serialize, Deserialize)]
+pub struct StartChargeEvent;
+
+/// Sent when the charge key is released, the effect applies if the charge was long enough.
+#[derive(Event, Debug, Clone, Serialize, Deserialize)]
+pub struct ReleaseChargeEvent;
+
+/// Replicated while a player charges, so other clients can loop the tool swing.
+#[derive(Component, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Charging;
+
+/// Server side state of a running charge.
+#[derive(Component, Debug)]
+struct ChargingAction {
+    tile: IVec2,
+    /// Ground of the target when the charge started, any change cancels it.
+    target: Ground,
+    start_pos: Vec2,
+    started_at: f32,
+}
+
+/// Charge of the local player, predicted from the key so the ring shows up right away.
+#[derive(Resource, Debug, Default)]
+pub struct LocalCharge(Option<LocalChargeState>);
+
+#[derive(Debug)]
+struct LocalChargeState {
+    start_pos: Vec2,
+    started_at: f32,
+    /// Set once the server's [`Charging`] marker arrived, its removal then ends the charge.
+    confirmed: bool,
+}
+
+impl LocalCharge {
+    pub fn is_charging(&self) -> bool {
+        self.0.is_some()
+    }
+}
+
+/// Ground of a global tile according to the replicated ChunkTiles.
+fn ground_of_tile(tile: IVec2, chunk_map: &ChunkIndexMap, chunks: &Query<&ChunkTiles>) -> Option<Ground> {
+    let (chunk_index, tile_pos) = split_global_tile(tile);
+    chunks.get(chunk_map.get(chunk_index)?).ok()?.get(&tile_pos).cloned()
+}
+
+/// Whether the tile can be broken by a charge.
+fn breakable(ground: &Ground) -> bool {
+    *ground == Ground::Stone
+}
+
+fn start_charges(
+    mut commands: Commands,
+    mut events: EventReader<FromClient<StartChargeEvent>>,
+    players: Query<(Entity, &NetworkOwner, &Transform, &Facing), (With<Player>, Without<ChargingAction>)>,
+    chunks: Query<&ChunkTiles>,
+    chunk_map: Res<ChunkIndexMap>,
+    occupancy: Res<TileOccupancy>,
+    time: Res<Time>,
+) {
+    for FromClient { client_id, .. } in events.read() {
+        let Some((entity, _, transform, facing)) = players
+            .iter()
+            .find(|(_, owner, ..)| owner.0 == client_id.get())
+        else {
+            continue;
+        };
+        let position = transform.translation.xy();
+        let tile = facing_tile(position, facing.0);
+        let Some(target) = ground_of_tile(tile, &chunk_map, &chunks).filter(breakable) else {
+            debug!("{client_id:?} started a charge without a breakable target");
+            continue;
+        };
+        if occupancy.is_occupied(tile) {
+            continue;
+        }
+        commands.entity(entity).insert((
+            ChargingAction {
+                tile,
+                target,
+                start_pos: position,
+                started_at: time.elapsed_seconds(),
+            },
+            Charging,
+        ));
+    }
+}
+
+/// Cancels charges of players that moved, took damage or whose target changed.
+fn cancel_charges(
+    mut commands: Commands,
+    mut damage_events: EventReader<DamageEvent>,
+    players: Query<(Entity, &Transform, &ChargingAction)>,
+    chunks: Query<&ChunkTiles>,
+    chunk_map: Res<ChunkIndexMap>,
+    settings: Res<ChargeSettings>,
+) {
+    let damaged: Vec<Entity> = damage_events.read().map(|event| event.target).collect();
+    for (entity, transform, charge) in players.iter() {
+        let moved = transform.translation.xy().distance(charge.start_pos) > settings.move_tolerance;
+        let target_changed = ground_of_tile(charge.tile, &chunk_map, &chunks) != Some(charge.target.clone());
+        if moved || target_changed || damaged.contains(&entity) {
+            commands.entity(entity).remove::<(ChargingAction, Charging)>();
+        }
+    }
+}
+
+fn release_charges(
+    mut commands: Commands,
+    mut events: EventReader<FromClient<ReleaseChargeEvent>>,
+    players: Query<(Entity, &NetworkOwner, &ChargingAction)>,
+    mut chunks: Query<&mut ChunkTiles>,
+    chunk_map: Res<ChunkIndexMap>,
+    settings: Res<ChargeSettings>,
+    time: Res<Time>,
+) {
+    for FromClient { client_id, .. } in events.read() {
+        let Some((entity, _, charge)) = players
+            .iter()
+            .find(|(_, owner, _)| owner.0 == client_id.get())
+        else {
+            continue;
+        };
+        commands.entity(entity).remove::<(ChargingAction, Charging)>();
+        if time.elapsed_seconds() - charge.started_at < settings.break_time {
+            debug!("{client_id:?} released its charge too early");
+            continue;
+        }
+        let (chunk_index, tile_pos) = split_global_tile(charge.tile);
+        if let Some(mut chunk_tiles) = chunk_map.get(chunk_index).and_then(|chunk| chunks.get_mut(chunk).ok()) {
+            chunk_tiles.set(&tile_pos, Ground::Dirt);
+        }
+    }
+}
+
+/// Starts and releases the local charge. Starting is only predicted on a breakable target, the
+/// server decides the same from the replicated tiles.
+fn send_charge_events(
+    input: Res<ButtonInput<KeyCode>>,
+    mut start_events: EventWriter<StartChargeEvent>,
+    mut release_events: EventWriter<ReleaseChargeEvent>,
+    mut local_charge: ResMut<LocalCharge>,
+    players: Query<(&NetworkOwner, &Transform, &Facing, Has<Charging>), With<Player>>,
+    chunks: Query<&ChunkTiles>,
+    chunk_map: Res<ChunkIndexMap>,
+    settings: Res<ChargeSettings>,
+    client: Res<RepliconClient>,
+    time: Res<Time>,
+) {
+    let Some((_, transform, facing, charging)) = client.id().and_then(|client_id| {
+        players
+            .iter()
+            .find(|(owner, ..)| ClientId::new(owner.0) == client_id)
+    }) else {
+        return;
+    };
+    let now = time.elapsed_seconds();
+    let position = transform.translation.xy();
+
+    if input.just_pressed(CHARGE_KEY) {
+        start_events.send(StartChargeEvent);
+        let tile = facing_tile(position, facing.0);
+        if ground_of_tile(tile, &chunk_map, &chunks).is_some_and(|ground| breakable(&ground)) {
+            local_charge.0 = Some(LocalChargeState {
+                start_pos: position,
+                started_at: now,
+                confirmed: false,
+            });
+        }
+    }
+    if input.just_released(CHARGE_KEY) {
+        release_events.send(ReleaseChargeEvent);
+        local_charge.0 = None;
+    }
+
+    let Some(state) = local_charge.0.as_mut() else {
+        return;
+    };
+    state.confirmed |= charging;
+    let cancelled = (state.confirmed && !charging)
+        || (!state.confirmed && now - state.started_at > CHARGE_CONFIRM_TIMEOUT)
+        || position.distance(state.start_pos) > settings.move_tolerance;
+    if cancelled {
+        local_charge.0 = None;
+    }
+}
+
+fn draw_charge_ring(
+    mut gizmos: Gizmos,
+    local_charge: Res<LocalCharge>,
+    players: Query<(&NetworkOwner, &Transform), With<Player>>,
+    settings: Res<ChargeSettings>,
+    client: Res<RepliconClient>,
+    time: Res<Time>,
+) {
+    let Some(state) = local_charge.0.as_ref() else {
+        return;
+    };
+    let Some((_, transform)) = client.id().and_then(|client_id| {
+        players
+            .iter()
+            .find(|(owner, _)| ClientId::new(owner.0) == client_id)
+    }) else {
+        return;
+    };
+    let progress = ((time.elapsed_seconds() - state.started_at) / settings.break_time).clamp(0.0, 1.0);
+    let arc = progress * TAU;
+    // the arc is centered on its direction, this makes it grow clockwise from the top
+    gizmos.arc_2d(
+        transform.translation.xy() + RING_OFFSET,
+        arc * 0.5,
+        arc,
+        RING_RADIUS,
+        RING_COLOR,
+    );
+}
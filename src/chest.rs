@@ -0,0 +1,45 @@
+use bevy::prelude::*;
+use bevy_replicon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    interact::{InteractKind, Interactable},
+    item_container::ItemContainer,
+    world_object::{ObjectRegistry, WorldObject},
+};
+
+/// Slots of a freshly placed chest.
+pub const CHEST_CAPACITY: usize = 16;
+
+pub struct ChestPlugin;
+
+impl Plugin for ChestPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, init_chests.run_if(server_running));
+    }
+}
+
+/// A world object storing items in its [`ItemContainer`]. Players open it with the interact key
+/// and move items in and out through the server like for any other container.
+#[derive(Component, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Chest;
+
+/// Components that turn a world object into a chest with the given contents.
+pub fn chest_components(container: ItemContainer) -> impl Bundle {
+    (Chest, container, Interactable::new(InteractKind::Open, "object.chest"))
+}
+
+/// Turns freshly placed chest objects into empty chests. Loaded ones come with their contents.
+fn init_chests(
+    mut commands: Commands,
+    objects: Query<(Entity, &WorldObject), (Added<WorldObject>, Without<Chest>)>,
+    registry: Res<ObjectRegistry>,
+) {
+    for (entity, object) in objects.iter() {
+        if registry.get(&object.kind).is_some_and(|def| def.chest) {
+            commands
+                .entity(entity)
+                .insert(chest_components(ItemContainer::new(Vec::new(), CHEST_CAPACITY)));
+        }
+    }
+}
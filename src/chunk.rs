@@ -1,17 +1,16 @@
 use std::{
-    fs::{create_dir_all, File},
+    fs::File,
     io::{Read, Write},
-    path::Path,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
 };
 
 use anyhow::Context;
 use bevy::{
     ecs::world::CommandQueue,
     prelude::*,
-    tasks::{
-        block_on,
-        futures_lite::future, IoTaskPool, Task,
-    },
+    tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, IoTaskPool, Task},
+    utils::{HashMap, HashSet},
 };
 use bevy_ecs_tilemap::{
     map::{
@@ -22,16 +21,35 @@ use bevy_ecs_tilemap::{
     tiles::{TileColor, TileFlip, TilePos, TilePosOld, TileStorage, TileTextureIndex, TileVisible},
     FrustumCulling,
 };
-use bevy_rand::prelude::ForkableRng;
-use bevy_rand::prelude::{GlobalEntropy, WyRand};
-use bevy_replicon::{
-    client::ClientSet,
-    core::Replicated,
-    prelude::{server_running, ParentSync},
-};
+use bevy_rand::prelude::{EntropyComponent, WyRand};
+use bevy_replicon::{client::ClientSet, core::Replicated, prelude::server_running};
+use rand_core::SeedableRng;
 use serde::{Deserialize, Serialize};
 
-use crate::{player::Player, world::Ground};
+use crate::{
+    biome::{chunk_biome, Biome},
+    camera::{Spectator, SpectatorSettings},
+    chest::{chest_components, Chest},
+    composter::{composter_components, Composter, ComposterData},
+    console::ConsoleCommand,
+    critter::{spawn_critter, Critter, CritterData},
+    furnace::{furnace_components, Furnace, FurnaceData},
+    game_assets::GameAssets,
+    item_container::ItemContainer,
+    loot_bag::{spawn_loot_bag, LootBag, LootBagData},
+    player::Player,
+    sapling::Growth,
+    sign::{sign_components, SignText},
+    soil::{SoilQuality, DEFAULT_SOIL_QUALITY},
+    structure::{structure_at, StructureRegistry, StructureTemplate},
+    terrain::{decorations, ground_at, ore_veins},
+    tile_prediction::PredictedTileChange,
+    world::{chunk_index_at, global_tile, split_global_tile, Ground},
+    world_meta::{WorldPaths, AUTOSAVE_INTERVAL},
+    world_object::{
+        footprint_tiles, spawn_world_object, ObjectRegistry, WorldObject, WorldObjectData,
+    },
+};
 
 pub const TILES_PER_CHUNK: u32 = 8;
 pub const TILE_LENGTH: f32 = 32.0;
@@ -49,14 +67,59 @@ pub const GRID_SIZE: TilemapGridSize = TilemapGridSize {
     y: TILE_LENGTH,
 };
 
+pub const DEFAULT_WORLD_SEED: u64 = 0x5eed_7e57;
+/// Version of the chunk save format, bump it on changes older game versions can't read.
+pub const CHUNK_FORMAT_VERSION: u32 = 1;
+
+/// Seed of the world on the server. Clients don't need it, they receive the derived chunk seeds.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct WorldSeed(pub u64);
+
+impl Default for WorldSeed {
+    fn default() -> Self {
+        Self(DEFAULT_WORLD_SEED)
+    }
+}
+
+impl WorldSeed {
+    pub fn chunk_seed(&self, chunk_index: IVec2) -> u64 {
+        mix_seed(
+            self.0,
+            (chunk_index.x as u32 as u64) | ((chunk_index.y as u32 as u64) << 32),
+        )
+    }
+}
+
+/// Combines a seed with a value into a new, well distributed seed (splitmix64 finalizer).
+pub fn mix_seed(seed: u64, value: u64) -> u64 {
+    let mut x = seed ^ value.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// Seed of a single tile inside a chunk.
+pub fn tile_seed(chunk_seed: u64, tile_pos: &TilePos) -> u64 {
+    mix_seed(chunk_seed, ((tile_pos.x as u64) << 16) | tile_pos.y as u64)
+}
+
 pub struct ChunkPlugin;
 
 impl Plugin for ChunkPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(ViewDistance::default())
+            .insert_resource(ChunkParkTime::default())
+            .init_resource::<ChunkIndexMap>()
+            .init_resource::<WorldSeed>()
+            .init_resource::<LateChunkLoads>()
+            .init_resource::<CorruptChunks>()
+            .observe(track_chunk_added)
+            .observe(track_chunk_removed)
+            .observe(track_task_added)
+            .observe(track_task_removed)
             .observe(load_chunk_observer)
+            .observe(generate_chunk_observer)
             .observe(save_chunk_observer)
-            .add_systems(Startup, init_save_folder)
             .add_systems(
                 PreUpdate,
                 task_poll
@@ -66,28 +129,246 @@ impl Plugin for ChunkPlugin {
             .add_systems(
                 PreUpdate,
                 (
+                    track_player_velocity.run_if(server_running),
                     load_deload_chunks
                         .run_if(server_running)
+                        .run_if(registries_loaded)
                         .after(ClientSet::SyncHierarchy),
                     init_chunk.after(ClientSet::Receive),
+                    apply_chunk_tiles,
                 )
                     .chain(),
             )
+            .add_systems(
+                PreUpdate,
+                update_parked_visibility
+                    .run_if(server_running)
+                    .after(load_deload_chunks),
+            )
+            .add_systems(Update, regen_chunks.run_if(server_running))
+            .add_systems(Update, autosave_chunks.run_if(server_running))
+            .add_systems(Last, save_chunks_on_exit.run_if(server_running))
             .add_event::<LoadChunk>()
+            .add_event::<GenerateChunk>()
             .add_event::<SaveChunk>();
     }
 }
 
+/// Numbers the temp files of chunk saves.
+static SAVE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
 #[derive(Debug, Serialize, Deserialize)]
 struct ChunkData {
     chunk_index: IVec2,
     tiles: Vec<TileData>,
+    #[serde(default)]
+    critters: Vec<CritterData>,
+    #[serde(default)]
+    objects: Vec<WorldObjectData>,
+    #[serde(default)]
+    bags: Vec<LootBagData>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 struct TileData {
     tile_index: UVec2,
     ground: Ground,
+    #[serde(default = "default_soil_quality")]
+    soil_quality: f32,
+}
+
+fn default_soil_quality() -> f32 {
+    DEFAULT_SOIL_QUALITY
+}
+
+/// Packed ground of all tiles of a chunk. This is the only replicated tile data, tile entities are
+/// created locally from it by [`init_chunk`] and kept in sync by [`apply_chunk_tiles`].
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkTiles {
+    grounds: Vec<Ground>,
+    /// Soil quality per tile, only tilled dirt differs from [`DEFAULT_SOIL_QUALITY`].
+    soil: Vec<f32>,
+}
+
+impl ChunkTiles {
+    fn index(tile_pos: &TilePos) -> Option<usize> {
+        if tile_pos.x >= TILES_PER_CHUNK || tile_pos.y >= TILES_PER_CHUNK {
+            return None;
+        }
+        Some((tile_pos.y * TILES_PER_CHUNK + tile_pos.x) as usize)
+    }
+
+    pub fn get(&self, tile_pos: &TilePos) -> Option<&Ground> {
+        self.grounds.get(Self::index(tile_pos)?)
+    }
+
+    /// Sets the ground of a tile. Anything but dirt loses its soil quality.
+    pub fn set(&mut self, tile_pos: &TilePos, ground: Ground) {
+        if let Some(index) = Self::index(tile_pos) {
+            if ground != Ground::Dirt {
+                self.soil[index] = DEFAULT_SOIL_QUALITY;
+            }
+            self.grounds[index] = ground;
+        }
+    }
+
+    pub fn soil_quality(&self, tile_pos: &TilePos) -> Option<f32> {
+        self.soil.get(Self::index(tile_pos)?).copied()
+    }
+
+    pub fn set_soil_quality(&mut self, tile_pos: &TilePos, quality: f32) {
+        if let Some(index) = Self::index(tile_pos) {
+            self.soil[index] = quality;
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (TilePos, &Ground)> {
+        self.grounds.iter().enumerate().map(|(i, ground)| {
+            let i = i as u32;
+            (
+                TilePos::new(i % TILES_PER_CHUNK, i / TILES_PER_CHUNK),
+                ground,
+            )
+        })
+    }
+}
+
+impl From<Vec<TileData>> for ChunkTiles {
+    fn from(tiles: Vec<TileData>) -> Self {
+        let mut chunk_tiles = ChunkTiles {
+            grounds: vec![Ground::Grass; (TILES_PER_CHUNK * TILES_PER_CHUNK) as usize],
+            soil: vec![DEFAULT_SOIL_QUALITY; (TILES_PER_CHUNK * TILES_PER_CHUNK) as usize],
+        };
+        for tile in tiles {
+            let tile_pos = tile.tile_index.into();
+            chunk_tiles.set(&tile_pos, tile.ground);
+            if chunk_tiles.get(&tile_pos) == Some(&Ground::Dirt) {
+                chunk_tiles.set_soil_quality(&tile_pos, tile.soil_quality);
+            }
+        }
+        chunk_tiles
+    }
+}
+
+/// Lookup from chunk index to the spawned chunk entity, plus the indices that are currently being loaded.
+#[derive(Debug, Resource, Default)]
+pub struct ChunkIndexMap {
+    pub chunks: HashMap<IVec2, Entity>,
+    pub pending: HashSet<IVec2>,
+    /// Chunks whose save file couldn't be read, see [`ChunkLoadError::Io`].
+    pub failed: HashMap<IVec2, FailedLoad>,
+}
+
+impl ChunkIndexMap {
+    pub fn get(&self, index: IVec2) -> Option<Entity> {
+        self.chunks.get(&index).copied()
+    }
+
+    /// Whether the chunk is either spawned or about to be.
+    pub fn is_known(&self, index: IVec2) -> bool {
+        self.chunks.contains_key(&index) || self.pending.contains(&index)
+    }
+
+    /// Whether loading the chunk failed and shouldn't be attempted again yet, or at all.
+    pub fn is_backing_off(&self, index: IVec2, now: f32) -> bool {
+        self.failed
+            .get(&index)
+            .is_some_and(|failed| failed.attempts >= MAX_LOAD_ATTEMPTS || now < failed.retry_at)
+    }
+}
+
+/// Load attempts of a chunk with an unreadable save file before it is given up.
+const MAX_LOAD_ATTEMPTS: u32 = 5;
+/// Seconds until the first retry, doubled after every further failure.
+const LOAD_RETRY_DELAY: f32 = 0.5;
+
+#[derive(Debug, Clone, Copy)]
+pub struct FailedLoad {
+    pub attempts: u32,
+    pub retry_at: f32,
+}
+
+/// Why the save file of a chunk couldn't be turned into a chunk.
+#[derive(Debug)]
+pub enum ChunkLoadError {
+    /// The file vanished between checking for it and reading it.
+    NotFound,
+    /// The file exists but doesn't contain a readable chunk, e.g. after a truncated write.
+    Corrupt(String),
+    /// Reading failed for a reason that might go away, like a locked file.
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for ChunkLoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChunkLoadError::NotFound => write!(f, "save file not found"),
+            ChunkLoadError::Corrupt(reason) => write!(f, "corrupt save file: {reason}"),
+            ChunkLoadError::Io(err) => write!(f, "failed reading save file: {err}"),
+        }
+    }
+}
+
+/// Number of corrupt chunk files that were set aside and regenerated, shown in the debug overlay.
+#[derive(Debug, Resource, Default)]
+pub struct CorruptChunks(pub u32);
+
+fn track_chunk_added(
+    trigger: Trigger<OnAdd, Chunk>,
+    chunk_q: Query<&Chunk>,
+    mut chunk_map: ResMut<ChunkIndexMap>,
+) {
+    if let Ok(chunk) = chunk_q.get(trigger.entity()) {
+        chunk_map.chunks.insert(chunk.chunk_index, trigger.entity());
+    }
+}
+
+fn track_chunk_removed(
+    trigger: Trigger<OnRemove, Chunk>,
+    chunk_q: Query<&Chunk>,
+    mut chunk_map: ResMut<ChunkIndexMap>,
+) {
+    if let Ok(chunk) = chunk_q.get(trigger.entity()) {
+        if chunk_map.chunks.get(&chunk.chunk_index) == Some(&trigger.entity()) {
+            chunk_map.chunks.remove(&chunk.chunk_index);
+        }
+    }
+}
+
+fn track_task_added(
+    trigger: Trigger<OnAdd, ComputeTask>,
+    task_q: Query<&ComputeTask>,
+    mut chunk_map: ResMut<ChunkIndexMap>,
+) {
+    if let Ok(task) = task_q.get(trigger.entity()) {
+        chunk_map.pending.insert(task.0);
+    }
+}
+
+fn track_task_removed(
+    trigger: Trigger<OnRemove, ComputeTask>,
+    task_q: Query<&ComputeTask>,
+    mut chunk_map: ResMut<ChunkIndexMap>,
+) {
+    if let Ok(task) = task_q.get(trigger.entity()) {
+        chunk_map.pending.remove(&task.0);
+    }
+}
+
+/// Seconds a chunk stays parked after leaving the view before it gets saved and despawned.
+#[derive(Debug, Resource)]
+pub struct ChunkParkTime(pub f32);
+impl Default for ChunkParkTime {
+    fn default() -> Self {
+        Self(30.0)
+    }
+}
+
+/// Marks a chunk that is out of view but kept alive for a while in case a player comes back.
+/// Parked chunks are hidden and not replicated to clients.
+#[derive(Component, Debug)]
+pub struct ParkedChunk {
+    parked_at: f32,
 }
 
 #[derive(Debug, Resource)]
@@ -98,97 +379,340 @@ impl Default for ViewDistance {
     }
 }
 
-fn init_save_folder() {
-    create_dir_all("world").expect("Unable to create save folder");
+/// Speed in units per second below which a player counts as standing still for prefetching.
+const PREFETCH_MIN_SPEED: f32 = 16.0;
+/// How fast the tracked velocity follows the sampled one, per second.
+const VELOCITY_SMOOTHING: f32 = 4.0;
+
+/// Smoothed velocity of a player sampled from consecutive transforms, used to load chunks ahead
+/// of its movement.
+#[derive(Component, Debug)]
+struct RecentVelocity {
+    last_position: Vec2,
+    velocity: Vec2,
+}
+
+/// Counts chunks that finished loading only after they entered a player's view.
+#[derive(Debug, Resource, Default)]
+struct LateChunkLoads {
+    waiting: HashSet<IVec2>,
+    count: u32,
+}
+
+fn track_player_velocity(
+    mut commands: Commands,
+    mut player_query: Query<(Entity, &Transform, Option<&mut RecentVelocity>), With<Player>>,
+    time: Res<Time>,
+) {
+    let delta = time.delta_seconds();
+    for (entity, transform, recent) in player_query.iter_mut() {
+        let position = transform.translation.xy();
+        let Some(mut recent) = recent else {
+            commands.entity(entity).insert(RecentVelocity {
+                last_position: position,
+                velocity: Vec2::ZERO,
+            });
+            continue;
+        };
+        if delta > 0.0 {
+            let sample = (position - recent.last_position) / delta;
+            let factor = (delta * VELOCITY_SMOOTHING).min(1.0);
+            recent.velocity = recent.velocity.lerp(sample, factor);
+        }
+        recent.last_position = position;
+    }
+}
+
+/// Chunk offset one ring ahead of the movement, zero for a player standing still.
+fn prefetch_offset(velocity: Vec2) -> Vec2 {
+    let axis = |speed: f32| {
+        if speed.abs() < PREFETCH_MIN_SPEED {
+            0.0
+        } else {
+            speed.signum()
+        }
+    };
+    Vec2::new(axis(velocity.x), axis(velocity.y)) * TILES_PER_CHUNK as f32 * TILE_LENGTH
 }
 
-fn load_chunk_observer(trigger: Trigger<LoadChunk>, mut commands: Commands) {
+fn load_chunk_observer(
+    trigger: Trigger<LoadChunk>,
+    mut commands: Commands,
+    paths: Res<WorldPaths>,
+) {
     let index = trigger.event().index;
-    let task = IoTaskPool::get().spawn(load_chunk(index));
+    let task = IoTaskPool::get().spawn(load_chunk(index, paths.chunk(index)));
     commands.spawn(ComputeTask(index, task));
 }
 
-async fn load_chunk(index: IVec2) -> CommandQueue {
+fn read_chunk_file(path: &Path) -> Result<ChunkData, ChunkLoadError> {
     let mut bytes = Vec::<u8>::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_end(&mut bytes))
+        .map_err(|err| match err.kind() {
+            std::io::ErrorKind::NotFound => ChunkLoadError::NotFound,
+            std::io::ErrorKind::InvalidData | std::io::ErrorKind::UnexpectedEof => {
+                ChunkLoadError::Corrupt(err.to_string())
+            }
+            _ => ChunkLoadError::Io(err),
+        })?;
+    ron::de::from_bytes::<ChunkData>(&bytes).map_err(|err| ChunkLoadError::Corrupt(err.to_string()))
+}
+
+/// Grounds of a saved chunk by tile index, read straight from its file without spawning it.
+pub fn read_saved_grounds(path: &Path) -> Result<Vec<(UVec2, Ground)>, ChunkLoadError> {
+    let chunk_data = read_chunk_file(path)?;
+    Ok(chunk_data
+        .tiles
+        .into_iter()
+        .map(|tile| (tile.tile_index, tile.ground))
+        .collect())
+}
+
+async fn load_chunk(index: IVec2, path: PathBuf) -> CommandQueue {
     let mut command_queue = CommandQueue::default();
 
-    match File::open(&format!("world/{}_{}.ron", index.x, index.y))
-        .and_then(|mut path| path.read_to_end(&mut bytes))
-        .context("Failed reading the file")
-        .and_then(|_| ron::de::from_bytes::<ChunkData>(&bytes).context("Failed deserialisation"))
-    {
+    match read_chunk_file(&path) {
         Ok(chunk_data) => {
             command_queue.push(move |world: &mut World| {
-                spawn_chunk_stub(&mut world.commands(), chunk_data);
+                world.resource_mut::<ChunkIndexMap>().failed.remove(&index);
+                let world_seed = *world.resource::<WorldSeed>();
+                world.resource_scope(|world, registry: Mut<ObjectRegistry>| {
+                    spawn_chunk_stub(&mut world.commands(), chunk_data, world_seed, &registry);
+                });
             });
         }
-        Err(err) => {
-            error!("{err}");
+        Err(ChunkLoadError::NotFound) => {
+            warn!("Save file of chunk {index} disappeared, generating it");
+            command_queue.push(move |world: &mut World| {
+                world.trigger(GenerateChunk { index });
+            });
+        }
+        Err(err @ ChunkLoadError::Corrupt(_)) => {
+            // keep the broken file around for inspection, the world heals from the seed
+            let corrupt_path = path.with_extension("ron.corrupt");
+            match std::fs::rename(&path, &corrupt_path) {
+                Ok(()) => error!(
+                    "Chunk {index} has a {err}, moved it to {} and regenerating",
+                    corrupt_path.display()
+                ),
+                Err(rename_err) => {
+                    error!("Chunk {index} has a {err}, moving it failed: {rename_err}")
+                }
+            }
+            command_queue.push(move |world: &mut World| {
+                world.resource_mut::<CorruptChunks>().0 += 1;
+                world.resource_mut::<ChunkIndexMap>().failed.remove(&index);
+                world.trigger(GenerateChunk { index });
+            });
+        }
+        Err(err @ ChunkLoadError::Io(_)) => {
+            command_queue.push(move |world: &mut World| {
+                let now = world.resource::<Time>().elapsed_seconds();
+                let mut chunk_map = world.resource_mut::<ChunkIndexMap>();
+                let failed = chunk_map.failed.entry(index).or_insert(FailedLoad {
+                    attempts: 0,
+                    retry_at: now,
+                });
+                failed.retry_at = now + LOAD_RETRY_DELAY * 2f32.powi(failed.attempts as i32);
+                failed.attempts += 1;
+                if failed.attempts >= MAX_LOAD_ATTEMPTS {
+                    error!(
+                        "Chunk {index}: {err}, giving up after {} attempts",
+                        failed.attempts
+                    );
+                } else {
+                    warn!("Chunk {index}: {err}, retrying");
+                }
+            });
         }
     }
     return command_queue;
 }
 
+fn generate_chunk_observer(
+    trigger: Trigger<GenerateChunk>,
+    mut commands: Commands,
+    world_seed: Res<WorldSeed>,
+    object_registry: Res<ObjectRegistry>,
+    structure_registry: Res<StructureRegistry>,
+) {
+    let index = trigger.event().index;
+    let world_seed = *world_seed;
+    let templates = structure_registry.templates();
+    // the task can't reach the registry, so it gets the footprints of everything it may place
+    let footprints =
+        Biome::ALL
+            .iter()
+            .flat_map(|biome| biome.decorations())
+            .map(|(kind, _)| *kind)
+            .chain(templates.iter().flat_map(|(_, template)| {
+                template.objects.iter().map(|object| object.kind.as_str())
+            }))
+            .map(|kind| (kind.to_string(), object_registry.footprint(kind)))
+            .collect();
+    let task = AsyncComputeTaskPool::get().spawn(async move {
+        let chunk_data = gen_chunk(index, world_seed, &footprints, &templates);
+        let mut command_queue = CommandQueue::default();
+        command_queue.push(move |world: &mut World| {
+            let world_seed = *world.resource::<WorldSeed>();
+            world.resource_scope(|world, registry: Mut<ObjectRegistry>| {
+                spawn_chunk_stub(&mut world.commands(), chunk_data, world_seed, &registry);
+            });
+        });
+        command_queue
+    });
+    commands.spawn(ComputeTask(index, task));
+}
+
 fn save_chunk_observer(
     trigger: Trigger<SaveChunk>,
-    chunks_q: Query<(&Chunk, &Children)>,
-    tiles_q: Query<(&TilePos, &Ground)>,
+    chunk_map: Res<ChunkIndexMap>,
+    chunks_q: Query<&ChunkTiles>,
+    critters_q: Query<(&Critter, &Transform)>,
+    objects_q: Query<(
+        &WorldObject,
+        Option<&SignText>,
+        Option<(&Composter, &ItemContainer)>,
+        Option<&Growth>,
+        Option<(&Chest, &ItemContainer)>,
+        Option<(&Furnace, &ItemContainer)>,
+    )>,
+    bags_q: Query<(&LootBag, &ItemContainer, &Transform)>,
+    paths: Res<WorldPaths>,
 ) {
     let index = trigger.event().index;
-    let chunk_data = extract_chunk_data(index, chunks_q, tiles_q);
+    let Some(chunk_data) = extract_chunk_data(
+        index,
+        &chunk_map,
+        &chunks_q,
+        &critters_q,
+        &objects_q,
+        &bags_q,
+    ) else {
+        error!("Chunk {index} to save does not exist!");
+        return;
+    };
     IoTaskPool::get()
-        .spawn(save_chunk(index, chunk_data))
+        .spawn(save_chunk(paths.chunk(index), chunk_data))
         .detach();
 }
 
-async fn save_chunk(index: IVec2, chunk_data: ChunkData) {
-    match ron::to_string(&chunk_data)
-        .context("Failed serialisation")
-        .and_then(|serialized| {
-            File::create(&format!("world/{}_{}.ron", index.x, index.y))
-                .context("Failed file creation")
-                .and_then(|mut path| {
-                    path.write(serialized.as_bytes())
-                        .context("Error while writing chunk data to file")
-                })
-        }) {
-        Ok(_) => {}
-        Err(err) => error!("{err}"),
+async fn save_chunk(path: PathBuf, chunk_data: ChunkData) {
+    if let Err(err) = write_chunk_file(&path, &chunk_data) {
+        error!("{err:#}");
+    }
+}
+
+/// Writes the chunk to a temp file next to its save and renames it over the save, so a load
+/// racing the write or a save cut off by the exit never sees a partial file.
+fn write_chunk_file(path: &Path, chunk_data: &ChunkData) -> anyhow::Result<()> {
+    let serialized = ron::to_string(chunk_data).context("Failed serialisation")?;
+    // unique per save, two pending saves of the same chunk must not share a temp file
+    let temp_path = path.with_extension(format!(
+        "ron.{}.tmp",
+        SAVE_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let written = File::create(&temp_path)
+        .context("Failed file creation")
+        .and_then(|mut file| {
+            file.write_all(serialized.as_bytes())
+                .context("Error while writing chunk data to file")
+        })
+        .and_then(|()| {
+            std::fs::rename(&temp_path, path).context("Failed to replace the chunk file")
+        });
+    if written.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
     }
+    written
 }
 
 fn extract_chunk_data(
     index: IVec2,
-    chunks_q: Query<(&Chunk, &Children)>,
-    tiles_q: Query<(&TilePos, &Ground)>,
-) -> ChunkData {
-    let (_chunk, children) = chunks_q
+    chunk_map: &ChunkIndexMap,
+    chunks_q: &Query<&ChunkTiles>,
+    critters_q: &Query<(&Critter, &Transform)>,
+    objects_q: &Query<(
+        &WorldObject,
+        Option<&SignText>,
+        Option<(&Composter, &ItemContainer)>,
+        Option<&Growth>,
+        Option<(&Chest, &ItemContainer)>,
+        Option<(&Furnace, &ItemContainer)>,
+    )>,
+    bags_q: &Query<(&LootBag, &ItemContainer, &Transform)>,
+) -> Option<ChunkData> {
+    let chunk_tiles = chunks_q.get(chunk_map.get(index)?).ok()?;
+
+    let tile_data: Vec<TileData> = chunk_tiles
         .iter()
-        .find(|(x, _)| x.chunk_index == index)
-        .expect("Chunk to save does not exist!");
+        .map(|(tile_pos, ground)| TileData {
+            tile_index: UVec2::new(tile_pos.x, tile_pos.y),
+            ground: ground.clone(),
+            soil_quality: chunk_tiles
+                .soil_quality(&tile_pos)
+                .unwrap_or(DEFAULT_SOIL_QUALITY),
+        })
+        .collect();
 
-    let tile_data: Vec<TileData> = children
+    let critters = critters_q
         .iter()
-        .map(|&e| tiles_q.get(e))
-        .filter_map(|x| x.ok())
-        .map(|x| TileData {
-            tile_index: UVec2::new(x.0.x, x.0.y),
-            ground: x.1.clone(),
+        .filter(|(critter, _)| critter.home_chunk == index)
+        .map(|(critter, transform)| CritterData {
+            kind: critter.kind.clone(),
+            position: transform.translation.xy(),
         })
         .collect();
 
-    ChunkData {
+    let objects = objects_q
+        .iter()
+        .filter(|(object, ..)| object.chunk_index() == index)
+        .map(
+            |(object, sign, composter, growth, chest, furnace)| WorldObjectData {
+                kind: object.kind.clone(),
+                anchor: object.anchor,
+                sign: sign.cloned(),
+                composter: composter.map(|(state, container)| ComposterData {
+                    state: *state,
+                    items: container.items().cloned().collect(),
+                }),
+                growth: growth.copied(),
+                chest: chest.map(|(_, container)| container.clone()),
+                furnace: furnace.map(|(state, container)| FurnaceData {
+                    state: *state,
+                    container: container.clone(),
+                }),
+            },
+        )
+        .collect();
+
+    let bags = bags_q
+        .iter()
+        .filter(|(.., transform)| chunk_index_at(transform.translation.xy()) == index)
+        .map(|(bag, container, transform)| LootBagData {
+            position: transform.translation.xy(),
+            items: container.items().cloned().collect(),
+            remaining: bag.remaining,
+        })
+        .collect();
+
+    Some(ChunkData {
         chunk_index: index,
         tiles: tile_data,
-    }
+        critters,
+        objects,
+        bags,
+    })
 }
 
 fn task_poll(mut commands: Commands, mut tasks_q: Query<(Entity, &mut ComputeTask)>) {
     for (entity, mut task) in &mut tasks_q {
         if let Some(mut commands_queue) = block_on(future::poll_once(&mut task.1)) {
+            // despawn first, so a chunk that gets generated after a failed load is tracked as pending
+            commands.entity(entity).despawn_recursive();
             // append the returned command queue to have it execute later
             commands.append(&mut commands_queue);
-            commands.entity(entity).despawn_recursive();
         }
     }
 }
@@ -198,6 +722,11 @@ pub struct LoadChunk {
     pub index: IVec2,
 }
 
+#[derive(Event)]
+pub struct GenerateChunk {
+    pub index: IVec2,
+}
+
 #[derive(Event)]
 pub struct SaveChunk {
     pub index: IVec2,
@@ -206,106 +735,398 @@ pub struct SaveChunk {
 #[derive(Component)]
 pub struct ComputeTask(pub IVec2, pub Task<CommandQueue>);
 
-fn spawn_chunk_stub(commands: &mut Commands, chunk_data: ChunkData) {
-    let mut tile_storage = TileStorage::empty(MAP_SIZE);
-    let tilemap_entity = commands
-        .spawn((
-            Chunk {
-                chunk_index: chunk_data.chunk_index,
-            },
-            Replicated,
-        ))
-        .with_children(|parent| {
-            for tile_data in chunk_data.tiles {
-                let tile_pos: TilePos = tile_data.tile_index.into();
-                let ground = tile_data.ground;
-                let tile_entity = parent
-                    .spawn((tile_pos, ground, Replicated, ParentSync::default()))
-                    .id();
-                tile_storage.set(&tile_pos, tile_entity);
-            }
-        })
-        .remove::<ComputeTask>()
-        .id();
-    commands.entity(tilemap_entity).insert(tile_storage);
+fn spawn_chunk_stub(
+    commands: &mut Commands,
+    chunk_data: ChunkData,
+    world_seed: WorldSeed,
+    object_registry: &ObjectRegistry,
+) {
+    commands.spawn((
+        Chunk {
+            chunk_index: chunk_data.chunk_index,
+            seed: world_seed.chunk_seed(chunk_data.chunk_index),
+            biome: chunk_biome(world_seed, chunk_data.chunk_index),
+        },
+        ChunkTiles::from(chunk_data.tiles),
+        Replicated,
+    ));
+    for critter in chunk_data.critters {
+        spawn_critter(
+            commands,
+            critter.kind,
+            chunk_data.chunk_index,
+            critter.position,
+        );
+    }
+    for object in chunk_data.objects {
+        if object_registry.get(&object.kind).is_none() {
+            warn!(
+                "Unknown world object {} in chunk {}",
+                object.kind, chunk_data.chunk_index
+            );
+        }
+        let footprint = object_registry.footprint(&object.kind);
+        let entity = spawn_world_object(commands, object.kind, object.anchor, footprint);
+        if let Some(sign) = object.sign {
+            commands.entity(entity).insert(sign_components(sign));
+        }
+        if let Some(composter) = object.composter {
+            commands
+                .entity(entity)
+                .insert(composter_components(composter));
+        }
+        if let Some(growth) = object.growth {
+            commands.entity(entity).insert(growth);
+        }
+        if let Some(container) = object.chest {
+            commands.entity(entity).insert(chest_components(container));
+        }
+        if let Some(furnace) = object.furnace {
+            commands.entity(entity).insert(furnace_components(furnace));
+        }
+    }
+    for bag in chunk_data.bags {
+        spawn_loot_bag(commands, bag.items, bag.position, bag.remaining);
+    }
+}
+
+/// Generated chunks depend on the object footprints and the structure templates. Generating one
+/// before they are read would save it with missing structure parts for good.
+fn registries_loaded(objects: Res<ObjectRegistry>, structures: Res<StructureRegistry>) -> bool {
+    objects.is_loaded() && structures.is_loaded()
 }
 
 fn load_deload_chunks(
     mut commands: Commands,
-    chunk_query: Query<(Entity, &Chunk)>,
-    loading_tasks_query: Query<&ComputeTask>,
-    player_query: Query<&Transform, With<Player>>,
+    chunk_map: Res<ChunkIndexMap>,
+    parked_query: Query<&ParkedChunk>,
+    tasks_query: Query<(Entity, &ComputeTask)>,
+    player_query: Query<(&Transform, Option<&RecentVelocity>), With<Player>>,
+    critter_query: Query<(Entity, &Critter)>,
+    object_query: Query<(Entity, &WorldObject)>,
+    bag_query: Query<(Entity, &Transform), With<LootBag>>,
     view_distance: Res<ViewDistance>,
+    mut late_loads: ResMut<LateChunkLoads>,
+    spectator: Res<Spectator>,
+    spectator_settings: Res<SpectatorSettings>,
+    park_time: Res<ChunkParkTime>,
+    paths: Res<WorldPaths>,
+    time: Res<Time>,
 ) {
+    let now = time.elapsed_seconds();
     //collect all chunks that are visible and therefore should be loaded
-    let mut visible_chunk_indices: Vec<IVec2> = player_query
-        .iter()
-        .flat_map(|player_transform| {
-            let view_border = Rect::from_center_size(
-                player_transform.translation.xy(),
-                Vec2::splat(view_distance.0 * GRID_SIZE.x),
-            );
-            chunk_indices_inside(view_border)
-        }).collect();
+    let mut visible_chunk_indices: HashSet<IVec2> = HashSet::new();
+    // moving players additionally load one ring of chunks ahead, which are not kept alive by it
+    let mut prefetch_chunk_indices: HashSet<IVec2> = HashSet::new();
+    for (player_transform, recent) in player_query.iter() {
+        let view_border = Rect::from_center_size(
+            player_transform.translation.xy(),
+            Vec2::splat(view_distance.0 * GRID_SIZE.x),
+        );
+        visible_chunk_indices.extend(chunk_indices_inside(view_border));
+        let offset = recent.map_or(Vec2::ZERO, |recent| prefetch_offset(recent.velocity));
+        if offset != Vec2::ZERO {
+            let ahead = Rect::from_center_size(view_border.center() + offset, view_border.size());
+            prefetch_chunk_indices.extend(chunk_indices_inside(ahead));
+        }
+    }
+    // a spectating host can inspect terrain far away from every player
+    if spectator.active {
+        let view_border = Rect::from_center_size(
+            spectator.position,
+            Vec2::splat(spectator_settings.view_distance * GRID_SIZE.x),
+        );
+        visible_chunk_indices.extend(chunk_indices_inside(view_border));
+    }
 
-    for (entity, chunk) in chunk_query.iter() {
-        let pos = visible_chunk_indices
-            .iter()
-            .position(|&x| x == chunk.chunk_index);
-        match pos {
-            Some(pos) => {
-                // remove visible chunks that are already spawned form the list
-                visible_chunk_indices.swap_remove(pos);
+    // park chunks that are not visible and save and despawn them once they were parked long enough
+    for (&index, &entity) in chunk_map.chunks.iter() {
+        let parked = parked_query.get(entity).ok();
+        match (visible_chunk_indices.contains(&index), parked) {
+            (true, Some(_)) => {
+                commands.entity(entity).remove::<ParkedChunk>();
             }
-            None => {
-                // save and despawn chunks that are not visible
-                commands.trigger(SaveChunk {
-                    index: chunk.chunk_index,
-                });
-                commands.entity(entity).despawn_recursive();
+            (false, None) => {
+                commands
+                    .entity(entity)
+                    .insert(ParkedChunk { parked_at: now });
+            }
+            (false, Some(parked)) if now - parked.parked_at >= park_time.0 => {
+                commands.trigger(SaveChunk { index });
+                despawn_chunk(
+                    &mut commands,
+                    index,
+                    entity,
+                    &critter_query,
+                    &object_query,
+                    &bag_query,
+                );
             }
+            _ => {}
         }
     }
 
-    // remove visible chunks that are already loading form the list
-    for loading_task in loading_tasks_query.iter() {
-        visible_chunk_indices.swap_remove(visible_chunk_indices
+    // a chunk that is visible but not spawned yet pops in late for the players seeing it
+    late_loads.waiting.extend(
+        visible_chunk_indices
             .iter()
-            .position(|&x| x == loading_task.0).unwrap());
+            .filter(|&&index| chunk_map.get(index).is_none()),
+    );
+    let mut finished = 0;
+    late_loads.waiting.retain(|&index| {
+        if chunk_map.get(index).is_some() {
+            finished += 1;
+            return false;
+        }
+        visible_chunk_indices.contains(&index)
+    });
+    if finished > 0 {
+        late_loads.count += finished;
+        debug!(
+            "{finished} chunks finished loading while in view, {} in total",
+            late_loads.count
+        );
+    }
+
+    let load_chunk_indices: HashSet<IVec2> = visible_chunk_indices
+        .union(&prefetch_chunk_indices)
+        .copied()
+        .collect();
+
+    // drop results of loads and generations for chunks that went out of view in the meantime
+    for (entity, task) in tasks_query.iter() {
+        if !load_chunk_indices.contains(&task.0) {
+            commands.entity(entity).despawn_recursive();
+        }
     }
 
-    //spawning chunks that are visible but not yet spawned
-    for chunk_to_spawn in visible_chunk_indices {
-        if Path::new(&format!(
-            "world/{}_{}.ron",
-            chunk_to_spawn.x, chunk_to_spawn.y
-        ))
-        .exists()
-        {
+    //spawning chunks that are visible or ahead of a player but neither spawned nor loading
+    for chunk_to_spawn in load_chunk_indices
+        .into_iter()
+        .filter(|&index| !chunk_map.is_known(index) && !chunk_map.is_backing_off(index, now))
+    {
+        if paths.chunk(chunk_to_spawn).exists() {
             //trigger load if the chunk has a save file
             commands.trigger(LoadChunk {
                 index: chunk_to_spawn,
             });
         } else {
             //generate new chunk if it wasn't visited before
-            spawn_chunk_stub(&mut commands, gen_chunk(chunk_to_spawn));
+            commands.trigger(GenerateChunk {
+                index: chunk_to_spawn,
+            });
         }
     }
 }
 
-fn gen_chunk(index: IVec2) -> ChunkData {
+/// Despawns a chunk together with the critters and objects that belong to it.
+fn despawn_chunk(
+    commands: &mut Commands,
+    index: IVec2,
+    entity: Entity,
+    critter_query: &Query<(Entity, &Critter)>,
+    object_query: &Query<(Entity, &WorldObject)>,
+    bag_query: &Query<(Entity, &Transform), With<LootBag>>,
+) {
+    commands.entity(entity).despawn_recursive();
+    for (critter_entity, critter) in critter_query.iter() {
+        if critter.home_chunk == index {
+            commands.entity(critter_entity).despawn_recursive();
+        }
+    }
+    for (object_entity, object) in object_query.iter() {
+        if object.chunk_index() == index {
+            commands.entity(object_entity).despawn_recursive();
+        }
+    }
+    for (bag_entity, transform) in bag_query.iter() {
+        if chunk_index_at(transform.translation.xy()) == index {
+            commands.entity(bag_entity).despawn_recursive();
+        }
+    }
+}
+
+/// Handles the `regen` console command: drops the chunk without saving and deletes its file, so
+/// it is generated from the current seed the next time it comes into view.
+fn regen_chunks(
+    mut commands: Commands,
+    mut console_commands: EventReader<ConsoleCommand>,
+    mut chunk_map: ResMut<ChunkIndexMap>,
+    tasks_query: Query<(Entity, &ComputeTask)>,
+    critter_query: Query<(Entity, &Critter)>,
+    object_query: Query<(Entity, &WorldObject)>,
+    bag_query: Query<(Entity, &Transform), With<LootBag>>,
+    paths: Res<WorldPaths>,
+) {
+    for command in console_commands.read() {
+        let ConsoleCommand::Regen { chunk: index } = *command else {
+            continue;
+        };
+        if let Some(entity) = chunk_map.get(index) {
+            despawn_chunk(
+                &mut commands,
+                index,
+                entity,
+                &critter_query,
+                &object_query,
+                &bag_query,
+            );
+        }
+        for (entity, task) in tasks_query.iter() {
+            if task.0 == index {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+        chunk_map.failed.remove(&index);
+        let path = paths.chunk(index);
+        match std::fs::remove_file(&path) {
+            Ok(()) => info!("Regenerating chunk {index}"),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                info!("Chunk {index} was never saved, regenerating it")
+            }
+            Err(err) => error!("Failed deleting {}: {err}", path.display()),
+        }
+    }
+}
+
+/// Hides parked chunks locally. Which chunks clients receive is decided by
+/// [`crate::chunk_stream`].
+fn update_parked_visibility(
+    parked_added: Query<Entity, Added<ParkedChunk>>,
+    mut parked_removed: RemovedComponents<ParkedChunk>,
+    mut visibility_query: Query<&mut Visibility, With<Chunk>>,
+) {
+    for entity in parked_added.iter() {
+        if let Ok(mut visibility) = visibility_query.get_mut(entity) {
+            *visibility = Visibility::Hidden;
+        }
+    }
+    for entity in parked_removed.read() {
+        if let Ok(mut visibility) = visibility_query.get_mut(entity) {
+            *visibility = Visibility::Inherited;
+        }
+    }
+}
+
+/// Saves every loaded chunk, parked or not, in the background every [`AUTOSAVE_INTERVAL`]
+/// seconds, so a crash only loses the changes since the last autosave.
+fn autosave_chunks(
+    mut commands: Commands,
+    chunk_map: Res<ChunkIndexMap>,
+    mut since_last_save: Local<f32>,
+    time: Res<Time>,
+) {
+    *since_last_save += time.delta_seconds();
+    if *since_last_save < AUTOSAVE_INTERVAL {
+        return;
+    }
+    *since_last_save = 0.0;
+    for &index in chunk_map.chunks.keys() {
+        commands.trigger(SaveChunk { index });
+    }
+}
+
+/// Saves every loaded chunk, parked or not, synchronously before the app shuts down.
+fn save_chunks_on_exit(
+    mut exit_events: EventReader<AppExit>,
+    chunk_map: Res<ChunkIndexMap>,
+    chunks_q: Query<&ChunkTiles>,
+    critters_q: Query<(&Critter, &Transform)>,
+    objects_q: Query<(
+        &WorldObject,
+        Option<&SignText>,
+        Option<(&Composter, &ItemContainer)>,
+        Option<&Growth>,
+        Option<(&Chest, &ItemContainer)>,
+        Option<(&Furnace, &ItemContainer)>,
+    )>,
+    bags_q: Query<(&LootBag, &ItemContainer, &Transform)>,
+    paths: Res<WorldPaths>,
+) {
+    if exit_events.is_empty() {
+        return;
+    }
+    exit_events.clear();
+    for &index in chunk_map.chunks.keys() {
+        if let Some(chunk_data) = extract_chunk_data(
+            index,
+            &chunk_map,
+            &chunks_q,
+            &critters_q,
+            &objects_q,
+            &bags_q,
+        ) {
+            block_on(save_chunk(paths.chunk(index), chunk_data));
+        }
+    }
+}
+
+/// Lays out the grounds of a new chunk with its part of a structure stamped on top, then the ore
+/// veins and finally the decorations around them.
+fn gen_chunk(
+    index: IVec2,
+    world_seed: WorldSeed,
+    footprints: &HashMap<String, UVec2>,
+    templates: &[(String, StructureTemplate)],
+) -> ChunkData {
+    let structure = structure_at(world_seed, index, templates);
     let mut tile_data: Vec<TileData> = Vec::new();
+    // the whole structure area stays free of veins and decorations
+    let mut occupied: Vec<IVec2> = Vec::new();
     for x in 0..TILES_PER_CHUNK {
         for y in 0..TILES_PER_CHUNK {
+            let tile = global_tile(index, &TilePos::new(x, y));
+            if structure.is_some_and(|structure| structure.contains(tile)) {
+                occupied.push(tile);
+            }
             tile_data.push(TileData {
                 tile_index: UVec2::new(x, y),
-                ground: Ground::Grass,
+                ground: structure
+                    .and_then(|structure| structure.ground(tile))
+                    .unwrap_or_else(|| ground_at(world_seed, tile)),
+                soil_quality: DEFAULT_SOIL_QUALITY,
             });
         }
     }
+    // structure objects belong to the chunk of their anchor and are dropped if they'd cross a border
+    let structure_objects = structure.into_iter().flat_map(move |structure| {
+        structure.template.objects.iter().filter_map(move |object| {
+            let anchor = structure.origin + object.offset;
+            let footprint = footprints.get(&object.kind).copied().unwrap_or(UVec2::ONE);
+            footprint_tiles(anchor, footprint)
+                .all(|tile| split_global_tile(tile).0 == index)
+                .then(|| (object.kind.clone(), anchor))
+        })
+    });
+    let veins: Vec<_> = ore_veins(world_seed, index)
+        .into_iter()
+        .filter(|(_, tile)| !occupied.contains(tile))
+        .collect();
+    occupied.extend(veins.iter().map(|(_, tile)| *tile));
+    let decorated = decorations(world_seed, index, footprints, &mut occupied);
+    let objects = structure_objects
+        .chain(
+            veins
+                .into_iter()
+                .chain(decorated)
+                .map(|(kind, anchor)| (kind.to_string(), anchor)),
+        )
+        .map(|(kind, anchor)| WorldObjectData {
+            kind,
+            anchor,
+            sign: None,
+            composter: None,
+            growth: None,
+            furnace: None,
+            chest: None,
+        })
+        .collect();
     ChunkData {
         chunk_index: index,
         tiles: tile_data,
+        critters: Vec::new(),
+        objects,
+        bags: Vec::new(),
     }
 }
 
@@ -313,6 +1134,10 @@ fn gen_chunk(index: IVec2) -> ChunkData {
 #[reflect(Component)]
 pub struct Chunk {
     pub chunk_index: IVec2,
+    /// Derived from the [`WorldSeed`] by the server, every random stream of the chunk starts from it.
+    pub seed: u64,
+    /// Not saved, the server derives it from the seed as well.
+    pub biome: Biome,
 }
 
 impl Chunk {
@@ -340,23 +1165,23 @@ pub fn chunk_indices_inside(rect: Rect) -> Vec<IVec2> {
 
 fn init_chunk(
     mut commands: Commands,
-    chunks_q: Query<(Entity, &Chunk, &Children), Without<TilemapGridSize>>,
-    asset_server: Res<AssetServer>,
-    mut glob: ResMut<GlobalEntropy<WyRand>>,
+    chunks_q: Query<(Entity, &Chunk, &ChunkTiles), Without<TilemapGridSize>>,
+    game_assets: Res<GameAssets>,
 ) {
-    let texture_handle: Handle<Image> = asset_server.load("TX Tileset Grass.png");
     let map_type = TilemapType::default();
-    for (entity, chunk, children) in chunks_q.iter() {
+    for (entity, chunk, chunk_tiles) in chunks_q.iter() {
         commands.entity(entity).insert((
             Name::new("Chunk"),
             RenderTilemapBundle {
                 grid_size: GRID_SIZE,
                 map_type,
                 size: MAP_SIZE,
-                texture: TilemapTexture::Single(texture_handle.clone()),
+                texture: TilemapTexture::Single(game_assets.tileset_texture.clone()),
+                // whole pixels, so neighboring chunks rasterize their shared border identically
                 transform: Transform::from_translation(
-                    chunk.get_world_coords().extend(0.0)
-                        + Vec3::new(TILE_LENGTH, TILE_LENGTH, 0.0) * 0.5,
+                    (chunk.get_world_coords() + Vec2::splat(TILE_LENGTH) * 0.5)
+                        .round()
+                        .extend(0.0),
                 ),
                 tile_size: TILE_SIZE,
 
@@ -364,17 +1189,57 @@ fn init_chunk(
             },
         ));
 
-        for child in children {
-            commands.entity(*child).insert((
-                Name::new("Tile"),
-                TileTextureIndex::default(),
-                TilemapId(entity),
-                TileVisible::default(),
-                TileFlip::default(),
-                TileColor::default(),
-                TilePosOld::default(),
-                glob.fork_rng(),
-            ));
+        let mut tile_storage = TileStorage::empty(MAP_SIZE);
+        commands.entity(entity).with_children(|parent| {
+            for (tile_pos, ground) in chunk_tiles.iter() {
+                let tile_entity = parent
+                    .spawn((
+                        Name::new("Tile"),
+                        tile_pos,
+                        ground.clone(),
+                        SoilQuality(
+                            chunk_tiles
+                                .soil_quality(&tile_pos)
+                                .unwrap_or(DEFAULT_SOIL_QUALITY),
+                        ),
+                        TileTextureIndex::default(),
+                        TilemapId(entity),
+                        TileVisible::default(),
+                        TileFlip::default(),
+                        TileColor::default(),
+                        TilePosOld::default(),
+                        EntropyComponent::<WyRand>::from_seed(
+                            tile_seed(chunk.seed, &tile_pos).to_le_bytes(),
+                        ),
+                    ))
+                    .id();
+                tile_storage.set(&tile_pos, tile_entity);
+            }
+        });
+        commands.entity(entity).insert(tile_storage);
+    }
+}
+
+/// Copies changed grounds from the replicated ChunkTiles onto the local tile entities. Tiles with
+/// a pending prediction are left to [`crate::tile_prediction`].
+fn apply_chunk_tiles(
+    chunks_q: Query<(&ChunkTiles, &TileStorage), Changed<ChunkTiles>>,
+    mut tiles_q: Query<(&mut Ground, &mut SoilQuality), Without<PredictedTileChange>>,
+) {
+    for (chunk_tiles, tile_storage) in chunks_q.iter() {
+        for (tile_pos, ground) in chunk_tiles.iter() {
+            let Some((mut tile_ground, mut soil)) = tile_storage
+                .get(&tile_pos)
+                .and_then(|tile| tiles_q.get_mut(tile).ok())
+            else {
+                continue;
+            };
+            if *tile_ground != *ground {
+                *tile_ground = ground.clone();
+            }
+            if let Some(quality) = chunk_tiles.soil_quality(&tile_pos) {
+                soil.set_if_neq(SoilQuality(quality));
+            }
         }
     }
 }
@@ -399,3 +1264,124 @@ pub struct RenderTilemapBundle {
     pub frustum_culling: FrustumCulling,
     pub material: Handle<StandardTilemapMaterial>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fresh path in the system temp folder, unique per test and test run.
+    fn temp_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("crafting_game_chunk_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join(name)
+    }
+
+    #[test]
+    fn truncated_chunk_file_is_corrupt() {
+        let path = temp_path("truncated.ron");
+        std::fs::write(
+            &path,
+            "(chunk_index: (0, 0), tiles: [(tile_index: (0, 0), gro",
+        )
+        .unwrap();
+        assert!(matches!(
+            read_chunk_file(&path),
+            Err(ChunkLoadError::Corrupt(_))
+        ));
+    }
+
+    #[test]
+    fn garbage_chunk_file_is_corrupt() {
+        let path = temp_path("garbage.ron");
+        std::fs::write(&path, [0xff, 0xfe, 0x00, 0x13, 0x37, 0x80]).unwrap();
+        assert!(matches!(
+            read_chunk_file(&path),
+            Err(ChunkLoadError::Corrupt(_))
+        ));
+    }
+
+    #[test]
+    fn missing_chunk_file_is_not_found() {
+        let path = temp_path("missing.ron");
+        assert!(matches!(
+            read_chunk_file(&path),
+            Err(ChunkLoadError::NotFound)
+        ));
+    }
+
+    fn chunk_data(ground: Ground) -> ChunkData {
+        ChunkData {
+            chunk_index: IVec2::new(1, -1),
+            tiles: (0..TILES_PER_CHUNK * TILES_PER_CHUNK)
+                .map(|i| TileData {
+                    tile_index: UVec2::new(i % TILES_PER_CHUNK, i / TILES_PER_CHUNK),
+                    ground: ground.clone(),
+                    soil_quality: DEFAULT_SOIL_QUALITY,
+                })
+                .collect(),
+            critters: Vec::new(),
+            objects: Vec::new(),
+            bags: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn load_racing_a_save_reads_a_whole_chunk() {
+        let path = temp_path("racing.ron");
+        write_chunk_file(&path, &chunk_data(Ground::Grass)).unwrap();
+        let writer = {
+            let path = path.clone();
+            std::thread::spawn(move || {
+                for i in 0..200 {
+                    let ground = if i % 2 == 0 {
+                        Ground::Dirt
+                    } else {
+                        Ground::Grass
+                    };
+                    write_chunk_file(&path, &chunk_data(ground)).unwrap();
+                }
+            })
+        };
+        while !writer.is_finished() {
+            let chunk = read_chunk_file(&path).expect("load saw a partial save");
+            assert_eq!(
+                chunk.tiles.len(),
+                (TILES_PER_CHUNK * TILES_PER_CHUNK) as usize
+            );
+        }
+        writer.join().unwrap();
+        assert_eq!(
+            read_chunk_file(&path).unwrap().tiles[0].ground,
+            Ground::Grass
+        );
+    }
+
+    #[test]
+    fn index_map_follows_chunk_entities() {
+        let mut app = App::new();
+        app.init_resource::<ChunkIndexMap>()
+            .observe(track_chunk_added)
+            .observe(track_chunk_removed);
+        let index = IVec2::new(-3, 2);
+        let chunk = || Chunk {
+            chunk_index: index,
+            seed: 0,
+            biome: Biome::Plains,
+        };
+
+        let first = app.world_mut().spawn(chunk()).id();
+        assert_eq!(
+            app.world().resource::<ChunkIndexMap>().get(index),
+            Some(first)
+        );
+
+        app.world_mut().despawn(first);
+        assert_eq!(app.world().resource::<ChunkIndexMap>().get(index), None);
+
+        let second = app.world_mut().spawn(chunk()).id();
+        assert_eq!(
+            app.world().resource::<ChunkIndexMap>().get(index),
+            Some(second)
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use bevy_replicon::{core::ClientId, prelude::*, server::ServerSet};
+use bevy_replicon_snap::NetworkOwner;
+
+use crate::{
+    chunk::{Chunk, ParkedChunk},
+    network::ServerSettings,
+    player::Player,
+    world::chunk_index_at,
+};
+
+pub struct ChunkStreamPlugin;
+
+impl Plugin for ChunkStreamPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<StreamedChunks>().add_systems(
+            PostUpdate,
+            (
+                hide_new_chunks,
+                stream_chunks.run_if(resource_changed::<RepliconTick>),
+            )
+                .chain()
+                .before(ServerSet::Send)
+                .run_if(server_running),
+        );
+    }
+}
+
+/// Chunks revealed to each client so far. Every other chunk is hidden from the client, so a
+/// connect or teleport doesn't send the whole view at once.
+#[derive(Resource, Debug, Default)]
+struct StreamedChunks(HashMap<ClientId, HashSet<Entity>>);
+
+/// Chunks are visible to everyone by default, hide them until they are streamed.
+fn hide_new_chunks(
+    mut connected_clients: ResMut<ConnectedClients>,
+    new_chunks: Query<Entity, Added<Chunk>>,
+) {
+    for entity in new_chunks.iter() {
+        for client in connected_clients.iter_mut() {
+            client.visibility_mut().set_visibility(entity, false);
+        }
+    }
+}
+
+/// Reveals up to [`ServerSettings::chunks_per_tick`] chunks per client, nearest to its player
+/// first. The order is computed from the current position every tick, so a client that moves
+/// while streaming gets the chunks around its new position first. Parked chunks are hidden again.
+fn stream_chunks(
+    mut connected_clients: ResMut<ConnectedClients>,
+    mut streamed: ResMut<StreamedChunks>,
+    chunks: Query<(Entity, &Chunk, Has<ParkedChunk>)>,
+    players: Query<(&NetworkOwner, &Transform), With<Player>>,
+    settings: Res<ServerSettings>,
+) {
+    streamed
+        .0
+        .retain(|client_id, _| connected_clients.get_client(*client_id).is_some());
+
+    for client in connected_clients.iter_mut() {
+        let revealed = streamed.0.entry(client.id()).or_insert_with(|| {
+            // chunks that existed before the client connected are visible to it as well
+            for (entity, ..) in chunks.iter() {
+                client.visibility_mut().set_visibility(entity, false);
+            }
+            HashSet::new()
+        });
+        revealed.retain(|&entity| match chunks.get(entity) {
+            Ok((.., false)) => true,
+            Ok((.., true)) => {
+                client.visibility_mut().set_visibility(entity, false);
+                false
+            }
+            Err(_) => false,
+        });
+
+        let Some((_, transform)) = players
+            .iter()
+            .find(|(owner, _)| ClientId::new(owner.0) == client.id())
+        else {
+            continue;
+        };
+        let center = chunk_index_at(transform.translation.xy());
+        let mut pending: Vec<(Entity, IVec2)> = chunks
+            .iter()
+            .filter(|(entity, _, parked)| !parked && !revealed.contains(entity))
+            .map(|(entity, chunk, _)| (entity, chunk.chunk_index - center))
+            .collect();
+        // ring by ring, so the chunk under the player and its neighbors always come first
+        pending.sort_by_key(|(_, offset)| (offset.abs().max_element(), offset.length_squared()));
+        for (entity, _) in pending.into_iter().take(settings.chunks_per_tick.max(1)) {
+            client.visibility_mut().set_visibility(entity, true);
+            revealed.insert(entity);
+        }
+    }
+}
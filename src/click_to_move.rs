@@ -0,0 +1,315 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, VecDeque},
+};
+
+use bevy::{
+    color::palettes::css::LIME,
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use bevy_ecs_tilemap::tiles::TilePos;
+use bevy_mod_picking::{
+    events::{Click, Pointer},
+    pointer::PointerButton,
+};
+use bevy_replicon::prelude::*;
+use bevy_replicon_snap::NetworkOwner;
+
+use crate::{
+    chunk::{Chunk, ChunkIndexMap, ChunkTiles, TILE_LENGTH},
+    debug_overlay::overlay_visible,
+    ground_registry::GroundRegistry,
+    loading_screen::GameState,
+    player::Player,
+    water::WaterSettings,
+    world::{global_tile_at, split_global_tile, ChunkPosExt},
+    world_object::TileOccupancy,
+    MoveEvent, MoveEventClock,
+};
+
+/// Upper bound of tiles expanded per search, so unreachable targets fail fast.
+const MAX_SEARCH_NODES: usize = 4096;
+/// Lowest possible step cost, keeps the heuristic admissible for grounds up to twice as fast as grass.
+const MIN_STEP_COST: f32 = 0.5;
+/// Distance at which a waypoint counts as reached.
+const WAYPOINT_RADIUS: f32 = 2.0;
+/// Distance a move input of length 1 moves a player with speed 1, see the Predict impl.
+const MOVE_PER_INPUT: f32 = 0.005;
+
+pub struct ClickToMovePlugin;
+
+impl Plugin for ClickToMovePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ClickToMove>()
+            .add_systems(
+                Update,
+                (set_move_target, replan_path, follow_path)
+                    .chain()
+                    .run_if(client_connected)
+                    .run_if(in_state(GameState::InGame)),
+            )
+            .add_systems(Update, draw_planned_path.run_if(overlay_visible));
+    }
+}
+
+/// Target of the local player's click-to-move and the remaining path to it, in global tile
+/// coordinates.
+#[derive(Resource, Debug, Default)]
+pub struct ClickToMove {
+    target: Option<IVec2>,
+    path: VecDeque<IVec2>,
+    needs_replan: bool,
+}
+
+impl ClickToMove {
+    fn cancel(&mut self) {
+        self.target = None;
+        self.path.clear();
+        self.needs_replan = false;
+    }
+}
+
+fn global_tile_center(tile: IVec2) -> Vec2 {
+    (tile.as_vec2() + Vec2::splat(0.5)) * TILE_LENGTH
+}
+
+fn local_player<'a>(
+    players: &'a Query<(&NetworkOwner, &Transform, &Player)>,
+    client: &RepliconClient,
+) -> Option<(&'a Transform, &'a Player)> {
+    let client_id = client.id()?;
+    players
+        .iter()
+        .find(|(owner, ..)| owner.0 == client_id.get())
+        .map(|(_, transform, player)| (transform, player))
+}
+
+fn set_move_target(
+    mut click_events: EventReader<Pointer<Click>>,
+    mut click_to_move: ResMut<ClickToMove>,
+    tiles: Query<(&TilePos, &Parent)>,
+    chunks: Query<&Chunk>,
+) {
+    for click in click_events.read() {
+        if click.button != PointerButton::Secondary {
+            continue;
+        }
+        let Some((tile_pos, parent)) = tiles.get(click.target).ok() else {
+            continue;
+        };
+        let Ok(chunk) = chunks.get(parent.get()) else {
+            continue;
+        };
+        let center = chunk.get_world_coords() + tile_pos.get_in_chunk_pos() + TILE_LENGTH * 0.5;
+        click_to_move.target = Some(global_tile_at(center));
+        click_to_move.needs_replan = true;
+    }
+}
+
+/// Cost of stepping onto a tile, `None` if it can't be walked on.
+fn step_cost(
+    tile: IVec2,
+    chunk_map: &ChunkIndexMap,
+    chunks: &Query<(&Chunk, Ref<ChunkTiles>)>,
+    occupancy: &TileOccupancy,
+    registry: &GroundRegistry,
+    water: &WaterSettings,
+) -> Option<f32> {
+    if occupancy.is_occupied(tile) {
+        return None;
+    }
+    let (chunk_index, tile_pos) = split_global_tile(tile);
+    let (_, chunk_tiles) = chunks.get(chunk_map.get(chunk_index)?).ok()?;
+    let ground = chunk_tiles.get(&tile_pos)?;
+    if water.blocks(ground) {
+        return None;
+    }
+    let speed = if water.swims(ground) {
+        water.swim_speed_multiplier
+    } else {
+        registry.get(ground)?.speed_multiplier
+    };
+    (speed > 0.0).then(|| 1.0 / speed)
+}
+
+#[derive(PartialEq)]
+struct OpenNode {
+    estimate: f32,
+    tile: IVec2,
+}
+
+impl Eq for OpenNode {}
+
+impl Ord for OpenNode {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // reversed to turn the max heap into a min heap
+        other.estimate.total_cmp(&self.estimate)
+    }
+}
+
+impl PartialOrd for OpenNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// A* over the loaded tiles. The returned path excludes the start tile.
+fn find_path(
+    start: IVec2,
+    goal: IVec2,
+    mut cost_of: impl FnMut(IVec2) -> Option<f32>,
+) -> Option<VecDeque<IVec2>> {
+    let heuristic = |tile: IVec2| (goal - tile).abs().element_sum() as f32 * MIN_STEP_COST;
+    let mut open = BinaryHeap::from([OpenNode {
+        estimate: heuristic(start),
+        tile: start,
+    }]);
+    let mut came_from = HashMap::<IVec2, IVec2>::default();
+    let mut cost_so_far = HashMap::from_iter([(start, 0.0)]);
+
+    let mut expanded = 0;
+    while let Some(OpenNode { tile, .. }) = open.pop() {
+        if tile == goal {
+            let mut path = VecDeque::from([goal]);
+            let mut current = goal;
+            while let Some(previous) = came_from.get(&current) {
+                if *previous != start {
+                    path.push_front(*previous);
+                }
+                current = *previous;
+            }
+            return Some(path);
+        }
+        expanded += 1;
+        if expanded > MAX_SEARCH_NODES {
+            return None;
+        }
+        for neighbour in [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y].map(|step| tile + step) {
+            let Some(step) = cost_of(neighbour) else {
+                continue;
+            };
+            let cost = cost_so_far[&tile] + step;
+            if cost_so_far.get(&neighbour).is_some_and(|known| *known <= cost) {
+                continue;
+            }
+            cost_so_far.insert(neighbour, cost);
+            came_from.insert(neighbour, tile);
+            open.push(OpenNode {
+                estimate: cost + heuristic(neighbour),
+                tile: neighbour,
+            });
+        }
+    }
+    None
+}
+
+/// Plans a new path when the target was set, a chunk along the path changed its tiles
+/// or the target chunk was unloaded.
+fn replan_path(
+    mut click_to_move: ResMut<ClickToMove>,
+    players: Query<(&NetworkOwner, &Transform, &Player)>,
+    chunks: Query<(&Chunk, Ref<ChunkTiles>)>,
+    occupancy: Res<TileOccupancy>,
+    chunk_map: Res<ChunkIndexMap>,
+    registry: Res<GroundRegistry>,
+    water: Res<WaterSettings>,
+    client: Res<RepliconClient>,
+) {
+    let Some(target) = click_to_move.target else {
+        return;
+    };
+    let path_chunks: HashSet<IVec2> = click_to_move
+        .path
+        .iter()
+        .map(|tile| split_global_tile(*tile).0)
+        .collect();
+    let tiles_changed = chunks
+        .iter()
+        .any(|(chunk, tiles)| tiles.is_changed() && path_chunks.contains(&chunk.chunk_index));
+    let target_unloaded = chunk_map.get(split_global_tile(target).0).is_none();
+    if !click_to_move.needs_replan && !tiles_changed && !target_unloaded {
+        return;
+    }
+    click_to_move.needs_replan = false;
+
+    let Some((transform, _)) = local_player(&players, &client) else {
+        return;
+    };
+    let start = global_tile_at(transform.translation.xy());
+    let path = find_path(start, target, |tile| {
+        step_cost(tile, &chunk_map, &chunks, &occupancy, &registry, &water)
+    });
+    match path {
+        Some(path) => click_to_move.path = path,
+        None => {
+            debug!("No path to tile {target}");
+            click_to_move.cancel();
+        }
+    }
+}
+
+/// Sends move events towards the next waypoint until the target is reached.
+/// Any WASD input cancels the path.
+fn follow_path(
+    mut click_to_move: ResMut<ClickToMove>,
+    mut move_ev: EventWriter<MoveEvent>,
+    players: Query<(&NetworkOwner, &Transform, &Player)>,
+    input: Res<ButtonInput<KeyCode>>,
+    client: Res<RepliconClient>,
+    time: Res<Time>,
+    mut move_clock: Local<MoveEventClock>,
+) {
+    if click_to_move.target.is_none() {
+        move_clock.due(time.delta_seconds(), false);
+        return;
+    }
+    if input.any_pressed([KeyCode::KeyW, KeyCode::KeyA, KeyCode::KeyS, KeyCode::KeyD]) {
+        click_to_move.cancel();
+        return;
+    }
+    let Some((transform, player)) = local_player(&players, &client) else {
+        return;
+    };
+    let position = transform.translation.xy();
+    while let Some(next) = click_to_move.path.front() {
+        if position.distance(global_tile_center(*next)) > WAYPOINT_RADIUS {
+            break;
+        }
+        click_to_move.path.pop_front();
+    }
+    let Some(next) = click_to_move.path.front() else {
+        click_to_move.cancel();
+        return;
+    };
+
+    // scale the last step down so the player doesn't overshoot the waypoint
+    let offset = global_tile_center(*next) - position;
+    let input = (offset / (MOVE_PER_INPUT * player.effective_speed())).clamp_length_max(1.0);
+    // a single step per frame at most, the scaled last step must not be repeated
+    if move_clock.due(time.delta_seconds(), true) == 0 {
+        return;
+    }
+    move_ev.send(MoveEvent {
+        input,
+        timestamp: time.elapsed_seconds_wrapped(),
+        sprint: false,
+    });
+}
+
+fn draw_planned_path(
+    click_to_move: Res<ClickToMove>,
+    players: Query<(&NetworkOwner, &Transform, &Player)>,
+    client: Option<Res<RepliconClient>>,
+    mut gizmos: Gizmos,
+) {
+    if click_to_move.path.is_empty() {
+        return;
+    }
+    let Some((transform, _)) = client.and_then(|client| local_player(&players, &client)) else {
+        return;
+    };
+    let points = std::iter::once(transform.translation.xy())
+        .chain(click_to_move.path.iter().map(|tile| global_tile_center(*tile)));
+    gizmos.linestrip_2d(points, LIME);
+}
@@ -0,0 +1,175 @@
+use bevy::prelude::*;
+use bevy_replicon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    interact::{InteractEvent, InteractKind, Interactable},
+    item::{spawn_ground_item, Item},
+    item_container::{in_interaction_range, notify_full, ContainerFull, InventoryFullEvent, ItemContainer},
+    item_registry::ItemRegistry,
+    world_object::{ObjectRegistry, WorldObject},
+};
+
+const FERTILIZER_ID: &str = "fertilizer";
+const WORKING_COLOR: Color = Color::srgb(0.75, 0.6, 0.45);
+const READY_COLOR: Color = Color::srgb(0.6, 1.0, 0.5);
+
+pub struct ComposterPlugin;
+
+impl Plugin for ComposterPlugin {
+    fn build(&self, app: &mut App) {
+        app.replicate::<Composter>()
+            .register_type::<ComposterSettings>()
+            .init_resource::<ComposterSettings>()
+            .add_systems(
+                Update,
+                (init_composters, run_composters, collect_fertilizer)
+                    .chain()
+                    .run_if(server_running),
+            )
+            .add_systems(Update, update_composter_sprites);
+    }
+}
+
+#[derive(Resource, Debug, Reflect)]
+#[reflect(Resource)]
+pub struct ComposterSettings {
+    /// Seconds it takes to turn a deposited item into fertilizer.
+    pub compost_time: f32,
+}
+
+impl Default for ComposterSettings {
+    fn default() -> Self {
+        Self { compost_time: 60.0 }
+    }
+}
+
+/// State of a composter. Its single slot [`ItemContainer`] holds the item being composted.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Composter {
+    Empty,
+    /// Seconds left are counted down on the server only, clients just show that it works.
+    Working { remaining: f32 },
+    Ready,
+}
+
+/// Save format of a composter inside its chunk file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComposterData {
+    pub state: Composter,
+    pub items: Vec<Item>,
+}
+
+/// Components that turn a world object into a composter.
+pub fn composter_components(data: ComposterData) -> impl Bundle {
+    let interactable = match data.state {
+        Composter::Ready => Interactable::new(InteractKind::Collect, "item.fertilizer"),
+        _ => Interactable::new(InteractKind::Open, "object.composter"),
+    };
+    (data.state, ItemContainer::new(data.items, 1), interactable)
+}
+
+/// Whether a composter accepts the item into its slot.
+pub fn accepts(composter: &Composter, item: &Item, registry: &ItemRegistry) -> bool {
+    *composter == Composter::Empty && registry.is_compostable(&item.id)
+}
+
+/// Turns freshly placed composter objects into composters. Loaded ones come with their state.
+fn init_composters(
+    mut commands: Commands,
+    objects: Query<(Entity, &WorldObject), (Added<WorldObject>, Without<Composter>)>,
+    registry: Res<ObjectRegistry>,
+) {
+    for (entity, object) in objects.iter() {
+        if registry.get(&object.kind).is_some_and(|def| def.composter) {
+            commands.entity(entity).insert(composter_components(ComposterData {
+                state: Composter::Empty,
+                items: Vec::new(),
+            }));
+        }
+    }
+}
+
+fn run_composters(
+    mut composters: Query<(&mut Composter, &mut ItemContainer, &mut Interactable)>,
+    settings: Res<ComposterSettings>,
+    time: Res<Time>,
+) {
+    for (mut composter, mut container, mut interactable) in composters.iter_mut() {
+        match *composter {
+            Composter::Empty if !container.is_empty() => {
+                *composter = Composter::Working {
+                    remaining: settings.compost_time,
+                };
+            }
+            // the item was taken out again
+            Composter::Working { .. } if container.is_empty() => {
+                *composter = Composter::Empty;
+            }
+            Composter::Working { .. } => {
+                // clients only need to know that it works, not the countdown
+                let remaining = match composter.bypass_change_detection() {
+                    Composter::Working { ref mut remaining } => {
+                        *remaining -= time.delta_seconds();
+                        *remaining
+                    }
+                    _ => unreachable!(),
+                };
+                if remaining <= 0.0 {
+                    container.take_from_slot(0, 1);
+                    *composter = Composter::Ready;
+                    *interactable = Interactable::new(InteractKind::Collect, "item.fertilizer");
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Gives the fertilizer of a ready composter to the player, or drops it if the inventory is full.
+fn collect_fertilizer(
+    mut commands: Commands,
+    mut events: EventReader<InteractEvent>,
+    mut full_events: EventWriter<ToClients<InventoryFullEvent>>,
+    mut composters: Query<(&mut Composter, &mut Interactable, &Transform)>,
+    mut players: Query<(&mut ItemContainer, &Transform)>,
+    registry: Res<ItemRegistry>,
+) {
+    for event in events.read() {
+        if event.kind != InteractKind::Collect {
+            continue;
+        }
+        let (Ok((mut composter, mut interactable, transform)), Ok((mut inventory, player))) =
+            (composters.get_mut(event.target), players.get_mut(event.player))
+        else {
+            continue;
+        };
+        if *composter != Composter::Ready
+            || !in_interaction_range(player.translation.xy(), transform.translation.xy())
+        {
+            continue;
+        }
+        let Some(fertilizer) = registry.create(FERTILIZER_ID) else {
+            warn!("Item {FERTILIZER_ID} is not defined");
+            continue;
+        };
+        if let Err(ContainerFull(rest)) = inventory.insert(fertilizer) {
+            spawn_ground_item(&mut commands, &rest, transform.translation.xy(), None);
+            notify_full(&mut full_events, event.client_id);
+        }
+        *composter = Composter::Empty;
+        *interactable = Interactable::new(InteractKind::Open, "object.composter");
+    }
+}
+
+fn update_composter_sprites(
+    mut composters: Query<(&Composter, &mut Sprite), Or<(Changed<Composter>, Added<Sprite>)>>,
+) {
+    for (composter, mut sprite) in composters.iter_mut() {
+        sprite.color = match composter {
+            Composter::Empty => Color::WHITE,
+            Composter::Working { .. } => WORKING_COLOR,
+            Composter::Ready => READY_COLOR,
+        };
+    }
+}
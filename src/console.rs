@@ -0,0 +1,119 @@
+use std::{
+    io::BufRead,
+    sync::{
+        mpsc::{channel, Receiver, TryRecvError},
+        Mutex,
+    },
+    thread,
+};
+
+use bevy::prelude::*;
+
+pub struct ConsolePlugin;
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ConsoleCommand>()
+            // the server takes admin commands, clients their own ones like give
+            .add_systems(Startup, start_console)
+            .add_systems(
+                PreUpdate,
+                read_console.run_if(resource_exists::<ConsoleInput>),
+            );
+    }
+}
+
+/// Commands typed into the terminal. A leading `/` is optional, so chat style input works too.
+#[derive(Event, Debug, Clone, PartialEq)]
+pub enum ConsoleCommand {
+    /// Deletes the save file of a chunk and generates it again from the current seed.
+    Regen { chunk: IVec2 },
+    /// Toggles creative mode of a connected client, server only.
+    Creative { client: u64 },
+    /// Asks the server for items, client only and accepted in creative mode only.
+    Give { item: String, count: u32 },
+    /// Renders the saved chunks within `radius` chunks of the origin to a PNG, server only.
+    ExportMap { radius: u32 },
+}
+
+impl ConsoleCommand {
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let mut words = line.trim_start().trim_start_matches('/').split_whitespace();
+        match words.next() {
+            Some("regen") => {
+                let mut coordinate = || -> Result<i32, String> {
+                    words
+                        .next()
+                        .ok_or("usage: regen <x> <y>")?
+                        .parse()
+                        .map_err(|err| format!("invalid chunk coordinate: {err}"))
+                };
+                let chunk = IVec2::new(coordinate()?, coordinate()?);
+                Ok(ConsoleCommand::Regen { chunk })
+            }
+            Some("creative") => {
+                let client = words
+                    .next()
+                    .ok_or("usage: creative <client>")?
+                    .parse()
+                    .map_err(|err| format!("invalid client id: {err}"))?;
+                Ok(ConsoleCommand::Creative { client })
+            }
+            Some("give") => {
+                let item = words.next().ok_or("usage: give <item> [count]")?.to_string();
+                let count = match words.next() {
+                    Some(count) => count.parse().map_err(|err| format!("invalid count: {err}"))?,
+                    None => 1,
+                };
+                Ok(ConsoleCommand::Give { item, count })
+            }
+            Some("exportmap") => {
+                let radius = words
+                    .next()
+                    .ok_or("usage: exportmap <radius>")?
+                    .parse()
+                    .map_err(|err| format!("invalid radius: {err}"))?;
+                Ok(ConsoleCommand::ExportMap { radius })
+            }
+            Some(command) => Err(format!("unknown command {command}")),
+            None => Err("empty command".into()),
+        }
+    }
+}
+
+/// Lines read from stdin by a background thread, blocking reads would stall the app.
+#[derive(Resource)]
+struct ConsoleInput(Mutex<Receiver<String>>);
+
+fn start_console(mut commands: Commands) {
+    let (sender, receiver) = channel();
+    thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    commands.insert_resource(ConsoleInput(Mutex::new(receiver)));
+}
+
+fn read_console(input: Res<ConsoleInput>, mut commands: EventWriter<ConsoleCommand>) {
+    let Ok(receiver) = input.0.lock() else {
+        return;
+    };
+    loop {
+        match receiver.try_recv() {
+            Ok(line) if line.trim().is_empty() => {}
+            Ok(line) => match ConsoleCommand::parse(&line) {
+                Ok(command) => {
+                    commands.send(command);
+                }
+                Err(err) => warn!("{err}"),
+            },
+            Err(TryRecvError::Empty | TryRecvError::Disconnected) => return,
+        }
+    }
+}
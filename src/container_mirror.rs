@@ -0,0 +1,142 @@
+use bevy::{
+    ecs::entity::{EntityHashMap, MapEntities},
+    prelude::*,
+};
+use bevy_replicon::{client::ClientSet, core::Replicated, prelude::*};
+use bevy_replicon_snap::NetworkOwner;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    crafting::CraftQueue,
+    item_container::{in_interaction_range, ItemContainer},
+    player::Player,
+};
+
+pub struct ContainerMirrorPlugin;
+
+impl Plugin for ContainerMirrorPlugin {
+    fn build(&self, app: &mut App) {
+        app.replicate::<ContainerMirror>()
+            .replicate_mapped::<MirrorHost>()
+            .add_systems(
+                Update,
+                (
+                    spawn_container_mirrors,
+                    despawn_orphaned_mirrors,
+                    update_mirror_visibility,
+                    (sync_to_mirror::<ItemContainer>, sync_to_mirror::<CraftQueue>),
+                )
+                    .chain()
+                    .run_if(server_running),
+            )
+            .add_systems(
+                PreUpdate,
+                (apply_mirrored::<ItemContainer>, apply_mirrored::<CraftQueue>)
+                    .after(ClientSet::Receive)
+                    .run_if(client_connected),
+            );
+    }
+}
+
+/// Separate entity that carries copies of the private components of a container, since replicon
+/// can only hide whole entities. Its host is visible to everyone, the mirror only to the owning
+/// client, or for unowned containers to clients whose player is within interaction range.
+///
+/// Components are only replicated together with this marker, e.g.
+/// `replicate_group::<(ItemContainer, ContainerMirror)>()`, so the host never sends them.
+#[derive(Component, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ContainerMirror;
+
+/// The entity a [`ContainerMirror`] copies its components from.
+#[derive(Component, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct MirrorHost(pub Entity);
+
+impl MapEntities for MirrorHost {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.0 = entity_mapper.map_entity(self.0);
+    }
+}
+
+/// Server side link from a container to its mirror.
+#[derive(Component, Debug)]
+struct MirroredBy(Entity);
+
+fn spawn_container_mirrors(
+    mut commands: Commands,
+    hosts: Query<Entity, (With<ItemContainer>, Without<MirroredBy>, Without<ContainerMirror>)>,
+) {
+    for host in hosts.iter() {
+        let mirror = commands
+            .spawn((Name::new("Container Mirror"), ContainerMirror, MirrorHost(host), Replicated))
+            .id();
+        commands.entity(host).insert(MirroredBy(mirror));
+    }
+}
+
+fn despawn_orphaned_mirrors(
+    mut commands: Commands,
+    mirrors: Query<(Entity, &MirrorHost)>,
+    hosts: Query<(), With<MirroredBy>>,
+) {
+    for (mirror, host) in mirrors.iter() {
+        if !hosts.contains(host.0) {
+            commands.entity(mirror).despawn();
+        }
+    }
+}
+
+fn update_mirror_visibility(
+    mut connected_clients: ResMut<ConnectedClients>,
+    mirrors: Query<(Entity, &MirrorHost)>,
+    hosts: Query<(Option<&NetworkOwner>, Option<&Transform>)>,
+    players: Query<(&NetworkOwner, &Transform), With<Player>>,
+) {
+    for client in connected_clients.iter_mut() {
+        let client_id = client.id().get();
+        let player_pos = players
+            .iter()
+            .find(|(owner, _)| owner.0 == client_id)
+            .map(|(_, transform)| transform.translation.xy());
+        for (mirror, host) in mirrors.iter() {
+            let visible = match hosts.get(host.0) {
+                Ok((Some(owner), _)) => owner.0 == client_id,
+                Ok((None, Some(transform))) => player_pos
+                    .is_some_and(|pos| in_interaction_range(pos, transform.translation.xy())),
+                _ => false,
+            };
+            client.visibility_mut().set_visibility(mirror, visible);
+        }
+    }
+}
+
+fn sync_to_mirror<C: Component + Clone>(
+    mut commands: Commands,
+    hosts: Query<(&C, &MirroredBy), Or<(Changed<C>, Added<MirroredBy>)>>,
+) {
+    for (component, mirror) in hosts.iter() {
+        commands.entity(mirror.0).insert(component.clone());
+    }
+}
+
+/// Puts the mirrored components back onto the host, so the rest of the client reads them where
+/// the server has them. They are removed again once the mirror goes out of sight.
+fn apply_mirrored<C: Component + Clone>(
+    mut commands: Commands,
+    mut removed: RemovedComponents<C>,
+    mut hosts_of: Local<EntityHashMap<Entity>>,
+    mirrors: Query<(Entity, &MirrorHost, &C), (With<ContainerMirror>, Changed<C>)>,
+) {
+    for mirror in removed.read() {
+        if let Some(host) = hosts_of.remove(&mirror) {
+            if let Some(mut host) = commands.get_entity(host) {
+                host.remove::<C>();
+            }
+        }
+    }
+    for (mirror, host, component) in mirrors.iter() {
+        if let Some(mut host_commands) = commands.get_entity(host.0) {
+            host_commands.insert(component.clone());
+            hosts_of.insert(mirror, host.0);
+        }
+    }
+}
@@ -0,0 +1,521 @@
+use bevy::{asset::LoadedFolder, prelude::*, utils::HashMap};
+use bevy_replicon::{core::ClientId, prelude::*};
+use bevy_replicon_snap::NetworkOwner;
+use serde::{Deserialize, Serialize};
+use sickle_ui::prelude::*;
+
+use crate::{
+    container_mirror::ContainerMirror,
+    creative::Creative,
+    item::{spawn_ground_item, Item},
+    item_container::{in_interaction_range, notify_full, ContainerFull, InventoryFullEvent, ItemContainer},
+    item_registry::ItemRegistry,
+    localization::{Localization, LocalizedText},
+    player::Player,
+    ron_asset::RonAssetPlugin,
+    world_object::WorldObject,
+    ActionEvent,
+};
+
+/// Every `.recipe.ron` file in this asset folder is merged into the [`RecipeRegistry`].
+const RECIPES_FOLDER: &str = "recipes";
+/// Jobs a player can have queued at once, including the running one.
+pub const MAX_CRAFT_JOBS: usize = 8;
+const PANEL_BACKGROUND_COLOR: Color = Color::srgba(0.1, 0.1, 0.1, 0.8);
+const RECIPE_COLOR: Color = Color::srgb(0.25, 0.25, 0.25);
+const RECIPE_MISSING_COLOR: Color = Color::srgb(0.12, 0.12, 0.12);
+
+pub struct CraftingPlugin;
+
+impl Plugin for CraftingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(RonAssetPlugin::<RecipeDefinitions>::new(&["recipe.ron"]))
+            .init_resource::<CraftingPanel>()
+            .replicate_group::<(CraftQueue, ContainerMirror)>()
+            .add_client_event::<CraftEvent>(ChannelKind::Ordered)
+            .add_client_event::<CancelCraftEvent>(ChannelKind::Ordered)
+            .add_systems(PreStartup, load_recipe_registry)
+            .add_systems(PreUpdate, update_recipe_registry)
+            .add_systems(
+                Update,
+                (init_craft_queues, queue_crafts, cancel_crafts, run_crafts)
+                    .chain()
+                    .run_if(server_running),
+            )
+            .add_systems(
+                Update,
+                (
+                    rebuild_crafting_panel,
+                    toggle_crafting_panel,
+                    press_recipe_buttons,
+                    press_cancel_button,
+                    update_crafting_panel,
+                )
+                    .chain()
+                    .run_if(client_connected),
+            );
+    }
+}
+
+/// How to turn ingredients into an item, keyed by recipe id.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recipe {
+    /// Localization key of the display name.
+    pub name: String,
+    /// Item ids and how many of each are used up.
+    pub ingredients: Vec<(String, u32)>,
+    pub output: String,
+    #[serde(default = "default_output_count")]
+    pub output_count: u32,
+    /// Seconds one craft takes.
+    pub craft_time: f32,
+    /// Kind of world object the player has to stand next to, crafts anywhere if unset.
+    #[serde(default)]
+    pub station: Option<String>,
+}
+
+fn default_output_count() -> u32 {
+    1
+}
+
+#[derive(Asset, TypePath, Debug, Serialize, Deserialize)]
+pub struct RecipeDefinitions {
+    pub recipes: HashMap<String, Recipe>,
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct RecipeRegistry {
+    folder: Handle<LoadedFolder>,
+    recipes: HashMap<String, Recipe>,
+}
+
+impl RecipeRegistry {
+    pub fn get(&self, id: &str) -> Option<&Recipe> {
+        self.recipes.get(id)
+    }
+}
+
+/// Crafts a player waits for. Ingredients are taken when a job is queued, the output is delivered
+/// when it finishes. Replicated to the owning client only, through its inventory's mirror.
+#[derive(Component, Debug, Default, Clone, Serialize, Deserialize)]
+pub struct CraftQueue {
+    /// The first job is the running one.
+    pub jobs: Vec<CraftJob>,
+    /// Finished fraction of the running job.
+    pub progress: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CraftJob {
+    pub recipe: String,
+    /// Items used up for the job, given back if it is cancelled before it starts.
+    pub ingredients: Vec<Item>,
+}
+
+/// Queues `quantity` crafts of a recipe for the sender.
+#[derive(Event, Debug, Clone, Serialize, Deserialize)]
+pub struct CraftEvent {
+    pub recipe: String,
+    pub quantity: u32,
+}
+
+/// Drops all queued jobs of the sender. Ingredients of jobs that didn't start yet are refunded,
+/// the running job is lost.
+#[derive(Event, Debug, Clone, Serialize, Deserialize)]
+pub struct CancelCraftEvent;
+
+#[derive(Resource, Debug, Default)]
+struct CraftingPanel {
+    root: Option<Entity>,
+    visible: bool,
+}
+
+#[derive(Component, Debug)]
+struct RecipeButton {
+    recipe: String,
+}
+
+#[derive(Component, Debug)]
+struct CancelCraftButton;
+
+#[derive(Component, Debug)]
+struct CraftStatusText;
+
+fn has_ingredients(container: &ItemContainer, recipe: &Recipe) -> bool {
+    recipe
+        .ingredients
+        .iter()
+        .all(|(id, count)| container.count(id) >= *count)
+}
+
+/// Removes the ingredients of one craft from the container. Check [`has_ingredients`] first.
+fn take_ingredients(container: &mut ItemContainer, recipe: &Recipe) -> Vec<Item> {
+    recipe
+        .ingredients
+        .iter()
+        .filter_map(|(id, count)| container.remove(id, *count))
+        .collect()
+}
+
+fn near_station(recipe: &Recipe, position: Vec2, objects: &Query<(&WorldObject, &Transform)>) -> bool {
+    recipe.station.as_ref().map_or(true, |station| {
+        objects.iter().any(|(object, transform)| {
+            object.kind == *station && in_interaction_range(position, transform.translation.xy())
+        })
+    })
+}
+
+fn init_craft_queues(mut commands: Commands, players: Query<Entity, (With<Player>, Without<CraftQueue>)>) {
+    for entity in players.iter() {
+        commands.entity(entity).insert(CraftQueue::default());
+    }
+}
+
+/// Creative players craft without ingredients or stations.
+fn queue_crafts(
+    mut events: EventReader<FromClient<CraftEvent>>,
+    mut players: Query<
+        (&NetworkOwner, &Transform, &mut ItemContainer, &mut CraftQueue, Has<Creative>),
+        With<Player>,
+    >,
+    stations: Query<(&WorldObject, &Transform)>,
+    recipes: Res<RecipeRegistry>,
+) {
+    for FromClient { client_id, event } in events.read() {
+        let Some((_, transform, mut container, mut queue, creative)) = players
+            .iter_mut()
+            .find(|(owner, ..)| owner.0 == client_id.get())
+        else {
+            continue;
+        };
+        let Some(recipe) = recipes.get(&event.recipe) else {
+            debug!("{client_id:?} tried to craft unknown recipe {}", event.recipe);
+            continue;
+        };
+        if !creative && !near_station(recipe, transform.translation.xy(), &stations) {
+            debug!("{client_id:?} is not at the station of recipe {}", event.recipe);
+            continue;
+        }
+        for _ in 0..event.quantity {
+            if queue.jobs.len() >= MAX_CRAFT_JOBS {
+                debug!("Craft queue of {client_id:?} is full");
+                break;
+            }
+            let ingredients = if creative {
+                Vec::new()
+            } else if has_ingredients(&container, recipe) {
+                take_ingredients(&mut container, recipe)
+            } else {
+                break;
+            };
+            queue.jobs.push(CraftJob {
+                recipe: event.recipe.clone(),
+                ingredients,
+            });
+        }
+    }
+}
+
+fn cancel_crafts(
+    mut commands: Commands,
+    mut events: EventReader<FromClient<CancelCraftEvent>>,
+    mut full_events: EventWriter<ToClients<InventoryFullEvent>>,
+    mut players: Query<(&NetworkOwner, &Transform, &mut ItemContainer, &mut CraftQueue), With<Player>>,
+) {
+    for FromClient { client_id, .. } in events.read() {
+        let Some((_, transform, mut container, mut queue)) = players
+            .iter_mut()
+            .find(|(owner, ..)| owner.0 == client_id.get())
+        else {
+            continue;
+        };
+        let pending: Vec<CraftJob> = queue.jobs.drain(..).skip(1).collect();
+        queue.progress = 0.0;
+        for item in pending.into_iter().flat_map(|job| job.ingredients) {
+            if !give_or_drop(&mut commands, &mut container, item, transform.translation.xy()) {
+                notify_full(&mut full_events, *client_id);
+            }
+        }
+    }
+}
+
+/// Puts the items into the container and drops what doesn't fit. Returns false if some were dropped.
+fn give_or_drop(commands: &mut Commands, container: &mut ItemContainer, item: Item, position: Vec2) -> bool {
+    let Err(ContainerFull(rest)) = container.insert(item) else {
+        return true;
+    };
+    spawn_ground_item(commands, &rest, position, None);
+    false
+}
+
+fn run_crafts(
+    mut commands: Commands,
+    mut full_events: EventWriter<ToClients<InventoryFullEvent>>,
+    mut players: Query<(&NetworkOwner, &Transform, &mut ItemContainer, &mut CraftQueue), With<Player>>,
+    recipes: Res<RecipeRegistry>,
+    items: Res<ItemRegistry>,
+    time: Res<Time>,
+) {
+    for (owner, transform, mut container, mut queue) in players.iter_mut() {
+        let Some(job) = queue.jobs.first() else {
+            continue;
+        };
+        let Some(recipe) = recipes.get(&job.recipe) else {
+            warn!("Dropping craft of removed recipe {}", job.recipe);
+            queue.jobs.remove(0);
+            continue;
+        };
+        queue.progress += time.delta_seconds() / recipe.craft_time.max(f32::EPSILON);
+        if queue.progress < 1.0 {
+            continue;
+        }
+        let Some(output) = items.create(&recipe.output) else {
+            warn!("Recipe {} makes unknown item {}", job.recipe, recipe.output);
+            queue.jobs.remove(0);
+            queue.progress = 0.0;
+            continue;
+        };
+        let output = output.with_count(recipe.output_count);
+        if !give_or_drop(&mut commands, &mut container, output, transform.translation.xy()) {
+            notify_full(&mut full_events, ClientId::new(owner.0));
+        }
+        queue.jobs.remove(0);
+        queue.progress = 0.0;
+    }
+}
+
+fn load_recipe_registry(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(RecipeRegistry {
+        folder: asset_server.load_folder(RECIPES_FOLDER),
+        recipes: HashMap::default(),
+    });
+}
+
+/// Rebuilds the registry from all recipe files once the folder loaded and whenever one of them
+/// changes.
+fn update_recipe_registry(
+    mut folder_events: EventReader<AssetEvent<LoadedFolder>>,
+    mut recipe_events: EventReader<AssetEvent<RecipeDefinitions>>,
+    mut registry: ResMut<RecipeRegistry>,
+    folders: Res<Assets<LoadedFolder>>,
+    definitions: Res<Assets<RecipeDefinitions>>,
+) {
+    let folder_loaded = folder_events
+        .read()
+        .any(|event| event.is_loaded_with_dependencies(&registry.folder));
+    let recipes_changed = recipe_events
+        .read()
+        .any(|event| matches!(event, AssetEvent::Modified { .. }));
+    if !folder_loaded && !recipes_changed {
+        return;
+    }
+    let Some(folder) = folders.get(&registry.folder) else {
+        return;
+    };
+    let mut recipes = HashMap::default();
+    for handle in &folder.handles {
+        // other files in the folder are loaded as whatever asset their extension says
+        let Ok(handle) = handle.clone().try_typed::<RecipeDefinitions>() else {
+            continue;
+        };
+        let Some(loaded) = definitions.get(&handle) else {
+            continue;
+        };
+        for (id, recipe) in &loaded.recipes {
+            if recipes.insert(id.clone(), recipe.clone()).is_some() {
+                warn!("Recipe {id} is defined in more than one file");
+            }
+        }
+    }
+    registry.recipes = recipes;
+}
+
+fn recipe_label(recipe: &Recipe, items: &ItemRegistry, localization: &Localization) -> String {
+    let ingredients: Vec<String> = recipe
+        .ingredients
+        .iter()
+        .map(|(id, count)| {
+            let name = items.get(id).map_or_else(|| id.clone(), |def| localization.tr(&def.name));
+            format!("{count} {name}")
+        })
+        .collect();
+    let label = format!("{} ({})", localization.tr(&recipe.name), ingredients.join(", "));
+    match &recipe.station {
+        Some(station) => {
+            let station = localization.tr(&format!("object.{station}"));
+            format!("{label} {}", localization.tr_args("crafting.station", &[("station", station)]))
+        }
+        None => label,
+    }
+}
+
+/// One button per recipe, sorted by id. Clicking one queues a craft and it is greyed out while
+/// the local player can't craft it.
+pub fn spawn_recipe_buttons(
+    builder: &mut UiBuilder<Entity>,
+    recipes: &RecipeRegistry,
+    items: &ItemRegistry,
+    localization: &Localization,
+) {
+    let mut sorted: Vec<(&String, &Recipe)> = recipes.recipes.iter().collect();
+    sorted.sort_by_key(|(id, _)| id.as_str());
+    for (id, recipe) in sorted {
+        let button = ButtonBundle {
+            style: Style {
+                margin: UiRect::top(Val::Px(2.0)),
+                ..Default::default()
+            },
+            background_color: RECIPE_COLOR.into(),
+            ..Default::default()
+        };
+        builder.container((button, RecipeButton { recipe: id.clone() }), |button| {
+            button.label(LabelConfig {
+                label: recipe_label(recipe, items, localization),
+                ..Default::default()
+            });
+        });
+    }
+}
+
+/// Builds the panel again whenever the recipes (re)load or the language changes.
+fn rebuild_crafting_panel(
+    mut commands: Commands,
+    mut panel: ResMut<CraftingPanel>,
+    recipes: Res<RecipeRegistry>,
+    items: Res<ItemRegistry>,
+    localization: Res<Localization>,
+) {
+    if !recipes.is_changed() && !items.is_changed() && !localization.is_changed() {
+        return;
+    }
+    if let Some(root) = panel.root.take() {
+        commands.entity(root).despawn_recursive();
+    }
+    let root = commands
+        .ui_builder(UiRoot)
+        .container(
+            (
+                Name::new("Crafting Panel"),
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        bottom: Val::Px(8.0),
+                        left: Val::Px(8.0),
+                        flex_direction: FlexDirection::Column,
+                        padding: UiRect::all(Val::Px(4.0)),
+                        ..Default::default()
+                    },
+                    background_color: PANEL_BACKGROUND_COLOR.into(),
+                    visibility: if panel.visible { Visibility::Inherited } else { Visibility::Hidden },
+                    ..Default::default()
+                },
+            ),
+            |window| {
+                window.spawn((TextBundle::from_section("", TextStyle::default()), CraftStatusText));
+                window.container((ButtonBundle::default(), CancelCraftButton), |button| {
+                    button.label(LabelConfig::default()).insert(LocalizedText::new("crafting.cancel"));
+                });
+                spawn_recipe_buttons(window, &recipes, &items, &localization);
+            },
+        )
+        .id();
+    panel.root = Some(root);
+}
+
+fn toggle_crafting_panel(
+    mut event_reader: EventReader<ActionEvent>,
+    mut panel: ResMut<CraftingPanel>,
+    mut visibility: Query<&mut Visibility>,
+) {
+    for event in event_reader.read() {
+        if event.action != KeyCode::KeyC {
+            continue;
+        }
+        panel.visible = !panel.visible;
+        if let Some(mut visibility) = panel.root.and_then(|root| visibility.get_mut(root).ok()) {
+            *visibility = if panel.visible { Visibility::Inherited } else { Visibility::Hidden };
+        }
+    }
+}
+
+/// Clicking a recipe queues one craft, with shift five.
+fn press_recipe_buttons(
+    buttons: Query<(&RecipeButton, &Interaction), Changed<Interaction>>,
+    input: Res<ButtonInput<KeyCode>>,
+    mut writer: EventWriter<CraftEvent>,
+) {
+    for (button, interaction) in buttons.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let quantity = if input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) { 5 } else { 1 };
+        writer.send(CraftEvent {
+            recipe: button.recipe.clone(),
+            quantity,
+        });
+    }
+}
+
+fn press_cancel_button(
+    buttons: Query<&Interaction, (With<CancelCraftButton>, Changed<Interaction>)>,
+    mut writer: EventWriter<CancelCraftEvent>,
+) {
+    if buttons.iter().any(|interaction| *interaction == Interaction::Pressed) {
+        writer.send(CancelCraftEvent);
+    }
+}
+
+/// Shows the progress of the local queue and greys out recipes the player lacks ingredients or
+/// the station for.
+fn update_crafting_panel(
+    players: Query<
+        (&NetworkOwner, &Transform, &ItemContainer, Option<&CraftQueue>, Has<Creative>),
+        With<Player>,
+    >,
+    stations: Query<(&WorldObject, &Transform)>,
+    mut status: Query<&mut Text, With<CraftStatusText>>,
+    mut buttons: Query<(&RecipeButton, &mut BackgroundColor)>,
+    recipes: Res<RecipeRegistry>,
+    client: Res<RepliconClient>,
+    localization: Res<Localization>,
+) {
+    let Some((_, transform, container, queue, creative)) = client.id().and_then(|client_id| {
+        players
+            .iter()
+            .find(|(owner, ..)| ClientId::new(owner.0) == client_id)
+    }) else {
+        return;
+    };
+    if let Ok(mut text) = status.get_single_mut() {
+        let content = match queue.and_then(|queue| Some((queue, queue.jobs.first()?))) {
+            Some((queue, job)) => {
+                let name = recipes.get(&job.recipe).map_or(job.recipe.as_str(), |recipe| recipe.name.as_str());
+                localization.tr_args(
+                    "crafting.status",
+                    &[
+                        ("recipe", localization.tr(name)),
+                        ("progress", format!("{:.0}", queue.progress * 100.0)),
+                        ("queued", (queue.jobs.len() - 1).to_string()),
+                    ],
+                )
+            }
+            None => localization.tr("crafting.idle"),
+        };
+        if text.sections[0].value != content {
+            text.sections[0].value = content;
+        }
+    }
+    for (button, mut color) in buttons.iter_mut() {
+        let craftable = creative
+            || recipes
+                .get(&button.recipe)
+                .is_some_and(|recipe| {
+                    has_ingredients(container, recipe)
+                        && near_station(recipe, transform.translation.xy(), &stations)
+                });
+        let target = if craftable { RECIPE_COLOR } else { RECIPE_MISSING_COLOR };
+        if color.0 != target {
+            color.0 = target;
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use bevy::prelude::*;
+use bevy_mod_picking::picking_core::Pickable;
+use bevy_replicon::{core::ClientId, prelude::*};
+use bevy_replicon_snap::NetworkOwner;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    action::ActionCooldowns,
+    console::ConsoleCommand,
+    item::MAX_STACK_SIZE,
+    item_container::{notify_full, InventoryFullEvent, ItemContainer},
+    item_registry::ItemRegistry,
+    localization::LocalizedText,
+    player::Player,
+    Cli,
+};
+
+pub struct CreativePlugin;
+
+impl Plugin for CreativePlugin {
+    fn build(&self, app: &mut App) {
+        app.replicate::<Creative>()
+            .add_client_event::<GiveEvent>(ChannelKind::Ordered)
+            .add_systems(Startup, setup_creative_badge)
+            .add_systems(
+                Update,
+                (creative_by_default, toggle_creative, give_items).run_if(server_running),
+            )
+            .add_systems(Update, send_give_events.run_if(client_connected))
+            .add_systems(Update, (sync_instant_cooldowns, update_creative_badge));
+    }
+}
+
+/// Marks a player in creative mode, for testing. Toggled through the server console or given to
+/// everyone with `--creative`. It is not part of any save data, so it ends with the session.
+#[derive(Component, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Creative;
+
+/// Asks the server to put `count` items of a kind into the sender's inventory.
+#[derive(Event, Debug, Clone, Serialize, Deserialize)]
+pub struct GiveEvent {
+    pub item_id: String,
+    pub count: u32,
+}
+
+#[derive(Component, Debug)]
+struct CreativeBadge;
+
+fn creative_by_default(
+    mut commands: Commands,
+    players: Query<Entity, Added<Player>>,
+    cli: Res<Cli>,
+) {
+    if !matches!(*cli, Cli::Server { creative: true, .. }) {
+        return;
+    }
+    for entity in players.iter() {
+        commands.entity(entity).insert(Creative);
+    }
+}
+
+fn toggle_creative(
+    mut commands: Commands,
+    mut console_commands: EventReader<ConsoleCommand>,
+    players: Query<(Entity, &NetworkOwner, Has<Creative>), With<Player>>,
+) {
+    for command in console_commands.read() {
+        let ConsoleCommand::Creative { client } = *command else {
+            continue;
+        };
+        let Some((entity, _, creative)) = players.iter().find(|(_, owner, _)| owner.0 == client) else {
+            warn!("No player for client {client}");
+            continue;
+        };
+        if creative {
+            commands.entity(entity).remove::<Creative>();
+        } else {
+            commands.entity(entity).insert(Creative);
+        }
+        info!("Creative mode of client {client} is now {}", if creative { "off" } else { "on" });
+    }
+}
+
+fn send_give_events(
+    mut console_commands: EventReader<ConsoleCommand>,
+    mut give_events: EventWriter<GiveEvent>,
+) {
+    for command in console_commands.read() {
+        if let ConsoleCommand::Give { item, count } = command {
+            give_events.send(GiveEvent {
+                item_id: item.clone(),
+                count: *count,
+            });
+        }
+    }
+}
+
+/// Fills the inventory of a creative sender with the requested items, up to a stack at a time.
+fn give_items(
+    mut events: EventReader<FromClient<GiveEvent>>,
+    mut full_events: EventWriter<ToClients<InventoryFullEvent>>,
+    mut players: Query<(&NetworkOwner, &mut ItemContainer, Has<Creative>), With<Player>>,
+    registry: Res<ItemRegistry>,
+) {
+    for FromClient { client_id, event } in events.read() {
+        let Some((_, mut inventory, creative)) = players
+            .iter_mut()
+            .find(|(owner, ..)| owner.0 == client_id.get())
+        else {
+            continue;
+        };
+        if !creative {
+            warn!("{client_id:?} tried to give itself items without creative mode");
+            continue;
+        }
+        let Some(item) = registry.create(&event.item_id) else {
+            debug!("{client_id:?} asked for unknown item {}", event.item_id);
+            continue;
+        };
+        // whatever doesn't fit is dropped
+        if inventory.insert(item.with_count(event.count.min(MAX_STACK_SIZE))).is_err() {
+            notify_full(&mut full_events, *client_id);
+        }
+    }
+}
+
+/// Creative players don't wait for cooldowns. Runs on the server and on clients, so the owning
+/// client predicts the same.
+fn sync_instant_cooldowns(mut players: Query<(&mut ActionCooldowns, Has<Creative>)>) {
+    for (mut cooldowns, creative) in players.iter_mut() {
+        if cooldowns.instant != creative {
+            cooldowns.instant = creative;
+        }
+    }
+}
+
+fn setup_creative_badge(mut commands: Commands) {
+    commands.spawn((
+        Name::new("Creative Badge"),
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 16.0,
+                color: Color::srgb(1.0, 0.85, 0.3),
+                ..Default::default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.0),
+            right: Val::Px(8.0),
+            ..Default::default()
+        }),
+        Visibility::Hidden,
+        Pickable::IGNORE,
+        CreativeBadge,
+        LocalizedText::new("creative.badge"),
+    ));
+}
+
+fn update_creative_badge(
+    mut badge: Query<&mut Visibility, With<CreativeBadge>>,
+    players: Query<&NetworkOwner, (With<Player>, With<Creative>)>,
+    client: Option<Res<RepliconClient>>,
+) {
+    let Ok(mut visibility) = badge.get_single_mut() else {
+        return;
+    };
+    let creative = client
+        .and_then(|client| client.id())
+        .is_some_and(|client_id| players.iter().any(|owner| ClientId::new(owner.0) == client_id));
+    visibility.set_if_neq(if creative { Visibility::Visible } else { Visibility::Hidden });
+}
@@ -0,0 +1,315 @@
+use bevy::{prelude::*, utils::HashMap};
+use bevy_ecs_tilemap::tiles::TilePos;
+use bevy_rand::prelude::{EntropyComponent, ForkableRng, GlobalEntropy, WyRand};
+use bevy_rapier2d::prelude::{Collider, Sensor};
+use bevy_replicon::{client::ClientSet, core::Replicated, prelude::*};
+use bevy_replicon_snap::interpolation::Interpolated;
+use rand_core::RngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chunk::{Chunk, ChunkIndexMap, ChunkTiles, ParkedChunk, TILES_PER_CHUNK, TILE_LENGTH},
+    game_assets::GameAssets,
+    ground_registry::GroundRegistry,
+    health::{DeathEvent, Health},
+    ron_asset::RonAssetPlugin,
+    water::WaterSettings,
+    world::{
+        chunk_index_at, global_tile_at, ground_at_world_pos, is_walkable,
+        tile_center_world_pos, Ground,
+    },
+    world_object::TileOccupancy,
+};
+
+const CRITTERS_PATH: &str = "data/critters.critter.ron";
+/// Columns of the player atlas, critters use one of its rows.
+const ATLAS_COLUMNS: usize = 8;
+/// Distance at which a critter counts as arrived at its wander target.
+const ARRIVAL_RADIUS: f32 = 1.0;
+const CRITTER_HEALTH: f32 = 5.0;
+const CRITTER_COLLIDER_RADIUS: f32 = 10.0;
+
+pub struct CritterPlugin;
+
+impl Plugin for CritterPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(RonAssetPlugin::<CritterDefinitions>::new(&["critter.ron"]))
+            .replicate::<Critter>()
+            .init_resource::<CritterSpawnSettings>()
+            .add_systems(PreStartup, load_critter_registry)
+            .add_systems(PreUpdate, update_critter_registry)
+            .add_systems(PreUpdate, init_critter.after(ClientSet::Receive))
+            .add_systems(
+                Update,
+                (spawn_critters, init_critter_ai, wander, remove_dead_critters)
+                    .chain()
+                    .run_if(server_running),
+            );
+    }
+}
+
+/// A passive animal. Critters stay inside their home chunk so they are saved and despawned with it.
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+pub struct Critter {
+    pub kind: String,
+    pub home_chunk: IVec2,
+}
+
+/// Save format of a critter inside its chunk file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CritterData {
+    pub kind: String,
+    pub position: Vec2,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CritterDefinition {
+    pub atlas_row: usize,
+    /// Walking speed in units per second.
+    pub speed: f32,
+    /// How many tiles away a critter picks its next wander target.
+    pub wander_radius: u32,
+    /// Range of seconds a critter idles between walks.
+    pub idle_time: (f32, f32),
+}
+
+#[derive(Asset, TypePath, Debug, Serialize, Deserialize)]
+pub struct CritterDefinitions {
+    pub critters: HashMap<String, CritterDefinition>,
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct CritterRegistry {
+    handle: Handle<CritterDefinitions>,
+    critters: HashMap<String, CritterDefinition>,
+}
+
+impl CritterRegistry {
+    pub fn get(&self, kind: &str) -> Option<&CritterDefinition> {
+        self.critters.get(kind)
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct CritterSpawnSettings {
+    pub max_per_chunk: usize,
+    /// Seconds between spawn attempts.
+    pub interval: f32,
+    /// Chance of a chunk below the cap to get a new critter per attempt.
+    pub spawn_chance: f32,
+}
+
+impl Default for CritterSpawnSettings {
+    fn default() -> Self {
+        Self {
+            max_per_chunk: 2,
+            interval: 10.0,
+            spawn_chance: 0.2,
+        }
+    }
+}
+
+/// Server side wander state.
+#[derive(Component, Debug)]
+enum Wander {
+    Idle { until: f32 },
+    Walking { target: Vec2 },
+}
+
+pub fn spawn_critter(commands: &mut Commands, kind: String, home_chunk: IVec2, position: Vec2) {
+    commands.spawn((
+        Name::new("Critter"),
+        Critter { kind, home_chunk },
+        Transform::from_translation(position.extend(1.0)),
+        Health::new(CRITTER_HEALTH),
+        Collider::ball(CRITTER_COLLIDER_RADIUS),
+        Sensor,
+        Interpolated,
+        Replicated,
+    ));
+}
+
+fn remove_dead_critters(
+    mut commands: Commands,
+    mut death_events: EventReader<DeathEvent>,
+    critters: Query<(), With<Critter>>,
+) {
+    for DeathEvent { entity } in death_events.read() {
+        if critters.contains(*entity) {
+            commands.entity(*entity).despawn_recursive();
+        }
+    }
+}
+
+fn load_critter_registry(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(CritterRegistry {
+        handle: asset_server.load(CRITTERS_PATH),
+        critters: HashMap::default(),
+    });
+}
+
+fn update_critter_registry(
+    mut events: EventReader<AssetEvent<CritterDefinitions>>,
+    mut registry: ResMut<CritterRegistry>,
+    definitions: Res<Assets<CritterDefinitions>>,
+) {
+    for event in events.read() {
+        if !event.is_loaded_with_dependencies(&registry.handle) && !event.is_modified(&registry.handle) {
+            continue;
+        }
+        if let Some(loaded) = definitions.get(&registry.handle) {
+            registry.critters = loaded.critters.clone();
+        }
+    }
+}
+
+fn init_critter(
+    mut commands: Commands,
+    query: Query<(Entity, &Critter), Without<Sprite>>,
+    registry: Res<CritterRegistry>,
+    game_assets: Res<GameAssets>,
+) {
+    for (entity, critter) in query.iter() {
+        let row = registry.get(&critter.kind).map_or(0, |def| def.atlas_row);
+        commands.entity(entity).insert((
+            Sprite {
+                custom_size: Some(Vec2::new(24.0, 24.0)),
+                ..Default::default()
+            },
+            TextureAtlas {
+                layout: game_assets.player_layout.clone(),
+                index: row * ATLAS_COLUMNS,
+            },
+            game_assets.player_texture.clone(),
+            VisibilityBundle::default(),
+            GlobalTransform::default(),
+        ));
+    }
+}
+
+/// Random float in `0.0..1.0`.
+fn random_unit(rng: &mut impl RngCore) -> f32 {
+    rng.next_u32() as f32 / u32::MAX as f32
+}
+
+/// Periodically adds critters to loaded chunks that are below the population cap.
+fn spawn_critters(
+    mut commands: Commands,
+    mut glob: ResMut<GlobalEntropy<WyRand>>,
+    mut since_last_spawn: Local<f32>,
+    chunks: Query<(&Chunk, &ChunkTiles), Without<ParkedChunk>>,
+    critters: Query<&Critter>,
+    registry: Res<CritterRegistry>,
+    settings: Res<CritterSpawnSettings>,
+    time: Res<Time>,
+) {
+    *since_last_spawn += time.delta_seconds();
+    if *since_last_spawn < settings.interval || registry.critters.is_empty() {
+        return;
+    }
+    *since_last_spawn = 0.0;
+
+    let mut population = HashMap::<IVec2, usize>::default();
+    for critter in critters.iter() {
+        *population.entry(critter.home_chunk).or_default() += 1;
+    }
+    let kinds: Vec<&String> = registry.critters.keys().collect();
+    for (chunk, chunk_tiles) in chunks.iter() {
+        let count = population.get(&chunk.chunk_index).copied().unwrap_or_default();
+        if count >= settings.max_per_chunk || random_unit(&mut *glob) >= settings.spawn_chance {
+            continue;
+        }
+        let tile_pos = TilePos::new(
+            glob.next_u32() % TILES_PER_CHUNK,
+            glob.next_u32() % TILES_PER_CHUNK,
+        );
+        if chunk_tiles.get(&tile_pos).is_some_and(Ground::is_water) {
+            continue;
+        }
+        let kind = kinds[glob.next_u32() as usize % kinds.len()].clone();
+        let position = tile_center_world_pos(chunk.chunk_index, &tile_pos);
+        spawn_critter(&mut commands, kind, chunk.chunk_index, position);
+    }
+}
+
+/// Gives critters spawned by the server or loaded from a chunk their AI state and random source.
+fn init_critter_ai(
+    mut commands: Commands,
+    query: Query<Entity, (With<Critter>, Without<Wander>)>,
+    mut glob: ResMut<GlobalEntropy<WyRand>>,
+) {
+    for entity in query.iter() {
+        commands
+            .entity(entity)
+            .insert((Wander::Idle { until: 0.0 }, glob.fork_rng()));
+    }
+}
+
+fn wander(
+    mut critters: Query<(
+        &Critter,
+        &mut Transform,
+        &mut Wander,
+        &mut EntropyComponent<WyRand>,
+    )>,
+    occupancy: Res<TileOccupancy>,
+    water: Res<WaterSettings>,
+    chunk_tiles: Query<&ChunkTiles>,
+    parked: Query<(), With<ParkedChunk>>,
+    chunk_map: Res<ChunkIndexMap>,
+    registry: Res<CritterRegistry>,
+    grounds: Res<GroundRegistry>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds();
+    for (critter, mut transform, mut wander, mut rng) in critters.iter_mut() {
+        let Some(definition) = registry.get(&critter.kind) else {
+            continue;
+        };
+        // critters of parked chunks are frozen until a player comes back
+        let Some(home) = chunk_map.get(critter.home_chunk) else {
+            continue;
+        };
+        if parked.contains(home) {
+            continue;
+        }
+
+        let position = transform.translation.xy();
+        match *wander {
+            Wander::Idle { until } if now >= until => {
+                let radius = definition.wander_radius as i32;
+                let span = (2 * radius + 1) as u32;
+                let offset = IVec2::new(
+                    (rng.next_u32() % span) as i32 - radius,
+                    (rng.next_u32() % span) as i32 - radius,
+                );
+                let target_tile = global_tile_at(position) + offset;
+                let target = (target_tile.as_vec2() + Vec2::splat(0.5)) * TILE_LENGTH;
+                if chunk_index_at(target) == critter.home_chunk
+                    && is_walkable(target, &chunk_map, &chunk_tiles, &occupancy, &water)
+                {
+                    *wander = Wander::Walking { target };
+                }
+            }
+            Wander::Idle { .. } => {}
+            Wander::Walking { target } => {
+                let ground_speed = ground_at_world_pos(position, &chunk_map, &chunk_tiles)
+                    .map_or(1.0, |ground| grounds.speed_multiplier(&ground));
+                let step = definition.speed * ground_speed * time.delta_seconds();
+                let offset = target - position;
+                let next = position + offset.clamp_length_max(step);
+                let arrived = offset.length() <= ARRIVAL_RADIUS.max(step);
+                let walkable = is_walkable(next, &chunk_map, &chunk_tiles, &occupancy, &water);
+                if walkable {
+                    transform.translation = next.extend(transform.translation.z);
+                }
+                if arrived || !walkable {
+                    let (min, max) = definition.idle_time;
+                    *wander = Wander::Idle {
+                        until: now + min + (max - min) * random_unit(&mut *rng),
+                    };
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use bevy::{
+    color::palettes::css::FUCHSIA,
+    prelude::*,
+    render::primitives::Aabb,
+    window::PrimaryWindow,
+};
+use bevy_ecs_tilemap::tiles::TilePos;
+use bevy_inspector_egui::{bevy_egui::EguiContext, bevy_inspector, egui};
+use bevy_mod_picking::{
+    events::{Click, Pointer},
+    pointer::PointerButton,
+};
+
+use crate::{
+    camera::CursorTile,
+    chunk::{Chunk, ChunkIndexMap, ChunkTiles, TILE_LENGTH},
+    debug_overlay::{debug_flag, DebugFlags},
+    tile_prediction::PredictedTileChange,
+    world::ChunkPosExt,
+};
+
+/// Alt + this key selects the chunk under the cursor.
+const SELECT_CHUNK_KEY: KeyCode = KeyCode::KeyJ;
+/// Highlight size of selected entities without bounds.
+const FALLBACK_HIGHLIGHT_SIZE: f32 = 8.0;
+
+pub struct DebugInspectorPlugin;
+
+impl Plugin for DebugInspectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InspectorSelection>().add_systems(
+            Update,
+            (
+                (select_clicked, select_cursor_chunk, clear_despawned_selection).chain(),
+                draw_selection_highlight,
+                selection_window,
+            )
+                .run_if(debug_flag(|flags| flags.inspector)),
+        );
+    }
+}
+
+/// Entity picked with Alt + click while [`DebugFlags::inspector`] is on.
+#[derive(Resource, Debug, Default)]
+struct InspectorSelection(Option<Entity>);
+
+/// Whether a click picks an entity for the inspector instead of acting in the game.
+pub fn inspector_pick_held(flags: &DebugFlags, input: &ButtonInput<KeyCode>) -> bool {
+    flags.inspector && input.any_pressed([KeyCode::AltLeft, KeyCode::AltRight])
+}
+
+fn select_clicked(
+    mut click_events: EventReader<Pointer<Click>>,
+    mut selection: ResMut<InspectorSelection>,
+    flags: Res<DebugFlags>,
+    input: Res<ButtonInput<KeyCode>>,
+) {
+    for click in click_events.read() {
+        if click.button == PointerButton::Primary && inspector_pick_held(&flags, &input) {
+            selection.0 = Some(click.target);
+        }
+    }
+}
+
+fn select_cursor_chunk(
+    mut selection: ResMut<InspectorSelection>,
+    input: Res<ButtonInput<KeyCode>>,
+    flags: Res<DebugFlags>,
+    cursor_tile: Res<CursorTile>,
+    chunk_map: Res<ChunkIndexMap>,
+) {
+    if !input.just_pressed(SELECT_CHUNK_KEY) || !inspector_pick_held(&flags, &input) {
+        return;
+    }
+    if let Some(chunk) = cursor_tile.and_then(|(chunk_index, _)| chunk_map.get(chunk_index)) {
+        selection.0 = Some(chunk);
+    }
+}
+
+fn clear_despawned_selection(mut selection: ResMut<InspectorSelection>, entities: &Entities) {
+    if selection.0.is_some_and(|entity| !entities.contains(entity)) {
+        selection.0 = None;
+    }
+}
+
+/// Outlines the selection: tiles by their grid cell, everything else by its bounds.
+fn draw_selection_highlight(
+    mut gizmos: Gizmos,
+    selection: Res<InspectorSelection>,
+    tiles: Query<(&TilePos, &Parent)>,
+    chunks: Query<&Chunk>,
+    bounds: Query<(&GlobalTransform, Option<&Aabb>)>,
+) {
+    let Some(entity) = selection.0 else {
+        return;
+    };
+    if let Ok((tile_pos, parent)) = tiles.get(entity) {
+        if let Ok(chunk) = chunks.get(parent.get()) {
+            let center = chunk.get_world_coords() + tile_pos.get_in_chunk_pos() + TILE_LENGTH * 0.5;
+            gizmos.rect_2d(center, 0.0, Vec2::splat(TILE_LENGTH), FUCHSIA);
+        }
+        return;
+    }
+    if let Ok(chunk) = chunks.get(entity) {
+        gizmos.rect_2d(chunk.get_world_coords() + chunk.get_size() * 0.5, 0.0, chunk.get_size(), FUCHSIA);
+        return;
+    }
+    if let Ok((transform, aabb)) = bounds.get(entity) {
+        let (center, size) = aabb.map_or(
+            (transform.translation().xy(), Vec2::splat(FALLBACK_HIGHLIGHT_SIZE)),
+            |aabb| {
+                let center = transform.transform_point(aabb.center.into()).xy();
+                (center, aabb.half_extents.xy() * 2.0 * transform.compute_transform().scale.xy())
+            },
+        );
+        gizmos.rect_2d(center, 0.0, size, FUCHSIA);
+    }
+}
+
+/// Read-only summary of a selected tile, the components of the selection below it.
+fn selection_window(world: &mut World) {
+    let Some(entity) = world.resource::<InspectorSelection>().0 else {
+        return;
+    };
+    let Ok(egui_context) = world
+        .query_filtered::<&mut EguiContext, With<PrimaryWindow>>()
+        .get_single(world)
+    else {
+        return;
+    };
+    let mut egui_context = egui_context.clone();
+    let tile_summary = tile_summary(world, entity);
+
+    egui::Window::new("Selection")
+        .default_pos((10.0, 300.0))
+        .show(egui_context.get_mut(), |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                if let Some(lines) = tile_summary {
+                    for line in lines {
+                        ui.label(line);
+                    }
+                    ui.separator();
+                }
+                bevy_inspector::ui_for_entity(world, entity, ui);
+            });
+        });
+}
+
+/// Ground, chunk and pending prediction of a tile entity, `None` for anything else.
+fn tile_summary(world: &World, entity: Entity) -> Option<Vec<String>> {
+    let tile = world.get_entity(entity)?;
+    let tile_pos = *tile.get::<TilePos>()?;
+    let chunk = world.get_entity(tile.get::<Parent>()?.get())?;
+    let chunk_index = chunk.get::<Chunk>()?.chunk_index;
+    let ground = chunk
+        .get::<ChunkTiles>()
+        .and_then(|tiles| tiles.get(&tile_pos))
+        .map_or_else(|| "-".to_string(), |ground| format!("{ground:?}"));
+    let pending = tile.contains::<PredictedTileChange>();
+    Some(vec![
+        format!("Ground: {ground}"),
+        format!("Chunk: {} / {}", chunk_index.x, chunk_index.y),
+        format!("TilePos: {} / {}", tile_pos.x, tile_pos.y),
+        format!("Unconfirmed prediction: {pending}"),
+    ])
+}
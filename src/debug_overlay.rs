@@ -0,0 +1,275 @@
+use std::collections::BTreeMap;
+
+use bevy::{
+    diagnostic::{
+        DiagnosticsStore, EntityCountDiagnosticsPlugin, FrameTimeDiagnosticsPlugin,
+    },
+    prelude::*,
+};
+use bevy_mod_picking::{debug::DebugPickingMode, picking_core::Pickable};
+use bevy_rapier2d::render::DebugRenderContext;
+use bevy_replicon::{
+    core::ClientId,
+    prelude::{client_connected, RepliconClient, RepliconTick},
+};
+use bevy_replicon_renet::renet::RenetClient;
+use bevy_replicon_snap::NetworkOwner;
+
+use crate::{
+    camera::{CursorTile, CursorWorldPos},
+    chunk::{Chunk, ComputeTask, CorruptChunks},
+    player::Player,
+    world::world_pos_to_chunk_tile,
+    ActionEvent,
+};
+
+pub struct DebugOverlayPlugin;
+
+impl Plugin for DebugOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<FrameTimeDiagnosticsPlugin>() {
+            app.add_plugins(FrameTimeDiagnosticsPlugin);
+        }
+        if !app.is_plugin_added::<EntityCountDiagnosticsPlugin>() {
+            app.add_plugins(EntityCountDiagnosticsPlugin);
+        }
+        app.init_resource::<DebugOverlayState>()
+            .init_resource::<DebugFlags>()
+            .register_type::<DebugFlags>()
+            .insert_resource(TickTime::default())
+            .add_systems(Startup, setup_debug_overlay)
+            .add_systems(Update, (toggle_debug_overlay, toggle_debug_flags))
+            .add_systems(Update, sync_debug_flags.run_if(resource_changed::<DebugFlags>))
+            .add_systems(
+                Update,
+                update_tick_time.run_if(resource_changed::<RepliconTick>),
+            )
+            .add_systems(
+                PostUpdate,
+                (
+                    (collect_general_lines, collect_player_lines.run_if(client_connected)),
+                    render_debug_overlay,
+                )
+                    .chain()
+                    .run_if(overlay_visible),
+            );
+    }
+}
+
+/// Lines shown in the F3 overlay. Any system can register its own line with [`DebugOverlayState::set_line`].
+#[derive(Resource, Debug, Default)]
+pub struct DebugOverlayState {
+    pub visible: bool,
+    lines: BTreeMap<&'static str, String>,
+}
+
+impl DebugOverlayState {
+    pub fn set_line(&mut self, key: &'static str, value: impl Into<String>) {
+        self.lines.insert(key, value.into());
+    }
+
+    pub fn remove_line(&mut self, key: &'static str) {
+        self.lines.remove(key);
+    }
+}
+
+/// Runtime switches for the debug visualisations. Everything is off by default and can be
+/// flipped with Alt + key chords.
+#[derive(Resource, Debug, Default, Reflect)]
+#[reflect(Resource)]
+pub struct DebugFlags {
+    /// Alt + C
+    pub chunk_borders: bool,
+    /// Alt + T
+    pub tile_borders: bool,
+    /// Alt + V
+    pub camera_view: bool,
+    /// Alt + P
+    pub physics: bool,
+    /// Alt + K
+    pub picking: bool,
+    /// Alt + G, tints every other chunk so seams between chunk tilemaps stand out
+    pub chunk_checker: bool,
+    /// Alt + I, Alt + click selects entities for the inspector, see [`crate::debug_inspector`]
+    pub inspector: bool,
+}
+
+/// Run condition that checks a single debug flag, e.g. `debug_flag(|f| f.chunk_borders)`.
+pub fn debug_flag(flag: fn(&DebugFlags) -> bool) -> impl Fn(Res<DebugFlags>) -> bool + Clone {
+    move |flags: Res<DebugFlags>| flag(&flags)
+}
+
+/// Time between the last two received server ticks.
+#[derive(Resource, Debug, Default)]
+struct TickTime {
+    last_tick_at: Option<f32>,
+    delta: f32,
+}
+
+#[derive(Component, Debug)]
+struct DebugOverlayText;
+
+pub fn overlay_visible(state: Res<DebugOverlayState>) -> bool {
+    state.visible
+}
+
+fn setup_debug_overlay(mut commands: Commands) {
+    commands.spawn((
+        Name::new("Debug Overlay"),
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 16.0,
+                color: Color::WHITE,
+                ..Default::default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(5.0),
+            left: Val::Px(5.0),
+            ..Default::default()
+        })
+        .with_background_color(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+        ZIndex::Global(i32::MAX),
+        Visibility::Hidden,
+        Pickable::IGNORE,
+        DebugOverlayText,
+    ));
+}
+
+fn toggle_debug_overlay(
+    mut event_reader: EventReader<ActionEvent>,
+    mut state: ResMut<DebugOverlayState>,
+    mut text_query: Query<&mut Visibility, With<DebugOverlayText>>,
+) {
+    for event in event_reader.read() {
+        if event.action != KeyCode::F3 {
+            continue;
+        }
+        state.visible = !state.visible;
+        for mut visibility in text_query.iter_mut() {
+            *visibility = if state.visible {
+                Visibility::Visible
+            } else {
+                Visibility::Hidden
+            };
+        }
+    }
+}
+
+fn toggle_debug_flags(input: Res<ButtonInput<KeyCode>>, mut flags: ResMut<DebugFlags>) {
+    if !input.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]) {
+        return;
+    }
+    for key in input.get_just_pressed() {
+        let flag = match key {
+            KeyCode::KeyC => &mut flags.chunk_borders,
+            KeyCode::KeyT => &mut flags.tile_borders,
+            KeyCode::KeyV => &mut flags.camera_view,
+            KeyCode::KeyP => &mut flags.physics,
+            KeyCode::KeyK => &mut flags.picking,
+            KeyCode::KeyG => &mut flags.chunk_checker,
+            KeyCode::KeyI => &mut flags.inspector,
+            _ => continue,
+        };
+        *flag = !*flag;
+        info!("Debug flags: {:?}", *flags);
+    }
+}
+
+fn sync_debug_flags(
+    flags: Res<DebugFlags>,
+    mut rapier_debug: ResMut<DebugRenderContext>,
+    mut picking_mode: ResMut<DebugPickingMode>,
+) {
+    rapier_debug.enabled = flags.physics;
+    *picking_mode = if flags.picking {
+        DebugPickingMode::Normal
+    } else {
+        DebugPickingMode::Disabled
+    };
+}
+
+fn update_tick_time(mut tick_time: ResMut<TickTime>, time: Res<Time>) {
+    let now = time.elapsed_seconds();
+    if let Some(last) = tick_time.last_tick_at {
+        tick_time.delta = now - last;
+    }
+    tick_time.last_tick_at = Some(now);
+}
+
+fn collect_general_lines(
+    mut state: ResMut<DebugOverlayState>,
+    diagnostics: Res<DiagnosticsStore>,
+    chunk_query: Query<(), With<Chunk>>,
+    task_query: Query<(), With<ComputeTask>>,
+    corrupt_chunks: Res<CorruptChunks>,
+    tick_time: Res<TickTime>,
+    client: Option<Res<RenetClient>>,
+    cursor_pos: Res<CursorWorldPos>,
+    cursor_tile: Res<CursorTile>,
+) {
+    let fps = diagnostics
+        .get(&FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(|fps| fps.smoothed())
+        .unwrap_or_default();
+    let entities = diagnostics
+        .get(&EntityCountDiagnosticsPlugin::ENTITY_COUNT)
+        .and_then(|count| count.value())
+        .unwrap_or_default();
+    state.set_line("00 fps", format!("FPS: {fps:.0}"));
+    state.set_line("01 entities", format!("Entities: {entities:.0}"));
+    state.set_line("02 chunks", format!("Loaded chunks: {}", chunk_query.iter().count()));
+    state.set_line("03 tasks", format!("Chunk tasks: {}", task_query.iter().count()));
+    if corrupt_chunks.0 > 0 {
+        state.set_line("03 corrupt", format!("Regenerated corrupt chunks: {}", corrupt_chunks.0));
+    }
+    if let Some(client) = client {
+        state.set_line("04 rtt", format!("RTT: {:.0} ms", client.rtt() * 1000.0));
+    }
+    state.set_line("05 tick", format!("Server tick delta: {:.0} ms", tick_time.delta * 1000.0));
+    match cursor_pos.0 {
+        Some(pos) => state.set_line("06 cursor", format!("Cursor: {:.1} / {:.1}", pos.x, pos.y)),
+        None => state.set_line("06 cursor", "Cursor: -"),
+    }
+    match cursor_tile.0 {
+        Some((chunk, tile)) => state.set_line(
+            "07 cursor tile",
+            format!("Cursor tile: {} / {} in chunk {} / {}", tile.x, tile.y, chunk.x, chunk.y),
+        ),
+        None => state.remove_line("07 cursor tile"),
+    }
+}
+
+fn collect_player_lines(
+    mut state: ResMut<DebugOverlayState>,
+    player_query: Query<(&Transform, &NetworkOwner), With<Player>>,
+    client: Res<RepliconClient>,
+) {
+    let Some(client_id) = client.id() else {
+        return;
+    };
+    let Some((transform, _)) = player_query
+        .iter()
+        .find(|(_, owner)| ClientId::new(owner.0) == client_id)
+    else {
+        return;
+    };
+    let pos = transform.translation.xy();
+    state.set_line("10 position", format!("Position: {:.1} / {:.1}", pos.x, pos.y));
+    if let Some((chunk_index, tile)) = world_pos_to_chunk_tile(pos) {
+        state.set_line("11 chunk", format!("Chunk: {} / {}", chunk_index.x, chunk_index.y));
+        state.set_line("12 tile", format!("Tile: {} / {}", tile.x, tile.y));
+    }
+}
+
+fn render_debug_overlay(
+    state: Res<DebugOverlayState>,
+    mut text_query: Query<&mut Text, With<DebugOverlayText>>,
+) {
+    let content = state.lines.values().cloned().collect::<Vec<_>>().join("\n");
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value.clone_from(&content);
+    }
+}
@@ -0,0 +1,173 @@
+use bevy::prelude::*;
+use bevy_replicon::prelude::*;
+use bevy_replicon_snap::NetworkOwner;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    action::{ActionCooldownConfig, ActionCooldowns, GameAction},
+    loading_screen::GameState,
+    player::Player,
+    sign::editing_sign,
+    MoveEvent,
+};
+
+/// First atlas indices of the emote rows.
+const WAVE_INDEX: usize = 32;
+const SIT_INDEX: usize = 40;
+const POINT_INDEX: usize = 48;
+pub const EMOTE_FRAMES: usize = 4;
+
+pub struct EmotePlugin;
+
+impl Plugin for EmotePlugin {
+    fn build(&self, app: &mut App) {
+        app.replicate::<ActiveEmote>()
+            .add_client_event::<EmoteEvent>(ChannelKind::Ordered)
+            .add_systems(
+                Update,
+                (start_emotes, cancel_moving_emotes, expire_emotes).run_if(server_running),
+            )
+            .add_systems(
+                Update,
+                send_emotes
+                    .run_if(client_connected)
+                    .run_if(in_state(GameState::InGame))
+                    .run_if(not(editing_sign)),
+            );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Emote {
+    Wave,
+    Sit,
+    Point,
+}
+
+impl Emote {
+    fn from_key(key: KeyCode) -> Option<Emote> {
+        match key {
+            KeyCode::Digit1 => Some(Emote::Wave),
+            KeyCode::Digit2 => Some(Emote::Sit),
+            KeyCode::Digit3 => Some(Emote::Point),
+            _ => None,
+        }
+    }
+
+    pub fn atlas_index(&self) -> usize {
+        match self {
+            Emote::Wave => WAVE_INDEX,
+            Emote::Sit => SIT_INDEX,
+            Emote::Point => POINT_INDEX,
+        }
+    }
+
+    /// Seconds the emote plays unless the player moves.
+    fn duration(&self) -> f32 {
+        match self {
+            Emote::Wave => 2.0,
+            Emote::Sit => 10.0,
+            Emote::Point => 2.0,
+        }
+    }
+}
+
+/// Asks the server to play an emote on the sender's player.
+#[derive(Event, Debug, Clone, Serialize, Deserialize)]
+pub struct EmoteEvent {
+    pub emote: Emote,
+}
+
+/// Emote a player is currently playing, removed by the server once it ran out or the player moved.
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+pub struct ActiveEmote {
+    pub emote: Emote,
+    /// Seconds left, counted down on the server only.
+    remaining: f32,
+}
+
+fn send_emotes(
+    input: Res<ButtonInput<KeyCode>>,
+    mut emote_events: EventWriter<EmoteEvent>,
+    mut player_query: Query<(&NetworkOwner, &mut ActionCooldowns), With<Player>>,
+    client: Res<RepliconClient>,
+    cooldown_config: Res<ActionCooldownConfig>,
+    time: Res<Time>,
+) {
+    let Some(emote) = input.get_just_pressed().find_map(|key| Emote::from_key(*key)) else {
+        return;
+    };
+    let now = time.elapsed_seconds();
+    let local_cooldowns = client.id().and_then(|client_id| {
+        player_query
+            .iter_mut()
+            .find(|(owner, _)| owner.0 == client_id.get())
+            .map(|(_, cooldowns)| cooldowns)
+    });
+    if let Some(mut cooldowns) = local_cooldowns {
+        // don't send emotes the server would reject anyway
+        if !cooldowns.ready(GameAction::Emote, now, &cooldown_config) {
+            return;
+        }
+        cooldowns.trigger(GameAction::Emote, now);
+    }
+    emote_events.send(EmoteEvent { emote });
+}
+
+fn start_emotes(
+    mut commands: Commands,
+    mut events: EventReader<FromClient<EmoteEvent>>,
+    mut player_query: Query<(Entity, &NetworkOwner, &mut ActionCooldowns), With<Player>>,
+    cooldown_config: Res<ActionCooldownConfig>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds();
+    for FromClient { client_id, event } in events.read() {
+        let Some((entity, _, mut cooldowns)) = player_query
+            .iter_mut()
+            .find(|(_, owner, _)| owner.0 == client_id.get())
+        else {
+            continue;
+        };
+        if !cooldowns.ready(GameAction::Emote, now, &cooldown_config) {
+            continue;
+        }
+        cooldowns.trigger(GameAction::Emote, now);
+        commands.entity(entity).insert(ActiveEmote {
+            emote: event.emote,
+            remaining: event.emote.duration(),
+        });
+    }
+}
+
+fn cancel_moving_emotes(
+    mut commands: Commands,
+    mut events: EventReader<FromClient<MoveEvent>>,
+    player_query: Query<(Entity, &NetworkOwner), With<ActiveEmote>>,
+) {
+    for FromClient { client_id, event } in events.read() {
+        if event.input == Vec2::ZERO {
+            continue;
+        }
+        if let Some((entity, _)) = player_query
+            .iter()
+            .find(|(_, owner)| owner.0 == client_id.get())
+        {
+            commands.entity(entity).remove::<ActiveEmote>();
+        }
+    }
+}
+
+fn expire_emotes(
+    mut commands: Commands,
+    mut player_query: Query<(Entity, &mut ActiveEmote)>,
+    time: Res<Time>,
+) {
+    for (entity, mut emote) in player_query.iter_mut() {
+        // clients only need to know which emote plays, not the countdown
+        emote.bypass_change_detection().remaining -= time.delta_seconds();
+        if emote.remaining <= 0.0 {
+            commands.entity(entity).remove::<ActiveEmote>();
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use bevy::prelude::*;
+use bevy_replicon::prelude::*;
+use bevy_replicon_snap::NetworkOwner;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game_assets::GameAssets,
+    item::Item,
+    item_container::ItemContainer,
+    loading_screen::GameState,
+    player::{Facing, Player},
+    sign::editing_sign,
+};
+
+/// The first row of the inventory doubles as the hotbar.
+pub const HOTBAR_SLOTS: usize = 8;
+/// Number keys are taken by emotes, the hotbar is cycled instead.
+const CYCLE_HOTBAR_KEY: KeyCode = KeyCode::KeyQ;
+const HELD_ITEM_OFFSET: Vec3 = Vec3::new(8.0, -6.0, 0.1);
+const HELD_ITEM_SIZE: f32 = 12.0;
+
+pub struct EquipmentPlugin;
+
+impl Plugin for EquipmentPlugin {
+    fn build(&self, app: &mut App) {
+        app.replicate::<Equipment>()
+            .replicate::<HotbarSlot>()
+            .add_client_event::<SelectHotbarEvent>(ChannelKind::Ordered)
+            .add_systems(
+                Update,
+                (select_hotbar_slots, update_held_items).chain().run_if(server_running),
+            )
+            .add_systems(
+                Update,
+                send_hotbar_selection
+                    .run_if(client_connected)
+                    .run_if(in_state(GameState::InGame))
+                    .run_if(not(editing_sign)),
+            )
+            .add_systems(Update, (add_held_item_sprites, update_held_item_sprites).chain());
+    }
+}
+
+/// Items a player has equipped. Only the server writes it, the hand follows the selected hotbar
+/// slot while head and body are reserved for armor.
+#[derive(Component, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Equipment {
+    pub hand: Option<Item>,
+    pub head: Option<Item>,
+    pub body: Option<Item>,
+}
+
+/// Inventory slot of the hotbar the player holds in hand.
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HotbarSlot(pub usize);
+
+/// Asks the server to hold the item of the given hotbar slot.
+#[derive(Event, Debug, Clone, Serialize, Deserialize)]
+pub struct SelectHotbarEvent {
+    pub slot: usize,
+}
+
+/// Sprite of the held item, child of the player.
+#[derive(Component, Debug)]
+struct HeldItemSprite;
+
+fn send_hotbar_selection(
+    input: Res<ButtonInput<KeyCode>>,
+    mut events: EventWriter<SelectHotbarEvent>,
+    players: Query<(&NetworkOwner, &HotbarSlot), With<Player>>,
+    client: Res<RepliconClient>,
+) {
+    if !input.just_pressed(CYCLE_HOTBAR_KEY) {
+        return;
+    }
+    let Some(current) = client.id().and_then(|client_id| {
+        players
+            .iter()
+            .find(|(owner, _)| owner.0 == client_id.get())
+            .map(|(_, slot)| slot.0)
+    }) else {
+        return;
+    };
+    let step = if input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        HOTBAR_SLOTS - 1
+    } else {
+        1
+    };
+    events.send(SelectHotbarEvent {
+        slot: (current + step) % HOTBAR_SLOTS,
+    });
+}
+
+fn select_hotbar_slots(
+    mut events: EventReader<FromClient<SelectHotbarEvent>>,
+    mut players: Query<(&NetworkOwner, &mut HotbarSlot), With<Player>>,
+) {
+    for FromClient { client_id, event } in events.read() {
+        if event.slot >= HOTBAR_SLOTS {
+            continue;
+        }
+        if let Some((_, mut slot)) = players
+            .iter_mut()
+            .find(|(owner, _)| owner.0 == client_id.get())
+        {
+            slot.set_if_neq(HotbarSlot(event.slot));
+        }
+    }
+}
+
+/// Keeps the hand in sync with the item in the selected hotbar slot.
+fn update_held_items(
+    mut players: Query<
+        (&HotbarSlot, &ItemContainer, &mut Equipment),
+        Or<(Changed<HotbarSlot>, Changed<ItemContainer>)>,
+    >,
+) {
+    for (slot, inventory, mut equipment) in players.iter_mut() {
+        let held = inventory.get(slot.0);
+        if held.map(|item| &item.id) != equipment.hand.as_ref().map(|item| &item.id) {
+            equipment.hand = held.cloned();
+        }
+    }
+}
+
+fn add_held_item_sprites(
+    mut commands: Commands,
+    players: Query<Entity, (With<Equipment>, Added<Sprite>)>,
+    game_assets: Res<GameAssets>,
+) {
+    for entity in players.iter() {
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn((
+                Name::new("Held Item"),
+                HeldItemSprite,
+                SpriteBundle {
+                    sprite: Sprite {
+                        custom_size: Some(Vec2::splat(HELD_ITEM_SIZE)),
+                        ..Default::default()
+                    },
+                    texture: game_assets.item_texture.clone(),
+                    transform: Transform::from_translation(HELD_ITEM_OFFSET),
+                    visibility: Visibility::Hidden,
+                    ..Default::default()
+                },
+                TextureAtlas {
+                    layout: game_assets.item_layout.clone(),
+                    index: 0,
+                },
+            ));
+        });
+    }
+}
+
+/// Shows the held item on the side the player faces.
+fn update_held_item_sprites(
+    players: Query<
+        (&Equipment, &Facing, &Children),
+        Or<(Changed<Equipment>, Changed<Facing>, Changed<Children>)>,
+    >,
+    mut sprites: Query<
+        (&mut TextureAtlas, &mut Sprite, &mut Transform, &mut Visibility),
+        With<HeldItemSprite>,
+    >,
+) {
+    for (equipment, facing, children) in players.iter() {
+        let mut held = sprites.iter_many_mut(children);
+        while let Some((mut atlas, mut sprite, mut transform, mut visibility)) = held.fetch_next() {
+            let Some(item) = &equipment.hand else {
+                *visibility = Visibility::Hidden;
+                continue;
+            };
+            *visibility = Visibility::Inherited;
+            atlas.index = item.texture_index;
+            let left = facing.0.x < 0.0;
+            sprite.flip_x = left;
+            transform.translation.x = if left { -HELD_ITEM_OFFSET.x } else { HELD_ITEM_OFFSET.x };
+        }
+    }
+}
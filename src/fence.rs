@@ -0,0 +1,334 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use bevy_replicon::{core::ClientId, prelude::*};
+use bevy_replicon_snap::NetworkOwner;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    action::{ActionCooldownConfig, ActionCooldowns, GameAction},
+    autotile::{connected_variant, neighbor_mask, rotate_mask, ConnectedVariant, NeighborUpdates, NEIGHBORS},
+    chunk::{ChunkIndexMap, ChunkTiles, TILE_LENGTH},
+    ground_registry::GroundRegistry,
+    inventory_ui::SelectedSlot,
+    item,
+    item_container::{notify_full, InventoryFullEvent, ItemContainer},
+    loading_screen::GameState,
+    player::{Facing, Player},
+    sign::{sign_components, EditSignEvent, SignText},
+    world::{global_tile_at, split_global_tile},
+    world_object::{spawn_world_object, validate_placement, ObjectRegistry, TileOccupancy, WorldObject},
+    ActionEvent,
+};
+
+const FENCE_COLOR: Color = Color::srgb(0.55, 0.36, 0.2);
+const POST_SIZE: f32 = 8.0;
+const RAIL_THICKNESS: f32 = 4.0;
+
+pub struct FencePlugin;
+
+impl Plugin for FencePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_client_event::<BuildEvent>(ChannelKind::Ordered)
+            .observe(notify_object_added)
+            .observe(notify_object_removed)
+            .add_systems(Update, update_connected_objects)
+            .add_systems(
+                Update,
+                send_build_events
+                    .run_if(client_connected)
+                    .run_if(in_state(GameState::InGame)),
+            )
+            .add_systems(
+                Update,
+                // a build uses up the UseItem press before it can drop anything
+                handle_build_events
+                    .run_if(has_authority)
+                    .before(item::apply_action),
+            );
+    }
+}
+
+/// Places the object of the item in `slot` of the sender's inventory on `tile`, or removes the
+/// object on `tile` and gives its item back. Only tiles next to the player are accepted.
+#[derive(Event, Debug, Clone, Serialize, Deserialize)]
+pub enum BuildEvent {
+    Place { tile: IVec2, slot: usize },
+    Remove { tile: IVec2 },
+}
+
+/// Current shape of a connected object like a fence.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnectedShape {
+    pub variant: ConnectedVariant,
+    /// Clockwise quarter turns of the variant.
+    pub rotation: u8,
+}
+
+impl ConnectedShape {
+    /// Connected sides of the rotated variant.
+    fn mask(&self) -> u8 {
+        rotate_mask(self.variant.base_mask(), self.rotation)
+    }
+}
+
+fn notify_object_added(
+    trigger: Trigger<OnAdd, WorldObject>,
+    objects: Query<&WorldObject>,
+    mut updates: ResMut<NeighborUpdates>,
+) {
+    if let Ok(object) = objects.get(trigger.entity()) {
+        object.tiles().for_each(|tile| updates.notify(tile));
+    }
+}
+
+fn notify_object_removed(
+    trigger: Trigger<OnRemove, WorldObject>,
+    objects: Query<&WorldObject>,
+    mut updates: ResMut<NeighborUpdates>,
+) {
+    if let Ok(object) = objects.get(trigger.entity()) {
+        object.tiles().for_each(|tile| updates.notify(tile));
+    }
+}
+
+/// Picks the variant of every connected object next to a change and rebuilds its rails and
+/// collider. Runs on server and clients since colliders and visuals are both local.
+fn update_connected_objects(
+    mut commands: Commands,
+    mut updates: ResMut<NeighborUpdates>,
+    mut shapes: Query<(&WorldObject, Option<&ConnectedShape>, &mut Sprite)>,
+    objects: Query<&WorldObject>,
+    occupancy: Res<TileOccupancy>,
+    registry: Res<ObjectRegistry>,
+) {
+    let mut postponed = Vec::new();
+    for tile in updates.drain() {
+        let Some(entity) = occupancy.get(tile) else {
+            continue;
+        };
+        let Ok((object, current, mut sprite)) = shapes.get_mut(entity) else {
+            // not initialized yet, try again next frame
+            if objects.contains(entity) {
+                postponed.push(tile);
+            }
+            continue;
+        };
+        if !registry.get(&object.kind).is_some_and(|def| def.connected) {
+            continue;
+        }
+        let mask = neighbor_mask(tile, |neighbor| {
+            occupancy
+                .get(neighbor)
+                .and_then(|neighbor| objects.get(neighbor).ok())
+                .is_some_and(|neighbor| neighbor.kind == object.kind)
+        });
+        let (variant, rotation) = connected_variant(mask);
+        let shape = ConnectedShape { variant, rotation };
+        if current == Some(&shape) {
+            continue;
+        }
+
+        sprite.color = FENCE_COLOR;
+        sprite.custom_size = Some(Vec2::splat(POST_SIZE));
+        let rails: Vec<(Vec2, Vec2)> = NEIGHBORS
+            .iter()
+            .filter(|(bit, _)| shape.mask() & bit != 0)
+            .map(|(_, offset)| {
+                let direction = offset.as_vec2();
+                let size = if direction.x != 0.0 {
+                    Vec2::new(TILE_LENGTH * 0.5, RAIL_THICKNESS)
+                } else {
+                    Vec2::new(RAIL_THICKNESS, TILE_LENGTH * 0.5)
+                };
+                (direction * TILE_LENGTH * 0.25, size)
+            })
+            .collect();
+        let colliders = std::iter::once((Vec2::ZERO, 0.0, Collider::cuboid(POST_SIZE * 0.5, POST_SIZE * 0.5)))
+            .chain(
+                rails
+                    .iter()
+                    .map(|(offset, size)| (*offset, 0.0, Collider::cuboid(size.x * 0.5, size.y * 0.5))),
+            )
+            .collect();
+
+        commands
+            .entity(entity)
+            .despawn_descendants()
+            .insert((shape, Collider::compound(colliders)))
+            .with_children(|parent| {
+                for (offset, size) in rails {
+                    parent.spawn(SpriteBundle {
+                        sprite: Sprite {
+                            color: FENCE_COLOR,
+                            custom_size: Some(size),
+                            ..Default::default()
+                        },
+                        transform: Transform::from_translation(offset.extend(-0.01)),
+                        ..Default::default()
+                    });
+                }
+            });
+    }
+    for tile in postponed {
+        updates.notify(tile);
+    }
+}
+
+/// Tile next to the player in the cardinal direction closest to its Facing.
+pub fn facing_tile(position: Vec2, facing: Vec2) -> IVec2 {
+    let step = if facing.x.abs() >= facing.y.abs() {
+        IVec2::new(facing.x.signum() as i32, 0)
+    } else {
+        IVec2::new(0, facing.y.signum() as i32)
+    };
+    global_tile_at(position) + step
+}
+
+fn is_adjacent(player_tile: IVec2, tile: IVec2) -> bool {
+    (tile - player_tile).abs().max_element() == 1
+}
+
+/// UseItem removes the object in front of the player, or places the selected item if it builds one.
+fn send_build_events(
+    mut action_events: EventReader<ActionEvent>,
+    mut build_events: EventWriter<BuildEvent>,
+    players: Query<(Entity, &NetworkOwner, &Transform, &Facing), With<Player>>,
+    containers: Query<&ItemContainer>,
+    objects: Query<&WorldObject>,
+    occupancy: Res<TileOccupancy>,
+    registry: Res<ObjectRegistry>,
+    selected: Res<SelectedSlot>,
+    client: Res<RepliconClient>,
+) {
+    let used = action_events
+        .read()
+        .any(|event| GameAction::from_key(event.action).contains(&GameAction::UseItem));
+    if !used {
+        return;
+    }
+    let Some((player, _, transform, facing)) = client.id().and_then(|client_id| {
+        players
+            .iter()
+            .find(|(_, owner, ..)| ClientId::new(owner.0) == client_id)
+    }) else {
+        return;
+    };
+    let tile = facing_tile(transform.translation.xy(), facing.0);
+
+    let removable = occupancy
+        .get(tile)
+        .and_then(|entity| objects.get(entity).ok())
+        .and_then(|object| registry.get(&object.kind))
+        .is_some_and(|def| def.item.is_some());
+    if removable {
+        build_events.send(BuildEvent::Remove { tile });
+        return;
+    }
+    let Some((container, slot)) = selected.0.filter(|(container, _)| *container == player) else {
+        return;
+    };
+    let placeable = containers
+        .get(container)
+        .ok()
+        .and_then(|container| container.get(slot))
+        .is_some_and(|item| registry.kind_for_item(&item.id).is_some());
+    if placeable {
+        build_events.send(BuildEvent::Place { tile, slot });
+    }
+}
+
+fn handle_build_events(
+    mut commands: Commands,
+    mut events: EventReader<FromClient<BuildEvent>>,
+    mut edit_events: EventWriter<ToClients<EditSignEvent>>,
+    mut full_events: EventWriter<ToClients<InventoryFullEvent>>,
+    mut players: Query<
+        (&NetworkOwner, &Transform, &mut ItemContainer, &mut ActionCooldowns),
+        With<Player>,
+    >,
+    objects: Query<&WorldObject>,
+    object_containers: Query<&ItemContainer, Without<Player>>,
+    chunk_tiles: Query<&ChunkTiles>,
+    chunk_map: Res<ChunkIndexMap>,
+    occupancy: Res<TileOccupancy>,
+    registry: Res<ObjectRegistry>,
+    grounds: Res<GroundRegistry>,
+    cooldown_config: Res<ActionCooldownConfig>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds();
+    for FromClient { client_id, event } in events.read() {
+        let Some((_, transform, mut container, mut cooldowns)) = players
+            .iter_mut()
+            .find(|(owner, ..)| owner.0 == client_id.get())
+        else {
+            continue;
+        };
+        if !cooldowns.ready(GameAction::UseItem, now, &cooldown_config) {
+            continue;
+        }
+        let player_tile = global_tile_at(transform.translation.xy());
+        match *event {
+            BuildEvent::Place { tile, slot } => {
+                if !is_adjacent(player_tile, tile) {
+                    continue;
+                }
+                let Some(kind) = container
+                    .get(slot)
+                    .and_then(|item| registry.kind_for_item(&item.id))
+                else {
+                    continue;
+                };
+                let footprint = registry.footprint(kind);
+                if let Err(err) =
+                    validate_placement(tile, footprint, &occupancy, &chunk_map, &chunk_tiles, &grounds)
+                {
+                    debug!("{client_id:?} can't place {kind} at {tile}: {err:?}");
+                    continue;
+                }
+                let (chunk_index, tile_pos) = split_global_tile(tile);
+                let ground_allowed = chunk_map
+                    .get(chunk_index)
+                    .and_then(|chunk| chunk_tiles.get(chunk).ok())
+                    .and_then(|tiles| tiles.get(&tile_pos))
+                    .zip(registry.get(kind))
+                    .is_some_and(|(ground, def)| def.allows_ground(ground));
+                if !ground_allowed {
+                    debug!("{client_id:?} can't place {kind} on the ground at {tile}");
+                    continue;
+                }
+                let entity = spawn_world_object(&mut commands, kind.to_string(), tile, footprint);
+                container.take_from_slot(slot, 1);
+                cooldowns.trigger(GameAction::UseItem, now);
+                if registry.get(kind).is_some_and(|def| def.writable) {
+                    commands.entity(entity).insert(sign_components(SignText::new(*client_id)));
+                    edit_events.send(ToClients {
+                        mode: SendMode::Direct(*client_id),
+                        event: EditSignEvent { sign: entity },
+                    });
+                }
+            }
+            BuildEvent::Remove { tile } => {
+                if !is_adjacent(player_tile, tile) {
+                    continue;
+                }
+                let Some((entity, item)) = occupancy.get(tile).and_then(|entity| {
+                    let object = objects.get(entity).ok()?;
+                    Some((entity, registry.get(&object.kind)?.item.clone()?))
+                }) else {
+                    continue;
+                };
+                if object_containers.get(entity).is_ok_and(|stored| !stored.is_empty()) {
+                    debug!("{client_id:?} can't remove a container that still holds items");
+                    continue;
+                }
+                if container.insert(item).is_err() {
+                    notify_full(&mut full_events, *client_id);
+                    continue;
+                }
+                commands.entity(entity).despawn_recursive();
+                cooldowns.trigger(GameAction::UseItem, now);
+            }
+        }
+    }
+}
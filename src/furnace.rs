@@ -0,0 +1,165 @@
+use bevy::prelude::*;
+use bevy_replicon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    interact::{InteractKind, Interactable},
+    item::Item,
+    item_container::ItemContainer,
+    item_registry::ItemRegistry,
+    world_object::{ObjectRegistry, WorldObject},
+};
+
+pub const INPUT_SLOT: usize = 0;
+pub const FUEL_SLOT: usize = 1;
+pub const OUTPUT_SLOT: usize = 2;
+const FURNACE_SLOTS: usize = 3;
+/// Progress is only replicated when it crosses one of this many steps, clients don't need every
+/// frame of it for the bar.
+const PROGRESS_STEPS: f32 = 20.0;
+const BURNING_COLOR: Color = Color::srgb(1.0, 0.75, 0.5);
+
+pub struct FurnacePlugin;
+
+impl Plugin for FurnacePlugin {
+    fn build(&self, app: &mut App) {
+        app.replicate::<Furnace>()
+            .register_type::<FurnaceSettings>()
+            .init_resource::<FurnaceSettings>()
+            .add_systems(
+                Update,
+                (init_furnaces, run_furnaces).chain().run_if(server_running),
+            )
+            .add_systems(Update, update_furnace_sprites);
+    }
+}
+
+#[derive(Resource, Debug, Reflect)]
+#[reflect(Resource)]
+pub struct FurnaceSettings {
+    /// Seconds to smelt a single item.
+    pub smelt_time: f32,
+}
+
+impl Default for FurnaceSettings {
+    fn default() -> Self {
+        Self { smelt_time: 8.0 }
+    }
+}
+
+/// Smelting state of a furnace. Its [`ItemContainer`] has an input, a fuel and an output slot.
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Furnace {
+    /// Progress of the item being smelted, between 0 and 1.
+    pub progress: f32,
+    /// Seconds the current piece of fuel keeps burning.
+    pub burn_left: f32,
+}
+
+impl Furnace {
+    pub fn is_burning(&self) -> bool {
+        self.burn_left > 0.0
+    }
+}
+
+/// Save format of a furnace inside its chunk file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FurnaceData {
+    pub state: Furnace,
+    pub container: ItemContainer,
+}
+
+/// Components that turn a world object into a furnace.
+pub fn furnace_components(data: FurnaceData) -> impl Bundle {
+    (data.state, data.container, Interactable::new(InteractKind::Open, "object.furnace"))
+}
+
+/// Slot of a furnace the item goes into, if players may put it in at all. The output slot is
+/// filled by the furnace only.
+pub fn slot_for(item: &Item, registry: &ItemRegistry) -> Option<usize> {
+    if registry.smelt_result(&item.id).is_some() {
+        Some(INPUT_SLOT)
+    } else if registry.fuel_time(&item.id).is_some() {
+        Some(FUEL_SLOT)
+    } else {
+        None
+    }
+}
+
+/// Turns freshly placed furnace objects into furnaces. Loaded ones come with their state.
+fn init_furnaces(
+    mut commands: Commands,
+    objects: Query<(Entity, &WorldObject), (Added<WorldObject>, Without<Furnace>)>,
+    registry: Res<ObjectRegistry>,
+) {
+    for (entity, object) in objects.iter() {
+        if registry.get(&object.kind).is_some_and(|def| def.furnace) {
+            commands.entity(entity).insert(furnace_components(FurnaceData {
+                state: Furnace::default(),
+                container: ItemContainer::new(Vec::new(), FURNACE_SLOTS),
+            }));
+        }
+    }
+}
+
+/// Smelts the input while fuel burns. A new piece of fuel is only lit when there is something to
+/// smelt and room for the result, fuel that is already burning burns out regardless.
+fn run_furnaces(
+    mut furnaces: Query<(&mut Furnace, &mut ItemContainer)>,
+    registry: Res<ItemRegistry>,
+    settings: Res<FurnaceSettings>,
+    time: Res<Time>,
+) {
+    let delta = time.delta_seconds();
+    for (mut furnace, mut container) in furnaces.iter_mut() {
+        let product = container
+            .get(INPUT_SLOT)
+            .and_then(|input| registry.smelt_result(&input.id))
+            .and_then(|id| registry.create(id));
+        let fits = product.as_ref().is_some_and(|product| {
+            container
+                .get(OUTPUT_SLOT)
+                .map_or(true, |output| output.stacks_with(product) && output.space() > 0)
+        });
+        if !furnace.is_burning() && fits {
+            if let Some(burn_time) = container.get(FUEL_SLOT).and_then(|fuel| registry.fuel_time(&fuel.id)) {
+                container.take_from_slot(FUEL_SLOT, 1);
+                furnace.burn_left = burn_time;
+            }
+        }
+        if !furnace.is_burning() {
+            if furnace.progress != 0.0 {
+                furnace.progress = 0.0;
+            }
+            continue;
+        }
+        let was_burning = furnace.is_burning();
+        let step = (furnace.progress * PROGRESS_STEPS).floor();
+        let state = furnace.bypass_change_detection();
+        state.burn_left = (state.burn_left - delta).max(0.0);
+        state.progress = if fits {
+            state.progress + delta / settings.smelt_time.max(f32::EPSILON)
+        } else {
+            0.0
+        };
+        if (furnace.progress * PROGRESS_STEPS).floor() != step || furnace.is_burning() != was_burning {
+            furnace.set_changed();
+        }
+        let Some(product) = product.filter(|_| furnace.progress >= 1.0) else {
+            continue;
+        };
+        furnace.progress = 0.0;
+        container.take_from_slot(INPUT_SLOT, 1);
+        if container.insert_into(OUTPUT_SLOT, product).is_err() {
+            warn!("Furnace output was full although it had room");
+        }
+    }
+}
+
+fn update_furnace_sprites(
+    mut furnaces: Query<(&Furnace, &mut Sprite), Or<(Changed<Furnace>, Added<Sprite>)>>,
+) {
+    for (furnace, mut sprite) in furnaces.iter_mut() {
+        sprite.color = if furnace.is_burning() { BURNING_COLOR } else { Color::WHITE };
+    }
+}
@@ -0,0 +1,58 @@
+use bevy::{asset::LoadState, prelude::*};
+
+pub struct GameAssetsPlugin;
+
+impl Plugin for GameAssetsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PreStartup, load_game_assets);
+    }
+}
+
+/// Image and atlas layout handles shared by all systems that render game entities.
+#[derive(Resource, Debug, Clone)]
+pub struct GameAssets {
+    pub player_texture: Handle<Image>,
+    pub player_layout: Handle<TextureAtlasLayout>,
+    pub item_texture: Handle<Image>,
+    pub item_layout: Handle<TextureAtlasLayout>,
+    pub props_texture: Handle<Image>,
+    pub props_layout: Handle<TextureAtlasLayout>,
+    pub tileset_texture: Handle<Image>,
+}
+
+impl GameAssets {
+    pub fn all_loaded(&self, asset_server: &AssetServer) -> bool {
+        [
+            &self.player_texture,
+            &self.item_texture,
+            &self.props_texture,
+            &self.tileset_texture,
+        ]
+        .iter()
+        .all(|handle| matches!(asset_server.get_load_state(handle.id()), Some(LoadState::Loaded)))
+    }
+}
+
+fn load_game_assets(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+) {
+    let player_layout = TextureAtlasLayout::from_grid(UVec2::new(32, 32), 8, 10, None, None);
+    let item_layout = TextureAtlasLayout::from_grid(UVec2::splat(24), 16, 16, None, None);
+    let mut props_layout = TextureAtlasLayout::new_empty(UVec2::new(512, 512));
+    props_layout.add_texture(URect::new(162, 16, 190, 64));
+    props_layout.add_texture(URect::new(96, 160, 128, 192));
+    props_layout.add_texture(URect::new(288, 84, 352, 124));
+    props_layout.add_texture(URect::new(160, 150, 192, 192));
+
+    commands.insert_resource(GameAssets {
+        player_texture: asset_server.load("player/global.png"),
+        player_layout: texture_atlas_layouts.add(player_layout),
+        item_texture: asset_server.load("rpgitems.png"),
+        item_layout: texture_atlas_layouts.add(item_layout),
+        props_texture: asset_server.load("TX Props.png"),
+        props_layout: texture_atlas_layouts.add(props_layout),
+        tileset_texture: asset_server.load("TX Tileset Grass.png"),
+    });
+}
@@ -0,0 +1,62 @@
+use bevy::prelude::*;
+use bevy_replicon::prelude::*;
+
+/// Fraction of the day at which night begins. Night lasts until the day wraps around at dawn.
+const NIGHT_START: f32 = 0.7;
+
+pub struct GameTimePlugin;
+
+impl Plugin for GameTimePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<GameTime>()
+            .init_resource::<GameTime>()
+            .add_event::<TimeSkipped>()
+            .add_systems(First, advance_game_time.run_if(server_running));
+    }
+}
+
+/// In-game clock of the server, driving the day and night cycle.
+#[derive(Resource, Debug, Reflect)]
+#[reflect(Resource)]
+pub struct GameTime {
+    pub elapsed: f32,
+    /// Length of a full day in seconds.
+    pub day_length: f32,
+}
+
+impl Default for GameTime {
+    fn default() -> Self {
+        Self {
+            elapsed: 0.0,
+            day_length: 600.0,
+        }
+    }
+}
+
+impl GameTime {
+    /// Progress through the current day, 0.0 is dawn.
+    pub fn time_of_day(&self) -> f32 {
+        (self.elapsed / self.day_length).fract()
+    }
+
+    pub fn is_night(&self) -> bool {
+        self.time_of_day() >= NIGHT_START
+    }
+
+    /// Jumps forward to the next dawn and returns the skipped seconds.
+    pub fn skip_to_dawn(&mut self) -> f32 {
+        let skipped = (1.0 - self.time_of_day()) * self.day_length;
+        self.elapsed += skipped;
+        skipped
+    }
+}
+
+/// The server jumped forward in time, systems that count time themselves can catch up.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct TimeSkipped {
+    pub seconds: f32,
+}
+
+fn advance_game_time(mut game_time: ResMut<GameTime>, time: Res<Time>) {
+    game_time.elapsed += time.delta_seconds();
+}
@@ -0,0 +1,106 @@
+use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use crate::{ron_asset::RonAssetPlugin, world::Ground};
+
+/// Texture index used for grounds that have no definition, so missing entries stand out.
+pub const ERROR_TEXTURE_INDEX: u32 = 0;
+pub const ERROR_TILE_COLOR: Color = Color::srgb(1.0, 0.0, 1.0);
+
+const GROUNDS_PATH: &str = "data/grounds.ground.ron";
+
+pub struct GroundRegistryPlugin;
+
+impl Plugin for GroundRegistryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(RonAssetPlugin::<GroundDefinitions>::new(&["ground.ron"]))
+            .add_systems(PreStartup, load_ground_registry)
+            .add_systems(PreUpdate, update_ground_registry);
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct TextureRange {
+    pub start: u32,
+    pub count: u32,
+}
+
+/// Gameplay and rendering data of a single ground type.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroundDefinition {
+    pub textures: TextureRange,
+    pub speed_multiplier: f32,
+    pub footstep_sound: String,
+    pub buildable: bool,
+    pub tillable: bool,
+    /// Hostile mobs may spawn on this ground at night, see [`crate::mob`].
+    #[serde(default)]
+    pub hostile_spawns: bool,
+    /// Variants used instead of `textures` on fertile soil, see [`crate::soil`].
+    #[serde(default)]
+    pub fertile_textures: Option<TextureRange>,
+}
+
+impl GroundDefinition {
+    /// Picks one of the texture variants of this ground from a random number.
+    pub fn texture_index(&self, random: u32, fertile: bool) -> u32 {
+        let textures = match self.fertile_textures {
+            Some(fertile_textures) if fertile => fertile_textures,
+            _ => self.textures,
+        };
+        textures.start + random % textures.count.max(1)
+    }
+}
+
+#[derive(Asset, TypePath, Debug, Serialize, Deserialize)]
+pub struct GroundDefinitions {
+    pub grounds: HashMap<Ground, GroundDefinition>,
+}
+
+/// Ground definitions from the ground RON asset, kept up to date when the asset (re)loads.
+#[derive(Resource, Debug, Default)]
+pub struct GroundRegistry {
+    handle: Handle<GroundDefinitions>,
+    grounds: HashMap<Ground, GroundDefinition>,
+}
+
+impl GroundRegistry {
+    pub fn get(&self, ground: &Ground) -> Option<&GroundDefinition> {
+        self.grounds.get(ground)
+    }
+
+    pub fn speed_multiplier(&self, ground: &Ground) -> f32 {
+        self.get(ground).map_or(1.0, |def| def.speed_multiplier)
+    }
+
+    pub fn is_tillable(&self, ground: &Ground) -> bool {
+        self.get(ground).is_some_and(|def| def.tillable)
+    }
+
+    pub fn is_buildable(&self, ground: &Ground) -> bool {
+        self.get(ground).is_some_and(|def| def.buildable)
+    }
+}
+
+fn load_ground_registry(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(GroundRegistry {
+        handle: asset_server.load(GROUNDS_PATH),
+        grounds: HashMap::default(),
+    });
+}
+
+fn update_ground_registry(
+    mut events: EventReader<AssetEvent<GroundDefinitions>>,
+    mut registry: ResMut<GroundRegistry>,
+    definitions: Res<Assets<GroundDefinitions>>,
+) {
+    for event in events.read() {
+        if !event.is_loaded_with_dependencies(&registry.handle) && !event.is_modified(&registry.handle) {
+            continue;
+        }
+        if let Some(loaded) = definitions.get(&registry.handle) {
+            registry.grounds = loaded.grounds.clone();
+            info!("Loaded {} ground definitions", registry.grounds.len());
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use bevy::prelude::*;
+use bevy_replicon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chunk::{ChunkIndexMap, ChunkTiles},
+    water::WaterSettings,
+    world::is_walkable,
+    world_object::{TileOccupancy, WorldObject},
+};
+
+/// Knockback speeds below this are dropped.
+const MIN_KNOCKBACK_SPEED: f32 = 1.0;
+const KNOCKBACK_DAMPING: f32 = 10.0;
+/// Flashes per second of invulnerable sprites.
+const FLASH_RATE: f32 = 10.0;
+const FLASH_ALPHA: f32 = 0.3;
+
+pub struct HealthPlugin;
+
+impl Plugin for HealthPlugin {
+    fn build(&self, app: &mut App) {
+        app.replicate::<Health>()
+            .replicate::<Invulnerable>()
+            .register_type::<HealthSettings>()
+            .init_resource::<HealthSettings>()
+            .add_event::<DamageEvent>()
+            .add_event::<DeathEvent>()
+            .add_systems(
+                Update,
+                (apply_damage, expire_invulnerability, apply_knockback)
+                    .chain()
+                    .run_if(server_running),
+            )
+            .add_systems(Update, flash_invulnerable);
+    }
+}
+
+#[derive(Resource, Debug, Reflect)]
+#[reflect(Resource)]
+pub struct HealthSettings {
+    /// Seconds after a hit during which further damage is ignored.
+    pub invulnerable_time: f32,
+}
+
+impl Default for HealthSettings {
+    fn default() -> Self {
+        Self {
+            invulnerable_time: 0.5,
+        }
+    }
+}
+
+#[derive(Component, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+}
+
+impl Health {
+    pub fn new(max: f32) -> Self {
+        Self { current: max, max }
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0.0
+    }
+}
+
+/// Ignores damage for a short time after a hit. Clients flash the sprite meanwhile.
+#[derive(Component, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Invulnerable {
+    /// Seconds left, counted down on the server only.
+    remaining: f32,
+}
+
+/// Velocity pushing a hit entity away from its attacker, decays over time.
+#[derive(Component, Debug)]
+pub struct Knockback {
+    pub velocity: Vec2,
+}
+
+/// Server side request to damage an entity with [`Health`].
+#[derive(Event, Debug, Clone, Copy)]
+pub struct DamageEvent {
+    pub target: Entity,
+    pub amount: f32,
+    /// Position the hit came from and the initial speed it pushes the target away with.
+    pub knockback: Option<(Vec2, f32)>,
+}
+
+impl DamageEvent {
+    pub fn new(target: Entity, amount: f32) -> Self {
+        Self {
+            target,
+            amount,
+            knockback: None,
+        }
+    }
+
+    /// Pushes the target away from `source`. World objects stay where they are.
+    pub fn with_knockback(mut self, source: Vec2, speed: f32) -> Self {
+        self.knockback = Some((source, speed));
+        self
+    }
+}
+
+/// Sent by the server once an entity's health dropped to zero. What happens next is up to the
+/// systems owning that kind of entity.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct DeathEvent {
+    pub entity: Entity,
+}
+
+fn apply_damage(
+    mut commands: Commands,
+    mut damage_events: EventReader<DamageEvent>,
+    mut death_events: EventWriter<DeathEvent>,
+    mut health_query: Query<(&mut Health, &Transform, Has<Invulnerable>, Has<WorldObject>)>,
+    settings: Res<HealthSettings>,
+) {
+    for event in damage_events.read() {
+        let Ok((mut health, transform, invulnerable, is_object)) = health_query.get_mut(event.target) else {
+            continue;
+        };
+        // dead entities wait for their owner to handle the death
+        if health.is_dead() || invulnerable {
+            continue;
+        }
+        health.current = (health.current - event.amount).max(0.0);
+        if health.is_dead() {
+            death_events.send(DeathEvent {
+                entity: event.target,
+            });
+            continue;
+        }
+        let mut target = commands.entity(event.target);
+        target.insert(Invulnerable {
+            remaining: settings.invulnerable_time,
+        });
+        if let Some((source, speed)) = event.knockback.filter(|_| !is_object) {
+            let direction = (transform.translation.xy() - source)
+                .try_normalize()
+                .unwrap_or(Vec2::Y);
+            target.insert(Knockback {
+                velocity: direction * speed,
+            });
+        }
+    }
+}
+
+fn expire_invulnerability(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Invulnerable)>,
+    time: Res<Time>,
+) {
+    for (entity, mut invulnerable) in query.iter_mut() {
+        // clients only need to know that it is invulnerable, not for how long
+        let invulnerable = invulnerable.bypass_change_detection();
+        invulnerable.remaining -= time.delta_seconds();
+        if invulnerable.remaining <= 0.0 {
+            commands.entity(entity).remove::<Invulnerable>();
+        }
+    }
+}
+
+fn apply_knockback(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Transform, &mut Knockback)>,
+    occupancy: Res<TileOccupancy>,
+    water: Res<WaterSettings>,
+    chunk_tiles: Query<&ChunkTiles>,
+    chunk_map: Res<ChunkIndexMap>,
+    time: Res<Time>,
+) {
+    let delta = time.delta_seconds();
+    for (entity, mut transform, mut knockback) in query.iter_mut() {
+        let next = transform.translation.xy() + knockback.velocity * delta;
+        if is_walkable(next, &chunk_map, &chunk_tiles, &occupancy, &water) {
+            transform.translation = next.extend(transform.translation.z);
+        }
+        knockback.velocity *= (-KNOCKBACK_DAMPING * delta).exp();
+        if knockback.velocity.length() < MIN_KNOCKBACK_SPEED {
+            commands.entity(entity).remove::<Knockback>();
+        }
+    }
+}
+
+/// Blinks the sprites of invulnerable entities and restores them once the component is gone.
+fn flash_invulnerable(
+    mut flashing: Query<&mut Sprite, With<Invulnerable>>,
+    mut restored: Query<&mut Sprite, Without<Invulnerable>>,
+    mut removed: RemovedComponents<Invulnerable>,
+    time: Res<Time>,
+) {
+    let dimmed = (time.elapsed_seconds() * FLASH_RATE) as u32 % 2 == 0;
+    let alpha = if dimmed { FLASH_ALPHA } else { 1.0 };
+    for mut sprite in flashing.iter_mut() {
+        if sprite.color.alpha() != alpha {
+            sprite.color.set_alpha(alpha);
+        }
+    }
+    for entity in removed.read() {
+        if let Ok(mut sprite) = restored.get_mut(entity) {
+            sprite.color.set_alpha(1.0);
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use bevy::{prelude::*, sprite::Anchor};
+
+use crate::{chunk::TILE_LENGTH, health::Health};
+
+const BAR_WIDTH: f32 = 20.0;
+const BAR_HEIGHT: f32 = 3.0;
+/// Gap between the top of the sprite and the bar.
+const BAR_MARGIN: f32 = 4.0;
+const BAR_BACKGROUND: Color = Color::srgba(0.0, 0.0, 0.0, 0.7);
+const BAR_COLOR: Color = Color::srgb(0.8, 0.15, 0.15);
+/// Seconds a bar stays visible after its entity healed up completely.
+const HIDE_DELAY: f32 = 3.0;
+
+/// World space health bars above damaged entities, drawn from the replicated [`Health`].
+pub struct HealthBarPlugin;
+
+impl Plugin for HealthBarPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (add_health_bars, update_health_bars).chain());
+    }
+}
+
+/// Bar of an entity with [`Health`], its entities are children of that entity.
+#[derive(Component, Debug)]
+struct HealthBar {
+    root: Entity,
+    fill: Entity,
+    /// Time the entity reached full health, the bar hides a while later.
+    full_since: Option<f32>,
+}
+
+/// Adds a hidden bar to every entity with health once it got its sprite.
+fn add_health_bars(
+    mut commands: Commands,
+    entities: Query<(Entity, &Sprite), (With<Health>, Without<HealthBar>)>,
+) {
+    for (entity, sprite) in entities.iter() {
+        let height = sprite.custom_size.map_or(TILE_LENGTH, |size| size.y);
+        let mut fill = Entity::PLACEHOLDER;
+        let root = commands
+            .spawn((
+                Name::new("Health Bar"),
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: BAR_BACKGROUND,
+                        custom_size: Some(Vec2::new(BAR_WIDTH, BAR_HEIGHT)),
+                        ..Default::default()
+                    },
+                    transform: Transform::from_xyz(0.0, height * 0.5 + BAR_MARGIN, 0.2),
+                    visibility: Visibility::Hidden,
+                    ..Default::default()
+                },
+            ))
+            .with_children(|bar| {
+                fill = bar
+                    .spawn(SpriteBundle {
+                        sprite: Sprite {
+                            color: BAR_COLOR,
+                            custom_size: Some(Vec2::new(BAR_WIDTH, BAR_HEIGHT)),
+                            anchor: Anchor::CenterLeft,
+                            ..Default::default()
+                        },
+                        transform: Transform::from_xyz(-BAR_WIDTH * 0.5, 0.0, 0.01),
+                        ..Default::default()
+                    })
+                    .id();
+            })
+            .id();
+        commands.entity(entity).add_child(root).insert(HealthBar {
+            root,
+            fill,
+            full_since: None,
+        });
+    }
+}
+
+/// Shows the bar while health is below max and hides it a few seconds after it is full again.
+fn update_health_bars(
+    mut entities: Query<(Ref<Health>, &mut HealthBar)>,
+    mut roots: Query<&mut Visibility>,
+    mut fills: Query<&mut Transform>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds();
+    for (health, mut bar) in entities.iter_mut() {
+        if health.is_changed() {
+            let ratio = (health.current / health.max.max(f32::EPSILON)).clamp(0.0, 1.0);
+            if let Ok(mut transform) = fills.get_mut(bar.fill) {
+                transform.scale.x = ratio;
+            }
+            if ratio < 1.0 {
+                bar.full_since = None;
+                if let Ok(mut visibility) = roots.get_mut(bar.root) {
+                    visibility.set_if_neq(Visibility::Inherited);
+                }
+            } else if bar.full_since.is_none() {
+                bar.full_since = Some(now);
+            }
+        }
+        if bar.full_since.is_some_and(|since| now - since >= HIDE_DELAY) {
+            bar.full_since = None;
+            if let Ok(mut visibility) = roots.get_mut(bar.root) {
+                visibility.set_if_neq(Visibility::Hidden);
+            }
+        }
+    }
+}
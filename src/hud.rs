@@ -0,0 +1,125 @@
+use bevy::prelude::*;
+use bevy_mod_picking::picking_core::Pickable;
+use bevy_replicon::prelude::*;
+use bevy_replicon_snap::NetworkOwner;
+
+use crate::{hunger::Hunger, player::Player, stamina::Stamina};
+
+const BAR_WIDTH: f32 = 120.0;
+const BAR_HEIGHT: f32 = 10.0;
+const BAR_BACKGROUND: Color = Color::srgba(0.0, 0.0, 0.0, 0.6);
+const HUNGER_COLOR: Color = Color::srgb(0.85, 0.55, 0.2);
+const STAMINA_COLOR: Color = Color::srgb(0.3, 0.8, 0.35);
+
+/// Status bars of the local player in the bottom left corner.
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_hud)
+            .add_systems(Update, (update_hunger_bar, update_stamina_bar).run_if(client_connected));
+    }
+}
+
+/// Filled part of the hunger bar.
+#[derive(Component, Debug)]
+struct HungerBar;
+
+/// Filled part of the stamina bar.
+#[derive(Component, Debug)]
+struct StaminaBar;
+
+fn setup_hud(mut commands: Commands) {
+    commands
+        .spawn((
+            Name::new("HUD"),
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(8.0),
+                    left: Val::Px(8.0),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(4.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            Pickable::IGNORE,
+        ))
+        .with_children(|parent| {
+            spawn_bar(parent, HUNGER_COLOR, HungerBar);
+            spawn_bar(parent, STAMINA_COLOR, StaminaBar);
+        });
+}
+
+fn spawn_bar(parent: &mut ChildBuilder, color: Color, marker: impl Component) {
+    parent
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Px(BAR_WIDTH),
+                    height: Val::Px(BAR_HEIGHT),
+                    ..Default::default()
+                },
+                background_color: BAR_BACKGROUND.into(),
+                ..Default::default()
+            },
+            Pickable::IGNORE,
+        ))
+        .with_children(|bar| {
+            bar.spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        height: Val::Percent(100.0),
+                        ..Default::default()
+                    },
+                    background_color: color.into(),
+                    ..Default::default()
+                },
+                Pickable::IGNORE,
+                marker,
+            ));
+        });
+}
+
+/// Width of a bar filled to the given share.
+fn fill_width(current: f32, max: f32) -> Val {
+    Val::Percent((current / max.max(f32::EPSILON)).clamp(0.0, 1.0) * 100.0)
+}
+
+fn update_hunger_bar(
+    mut bars: Query<&mut Style, With<HungerBar>>,
+    players: Query<(&NetworkOwner, &Hunger), (With<Player>, Changed<Hunger>)>,
+    client: Res<RepliconClient>,
+) {
+    let Some(hunger) = client.id().and_then(|client_id| {
+        players
+            .iter()
+            .find(|(owner, _)| owner.0 == client_id.get())
+            .map(|(_, hunger)| hunger)
+    }) else {
+        return;
+    };
+    for mut style in bars.iter_mut() {
+        style.width = fill_width(hunger.current, hunger.max);
+    }
+}
+
+fn update_stamina_bar(
+    mut bars: Query<&mut Style, With<StaminaBar>>,
+    players: Query<(&NetworkOwner, &Stamina), (With<Player>, Changed<Stamina>)>,
+    client: Res<RepliconClient>,
+) {
+    let Some(stamina) = client.id().and_then(|client_id| {
+        players
+            .iter()
+            .find(|(owner, _)| owner.0 == client_id.get())
+            .map(|(_, stamina)| stamina)
+    }) else {
+        return;
+    };
+    for mut style in bars.iter_mut() {
+        style.width = fill_width(stamina.current, stamina.max);
+    }
+}
@@ -0,0 +1,173 @@
+use bevy::prelude::*;
+use bevy_replicon::prelude::*;
+use bevy_replicon_snap::NetworkOwner;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    equipment::HotbarSlot,
+    health::DamageEvent,
+    item_container::ItemContainer,
+    item_registry::ItemRegistry,
+    loading_screen::GameState,
+    player::{Player, Respawning},
+    sign::editing_sign,
+};
+
+const EAT_KEY: KeyCode = KeyCode::KeyX;
+pub const PLAYER_HUNGER: f32 = 100.0;
+
+pub struct HungerPlugin;
+
+impl Plugin for HungerPlugin {
+    fn build(&self, app: &mut App) {
+        app.replicate::<Hunger>()
+            .register_type::<HungerSettings>()
+            .init_resource::<HungerSettings>()
+            .add_client_event::<EatItemEvent>(ChannelKind::Ordered)
+            .add_systems(
+                Update,
+                (eat_items, drain_hunger, starve_players).chain().run_if(server_running),
+            )
+            .add_systems(
+                Update,
+                send_eat_events
+                    .run_if(client_connected)
+                    .run_if(in_state(GameState::InGame))
+                    .run_if(not(editing_sign)),
+            );
+    }
+}
+
+#[derive(Resource, Debug, Reflect)]
+#[reflect(Resource)]
+pub struct HungerSettings {
+    /// Hunger lost per second.
+    pub drain: f32,
+    /// Damage a starving player takes every [`HungerSettings::starve_interval`] seconds.
+    pub starve_damage: f32,
+    pub starve_interval: f32,
+}
+
+impl Default for HungerSettings {
+    fn default() -> Self {
+        Self {
+            drain: 0.1,
+            starve_damage: 5.0,
+            starve_interval: 4.0,
+        }
+    }
+}
+
+/// How full a player is. Drains on the server and hurts the player once it hits zero.
+#[derive(Component, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Hunger {
+    pub current: f32,
+    pub max: f32,
+    /// Seconds until the next starvation damage, counted on the server only.
+    #[serde(skip)]
+    starve_timer: f32,
+}
+
+impl Hunger {
+    pub fn new(max: f32) -> Self {
+        Self {
+            current: max,
+            max,
+            starve_timer: 0.0,
+        }
+    }
+
+    pub fn is_starving(&self) -> bool {
+        self.current <= 0.0
+    }
+}
+
+/// Asks the server to eat one item from the given slot of the sender's inventory.
+#[derive(Event, Debug, Clone, Serialize, Deserialize)]
+pub struct EatItemEvent {
+    pub slot: usize,
+}
+
+/// Eats the item the local player holds.
+fn send_eat_events(
+    input: Res<ButtonInput<KeyCode>>,
+    mut events: EventWriter<EatItemEvent>,
+    players: Query<(&NetworkOwner, &HotbarSlot), (With<Player>, Without<Respawning>)>,
+    client: Res<RepliconClient>,
+) {
+    if !input.just_pressed(EAT_KEY) {
+        return;
+    }
+    let held = client.id().and_then(|client_id| {
+        players
+            .iter()
+            .find(|(owner, _)| owner.0 == client_id.get())
+            .map(|(_, slot)| slot.0)
+    });
+    if let Some(slot) = held {
+        events.send(EatItemEvent { slot });
+    }
+}
+
+/// Food is only eaten while the player has room for it, so none of it goes to waste.
+fn eat_items(
+    mut events: EventReader<FromClient<EatItemEvent>>,
+    mut players: Query<(&NetworkOwner, &mut Hunger, &mut ItemContainer), (With<Player>, Without<Respawning>)>,
+    registry: Res<ItemRegistry>,
+) {
+    for FromClient { client_id, event } in events.read() {
+        let Some((_, mut hunger, mut inventory)) = players
+            .iter_mut()
+            .find(|(owner, ..)| owner.0 == client_id.get())
+        else {
+            continue;
+        };
+        let Some(food) = inventory.get(event.slot).and_then(|item| registry.food(&item.id)) else {
+            continue;
+        };
+        if hunger.current >= hunger.max {
+            continue;
+        }
+        inventory.take_from_slot(event.slot, 1);
+        hunger.current = (hunger.current + food).min(hunger.max);
+    }
+}
+
+fn drain_hunger(
+    mut players: Query<&mut Hunger, (With<Player>, Without<Respawning>)>,
+    settings: Res<HungerSettings>,
+    time: Res<Time>,
+) {
+    for mut hunger in players.iter_mut() {
+        if hunger.is_starving() {
+            continue;
+        }
+        // clients only need whole points for the bar
+        let before = hunger.current.ceil();
+        let state = hunger.bypass_change_detection();
+        state.current = (state.current - settings.drain * time.delta_seconds()).max(0.0);
+        if hunger.current.ceil() != before {
+            hunger.set_changed();
+        }
+    }
+}
+
+fn starve_players(
+    mut damage_events: EventWriter<DamageEvent>,
+    mut players: Query<(Entity, &mut Hunger), (With<Player>, Without<Respawning>)>,
+    settings: Res<HungerSettings>,
+    time: Res<Time>,
+) {
+    for (entity, mut hunger) in players.iter_mut() {
+        let hunger = hunger.bypass_change_detection();
+        if !hunger.is_starving() {
+            hunger.starve_timer = settings.starve_interval;
+            continue;
+        }
+        hunger.starve_timer -= time.delta_seconds();
+        if hunger.starve_timer <= 0.0 {
+            hunger.starve_timer = settings.starve_interval;
+            damage_events.send(DamageEvent::new(entity, settings.starve_damage));
+        }
+    }
+}
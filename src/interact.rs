@@ -0,0 +1,316 @@
+use bevy::{ecs::entity::MapEntities, prelude::*};
+use bevy_replicon::{core::ClientId, prelude::*};
+use bevy_replicon_snap::NetworkOwner;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    action::{ActionCooldownConfig, ActionCooldowns, GameAction},
+    item::Item,
+    item_container::{in_interaction_range, notify_full, ContainerFull, InventoryFullEvent, ItemContainer},
+    loading_screen::GameState,
+    localization::Localization,
+    player::{Facing, Player},
+    sleep::Sleeping,
+    ActionEvent,
+};
+
+/// Maximum distance between a player and the entity it interacts with.
+pub const INTERACT_RANGE: f32 = 48.0;
+/// Candidates whose distances differ by less than this are tied and the one more aligned with
+/// the player's Facing wins.
+const TIE_DISTANCE: f32 = 8.0;
+/// Height of the prompt above the target.
+const PROMPT_OFFSET: f32 = 24.0;
+const PROMPT_Z: f32 = 10.0;
+
+pub struct InteractPlugin;
+
+impl Plugin for InteractPlugin {
+    fn build(&self, app: &mut App) {
+        app.replicate::<Interactable>()
+            .init_resource::<InteractTarget>()
+            .add_event::<InteractEvent>()
+            .add_mapped_server_event::<OpenContainerEvent>(ChannelKind::Ordered)
+            .add_systems(Startup, setup_interact_prompt)
+            .add_systems(
+                Update,
+                (resolve_interactions, (pick_up_items, open_containers))
+                    .chain()
+                    .run_if(has_authority),
+            )
+            .add_systems(
+                Update,
+                (update_interact_target, update_interact_prompt)
+                    .chain()
+                    .run_if(client_connected)
+                    .run_if(in_state(GameState::InGame)),
+            );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InteractKind {
+    Open,
+    PickUp,
+    Edit,
+    Sleep,
+    Collect,
+}
+
+impl InteractKind {
+    /// Localization key of the verb shown in the prompt.
+    pub fn verb_key(&self) -> &'static str {
+        match self {
+            InteractKind::Open => "interact.open",
+            InteractKind::PickUp => "interact.pick_up",
+            InteractKind::Edit => "interact.edit",
+            InteractKind::Sleep => "interact.sleep",
+            InteractKind::Collect => "interact.collect",
+        }
+    }
+}
+
+/// Marks an entity players can interact with using the Interact action.
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+pub struct Interactable {
+    pub kind: InteractKind,
+    /// Localization key of the name shown in the prompt, e.g. "object.bag".
+    pub label: String,
+}
+
+impl Interactable {
+    pub fn new(kind: InteractKind, label: impl Into<String>) -> Self {
+        Self {
+            kind,
+            label: label.into(),
+        }
+    }
+}
+
+/// Interaction the server accepted, dispatched to the handler of its kind.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct InteractEvent {
+    pub client_id: ClientId,
+    pub player: Entity,
+    pub target: Entity,
+    pub kind: InteractKind,
+}
+
+/// Tells a client to open the window of a container it interacted with.
+#[derive(Event, Debug, Clone, Serialize, Deserialize)]
+pub struct OpenContainerEvent {
+    pub container: Entity,
+}
+
+impl MapEntities for OpenContainerEvent {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.container = entity_mapper.map_entity(self.container);
+    }
+}
+
+/// What the local player would interact with right now, computed on the client from replicated
+/// components so the prompt and the inventory fallback don't wait for the server.
+#[derive(Resource, Debug, Default)]
+pub struct InteractTarget(pub Option<Entity>);
+
+#[derive(Component, Debug)]
+struct InteractPrompt;
+
+/// Picks the nearest candidate in range that isn't behind the player. Near ties prefer the
+/// candidate most aligned with `facing`.
+pub fn pick_interact_target(
+    player_pos: Vec2,
+    facing: Vec2,
+    candidates: impl Iterator<Item = (Entity, Vec2)>,
+) -> Option<Entity> {
+    let alignment = |pos: Vec2| (pos - player_pos).normalize_or_zero().dot(facing);
+    candidates
+        .map(|(entity, pos)| (entity, player_pos.distance(pos), alignment(pos)))
+        .filter(|(_, distance, alignment)| {
+            // something right on top of the player counts as in front of it
+            *distance <= INTERACT_RANGE && (*alignment >= 0.0 || *distance < TIE_DISTANCE)
+        })
+        .min_by(|a, b| {
+            if (a.1 - b.1).abs() < TIE_DISTANCE {
+                b.2.total_cmp(&a.2)
+            } else {
+                a.1.total_cmp(&b.1)
+            }
+        })
+        .map(|(entity, ..)| entity)
+}
+
+fn resolve_interactions(
+    mut events: EventReader<FromClient<ActionEvent>>,
+    mut interact_events: EventWriter<InteractEvent>,
+    mut players: Query<
+        (Entity, &NetworkOwner, &Transform, &Facing, &mut ActionCooldowns),
+        Without<Sleeping>,
+    >,
+    candidates: Query<(Entity, &Transform, &Interactable), Without<Player>>,
+    cooldown_config: Res<ActionCooldownConfig>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds();
+    for FromClient { client_id, event } in events.read() {
+        if !GameAction::from_key(event.action).contains(&GameAction::Interact) {
+            continue;
+        }
+        let Some((player, _, transform, facing, mut cooldowns)) = players
+            .iter_mut()
+            .find(|(_, owner, ..)| owner.0 == client_id.get())
+        else {
+            continue;
+        };
+        if !cooldowns.ready(GameAction::Interact, now, &cooldown_config) {
+            continue;
+        }
+        let target = pick_interact_target(
+            transform.translation.xy(),
+            facing.0,
+            candidates
+                .iter()
+                .map(|(entity, transform, _)| (entity, transform.translation.xy())),
+        );
+        // nothing to interact with, the client toggles its inventory instead
+        let Some((target, _, interactable)) = target.and_then(|target| candidates.get(target).ok())
+        else {
+            continue;
+        };
+        cooldowns.trigger(GameAction::Interact, now);
+        interact_events.send(InteractEvent {
+            client_id: *client_id,
+            player,
+            target,
+            kind: interactable.kind,
+        });
+    }
+}
+
+/// Picks up as much of a ground stack as fits, the rest stays on the ground.
+fn pick_up_items(
+    mut commands: Commands,
+    mut events: EventReader<InteractEvent>,
+    mut full_events: EventWriter<ToClients<InventoryFullEvent>>,
+    mut players: Query<&mut ItemContainer, With<Player>>,
+    mut items: Query<&mut Item>,
+) {
+    for event in events.read() {
+        if event.kind != InteractKind::PickUp {
+            continue;
+        }
+        let (Ok(mut container), Ok(mut item)) = (players.get_mut(event.player), items.get_mut(event.target))
+        else {
+            continue;
+        };
+        match container.insert(item.clone()) {
+            Ok(()) => commands.entity(event.target).despawn_recursive(),
+            Err(ContainerFull(rest)) => {
+                debug!("Inventory of {:?} is full", event.client_id);
+                if rest.count != item.count {
+                    item.count = rest.count;
+                }
+                notify_full(&mut full_events, event.client_id);
+            }
+        }
+    }
+}
+
+fn open_containers(
+    mut events: EventReader<InteractEvent>,
+    mut open_events: EventWriter<ToClients<OpenContainerEvent>>,
+    transforms: Query<&Transform>,
+    containers: Query<(), With<ItemContainer>>,
+) {
+    for event in events.read() {
+        if event.kind != InteractKind::Open || !containers.contains(event.target) {
+            continue;
+        }
+        let (Ok(player), Ok(container)) = (transforms.get(event.player), transforms.get(event.target))
+        else {
+            continue;
+        };
+        if !in_interaction_range(player.translation.xy(), container.translation.xy()) {
+            continue;
+        }
+        open_events.send(ToClients {
+            mode: SendMode::Direct(event.client_id),
+            event: OpenContainerEvent {
+                container: event.target,
+            },
+        });
+    }
+}
+
+fn update_interact_target(
+    mut target: ResMut<InteractTarget>,
+    players: Query<(&NetworkOwner, &Transform, &Facing)>,
+    candidates: Query<(Entity, &Transform), (With<Interactable>, Without<Player>)>,
+    client: Res<RepliconClient>,
+) {
+    let local_player = client.id().and_then(|client_id| {
+        players
+            .iter()
+            .find(|(owner, ..)| ClientId::new(owner.0) == client_id)
+    });
+    let new_target = local_player.and_then(|(_, transform, facing)| {
+        pick_interact_target(
+            transform.translation.xy(),
+            facing.0,
+            candidates
+                .iter()
+                .map(|(entity, transform)| (entity, transform.translation.xy())),
+        )
+    });
+    if target.0 != new_target {
+        target.0 = new_target;
+    }
+}
+
+fn setup_interact_prompt(mut commands: Commands) {
+    commands.spawn((
+        Name::new("Interact Prompt"),
+        Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: 12.0,
+                    color: Color::WHITE,
+                    ..Default::default()
+                },
+            ),
+            visibility: Visibility::Hidden,
+            ..Default::default()
+        },
+        InteractPrompt,
+    ));
+}
+
+fn update_interact_prompt(
+    target: Res<InteractTarget>,
+    targets: Query<(&Transform, &Interactable, Option<&Item>), Without<InteractPrompt>>,
+    mut prompt: Query<(&mut Text, &mut Transform, &mut Visibility), With<InteractPrompt>>,
+    localization: Res<Localization>,
+) {
+    let Ok((mut text, mut transform, mut visibility)) = prompt.get_single_mut() else {
+        return;
+    };
+    let Some((target_transform, interactable, item)) = target.0.and_then(|entity| targets.get(entity).ok())
+    else {
+        visibility.set_if_neq(Visibility::Hidden);
+        return;
+    };
+    let mut name = localization.tr(&interactable.label);
+    if let Some(item) = item.filter(|item| item.count > 1) {
+        name = format!("{} {name}", item.count);
+    }
+    let label = localization.tr_args(
+        "interact.prompt",
+        &[("verb", localization.tr(interactable.kind.verb_key())), ("target", name)],
+    );
+    if text.sections[0].value != label {
+        text.sections[0].value = label;
+    }
+    transform.translation = (target_transform.translation.xy() + Vec2::Y * PROMPT_OFFSET).extend(PROMPT_Z);
+    visibility.set_if_neq(Visibility::Inherited);
+}
@@ -1,8 +1,51 @@
-use bevy::prelude::*;
-use bevy_replicon::prelude::client_connected;
+use bevy::{prelude::*, utils::HashMap};
+use bevy_mod_picking::{
+    events::{Drag, DragEnd, DragStart, Drop, Pointer},
+    picking_core::Pickable,
+    pointer::PointerButton,
+};
+use bevy_replicon::{core::ClientId, prelude::{client_connected, RepliconClient}};
+use bevy_replicon_snap::NetworkOwner;
 use sickle_ui::prelude::*;
 
-use crate::{item_container::ItemContainer, ActionEvent};
+use crate::{
+    action::GameAction,
+    container_mirror::ContainerMirror,
+    crafting::{spawn_recipe_buttons, RecipeRegistry},
+    furnace::Furnace,
+    game_assets::GameAssets,
+    interact::{InteractTarget, OpenContainerEvent},
+    item::Item,
+    item_container::{
+        DestroyItemEvent, InventoryFullEvent, ItemContainer, SlotMoveEvent, TransferItemEvent,
+        CONTAINER_INTERACTION_RANGE,
+    },
+    item_registry::ItemRegistry,
+    localization::{Localization, LocalizedText},
+    player::Player,
+    ActionEvent,
+};
+
+const INVENTORY_COLUMNS: usize = 8;
+const SLOT_BORDER_COLOR: Color = Color::srgb(0.3, 0.3, 0.3);
+const SLOT_SELECTED_COLOR: Color = Color::srgb(1.0, 0.85, 0.2);
+const SLOT_BACKGROUND_COLOR: Color = Color::srgba(0.1, 0.1, 0.1, 0.8);
+
+/// Trashing more items than this at once needs a second click within [`TRASH_CONFIRM_WINDOW`] seconds.
+const TRASH_CONFIRM_THRESHOLD: u32 = 4;
+const TRASH_CONFIRM_WINDOW: f32 = 2.0;
+const TRASH_CONFIRM_COLOR: Color = Color::srgb(0.9, 0.2, 0.2);
+
+/// Seconds the "inventory full" message stays on screen.
+const FULL_MESSAGE_DURATION: f32 = 2.0;
+
+const PROGRESS_BAR_COLOR: Color = Color::srgb(1.0, 0.6, 0.2);
+
+/// Size of the icon following the cursor while an item is dragged.
+const DRAG_ICON_SIZE: f32 = 32.0;
+
+/// Extra distance beyond the interaction range before an open container closes, avoids flicker at the border.
+const AUTO_CLOSE_BUFFER: f32 = 16.0;
 
 
 pub struct InventoryUIPlugin;
@@ -10,8 +53,33 @@ pub struct InventoryUIPlugin;
 impl Plugin for InventoryUIPlugin {
     fn build(&self, _app: &mut App) {
         _app
+            .init_resource::<OpenContainers>()
+            .init_resource::<SelectedSlot>()
+            .init_resource::<PendingTrash>()
+            .init_resource::<DraggedSlot>()
+            .add_systems(Startup, (setup_inventory_layout, setup_full_message, setup_drag_icon))
+            .add_systems(Update, show_full_message.run_if(client_connected))
             .add_systems(Update, update_inventory_ui.run_if(client_connected))
-            .add_systems(Update, handle_inventory.run_if(client_connected));
+            .add_systems(Update, handle_inventory.run_if(client_connected))
+            .add_systems(Update, add_crafting_sections.run_if(client_connected))
+            .add_systems(Update, (add_furnace_bars, update_furnace_bars).chain().run_if(client_connected))
+            .add_systems(Update, open_interacted_containers.run_if(client_connected))
+            .add_systems(Update, (close_inventory_button, forget_closed_inventories).chain())
+            .add_systems(Update, (select_slot, highlight_selected_slot).chain())
+            .add_systems(
+                Update,
+                (trash_selected_slot, expire_pending_trash)
+                    .chain()
+                    .run_if(client_connected),
+            )
+            .add_systems(Update, transfer_on_ctrl_click.run_if(client_connected))
+            .add_systems(
+                Update,
+                (start_slot_drag, move_drag_icon, drop_on_slot, end_slot_drag)
+                    .chain()
+                    .run_if(client_connected),
+            )
+            .add_systems(Update, close_out_of_range_inventories.run_if(client_connected));
     }
 }
 
@@ -20,13 +88,95 @@ pub struct InventoryUI {
     container: Entity,
 }
 
+/// Container entities that currently have an open window, mapped to that window.
+#[derive(Resource, Debug, Default)]
+pub struct OpenContainers(pub HashMap<Entity, Entity>);
+
+/// Column of the inventory layout. The player's own inventory goes left, external containers right.
+#[derive(Component, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum InventoryColumn {
+    Own,
+    External,
+}
+
+#[derive(Component, Debug)]
+struct CloseInventoryButton;
+
+/// Destroys one item of the selected slot of `container` when clicked, with shift or when the stack
+/// is dropped on it the whole stack.
+#[derive(Component, Debug)]
+struct TrashSlot {
+    container: Entity,
+}
+
+/// Large trash request waiting for its confirming second click.
+#[derive(Resource, Debug, Default)]
+struct PendingTrash(Option<(DestroyItemEvent, f32)>);
+
+
+/// A single slot of an inventory grid, `index` is the container slot it shows.
+#[derive(Component, Debug)]
+pub struct InventorySlot {
+    pub container: Entity,
+    pub index: usize,
+}
+
+#[derive(Component, Debug)]
+struct SlotIcon;
+
+/// Stack size shown in the corner of a slot, empty for single items.
+#[derive(Component, Debug)]
+struct SlotCount;
+
+fn count_label(item: Option<&Item>) -> String {
+    match item {
+        Some(item) if item.count > 1 => item.count.to_string(),
+        _ => String::new(),
+    }
+}
+
+/// Slot whose stack is being dragged, as container and index.
+#[derive(Resource, Debug, Default)]
+struct DraggedSlot(Option<(Entity, usize)>);
 
+/// Icon of the dragged stack, follows the cursor.
 #[derive(Component, Debug)]
-pub struct ItemEntry;
+struct DragIcon;
+
+/// The slot the player clicked last, anchor for transfer, split and drop interactions.
+#[derive(Resource, Debug, Default)]
+pub struct SelectedSlot(pub Option<(Entity, usize)>);
 
 impl InventoryUI {
     fn frame() -> impl Bundle {
-        (Name::new("Inventory UI"), NodeBundle::default(),)
+        (Name::new("Inventory UI"), NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Column,
+                ..Default::default()
+            },
+            ..Default::default()
+        },)
+    }
+}
+
+impl InventorySlot {
+    fn frame() -> impl Bundle {
+        (Name::new("Inventory Slot"), ButtonBundle {
+            style: Style {
+                width: Val::VMin(5.0),
+                height: Val::VMin(5.0),
+                min_width: Val::Px(32.0),
+                min_height: Val::Px(32.0),
+                margin: UiRect::all(Val::Px(2.0)),
+                border: UiRect::all(Val::Px(2.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            border_color: SLOT_BORDER_COLOR.into(),
+            background_color: SLOT_BACKGROUND_COLOR.into(),
+            ..Default::default()
+        })
     }
 }
 
@@ -34,7 +184,8 @@ pub trait UiInventoryUIExt {
     fn inventory(
         &mut self,
         spawn_children: impl FnOnce(&mut UiBuilder<Entity>),
-        container: (Entity, &ItemContainer)
+        container: (Entity, &ItemContainer),
+        assets: &GameAssets,
     ) -> UiBuilder<Entity>;
 }
 
@@ -43,64 +194,695 @@ impl UiInventoryUIExt for UiBuilder<'_, Entity> {
         &mut self,
         spawn_children: impl FnOnce(&mut UiBuilder<Entity>),
         container: (Entity, &ItemContainer),
+        assets: &GameAssets,
     ) -> UiBuilder<Entity> {
-        self.container((InventoryUI::frame(), InventoryUI {container: container.0}), |parent| {
-            parent.label(LabelConfig { label: "Inventory Stuff...".to_string(), ..Default::default() });
-            for item in container.1.items.iter() {
-                parent.label(LabelConfig { label: item.name.to_string(), ..Default::default() }).insert(ItemEntry);
+        let (container_entity, item_container) = container;
+        self.container((InventoryUI::frame(), InventoryUI {container: container_entity}), |parent| {
+            let rows = item_container.capacity().div_ceil(INVENTORY_COLUMNS);
+            for row_index in 0..rows {
+                parent.row(|row| {
+                    for column in 0..INVENTORY_COLUMNS {
+                        let index = row_index * INVENTORY_COLUMNS + column;
+                        if index >= item_container.capacity() {
+                            break;
+                        }
+                        let item = item_container.get(index);
+                        row.container((InventorySlot::frame(), InventorySlot { container: container_entity, index }), |slot| {
+                            slot.spawn((
+                                ImageBundle {
+                                    style: Style {
+                                        width: Val::Percent(80.0),
+                                        height: Val::Percent(80.0),
+                                        ..Default::default()
+                                    },
+                                    image: UiImage::new(assets.item_texture.clone()),
+                                    visibility: if item.is_some() { Visibility::Inherited } else { Visibility::Hidden },
+                                    ..Default::default()
+                                },
+                                TextureAtlas {
+                                    layout: assets.item_layout.clone(),
+                                    index: item.map_or(0, |item| item.texture_index),
+                                },
+                                SlotIcon,
+                                // drags and drops hit the slot, not its contents
+                                Pickable::IGNORE,
+                            ));
+                            slot.spawn((
+                                TextBundle::from_section(count_label(item), TextStyle { font_size: 12.0, ..Default::default() })
+                                    .with_style(Style {
+                                        position_type: PositionType::Absolute,
+                                        right: Val::Px(2.0),
+                                        bottom: Val::Px(0.0),
+                                        ..Default::default()
+                                    }),
+                                SlotCount,
+                                Pickable::IGNORE,
+                            ));
+                        });
+                    }
+                });
             }
             spawn_children(parent)
         })
     }
 }
 
+fn setup_inventory_layout(mut commands: Commands) {
+    commands.ui_builder(UiRoot).row(|row| {
+        row.column(|_| {}).insert(InventoryColumn::Own);
+        row.column(|_| {}).insert(InventoryColumn::External);
+    }).insert(Name::new("Inventory Layout"));
+}
+
+/// Opens a window for the container unless it is already open.
+pub fn open_inventory_window(
+    commands: &mut Commands,
+    open_containers: &mut OpenContainers,
+    column_query: &Query<(Entity, &InventoryColumn)>,
+    container: (Entity, &ItemContainer),
+    column: InventoryColumn,
+    assets: &GameAssets,
+) {
+    if open_containers.0.contains_key(&container.0) {
+        return;
+    }
+    let Some((column_entity, _)) = column_query.iter().find(|(_, c)| **c == column) else {
+        return;
+    };
+    let window = commands.ui_builder(column_entity).column(|window| {
+        window.row(|header| {
+            header.container((ButtonBundle::default(), CloseInventoryButton), |button| {
+                button.label(LabelConfig { label: "x".to_string(), ..Default::default() });
+            });
+            if column == InventoryColumn::Own {
+                header.container((InventorySlot::frame(), TrashSlot { container: container.0 }), |button| {
+                    button
+                        .label(LabelConfig::default())
+                        .insert((LocalizedText::new("inventory.trash"), Pickable::IGNORE));
+                });
+            }
+        });
+        window.inventory(|_| {}, container, assets);
+    }).insert(InventoryRoot).id();
+    open_containers.0.insert(container.0, window);
+}
+
 fn handle_inventory(
     mut commands: Commands,
     mut event_reader: EventReader<ActionEvent>,
-    query: Query<Entity, With<InventoryRoot>>,
-    container_query: Query<(Entity, &ItemContainer)>,
+    mut open_containers: ResMut<OpenContainers>,
+    column_query: Query<(Entity, &InventoryColumn)>,
+    player_query: Query<(Entity, &ItemContainer, &NetworkOwner), (With<Player>, Without<ContainerMirror>)>,
+    client: Res<RepliconClient>,
+    interact_target: Res<InteractTarget>,
+    game_assets: Res<GameAssets>,
 ) {
     for event in event_reader.read() {
-        if event.action != KeyCode::KeyE {continue;}
-        if query.is_empty() {
-            if let Some(container) = container_query.iter().next() {
-                commands.ui_builder(UiRoot).column(|column| {
-                    column.inventory(|_| {}, container);
-                }).insert(InventoryRoot);
+        if !GameAction::from_key(event.action).contains(&GameAction::Interact) {continue;}
+        if open_containers.0.is_empty() {
+            // the server handles the interaction, the inventory is only the fallback
+            if interact_target.0.is_some() {
+                continue;
+            }
+            let own = player_query
+                .iter()
+                .find(|(.., owner)| Some(ClientId::new(owner.0)) == client.id());
+            if let Some((entity, container, _)) = own {
+                open_inventory_window(
+                    &mut commands,
+                    &mut open_containers,
+                    &column_query,
+                    (entity, container),
+                    InventoryColumn::Own,
+                    &game_assets,
+                );
             }
-
         } else {
-            for inventories in query.iter() {
-                commands.entity(inventories).despawn_recursive();
+            for (_, window) in open_containers.0.drain() {
+                commands.entity(window).despawn_recursive();
             }
         }
     }
 }
 
+/// Opens the container the server accepted an interaction with, next to the own inventory.
+fn open_interacted_containers(
+    mut commands: Commands,
+    mut events: EventReader<OpenContainerEvent>,
+    mut open_containers: ResMut<OpenContainers>,
+    column_query: Query<(Entity, &InventoryColumn)>,
+    container_query: Query<(Entity, &ItemContainer, Option<&NetworkOwner>), Without<ContainerMirror>>,
+    client: Res<RepliconClient>,
+    game_assets: Res<GameAssets>,
+) {
+    for event in events.read() {
+        let Ok((entity, container, _)) = container_query.get(event.container) else {
+            continue;
+        };
+        open_inventory_window(
+            &mut commands,
+            &mut open_containers,
+            &column_query,
+            (entity, container),
+            InventoryColumn::External,
+            &game_assets,
+        );
+        let own = container_query.iter().find(|(.., owner)| {
+            owner.is_some_and(|owner| Some(ClientId::new(owner.0)) == client.id())
+        });
+        if let Some((entity, container, _)) = own {
+            open_inventory_window(
+                &mut commands,
+                &mut open_containers,
+                &column_query,
+                (entity, container),
+                InventoryColumn::Own,
+                &game_assets,
+            );
+        }
+    }
+}
 
+fn close_inventory_button(
+    mut commands: Commands,
+    button_query: Query<(Entity, &Interaction), (With<CloseInventoryButton>, Changed<Interaction>)>,
+    root_query: Query<(), With<InventoryRoot>>,
+    parent_query: Query<&Parent>,
+) {
+    for (button, interaction) in button_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        if let Some(root) = parent_query
+            .iter_ancestors(button)
+            .find(|ancestor| root_query.contains(*ancestor))
+        {
+            commands.entity(root).despawn_recursive();
+        }
+    }
+}
 
-fn update_inventory_ui(
+/// Adds the recipes below the own inventory when its window opens. They are taken from the
+/// registry at that moment, reopening the inventory picks up reloaded recipes.
+fn add_crafting_sections(
+    mut commands: Commands,
+    windows: Query<(Entity, &Parent), Added<InventoryRoot>>,
+    columns: Query<&InventoryColumn>,
+    recipes: Res<RecipeRegistry>,
+    items: Res<ItemRegistry>,
+    localization: Res<Localization>,
+) {
+    for (window, parent) in windows.iter() {
+        if columns.get(parent.get()).ok() != Some(&InventoryColumn::Own) {
+            continue;
+        }
+        commands.ui_builder(window).column(|section| {
+            section.label(LabelConfig::default()).insert(LocalizedText::new("inventory.crafting"));
+            spawn_recipe_buttons(section, &recipes, &items, &localization);
+        });
+    }
+}
+
+/// Fill of a furnace window's progress bar, follows the replicated [`Furnace`] progress.
+#[derive(Component, Debug)]
+struct FurnaceBar {
+    furnace: Entity,
+}
+
+/// Adds a smelting progress bar below the slots of furnace windows when they open.
+fn add_furnace_bars(
     mut commands: Commands,
-    query: Query<(Entity, &InventoryUI)>,
-    container_query: Query<(Entity, &ItemContainer), Changed<ItemContainer>>,
-    entry_query: Query<(Entity, &Parent), With<ItemEntry>>,
-) {
-    for (inv_entity, inv) in query.iter() {
-        if let Some((_, item_container)) = container_query.get(inv.container).ok() {
-            for (entry, parent) in entry_query.iter() {
-                if parent.get() == inv_entity {
-                    commands.entity(entry).despawn_recursive();
+    windows: Query<Entity, Added<InventoryRoot>>,
+    open_containers: Res<OpenContainers>,
+    furnaces: Query<(), With<Furnace>>,
+) {
+    for window in windows.iter() {
+        let Some(&furnace) = open_containers
+            .0
+            .iter()
+            .find_map(|(container, open_window)| (*open_window == window).then_some(container))
+        else {
+            continue;
+        };
+        if !furnaces.contains(furnace) {
+            continue;
+        }
+        commands.entity(window).with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        height: Val::Px(6.0),
+                        margin: UiRect::all(Val::Px(2.0)),
+                        ..Default::default()
+                    },
+                    background_color: SLOT_BACKGROUND_COLOR.into(),
+                    ..Default::default()
+                })
+                .with_children(|bar| {
+                    bar.spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Percent(0.0),
+                                height: Val::Percent(100.0),
+                                ..Default::default()
+                            },
+                            background_color: PROGRESS_BAR_COLOR.into(),
+                            ..Default::default()
+                        },
+                        FurnaceBar { furnace },
+                    ));
+                });
+        });
+    }
+}
+
+fn update_furnace_bars(mut bars: Query<(&FurnaceBar, &mut Style)>, furnaces: Query<&Furnace>) {
+    for (bar, mut style) in bars.iter_mut() {
+        let Ok(furnace) = furnaces.get(bar.furnace) else {
+            continue;
+        };
+        let width = Val::Percent(furnace.progress.clamp(0.0, 1.0) * 100.0);
+        if style.width != width {
+            style.width = width;
+        }
+    }
+}
+
+/// Drops windows from [`OpenContainers`] that were closed by other means than the E key.
+fn forget_closed_inventories(
+    mut open_containers: ResMut<OpenContainers>,
+    root_query: Query<(), With<InventoryRoot>>,
+) {
+    open_containers.0.retain(|_, window| root_query.contains(*window));
+}
+
+
+
+/// Updates the slots of open inventories in place whenever their container changes.
+fn update_inventory_ui(
+    container_query: Query<&ItemContainer, Changed<ItemContainer>>,
+    slot_query: Query<(&InventorySlot, &Children)>,
+    mut icon_query: Query<(&mut TextureAtlas, &mut Visibility), With<SlotIcon>>,
+    mut count_query: Query<&mut Text, With<SlotCount>>,
+) {
+    for (slot, children) in slot_query.iter() {
+        let Ok(item_container) = container_query.get(slot.container) else {
+            continue;
+        };
+        let item = item_container.get(slot.index);
+        for &child in children.iter() {
+            if let Ok((mut atlas, mut visibility)) = icon_query.get_mut(child) {
+                match item {
+                    Some(item) => {
+                        if atlas.index != item.texture_index {
+                            atlas.index = item.texture_index;
+                        }
+                        visibility.set_if_neq(Visibility::Inherited);
+                    }
+                    None => {
+                        visibility.set_if_neq(Visibility::Hidden);
+                    }
                 }
             }
-            for item in item_container.items.iter() {
-                commands.ui_builder(inv_entity).label(LabelConfig { label: item.name.to_string(), ..Default::default() }).insert(ItemEntry);
+            if let Ok(mut text) = count_query.get_mut(child) {
+                let label = count_label(item);
+                if text.sections[0].value != label {
+                    text.sections[0].value = label;
+                }
             }
         }
     }
 }
 
+fn select_slot(
+    slot_query: Query<(&InventorySlot, &Interaction), Changed<Interaction>>,
+    mut selected: ResMut<SelectedSlot>,
+) {
+    for (slot, interaction) in slot_query.iter() {
+        if *interaction == Interaction::Pressed {
+            selected.0 = Some((slot.container, slot.index));
+        }
+    }
+}
+
+fn highlight_selected_slot(
+    selected: Res<SelectedSlot>,
+    mut slot_query: Query<(Ref<InventorySlot>, &mut BorderColor)>,
+) {
+    for (slot, mut border) in slot_query.iter_mut() {
+        if !selected.is_changed() && !slot.is_added() {
+            continue;
+        }
+        let color = if selected.0 == Some((slot.container, slot.index)) {
+            SLOT_SELECTED_COLOR
+        } else {
+            SLOT_BORDER_COLOR
+        };
+        border.0 = color;
+    }
+}
+
+/// Ctrl-clicking a slot moves its item to the other open container, the server validates it.
+fn transfer_on_ctrl_click(
+    slot_query: Query<(&InventorySlot, &Interaction), Changed<Interaction>>,
+    container_query: Query<&ItemContainer>,
+    open_containers: Res<OpenContainers>,
+    input: Res<ButtonInput<KeyCode>>,
+    mut writer: EventWriter<TransferItemEvent>,
+) {
+    if !input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        return;
+    }
+    for (slot, interaction) in slot_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let Some(item) = container_query
+            .get(slot.container)
+            .ok()
+            .and_then(|container| container.get(slot.index))
+        else {
+            continue;
+        };
+        let Some(&target) = open_containers.0.keys().find(|container| **container != slot.container) else {
+            continue;
+        };
+        writer.send(TransferItemEvent {
+            from: slot.container,
+            to: target,
+            index: slot.index,
+            item_id: item.id.clone(),
+        });
+    }
+}
+
+/// Clicking the trash destroys one item of the selected slot, shift-clicking its whole stack.
+fn trash_selected_slot(
+    trash_query: Query<(&TrashSlot, &Interaction), Changed<Interaction>>,
+    container_query: Query<&ItemContainer>,
+    selected: Res<SelectedSlot>,
+    mut pending: ResMut<PendingTrash>,
+    input: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+    mut writer: EventWriter<DestroyItemEvent>,
+) {
+    let now = time.elapsed_seconds();
+    for (trash, interaction) in trash_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let Some((container_entity, index)) = selected.0.filter(|(c, _)| *c == trash.container) else {
+            continue;
+        };
+        let Ok(container) = container_query.get(container_entity) else {
+            continue;
+        };
+        let Some(item) = container.get(index) else {
+            continue;
+        };
+        let count = if input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+            item.count
+        } else {
+            1
+        };
+        let event = DestroyItemEvent {
+            container: container_entity,
+            item_id: item.id.clone(),
+            count,
+        };
+        request_trash(event, &mut pending, now, &mut writer);
+    }
+}
+
+/// Sends a trash request. Requests for more than [`TRASH_CONFIRM_THRESHOLD`] items only go
+/// through when the same request is repeated within [`TRASH_CONFIRM_WINDOW`] seconds.
+fn request_trash(
+    event: DestroyItemEvent,
+    pending: &mut PendingTrash,
+    now: f32,
+    writer: &mut EventWriter<DestroyItemEvent>,
+) {
+    let confirmed = pending.0.as_ref().is_some_and(|(waiting, _)| {
+        waiting.container == event.container
+            && waiting.item_id == event.item_id
+            && waiting.count == event.count
+    });
+    if event.count > TRASH_CONFIRM_THRESHOLD && !confirmed {
+        pending.0 = Some((event, now));
+        return;
+    }
+    pending.0 = None;
+    writer.send(event);
+}
+
+/// Drops unconfirmed trash requests and colors the trash while one is waiting.
+fn expire_pending_trash(
+    mut pending: ResMut<PendingTrash>,
+    mut trash_query: Query<(&TrashSlot, &mut BorderColor)>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds();
+    if pending
+        .0
+        .as_ref()
+        .is_some_and(|(_, requested_at)| now - requested_at > TRASH_CONFIRM_WINDOW)
+    {
+        pending.0 = None;
+    }
+    for (trash, mut border) in trash_query.iter_mut() {
+        let waiting = pending
+            .0
+            .as_ref()
+            .is_some_and(|(event, _)| event.container == trash.container);
+        let color = if waiting { TRASH_CONFIRM_COLOR } else { SLOT_BORDER_COLOR };
+        if border.0 != color {
+            border.0 = color;
+        }
+    }
+}
 
 #[derive(Debug, Component)]
 struct InventoryRoot;
 
+fn setup_drag_icon(mut commands: Commands, assets: Res<GameAssets>) {
+    commands.spawn((
+        Name::new("Drag Icon"),
+        ImageBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                width: Val::Px(DRAG_ICON_SIZE),
+                height: Val::Px(DRAG_ICON_SIZE),
+                ..Default::default()
+            },
+            image: UiImage::new(assets.item_texture.clone()),
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(i32::MAX),
+            ..Default::default()
+        },
+        TextureAtlas {
+            layout: assets.item_layout.clone(),
+            index: 0,
+        },
+        Pickable::IGNORE,
+        DragIcon,
+    ));
+}
+
+/// Starts dragging the stack of a slot and shows its icon at the cursor.
+fn start_slot_drag(
+    mut events: EventReader<Pointer<DragStart>>,
+    mut dragged: ResMut<DraggedSlot>,
+    slot_query: Query<&InventorySlot>,
+    container_query: Query<&ItemContainer>,
+    mut icon_query: Query<(&mut TextureAtlas, &mut Visibility), With<DragIcon>>,
+) {
+    for event in events.read() {
+        if event.button != PointerButton::Primary {
+            continue;
+        }
+        let Ok(slot) = slot_query.get(event.target) else {
+            continue;
+        };
+        let Some(item) = container_query.get(slot.container).ok().and_then(|container| container.get(slot.index))
+        else {
+            continue;
+        };
+        dragged.0 = Some((slot.container, slot.index));
+        if let Ok((mut atlas, mut visibility)) = icon_query.get_single_mut() {
+            atlas.index = item.texture_index;
+            *visibility = Visibility::Visible;
+        }
+    }
+}
+
+fn move_drag_icon(
+    mut events: EventReader<Pointer<Drag>>,
+    dragged: Res<DraggedSlot>,
+    mut icon_query: Query<&mut Style, With<DragIcon>>,
+) {
+    let Some(event) = events.read().last() else {
+        return;
+    };
+    if dragged.0.is_none() {
+        return;
+    }
+    if let Ok(mut style) = icon_query.get_single_mut() {
+        let corner = event.pointer_location.position - Vec2::splat(DRAG_ICON_SIZE / 2.0);
+        style.left = Val::Px(corner.x);
+        style.top = Val::Px(corner.y);
+    }
+}
+
+/// Dropping a dragged stack on a slot asks the server to move it there, the server validates it.
+/// Dropping it on the trash of its container trashes the whole stack.
+fn drop_on_slot(
+    mut events: EventReader<Pointer<Drop>>,
+    dragged: Res<DraggedSlot>,
+    slot_query: Query<&InventorySlot>,
+    trash_query: Query<&TrashSlot>,
+    container_query: Query<&ItemContainer>,
+    mut pending: ResMut<PendingTrash>,
+    time: Res<Time>,
+    mut writer: EventWriter<SlotMoveEvent>,
+    mut destroy_writer: EventWriter<DestroyItemEvent>,
+) {
+    for event in events.read() {
+        let Some((from, from_index)) = dragged.0 else {
+            continue;
+        };
+        if let Ok(trash) = trash_query.get(event.target) {
+            let Some(item) = container_query
+                .get(from)
+                .ok()
+                .and_then(|container| container.get(from_index))
+                .filter(|_| trash.container == from)
+            else {
+                continue;
+            };
+            let destroy = DestroyItemEvent {
+                container: from,
+                item_id: item.id.clone(),
+                count: item.count,
+            };
+            request_trash(destroy, &mut pending, time.elapsed_seconds(), &mut destroy_writer);
+            continue;
+        }
+        let Ok(target) = slot_query.get(event.target) else {
+            continue;
+        };
+        if (from, from_index) == (target.container, target.index) {
+            continue;
+        }
+        let Some(item) = container_query.get(from).ok().and_then(|container| container.get(from_index)) else {
+            continue;
+        };
+        writer.send(SlotMoveEvent {
+            from,
+            from_index,
+            to: target.container,
+            to_index: target.index,
+            item_id: item.id.clone(),
+        });
+    }
+}
+
+fn end_slot_drag(
+    mut events: EventReader<Pointer<DragEnd>>,
+    mut dragged: ResMut<DraggedSlot>,
+    mut icon_query: Query<&mut Visibility, With<DragIcon>>,
+) {
+    if events.read().count() == 0 || dragged.0.is_none() {
+        return;
+    }
+    dragged.0 = None;
+    if let Ok(mut visibility) = icon_query.get_single_mut() {
+        *visibility = Visibility::Hidden;
+    }
+}
+
+/// Message shown for a moment when items didn't fit into the own inventory.
+#[derive(Component, Debug, Default)]
+struct FullMessage {
+    hide_at: f32,
+}
+
+fn setup_full_message(mut commands: Commands) {
+    commands.spawn((
+        Name::new("Inventory Full Message"),
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 18.0,
+                color: TRASH_CONFIRM_COLOR,
+                ..Default::default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(48.0),
+            left: Val::Percent(45.0),
+            ..Default::default()
+        }),
+        Visibility::Hidden,
+        Pickable::IGNORE,
+        LocalizedText::new("inventory.full"),
+        FullMessage::default(),
+    ));
+}
+
+fn show_full_message(
+    mut events: EventReader<InventoryFullEvent>,
+    mut message: Query<(&mut FullMessage, &mut Visibility)>,
+    time: Res<Time>,
+) {
+    let Ok((mut message, mut visibility)) = message.get_single_mut() else {
+        return;
+    };
+    let now = time.elapsed_seconds();
+    if events.read().count() > 0 {
+        message.hide_at = now + FULL_MESSAGE_DURATION;
+        visibility.set_if_neq(Visibility::Visible);
+    } else if now >= message.hide_at {
+        visibility.set_if_neq(Visibility::Hidden);
+    }
+}
+
+fn close_out_of_range_inventories(
+    mut commands: Commands,
+    inventory_query: Query<(Entity, &InventoryUI)>,
+    root_query: Query<(), With<InventoryRoot>>,
+    parent_query: Query<&Parent>,
+    container_query: Query<&GlobalTransform, (With<ItemContainer>, Without<Player>)>,
+    player_query: Query<(&Transform, &NetworkOwner), With<Player>>,
+    client: Res<RepliconClient>,
+) {
+    let Some(client_id) = client.id() else {
+        return;
+    };
+    let Some((player_transform, _)) = player_query
+        .iter()
+        .find(|(_, owner)| ClientId::new(owner.0) == client_id)
+    else {
+        return;
+    };
+    let player_pos = player_transform.translation.xy();
+
+    for (inv_entity, inv) in inventory_query.iter() {
+        // containers without a world position (or on a player) never close on their own
+        let Ok(container_transform) = container_query.get(inv.container) else {
+            continue;
+        };
+        let distance = player_pos.distance(container_transform.translation().xy());
+        if distance <= CONTAINER_INTERACTION_RANGE + AUTO_CLOSE_BUFFER {
+            continue;
+        }
+        let root = parent_query
+            .iter_ancestors(inv_entity)
+            .find(|ancestor| root_query.contains(*ancestor))
+            .unwrap_or(inv_entity);
+        commands.entity(root).despawn_recursive();
+    }
+}
+
 
@@ -1,9 +1,30 @@
-use bevy::{asset::AssetServer, math::Vec2, prelude::*, sprite::{Sprite, SpriteBundle}, };
+use bevy::{math::Vec2, prelude::*, sprite::{Sprite, SpriteBundle}, };
+use bevy_rapier2d::prelude::*;
 use bevy_replicon::prelude::*;
-use bevy_replicon_snap::NetworkOwner;
+use bevy_replicon_snap::{interpolation::Interpolated, NetworkOwner};
 use serde::{Deserialize, Serialize};
 
-use crate::ActionEvent;
+use crate::{
+    action::{ActionCooldownConfig, ActionCooldowns, GameAction},
+    chunk::TILE_LENGTH,
+    game_assets::GameAssets,
+    interact::{InteractKind, Interactable},
+    item_container::{ContainerFull, ItemContainer},
+    player::{Facing, Player},
+    sleep::Sleeping,
+    ActionEvent,
+};
+
+const THROW_IMPULSE: f32 = 40.0;
+const THROW_SPAWN_DISTANCE: f32 = 12.0;
+const ITEM_COLLIDER_RADIUS: f32 = 6.0;
+/// Thrown items are considered at rest below this speed.
+const REST_SPEED: f32 = 2.0;
+const MIN_THROW_TIME: f32 = 0.2;
+/// Ground items of the same kind closer than this are merged into one stack.
+const MERGE_DISTANCE: f32 = TILE_LENGTH * 0.5;
+/// Stack size of items whose definition doesn't set one.
+pub const MAX_STACK_SIZE: u32 = 64;
 
 pub struct ItemPlugin;
 
@@ -12,8 +33,17 @@ impl Plugin for ItemPlugin {
 
         app
             .add_systems(PreUpdate, init_grounditems)
-            .add_systems(Update, apply_action.map(Option::unwrap).run_if(has_authority))
-            .replicate::<Item>();
+            .add_systems(PreUpdate, drop_settled_bodies.run_if(client_connected))
+            .add_systems(Update, update_stack_labels)
+            .add_systems(Update, apply_action.run_if(has_authority))
+            .add_systems(Update, settle_thrown_items.run_if(has_authority))
+            .add_systems(Update, attract_items.run_if(has_authority))
+            .add_systems(Update, pick_up_touched_items.after(attract_items).run_if(has_authority))
+            .add_systems(Update, merge_ground_items.after(pick_up_touched_items).run_if(has_authority))
+            .register_type::<MagnetSettings>()
+            .init_resource::<MagnetSettings>()
+            .replicate::<Item>()
+            .replicate::<ThrownItem>();
     }
 }
 
@@ -26,64 +56,431 @@ struct GroundItemBundle {
 
 #[derive(Component, Serialize, Deserialize, Debug, Clone, Reflect)]
 pub struct Item {
+    /// Localization key of the display name, e.g. "item.bread".
     pub name: String,
     pub id: String,
     pub texture_index: usize,
+    /// Number of items in this stack.
+    #[serde(default = "default_count")]
+    pub count: u32,
+    /// Most items a single stack of this kind can hold.
+    #[serde(default = "default_max_stack")]
+    pub max_stack: u32,
+}
+
+fn default_count() -> u32 {
+    1
+}
+
+fn default_max_stack() -> u32 {
+    MAX_STACK_SIZE
 }
 
 impl Item {
     pub fn new(name: &str, id: &str, texture_index: usize) -> Self{
-        Self { name: name.to_string(), id: id.to_string(), texture_index: texture_index }
+        Self {
+            name: name.to_string(),
+            id: id.to_string(),
+            texture_index: texture_index,
+            count: 1,
+            max_stack: MAX_STACK_SIZE,
+        }
+    }
+
+    pub fn with_count(mut self, count: u32) -> Self {
+        self.count = count;
+        self
+    }
+
+    pub fn with_max_stack(mut self, max_stack: u32) -> Self {
+        self.max_stack = max_stack.max(1);
+        self
+    }
+
+    /// Whether the two stacks hold the same kind of item and could be merged.
+    pub fn stacks_with(&self, other: &Item) -> bool {
+        self.id == other.id
+    }
+
+    /// How many more items fit onto this stack.
+    pub fn space(&self) -> u32 {
+        self.max_stack.saturating_sub(self.count)
     }
 }
 
+/// Text child showing the count of a ground stack.
+#[derive(Component, Debug)]
+struct StackLabel;
+
+/// Sets up sprites of new ground items, and their physics if they were thrown. Only the authority
+/// simulates the thrown body, clients get a kinematic body that follows the replicated Transform,
+/// so the two never fight over the position.
 fn init_grounditems(
     mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    query: Query<(Entity, &Item), Without<Sprite>>,
-    mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+    game_assets: Res<GameAssets>,
+    query: Query<(Entity, &Item, Option<&ThrownItem>), Without<Sprite>>,
+    client: Option<Res<RepliconClient>>,
 ) {
-    let texture = asset_server.load::<Image>("rpgitems.png");
-    let layout = TextureAtlasLayout::from_grid(UVec2::splat(24), 16, 16, None, None);
-    let texture_atlas_layout = texture_atlas_layouts.add(layout);
-    for (entity, item ) in query.iter() {
-        commands.entity(entity).insert((
-            Sprite::default(),
-            TextureAtlas {
-                layout: texture_atlas_layout.clone(),
-                index: item.texture_index,
-            },
-            texture.clone(),
-            VisibilityBundle::default(),
-            GlobalTransform::default(),
-        ));
+    let authority = client.map_or(true, |client| client.is_disconnected());
+    for (entity, item, thrown) in query.iter() {
+        let mut entity = commands.entity(entity);
+        match thrown {
+            Some(thrown) if authority => {
+                entity.insert(thrown_body(thrown.impulse));
+            }
+            Some(_) => {
+                entity.insert((RigidBody::KinematicPositionBased, Collider::ball(ITEM_COLLIDER_RADIUS)));
+            }
+            None => {}
+        }
+        entity
+            .insert((
+                Sprite::default(),
+                TextureAtlas {
+                    layout: game_assets.item_layout.clone(),
+                    index: item.texture_index,
+                },
+                game_assets.item_texture.clone(),
+                VisibilityBundle::default(),
+                GlobalTransform::default(),
+            ))
+            .with_children(|parent| {
+                parent.spawn((
+                    Text2dBundle {
+                        text: Text::from_section(
+                            "",
+                            TextStyle {
+                                font_size: 8.0,
+                                color: Color::WHITE,
+                                ..Default::default()
+                            },
+                        ),
+                        transform: Transform::from_xyz(6.0, -6.0, 0.1),
+                        ..Default::default()
+                    },
+                    StackLabel,
+                ));
+            });
+    }
+}
+
+fn update_stack_labels(
+    stacks: Query<(&Item, &Children), Or<(Changed<Item>, Added<Children>)>>,
+    mut labels: Query<&mut Text, With<StackLabel>>,
+) {
+    for (item, children) in stacks.iter() {
+        for &child in children.iter() {
+            if let Ok(mut text) = labels.get_mut(child) {
+                text.sections[0].value = if item.count > 1 { item.count.to_string() } else { String::new() };
+            }
+        }
+    }
+}
+
+/// Tuning of how ground items get pulled toward nearby players.
+#[derive(Resource, Debug, Reflect)]
+#[reflect(Resource)]
+pub struct MagnetSettings {
+    /// Items closer than this to a player are attracted.
+    pub radius: f32,
+    /// Items closer than this are left alone for the pickup to take over, and get picked up
+    /// automatically.
+    pub pickup_radius: f32,
+    pub acceleration: f32,
+    pub max_speed: f32,
+}
+
+impl Default for MagnetSettings {
+    fn default() -> Self {
+        Self {
+            radius: 80.0,
+            pickup_radius: 12.0,
+            acceleration: 400.0,
+            max_speed: 220.0,
+        }
     }
 }
 
-fn spawn_ground_item(
+/// Current speed of an item that is being pulled toward a player.
+#[derive(Component, Debug, Default)]
+struct Magnetized {
+    speed: f32,
+}
+
+/// Ground item that was thrown and hasn't come to rest yet. Replicated so clients know the item is
+/// in flight, it is removed once the item settles.
+#[derive(Component, Debug, Default, Serialize, Deserialize)]
+struct ThrownItem {
+    impulse: Vec2,
+    /// Counted on the server only.
+    #[serde(skip)]
+    age: f32,
+}
+
+/// Dynamic body of a thrown item on the authority.
+fn thrown_body(impulse: Vec2) -> impl Bundle {
+    (
+        RigidBody::Dynamic,
+        Collider::ball(ITEM_COLLIDER_RADIUS),
+        Restitution::coefficient(0.7),
+        Damping {
+            linear_damping: 4.0,
+            angular_damping: 1.0,
+        },
+        GravityScale(0.0),
+        LockedAxes::ROTATION_LOCKED,
+        Ccd::enabled(),
+        Velocity::zero(),
+        ExternalImpulse {
+            impulse,
+            torque_impulse: 0.0,
+        },
+    )
+}
+
+/// Spawns a stack of items on the ground. With a throw direction the item gets a short lived rigid body
+/// and slides away from the position until it comes to rest.
+pub fn spawn_ground_item(
     commands: &mut Commands,
     item: &Item,
     position: Vec2,
+    throw_direction: Option<Vec2>,
 ) {
-    commands.spawn((
+    let mut entity = commands.spawn((
         Name::new("Item"),
         Transform::from_translation(position.extend(1.0)),
         item.clone(),
+        Interactable::new(InteractKind::PickUp, item.name.clone()),
         Replicated,
     ));
+    if let Some(direction) = throw_direction.and_then(Vec2::try_normalize) {
+        entity.insert((
+            Transform::from_translation((position + direction * THROW_SPAWN_DISTANCE).extend(1.0)),
+            ThrownItem {
+                impulse: direction * THROW_IMPULSE,
+                age: 0.0,
+            },
+            Interpolated,
+        ));
+    }
+}
+
+/// Removes the physics components of thrown items once they stopped so resting bodies don't pile up.
+/// Without a body nothing writes their Transform anymore, so resting items stop replicating it.
+fn settle_thrown_items(
+    mut commands: Commands,
+    mut items: Query<(Entity, &Velocity, &mut ThrownItem)>,
+    time: Res<Time>,
+) {
+    for (entity, velocity, mut thrown) in items.iter_mut() {
+        // the age isn't replicated, changing it must not resend the component
+        thrown.bypass_change_detection().age += time.delta_seconds();
+        if thrown.age < MIN_THROW_TIME || velocity.linvel.length() > REST_SPEED {
+            continue;
+        }
+        commands.entity(entity).remove::<(
+            RigidBody,
+            Collider,
+            Restitution,
+            Damping,
+            GravityScale,
+            LockedAxes,
+            Ccd,
+            Velocity,
+            ExternalImpulse,
+            ThrownItem,
+        )>();
+    }
+}
+
+/// Drops the kinematic body of a client side item once the server settled it.
+fn drop_settled_bodies(
+    mut commands: Commands,
+    mut settled: RemovedComponents<ThrownItem>,
+    items: Query<(), (With<Item>, With<RigidBody>)>,
+) {
+    for entity in settled.read() {
+        if items.contains(entity) {
+            commands.entity(entity).remove::<(RigidBody, Collider)>();
+        }
+    }
+}
+
+fn attract_items(
+    mut commands: Commands,
+    mut items: Query<(Entity, &mut Transform, &Item, Option<&mut Magnetized>), Without<ThrownItem>>,
+    players: Query<(&Transform, &ItemContainer), (With<Player>, Without<Item>)>,
+    settings: Res<MagnetSettings>,
+    time: Res<Time>,
+) {
+    for (entity, mut transform, item, magnetized) in items.iter_mut() {
+        let item_pos = transform.translation.xy();
+        let target = players
+            .iter()
+            .filter(|(_, container)| container.can_accept(item))
+            .map(|(player_transform, _)| player_transform.translation.xy())
+            .filter(|player_pos| player_pos.distance(item_pos) < settings.radius)
+            .min_by(|a, b| a.distance(item_pos).total_cmp(&b.distance(item_pos)));
+
+        let Some(target) = target else {
+            if magnetized.is_some() {
+                commands.entity(entity).remove::<Magnetized>();
+            }
+            continue;
+        };
+        let Some(mut magnetized) = magnetized else {
+            commands.entity(entity).insert(Magnetized::default());
+            continue;
+        };
+
+        let offset = target - item_pos;
+        let distance = offset.length();
+        if distance <= settings.pickup_radius {
+            continue;
+        }
+        // ease in: start slow and speed up the longer the item is attracted
+        magnetized.speed = (magnetized.speed + settings.acceleration * time.delta_seconds())
+            .min(settings.max_speed);
+        let step = (magnetized.speed * time.delta_seconds()).min(distance - settings.pickup_radius);
+        transform.translation += (offset / distance * step).extend(0.0);
+    }
+}
+
+/// Moves resting ground items within pickup range into the inventory of the closest player that
+/// has room for them. What doesn't fit stays on the ground.
+fn pick_up_touched_items(
+    mut commands: Commands,
+    mut items: Query<(Entity, &Transform, &mut Item), (Without<ThrownItem>, Without<Player>)>,
+    mut players: Query<(&Transform, &mut ItemContainer), With<Player>>,
+    settings: Res<MagnetSettings>,
+) {
+    for (entity, transform, mut item) in items.iter_mut() {
+        let item_pos = transform.translation.xy();
+        let closest = players
+            .iter_mut()
+            .filter(|(player, container)| {
+                player.translation.xy().distance(item_pos) <= settings.pickup_radius
+                    && container.can_accept(&item)
+            })
+            .min_by(|(a, _), (b, _)| {
+                a.translation.xy().distance(item_pos).total_cmp(&b.translation.xy().distance(item_pos))
+            });
+        let Some((_, mut container)) = closest else {
+            continue;
+        };
+        match container.insert(item.clone()) {
+            Ok(()) => commands.entity(entity).despawn_recursive(),
+            Err(ContainerFull(rest)) => item.count = rest.count,
+        }
+    }
+}
+
+/// Merges resting ground items of the same kind lying close together into a single stack.
+/// Thrown and attracted items are left alone until they settle.
+fn merge_ground_items(
+    mut commands: Commands,
+    mut items: Query<(Entity, &Transform, &mut Item), (Without<ThrownItem>, Without<Magnetized>)>,
+) {
+    let mut candidates: Vec<(Entity, Vec2, String)> = items
+        .iter()
+        .filter(|(.., item)| item.space() > 0)
+        .map(|(entity, transform, item)| (entity, transform.translation.xy(), item.id.clone()))
+        .collect();
+    // merge into the older entity so a pile keeps its place
+    candidates.sort_by_key(|(entity, ..)| *entity);
+
+    for i in 0..candidates.len() {
+        let (target, target_pos, ref id) = candidates[i];
+        if items.get(target).map_or(true, |(.., item)| item.count == 0) {
+            // emptied into an earlier stack and about to be despawned
+            continue;
+        }
+        for j in i + 1..candidates.len() {
+            let (source, source_pos, ref source_id) = candidates[j];
+            if source_id != id || target_pos.distance(source_pos) >= MERGE_DISTANCE {
+                continue;
+            }
+            let Ok([mut target_item, mut source_item]) = items.get_many_mut([target, source]) else {
+                continue;
+            };
+            let moved = target_item.2.space().min(source_item.2.count);
+            if moved == 0 {
+                // the source was emptied into an earlier stack, or the target is full
+                continue;
+            }
+            target_item.2.count += moved;
+            source_item.2.count -= moved;
+            if source_item.2.count == 0 {
+                commands.entity(source).despawn_recursive();
+            }
+        }
+    }
 }
 
-fn apply_action(
+pub fn apply_action(
     mut commands: Commands,
-    player_query: Query<(&NetworkOwner, &Transform)>,
+    mut player_query: Query<
+        (&NetworkOwner, &Transform, &Facing, &mut ActionCooldowns),
+        Without<Sleeping>,
+    >,
     mut events: EventReader<FromClient<ActionEvent>>,
-) -> Option<()>{
+    cooldown_config: Res<ActionCooldownConfig>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds();
     for FromClient { client_id, event } in events.read() {
-        if event.action != KeyCode::Space {continue;}
-        if let Some((_,t)) = player_query.iter().find(|p| p.0.0 == client_id.get()) {
-            spawn_ground_item(&mut commands, &Item::new("Bread", "bread", 1), t.translation.xy());
+        if !GameAction::from_key(event.action).contains(&GameAction::UseItem) {continue;}
+        if let Some((_, t, facing, mut cooldowns)) = player_query.iter_mut().find(|p| p.0.0 == client_id.get()) {
+            if !cooldowns.ready(GameAction::UseItem, now, &cooldown_config) {continue;}
+            spawn_ground_item(&mut commands, &Item::new("item.bread", "bread", 1), t.translation.xy(), Some(facing.0));
+            cooldowns.trigger(GameAction::UseItem, now);
         }
     }
-    Some(())
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Body a freshly replicated thrown item gets on an app with the given client state.
+    fn thrown_item_body(client: RepliconClient) -> RigidBody {
+        let mut app = App::new();
+        app.insert_resource(client)
+            .insert_resource(GameAssets {
+                player_texture: Handle::default(),
+                player_layout: Handle::default(),
+                item_texture: Handle::default(),
+                item_layout: Handle::default(),
+                props_texture: Handle::default(),
+                props_layout: Handle::default(),
+                tileset_texture: Handle::default(),
+            })
+            .add_systems(Update, init_grounditems);
+        let item = app
+            .world_mut()
+            .spawn((
+                Item::new("item.stone", "stone", 11),
+                ThrownItem {
+                    impulse: Vec2::X,
+                    age: 0.0,
+                },
+                Transform::default(),
+            ))
+            .id();
+        app.update();
+        *app.world().get::<RigidBody>(item).unwrap()
+    }
+
+    #[test]
+    fn only_the_listen_server_simulates_thrown_items() {
+        // the host of a listen server has a client that never connects
+        assert_eq!(thrown_item_body(RepliconClient::default()), RigidBody::Dynamic);
+
+        let mut remote = RepliconClient::default();
+        remote.set_status(RepliconClientStatus::Connected {
+            client_id: Some(ClientId::new(1)),
+        });
+        assert_eq!(thrown_item_body(remote), RigidBody::KinematicPositionBased);
+    }
+}
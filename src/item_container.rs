@@ -1,8 +1,24 @@
-use bevy::prelude::*;
-use bevy_replicon::{core::Replicated, prelude::AppRuleExt};
+use bevy::{ecs::entity::MapEntities, prelude::*};
+use bevy_replicon::{core::ClientId, prelude::*};
+use bevy_replicon_snap::NetworkOwner;
 use serde::{Deserialize, Serialize};
 
-use crate::{item::Item, read_cli, Cli};
+use crate::{
+    composter::{self, Composter},
+    furnace::{self, Furnace},
+    container_mirror::ContainerMirror,
+    item::Item,
+    item_registry::ItemRegistry,
+    player::Player,
+};
+
+/// Maximum distance between a player and an external container to open it or move items.
+pub const CONTAINER_INTERACTION_RANGE: f32 = 64.0;
+
+/// Server side range check for container interactions, e.g. item transfers.
+pub fn in_interaction_range(player_pos: Vec2, container_pos: Vec2) -> bool {
+    player_pos.distance(container_pos) <= CONTAINER_INTERACTION_RANGE
+}
 
 
 pub struct ItemContainerPlugin;
@@ -12,23 +28,444 @@ impl Plugin for ItemContainerPlugin {
     fn build(&self, _app: &mut App) {
         _app
             .register_type::<ItemContainer>()
-            .replicate::<ItemContainer>()
-            .add_systems(Startup, insert_dummy_container.after(read_cli));
+            // only sent through the mirror, which is hidden from clients that shouldn't see it
+            .replicate_group::<(ItemContainer, ContainerMirror)>()
+            .add_mapped_client_event::<DestroyItemEvent>(ChannelKind::Ordered)
+            .add_mapped_client_event::<TransferItemEvent>(ChannelKind::Ordered)
+            .add_mapped_client_event::<SlotMoveEvent>(ChannelKind::Ordered)
+            .add_server_event::<InventoryFullEvent>(ChannelKind::Ordered)
+            .add_systems(Update, destroy_items.run_if(has_authority))
+            .add_systems(Update, transfer_items.run_if(has_authority))
+            .add_systems(Update, move_slots.run_if(has_authority));
     }
 }
 
 
-#[derive(Debug, Component, Serialize, Deserialize, Reflect)]
-pub struct ItemContainer {pub items: Vec<Item>,}
+pub const DEFAULT_CONTAINER_CAPACITY: usize = 32;
+
+/// A fixed number of slots, each either empty or holding one stack of items.
+#[derive(Debug, Component, Clone, Serialize, Deserialize, Reflect)]
+pub struct ItemContainer {
+    slots: Vec<Option<Item>>,
+}
+
+/// A stack didn't fit into a container. Holds the part that was left over.
+#[derive(Debug, Clone)]
+pub struct ContainerFull(pub Item);
+
+impl ItemContainer {
+    /// A container with `capacity` slots, the first ones filled with `items`. Items beyond the
+    /// capacity are dropped.
+    pub fn new(items: Vec<Item>, capacity: usize) -> Self {
+        let mut slots: Vec<Option<Item>> = items.into_iter().map(Some).collect();
+        slots.resize(capacity, None);
+        Self { slots }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
 
-fn insert_dummy_container(
-    mut commands: Commands,
-    cli: Res<Cli>,
+    /// The stack in slot `index`, if the slot exists and isn't empty.
+    pub fn get(&self, index: usize) -> Option<&Item> {
+        self.slots.get(index)?.as_ref()
+    }
+
+    /// All stacks in slot order, empty slots are skipped.
+    pub fn items(&self) -> impl Iterator<Item = &Item> {
+        self.slots.iter().flatten()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.iter().all(Option::is_none)
+    }
+
+    /// Empties every slot and returns the stacks that were in them.
+    pub fn take_all(&mut self) -> Vec<Item> {
+        self.slots.iter_mut().filter_map(Option::take).collect()
+    }
+
+    /// Slot that takes at least one item of the stack: a stack of the same kind with room left,
+    /// otherwise the first empty slot.
+    pub fn find_space(&self, item: &Item) -> Option<usize> {
+        self.slots
+            .iter()
+            .position(|slot| slot.as_ref().is_some_and(|stack| stack.stacks_with(item) && stack.space() > 0))
+            .or_else(|| self.slots.iter().position(Option::is_none))
+    }
+
+    pub fn can_accept(&self, item: &Item) -> bool {
+        self.find_space(item).is_some()
+    }
+
+    /// Puts a stack into the container, filling up stacks of the same kind before empty slots.
+    /// Whatever fits stays in the container, the error holds the rest.
+    pub fn insert(&mut self, mut item: Item) -> Result<(), ContainerFull> {
+        while item.count > 0 {
+            let Some(index) = self.find_space(&item) else {
+                return Err(ContainerFull(item));
+            };
+            if let Some(stack) = &mut self.slots[index] {
+                let moved = stack.space().min(item.count);
+                stack.count += moved;
+                item.count -= moved;
+            } else {
+                let moved = item.count.min(item.max_stack.max(1));
+                self.slots[index] = Some(item.clone().with_count(moved));
+                item.count -= moved;
+            }
+        }
+        Ok(())
+    }
+
+    /// Puts as much of the stack as fits into slot `index`, onto a stack of the same kind or into
+    /// the empty slot. The error holds the rest.
+    pub fn insert_into(&mut self, index: usize, mut item: Item) -> Result<(), ContainerFull> {
+        let Some(slot) = self.slots.get_mut(index) else {
+            return Err(ContainerFull(item));
+        };
+        if slot.is_none() {
+            let moved = item.count.min(item.max_stack.max(1));
+            *slot = Some(item.clone().with_count(moved));
+            item.count -= moved;
+        } else if let Some(stack) = slot.as_mut().filter(|stack| stack.stacks_with(&item)) {
+            let moved = stack.space().min(item.count);
+            stack.count += moved;
+            item.count -= moved;
+        }
+        if item.count == 0 {
+            Ok(())
+        } else {
+            Err(ContainerFull(item))
+        }
+    }
+
+    /// Total number of items with the given id over all stacks.
+    pub fn count(&self, id: &str) -> u32 {
+        self.items().filter(|item| item.id == id).map(|item| item.count).sum()
+    }
+
+    /// Removes `count` items with the given id, emptying the last stacks first, and returns them as
+    /// one stack. Nothing is removed if there are fewer.
+    pub fn remove(&mut self, id: &str, count: u32) -> Option<Item> {
+        if count == 0 || self.count(id) < count {
+            return None;
+        }
+        let mut removed: Option<Item> = None;
+        let mut remaining = count;
+        for slot in self.slots.iter_mut().rev() {
+            let Some(stack) = slot.as_mut().filter(|stack| stack.id == id) else {
+                continue;
+            };
+            let taken = stack.count.min(remaining);
+            stack.count -= taken;
+            remaining -= taken;
+            match removed.as_mut() {
+                Some(removed) => removed.count += taken,
+                None => removed = Some(stack.clone().with_count(taken)),
+            }
+            if stack.count == 0 {
+                *slot = None;
+            }
+            if remaining == 0 {
+                break;
+            }
+        }
+        removed
+    }
+
+    /// Removes up to `count` items from the stack in slot `index`.
+    pub fn take_from_slot(&mut self, index: usize, count: u32) -> Option<Item> {
+        let slot = self.slots.get_mut(index)?;
+        let stack = slot.as_mut()?;
+        if count >= stack.count {
+            return slot.take();
+        }
+        stack.count -= count;
+        Some(stack.clone().with_count(count))
+    }
+
+    /// Moves the stack in slot `from` onto slot `to`, see [`move_stack`]. Returns false if either
+    /// slot doesn't exist.
+    pub fn move_slot(&mut self, from: usize, to: usize) -> bool {
+        if from == to || from >= self.slots.len() || to >= self.slots.len() {
+            return false;
+        }
+        let (source, target) = if from < to {
+            let (left, right) = self.slots.split_at_mut(to);
+            (&mut left[from], &mut right[0])
+        } else {
+            let (left, right) = self.slots.split_at_mut(from);
+            (&mut right[0], &mut left[to])
+        };
+        move_stack(source, target);
+        true
+    }
+
+    /// Like [`Self::move_slot`], with slot `to` in another container.
+    pub fn move_slot_to(&mut self, from: usize, other: &mut ItemContainer, to: usize) -> bool {
+        match (self.slots.get_mut(from), other.slots.get_mut(to)) {
+            (Some(source), Some(target)) => {
+                move_stack(source, target);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Swaps the contents of two slots. Returns false if either slot doesn't exist.
+    pub fn swap(&mut self, a: usize, b: usize) -> bool {
+        if a >= self.slots.len() || b >= self.slots.len() {
+            return false;
+        }
+        self.slots.swap(a, b);
+        true
+    }
+}
+
+/// Puts the source stack onto the target slot. A stack of the same kind there is filled up as far
+/// as it goes, any other stack swaps places with the source.
+fn move_stack(source: &mut Option<Item>, target: &mut Option<Item>) {
+    match (source.as_mut(), target.as_mut()) {
+        (Some(stack), Some(other)) if other.stacks_with(stack) => {
+            let moved = other.space().min(stack.count);
+            other.count += moved;
+            stack.count -= moved;
+            if stack.count == 0 {
+                *source = None;
+            }
+        }
+        _ => std::mem::swap(source, target),
+    }
+}
+
+/// Tells a client that items didn't fit into its inventory.
+#[derive(Event, Debug, Clone, Serialize, Deserialize)]
+pub struct InventoryFullEvent;
+
+/// Sends an [`InventoryFullEvent`] to the client.
+pub fn notify_full(writer: &mut EventWriter<ToClients<InventoryFullEvent>>, client_id: ClientId) {
+    writer.send(ToClients {
+        mode: SendMode::Direct(client_id),
+        event: InventoryFullEvent,
+    });
+}
+
+/// Permanently removes `count` items with the given id from a container the sender owns.
+#[derive(Event, Debug, Clone, Serialize, Deserialize)]
+pub struct DestroyItemEvent {
+    pub container: Entity,
+    pub item_id: String,
+    pub count: u32,
+}
+
+impl MapEntities for DestroyItemEvent {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.container = entity_mapper.map_entity(self.container);
+    }
+}
+
+/// Moves the stack in slot `index` of `from` into `to`, as much of it as fits. `item_id` has to
+/// match the slot, so a request based on an outdated view of the container can't move a different
+/// item.
+#[derive(Event, Debug, Clone, Serialize, Deserialize)]
+pub struct TransferItemEvent {
+    pub from: Entity,
+    pub to: Entity,
+    pub index: usize,
+    pub item_id: String,
+}
+
+impl MapEntities for TransferItemEvent {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.from = entity_mapper.map_entity(self.from);
+        self.to = entity_mapper.map_entity(self.to);
+    }
+}
+
+/// Drops the stack in slot `from_index` of `from` onto slot `to_index` of `to`, both containers may
+/// be the same. `item_id` has to match the source slot, like for [`TransferItemEvent`].
+#[derive(Event, Debug, Clone, Serialize, Deserialize)]
+pub struct SlotMoveEvent {
+    pub from: Entity,
+    pub from_index: usize,
+    pub to: Entity,
+    pub to_index: usize,
+    pub item_id: String,
+}
+
+impl MapEntities for SlotMoveEvent {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.from = entity_mapper.map_entity(self.from);
+        self.to = entity_mapper.map_entity(self.to);
+    }
+}
+
+/// Whether a client may take items from or put items into a container: it has to own it, or
+/// the container has to be an unowned one in the world within reach of the client's player.
+fn can_access(
+    client_id: ClientId,
+    container: Entity,
+    owners: &Query<&NetworkOwner>,
+    transforms: &Query<&Transform>,
+    players: &Query<(Entity, &NetworkOwner), With<Player>>,
+) -> bool {
+    if let Ok(owner) = owners.get(container) {
+        return owner.0 == client_id.get();
+    }
+    let Some((player, _)) = players.iter().find(|(_, owner)| owner.0 == client_id.get()) else {
+        return false;
+    };
+    match (transforms.get(player), transforms.get(container)) {
+        (Ok(player), Ok(container)) => {
+            in_interaction_range(player.translation.xy(), container.translation.xy())
+        }
+        _ => false,
+    }
+}
+
+fn transfer_items(
+    mut events: EventReader<FromClient<TransferItemEvent>>,
+    mut full_events: EventWriter<ToClients<InventoryFullEvent>>,
+    mut containers: Query<&mut ItemContainer>,
+    owners: Query<&NetworkOwner>,
+    transforms: Query<&Transform>,
+    players: Query<(Entity, &NetworkOwner), With<Player>>,
+    composters: Query<&Composter>,
+    furnaces: Query<(), With<Furnace>>,
+    item_registry: Res<ItemRegistry>,
 ) {
-    if let Cli::Server {.. } = *cli {
-        let items = vec![Item::new("Bread", "bread", 1)];
-        commands.spawn((Name::new("item container"), ItemContainer {
-            items
-        }, Replicated));
+    for FromClient { client_id, event } in events.read() {
+        if event.from == event.to
+            || !can_access(*client_id, event.from, &owners, &transforms, &players)
+            || !can_access(*client_id, event.to, &owners, &transforms, &players)
+        {
+            debug!("{client_id:?} can't transfer between {:?} and {:?}", event.from, event.to);
+            continue;
+        }
+        let Ok([mut from, mut to]) = containers.get_many_mut([event.from, event.to]) else {
+            continue;
+        };
+        let Some(item) = from.get(event.index).filter(|item| item.id == event.item_id) else {
+            debug!("Rejected outdated transfer of {} from {client_id:?}", event.item_id);
+            continue;
+        };
+        if let Ok(composter) = composters.get(event.to) {
+            if !composter::accepts(composter, item, &item_registry) {
+                debug!("Composter rejected {} from {client_id:?}", item.id);
+                continue;
+            }
+        }
+        // furnaces sort items into the slot of their role
+        let furnace_slot = if furnaces.contains(event.to) {
+            let Some(slot) = furnace::slot_for(item, &item_registry) else {
+                debug!("Furnace rejected {} from {client_id:?}", item.id);
+                continue;
+            };
+            Some(slot)
+        } else {
+            None
+        };
+        let count = item.count;
+        let inserted = match furnace_slot {
+            Some(slot) => to.insert_into(slot, item.clone()),
+            None => to.insert(item.clone()),
+        };
+        // only what fits leaves the slot, the rest stays where it was
+        let moved = match inserted {
+            Ok(()) => count,
+            Err(ContainerFull(rest)) => count - rest.count,
+        };
+        if moved == 0 {
+            notify_full(&mut full_events, *client_id);
+            continue;
+        }
+        from.take_from_slot(event.index, moved);
     }
-}
\ No newline at end of file
+}
+
+fn move_slots(
+    mut events: EventReader<FromClient<SlotMoveEvent>>,
+    mut containers: Query<&mut ItemContainer>,
+    owners: Query<&NetworkOwner>,
+    transforms: Query<&Transform>,
+    players: Query<(Entity, &NetworkOwner), With<Player>>,
+    composters: Query<&Composter>,
+    furnaces: Query<(), With<Furnace>>,
+    item_registry: Res<ItemRegistry>,
+) {
+    let accepts = |container: Entity, slot: usize, item: &Item| {
+        let composter_accepts = composters
+            .get(container)
+            .map_or(true, |composter| composter::accepts(composter, item, &item_registry));
+        let furnace_accepts =
+            !furnaces.contains(container) || furnace::slot_for(item, &item_registry) == Some(slot);
+        composter_accepts && furnace_accepts
+    };
+    // a different stack in the target slot ends up in the source slot
+    let allowed = |event: &SlotMoveEvent, item: &Item, displaced: Option<&Item>| {
+        accepts(event.to, event.to_index, item)
+            && displaced.map_or(true, |other| accepts(event.from, event.from_index, other))
+    };
+    for FromClient { client_id, event } in events.read() {
+        if !can_access(*client_id, event.from, &owners, &transforms, &players)
+            || !can_access(*client_id, event.to, &owners, &transforms, &players)
+        {
+            debug!("{client_id:?} can't move items between {:?} and {:?}", event.from, event.to);
+            continue;
+        }
+        if event.from == event.to {
+            let Ok(mut container) = containers.get_mut(event.from) else {
+                continue;
+            };
+            let Some(item) = container.get(event.from_index).filter(|item| item.id == event.item_id) else {
+                debug!("Rejected outdated move of {} from {client_id:?}", event.item_id);
+                continue;
+            };
+            let displaced = container.get(event.to_index).filter(|other| !other.stacks_with(item));
+            if !allowed(event, item, displaced) {
+                debug!("{:?} rejected the move of {} from {client_id:?}", event.to, item.id);
+                continue;
+            }
+            container.move_slot(event.from_index, event.to_index);
+            continue;
+        }
+        let Ok([mut from, mut to]) = containers.get_many_mut([event.from, event.to]) else {
+            continue;
+        };
+        let Some(item) = from.get(event.from_index).filter(|item| item.id == event.item_id) else {
+            debug!("Rejected outdated move of {} from {client_id:?}", event.item_id);
+            continue;
+        };
+        let displaced = to.get(event.to_index).filter(|other| !other.stacks_with(item));
+        if !allowed(event, item, displaced) {
+            debug!("{:?} rejected the move of {} from {client_id:?}", event.to, item.id);
+            continue;
+        }
+        from.move_slot_to(event.from_index, &mut to, event.to_index);
+    }
+}
+
+fn destroy_items(
+    mut events: EventReader<FromClient<DestroyItemEvent>>,
+    mut container_query: Query<(&mut ItemContainer, &NetworkOwner)>,
+) {
+    for FromClient { client_id, event } in events.read() {
+        let Ok((mut container, owner)) = container_query.get_mut(event.container) else {
+            debug!("{client_id:?} tried to destroy items of a container without owner");
+            continue;
+        };
+        if owner.0 != client_id.get() {
+            warn!("{client_id:?} tried to destroy items of a container it doesn't own");
+            continue;
+        }
+        if container.remove(&event.item_id, event.count).is_none() {
+            debug!(
+                "Rejected destroying {} {} from {client_id:?}, only {} available",
+                event.count,
+                event.item_id,
+                container.count(&event.item_id)
+            );
+        }
+    }
+}
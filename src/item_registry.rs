@@ -0,0 +1,120 @@
+use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    item::{Item, MAX_STACK_SIZE},
+    ron_asset::RonAssetPlugin,
+};
+
+const ITEMS_PATH: &str = "data/items.item.ron";
+
+pub struct ItemRegistryPlugin;
+
+impl Plugin for ItemRegistryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(RonAssetPlugin::<ItemDefinitions>::new(&["item.ron"]))
+            .add_systems(PreStartup, load_item_registry)
+            .add_systems(PreUpdate, update_item_registry);
+    }
+}
+
+/// Static data of an item kind, keyed by the item id.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemDefinition {
+    /// Localization key of the display name.
+    pub name: String,
+    pub texture_index: usize,
+    /// Can be turned into fertilizer by a composter.
+    #[serde(default)]
+    pub compostable: bool,
+    /// Item a furnace turns this one into.
+    #[serde(default)]
+    pub smelts_into: Option<String>,
+    /// Seconds a single item burns as furnace fuel.
+    #[serde(default)]
+    pub fuel_time: Option<f32>,
+    /// Kind of tool the item works as, e.g. for harvesting resource nodes.
+    #[serde(default)]
+    pub tool: Option<ToolKind>,
+    /// Hunger a single item restores when eaten.
+    #[serde(default)]
+    pub food: Option<f32>,
+    /// Most items of this kind a single slot can hold.
+    #[serde(default = "default_max_stack")]
+    pub max_stack: u32,
+}
+
+fn default_max_stack() -> u32 {
+    MAX_STACK_SIZE
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ToolKind {
+    Axe,
+    Pickaxe,
+}
+
+#[derive(Asset, TypePath, Debug, Serialize, Deserialize)]
+pub struct ItemDefinitions {
+    pub items: HashMap<String, ItemDefinition>,
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct ItemRegistry {
+    handle: Handle<ItemDefinitions>,
+    items: HashMap<String, ItemDefinition>,
+}
+
+impl ItemRegistry {
+    pub fn get(&self, id: &str) -> Option<&ItemDefinition> {
+        self.items.get(id)
+    }
+
+    /// A new item of the given kind.
+    pub fn create(&self, id: &str) -> Option<Item> {
+        self.get(id)
+            .map(|def| Item::new(&def.name, id, def.texture_index).with_max_stack(def.max_stack))
+    }
+
+    pub fn is_compostable(&self, id: &str) -> bool {
+        self.get(id).is_some_and(|def| def.compostable)
+    }
+
+    pub fn smelt_result(&self, id: &str) -> Option<&str> {
+        self.get(id).and_then(|def| def.smelts_into.as_deref())
+    }
+
+    pub fn fuel_time(&self, id: &str) -> Option<f32> {
+        self.get(id).and_then(|def| def.fuel_time)
+    }
+
+    pub fn tool(&self, id: &str) -> Option<ToolKind> {
+        self.get(id).and_then(|def| def.tool)
+    }
+
+    pub fn food(&self, id: &str) -> Option<f32> {
+        self.get(id).and_then(|def| def.food)
+    }
+}
+
+fn load_item_registry(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(ItemRegistry {
+        handle: asset_server.load(ITEMS_PATH),
+        items: HashMap::default(),
+    });
+}
+
+fn update_item_registry(
+    mut events: EventReader<AssetEvent<ItemDefinitions>>,
+    mut registry: ResMut<ItemRegistry>,
+    definitions: Res<Assets<ItemDefinitions>>,
+) {
+    for event in events.read() {
+        if !event.is_loaded_with_dependencies(&registry.handle) && !event.is_modified(&registry.handle) {
+            continue;
+        }
+        if let Some(loaded) = definitions.get(&registry.handle) {
+            registry.items = loaded.items.clone();
+        }
+    }
+}
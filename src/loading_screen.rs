@@ -0,0 +1,146 @@
+use bevy::prelude::*;
+use bevy_ecs_tilemap::tiles::TileStorage;
+use bevy_mod_picking::picking_core::Pickable;
+use bevy_replicon::prelude::*;
+use bevy_replicon_snap::NetworkOwner;
+
+use crate::{
+    chunk::ChunkIndexMap,
+    game_assets::GameAssets,
+    localization::Localization,
+    player::Player,
+    world::chunk_index_at,
+};
+
+/// Seconds a client waits for its player before giving up.
+const LOADING_TIMEOUT: f32 = 20.0;
+
+pub struct LoadingScreenPlugin;
+
+impl Plugin for LoadingScreenPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<GameState>()
+            .add_systems(OnEnter(GameState::Loading), setup_loading_screen)
+            .add_systems(OnExit(GameState::Loading), remove_loading_screen)
+            .add_systems(Update, check_loading.run_if(in_state(GameState::Loading)));
+    }
+}
+
+/// Clients stay in `Loading` until the world around their player has arrived, the server only
+/// waits for its assets.
+#[derive(States, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameState {
+    #[default]
+    Loading,
+    InGame,
+}
+
+#[derive(Component, Debug)]
+struct LoadingScreen;
+
+#[derive(Component, Debug)]
+struct LoadingText;
+
+fn setup_loading_screen(mut commands: Commands) {
+    commands
+        .spawn((
+            Name::new("Loading Screen"),
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    position_type: PositionType::Absolute,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..Default::default()
+                },
+                background_color: Color::BLACK.into(),
+                ..Default::default()
+            },
+            ZIndex::Global(i32::MAX),
+            Pickable::IGNORE,
+            LoadingScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "Connecting...",
+                    TextStyle {
+                        font_size: 32.0,
+                        color: Color::WHITE,
+                        ..Default::default()
+                    },
+                ),
+                LoadingText,
+            ));
+        });
+}
+
+fn remove_loading_screen(mut commands: Commands, query: Query<Entity, With<LoadingScreen>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Moves on to `InGame` once (a) the local player is replicated, (b) its chunk and the ring of
+/// neighbours are spawned with their tilemaps and (c) all game assets are loaded.
+fn check_loading(
+    mut next_state: ResMut<NextState<GameState>>,
+    mut text_query: Query<&mut Text, With<LoadingText>>,
+    mut waited: Local<f32>,
+    player_query: Query<(&NetworkOwner, &Transform), With<Player>>,
+    tilemap_query: Query<(), With<TileStorage>>,
+    chunk_map: Res<ChunkIndexMap>,
+    game_assets: Res<GameAssets>,
+    asset_server: Res<AssetServer>,
+    client: Res<RepliconClient>,
+    server: Res<RepliconServer>,
+    time: Res<Time>,
+    localization: Res<Localization>,
+) {
+    let assets_loaded = game_assets.all_loaded(&asset_server);
+    if server.is_running() {
+        if assets_loaded {
+            next_state.set(GameState::InGame);
+        }
+        return;
+    }
+
+    *waited += time.delta_seconds();
+    let local_player = client.id().and_then(|client_id| {
+        player_query
+            .iter()
+            .find(|(owner, _)| owner.0 == client_id.get())
+    });
+    let status = match local_player {
+        None if *waited > LOADING_TIMEOUT => {
+            "loading.no_player"
+        }
+        None if client.is_connected() => "loading.waiting",
+        None => "loading.connecting",
+        Some((_, transform)) => {
+            let center = chunk_index_at(transform.translation.xy());
+            let chunks_ready = (-1..=1)
+                .flat_map(|x| (-1..=1).map(move |y| center + IVec2::new(x, y)))
+                .all(|index| {
+                    chunk_map
+                        .get(index)
+                        .is_some_and(|chunk| tilemap_query.contains(chunk))
+                });
+            match (chunks_ready, assets_loaded) {
+                (true, true) => {
+                    next_state.set(GameState::InGame);
+                    return;
+                }
+                (false, _) => "loading.world",
+                (true, false) => "loading.assets",
+            }
+        }
+    };
+    let status = localization.tr(status);
+    if let Ok(mut text) = text_query.get_single_mut() {
+        if text.sections[0].value != status {
+            text.sections[0].value = status;
+        }
+    }
+}
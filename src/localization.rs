@@ -0,0 +1,132 @@
+use std::sync::Mutex;
+
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::ron_asset::RonAssetPlugin;
+
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+pub struct LocalizationPlugin;
+
+impl Plugin for LocalizationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(RonAssetPlugin::<LanguageStrings>::new(&["lang.ron"]))
+            .register_type::<Language>()
+            .init_resource::<Language>()
+            .init_resource::<Localization>()
+            .add_systems(PreUpdate, (load_language, update_localization).chain())
+            .add_systems(Update, update_localized_texts);
+    }
+}
+
+/// Selected language, the name of a file in `assets/lang`. Changing it at runtime loads the file
+/// and refreshes all [`LocalizedText`]s.
+#[derive(Resource, Debug, Reflect)]
+#[reflect(Resource)]
+pub struct Language(pub String);
+
+impl Default for Language {
+    fn default() -> Self {
+        Self(DEFAULT_LANGUAGE.to_string())
+    }
+}
+
+#[derive(Asset, TypePath, Debug, Serialize, Deserialize)]
+pub struct LanguageStrings {
+    pub strings: HashMap<String, String>,
+}
+
+/// User facing strings of the current language, looked up by key.
+#[derive(Resource, Debug, Default)]
+pub struct Localization {
+    handle: Handle<LanguageStrings>,
+    strings: HashMap<String, String>,
+    /// Keys that were already warned about, so a missing key doesn't flood the log.
+    missing: Mutex<HashSet<String>>,
+}
+
+impl Localization {
+    /// The string of `key`, or the key itself if the language doesn't have it.
+    pub fn tr(&self, key: &str) -> String {
+        if let Some(value) = self.strings.get(key) {
+            return value.clone();
+        }
+        // nothing is loaded yet, every key would be missing
+        if !self.strings.is_empty() {
+            if let Ok(mut missing) = self.missing.lock() {
+                if missing.insert(key.to_string()) {
+                    warn!("Missing translation for {key}");
+                }
+            }
+        }
+        key.to_string()
+    }
+
+    /// Like [`Localization::tr`], replacing each `{name}` placeholder with its argument.
+    pub fn tr_args(&self, key: &str, args: &[(&str, String)]) -> String {
+        let mut text = self.tr(key);
+        for (name, value) in args {
+            text = text.replace(&format!("{{{name}}}"), value);
+        }
+        text
+    }
+}
+
+/// Text whose first section shows the translation of `key`, kept up to date on language changes.
+#[derive(Component, Debug, Clone)]
+pub struct LocalizedText {
+    pub key: String,
+}
+
+impl LocalizedText {
+    pub fn new(key: impl Into<String>) -> Self {
+        Self { key: key.into() }
+    }
+}
+
+fn load_language(
+    mut localization: ResMut<Localization>,
+    language: Res<Language>,
+    asset_server: Res<AssetServer>,
+) {
+    if language.is_changed() {
+        localization.handle = asset_server.load(format!("lang/{}.lang.ron", language.0));
+    }
+}
+
+fn update_localization(
+    mut events: EventReader<AssetEvent<LanguageStrings>>,
+    mut localization: ResMut<Localization>,
+    definitions: Res<Assets<LanguageStrings>>,
+) {
+    for event in events.read() {
+        if !event.is_loaded_with_dependencies(&localization.handle)
+            && !event.is_modified(&localization.handle)
+        {
+            continue;
+        }
+        if let Some(loaded) = definitions.get(&localization.handle) {
+            localization.strings = loaded.strings.clone();
+            localization.missing = Mutex::default();
+            info!("Loaded {} translations", localization.strings.len());
+        }
+    }
+}
+
+fn update_localized_texts(
+    mut texts: Query<(Ref<LocalizedText>, &mut Text)>,
+    localization: Res<Localization>,
+) {
+    for (localized, mut text) in texts.iter_mut() {
+        if !localization.is_changed() && !localized.is_changed() {
+            continue;
+        }
+        if let Some(section) = text.sections.first_mut() {
+            section.value = localization.tr(&localized.key);
+        }
+    }
+}
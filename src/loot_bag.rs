@@ -0,0 +1,86 @@
+use bevy::prelude::*;
+use bevy_replicon::{client::ClientSet, core::Replicated, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game_assets::GameAssets,
+    interact::{InteractKind, Interactable},
+    item::Item,
+    item_container::ItemContainer,
+};
+
+/// Index of the bag icon in the item atlas.
+const BAG_TEXTURE_INDEX: usize = 173;
+/// Seconds until an untouched bag disappears together with its items.
+pub const BAG_LIFETIME: f32 = 30.0 * 60.0;
+
+pub struct LootBagPlugin;
+
+impl Plugin for LootBagPlugin {
+    fn build(&self, app: &mut App) {
+        app.replicate::<LootBag>()
+            .add_systems(PreUpdate, init_loot_bags.after(ClientSet::Receive))
+            .add_systems(Update, remove_loot_bags.run_if(server_running));
+    }
+}
+
+/// Holds the inventory of a player that died, openable like a chest.
+#[derive(Component, Debug, Serialize, Deserialize)]
+pub struct LootBag {
+    /// Seconds until the bag expires. Counted down on the server only.
+    pub remaining: f32,
+}
+
+/// Save format of a bag inside its chunk file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LootBagData {
+    pub position: Vec2,
+    pub items: Vec<Item>,
+    pub remaining: f32,
+}
+
+pub fn spawn_loot_bag(commands: &mut Commands, items: Vec<Item>, position: Vec2, remaining: f32) {
+    let capacity = items.len();
+    commands.spawn((
+        Name::new("Loot Bag"),
+        LootBag { remaining },
+        Transform::from_translation(position.extend(0.5)),
+        ItemContainer::new(items, capacity),
+        Interactable::new(InteractKind::Open, "object.bag"),
+        Replicated,
+    ));
+}
+
+/// Despawns bags once they are emptied or expired.
+fn remove_loot_bags(
+    mut commands: Commands,
+    mut bags: Query<(Entity, &mut LootBag, &ItemContainer)>,
+    time: Res<Time>,
+) {
+    for (entity, mut bag, container) in bags.iter_mut() {
+        // the timer isn't shown to clients, don't replicate it every frame
+        bag.bypass_change_detection().remaining -= time.delta_seconds();
+        if bag.remaining <= 0.0 || container.is_empty() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn init_loot_bags(
+    mut commands: Commands,
+    game_assets: Res<GameAssets>,
+    query: Query<Entity, (With<LootBag>, Without<Sprite>)>,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).insert((
+            Sprite::default(),
+            TextureAtlas {
+                layout: game_assets.item_layout.clone(),
+                index: BAG_TEXTURE_INDEX,
+            },
+            game_assets.item_texture.clone(),
+            VisibilityBundle::default(),
+            GlobalTransform::default(),
+        ));
+    }
+}
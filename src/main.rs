@@ -1,44 +1,86 @@
 use std::{
-    error::Error,
-    net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket},
-    time::SystemTime,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::PathBuf,
 };
 
 use bevy::{
     ecs::entity::MapEntities, log::LogPlugin, prelude::*, window::PresentMode, winit::WinitSettings
 };
+use bevy_ecs_tilemap::tiles::TilePos;
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 use bevy_mod_picking::{debug::DebugPickingMode, DefaultPickingPlugins};
 use bevy_rand::{plugin::EntropyPlugin, prelude::{EntropyComponent, WyRand}};
 use bevy_rapier2d::prelude::*;
 use bevy_replicon::prelude::*;
-use bevy_replicon_renet::RenetChannelsExt;
-use bevy_replicon_renet::{
-    renet::{
-        transport::{
-            ClientAuthentication, NetcodeClientTransport, NetcodeServerTransport,
-            ServerAuthentication, ServerConfig,
-        },
-        ConnectionConfig, RenetClient, RenetServer,
-    },
-    RepliconRenetPlugins,
-};
+use bevy_replicon_renet::RepliconRenetPlugins;
 use bevy_replicon_snap::{
     interpolation::AppInterpolationExt,
     NetworkOwner, SnapshotInterpolationPlugin,
 };
-use camera::CameraPlugin;
-use chunk::ChunkPlugin;
+use action::{ActionCooldownConfig, ActionCooldowns, ActionPlugin, GameAction};
+use camera::{CameraPlugin, Spectator};
+use chunk::{ChunkPlugin, WorldSeed};
 use clap::Parser;
+use debug_overlay::DebugOverlayPlugin;
+use game_assets::GameAssetsPlugin;
+use ground_registry::GroundRegistryPlugin;
+use tile_tick::TileTickPlugin;
+use click_to_move::ClickToMovePlugin;
+use critter::CritterPlugin;
+use game_time::GameTimePlugin;
+use health::HealthPlugin;
+use mob::MobPlugin;
+use melee::MeleePlugin;
+use loading_screen::{GameState, LoadingScreenPlugin};
+use server_status::{query_status, ServerInfo, ServerStatusPlugin};
 use inventory_ui::InventoryUIPlugin;
 use item::ItemPlugin;
 use item_container::ItemContainerPlugin;
-use player::{PlayerBundle, PlayerPlugin};
+use minimap::MinimapPlugin;
+use move_validation::MoveValidationPlugin;
+use player::{PlayerBundle, PlayerPlugin, Respawning};
+use player_list::PlayerListPlugin;
 use serde::{Deserialize, Serialize};
 use tile_picker_backend::TilemapBackend;
 use world::WorldPlugin;
 use world_object::WorldObjectPlugin;
+use world_meta::{WorldMeta, WorldMetaPlugin, WorldPaths};
+use interact::InteractPlugin;
+use autotile::AutotilePlugin;
+use fence::FencePlugin;
+use console::ConsolePlugin;
+use loot_bag::LootBagPlugin;
+use sign::{editing_sign, SignPlugin};
+use water::WaterPlugin;
+use emote::EmotePlugin;
+use sleep::{local_player_sleeping, SleepPlugin};
+use item_registry::ItemRegistryPlugin;
+use composter::ComposterPlugin;
+use soil::SoilPlugin;
+use creative::CreativePlugin;
+use tile_prediction::TilePredictionPlugin;
+use charge::ChargePlugin;
+use container_mirror::ContainerMirrorPlugin;
+use crafting::CraftingPlugin;
+use localization::{Language, LocalizationPlugin};
+use map_export::MapExportPlugin;
+use net_profiler::NetProfilerPlugin;
+use sapling::SaplingPlugin;
+use chunk_stream::ChunkStreamPlugin;
+use debug_inspector::DebugInspectorPlugin;
+use screenshot::ScreenshotPlugin;
+use chest::ChestPlugin;
+use furnace::FurnacePlugin;
+use equipment::EquipmentPlugin;
+use hunger::HungerPlugin;
+use hud::HudPlugin;
+use stamina::StaminaPlugin;
+use projectile::ProjectilePlugin;
+use health_bar::HealthBarPlugin;
+use structure::StructurePlugin;
+use network::{setup_client, setup_server, NetworkPlugin, NetworkSetupFailed, MAX_CLIENTS};
 
+mod action;
 mod player;
 mod world;
 mod item;
@@ -48,20 +90,105 @@ mod world_object;
 mod camera;
 mod tile_picker_backend;
 mod chunk;
+mod minimap;
+mod debug_overlay;
+mod game_assets;
+mod move_validation;
+mod player_list;
+mod ron_asset;
+mod ground_registry;
+mod tile_tick;
+mod click_to_move;
+mod critter;
+mod game_time;
+mod health;
+mod mob;
+mod melee;
+mod server_status;
+mod loading_screen;
+mod world_meta;
+mod interact;
+mod autotile;
+mod fence;
+mod console;
+mod loot_bag;
+mod sign;
+mod network;
+mod water;
+mod emote;
+mod sleep;
+mod item_registry;
+mod composter;
+mod soil;
+mod creative;
+mod tile_prediction;
+mod charge;
+mod container_mirror;
+mod crafting;
+mod localization;
+mod map_export;
+mod net_profiler;
+mod sapling;
+mod chunk_stream;
+mod debug_inspector;
+mod screenshot;
+mod chest;
+mod furnace;
+mod equipment;
+mod hunger;
+mod hud;
+mod stamina;
+mod projectile;
+mod health_bar;
+mod terrain;
+mod biome;
+mod structure;
 
-const PROTOCOL_ID: u64 = 0x1122334455667788;
 const MAX_TICK_RATE: u16 = 20;
+/// Move events a moving client sends per second, independent of its frame rate.
+pub const MOVE_EVENT_RATE: f32 = 60.0;
+/// Moves a client catches up on in one frame after a slow one.
+const MAX_MOVE_CATCH_UP: u32 = 4;
 
 fn main() {
-    App::new()
-        .init_resource::<Cli>()
-        // .insert_resource(TickTime(0.0))
-        // .add_systems(PostUpdate, debug_print_server_tick)
+    let cli = Cli::parse();
+    if let Cli::Status { ip, port } = cli {
+        match query_status(SocketAddr::new(ip, port)) {
+            Ok(status) => println!(
+                "{}\nPlayers: {}/{}\nWorld: {} (seed {})",
+                status.server_name, status.players, status.max_players, status.world_name, status.seed
+            ),
+            Err(err) => eprintln!("Status query failed: {err:#}"),
+        }
+        return;
+    }
+
+    let mut app = App::new();
+    if let Cli::Server { seed, force_seed, ref world, ref world_name, .. } = cli {
+        let paths = WorldPaths::resolve(world.as_deref());
+        match WorldMeta::open_or_create(&paths, world_name.clone(), seed, force_seed) {
+            Ok(meta) => {
+                app.insert_resource(WorldSeed(meta.seed))
+                    .insert_resource(meta)
+                    .insert_resource(paths);
+            }
+            Err(err) => {
+                eprintln!("Cannot open world: {err:#}");
+                return;
+            }
+        }
+    }
+
+    if let Cli::Client { ref lang, .. } = cli {
+        app.insert_resource(Language(lang.clone()));
+    }
+
+    app.insert_resource(cli)
         .insert_resource(WinitSettings {
             focused_mode: bevy::winit::UpdateMode::Continuous,
             unfocused_mode: bevy::winit::UpdateMode::Continuous,
         })
-        .insert_resource(DebugPickingMode::Normal)
+        .insert_resource(DebugPickingMode::Disabled)
         .add_plugins((
             DefaultPlugins
                 .set(LogPlugin {
@@ -76,9 +203,15 @@ fn main() {
                     }),
                     ..Default::default()
                 })
-                .set(ImagePlugin::default_nearest()),
+                .set(ImagePlugin::default_nearest())
+                .set(AssetPlugin {
+                    // hot reload of the data files while developing
+                    watch_for_changes_override: Some(cfg!(debug_assertions)),
+                    ..Default::default()
+                }),
             RepliconPlugins.set(ServerPlugin {
                 tick_policy: TickPolicy::MaxTickRate(MAX_TICK_RATE),
+                visibility_policy: VisibilityPolicy::Blacklist,
                 ..Default::default()
             }),
             RepliconRenetPlugins,
@@ -88,7 +221,9 @@ fn main() {
             },
             EntropyPlugin::<WyRand>::default(),
             DefaultPickingPlugins,
+            GameAssetsPlugin,
             TilemapBackend,
+            ActionPlugin,
             PlayerPlugin,
             WorldPlugin,
             ItemPlugin,
@@ -98,15 +233,73 @@ fn main() {
             CameraPlugin,
         ))
         .add_plugins(ChunkPlugin)
+        .add_plugins(MinimapPlugin)
+        .add_plugins(DebugOverlayPlugin)
+        .add_plugins(MoveValidationPlugin)
+        .add_plugins(PlayerListPlugin)
+        .add_plugins(GroundRegistryPlugin)
+        .add_plugins(TileTickPlugin)
+        .add_plugins(ClickToMovePlugin)
+        .add_plugins(CritterPlugin)
+        .add_plugins(GameTimePlugin)
+        .add_plugins(HealthPlugin)
+        .add_plugins(MobPlugin)
+        .add_plugins(MeleePlugin)
+        .add_plugins(ServerStatusPlugin)
+        .add_plugins(LoadingScreenPlugin)
+        .add_plugins(WorldMetaPlugin)
+        .add_plugins(InteractPlugin)
+        .add_plugins(AutotilePlugin)
+        .add_plugins(FencePlugin)
+        .add_plugins(ConsolePlugin)
+        .add_plugins(LootBagPlugin)
+        .add_plugins(SignPlugin)
+        .add_plugins(NetworkPlugin)
+        .add_plugins(WaterPlugin)
+        .add_plugins(EmotePlugin)
+        .add_plugins(SleepPlugin)
+        .add_plugins(ItemRegistryPlugin)
+        .add_plugins(ComposterPlugin)
+        .add_plugins(SoilPlugin)
+        .add_plugins(CreativePlugin)
+        .add_plugins(TilePredictionPlugin)
+        .add_plugins(CraftingPlugin)
+        .add_plugins(LocalizationPlugin)
+        .add_plugins(MapExportPlugin)
+        .add_plugins(ScreenshotPlugin)
+        .add_plugins(ContainerMirrorPlugin)
+        .add_plugins(ChargePlugin)
+        .add_plugins(NetProfilerPlugin)
+        .add_plugins(SaplingPlugin)
+        .add_plugins(ChunkStreamPlugin)
+        .add_plugins(DebugInspectorPlugin)
+        .add_plugins(ChestPlugin)
+        .add_plugins(FurnacePlugin)
+        .add_plugins(EquipmentPlugin)
+        .add_plugins(HungerPlugin)
+        .add_plugins(HudPlugin)
+        .add_plugins(StaminaPlugin)
+        .add_plugins(ProjectilePlugin)
+        .add_plugins(HealthBarPlugin)
+        .add_plugins(StructurePlugin)
         .add_plugins(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0))
-        .add_plugins(RapierDebugRenderPlugin::default())
+        .add_plugins(RapierDebugRenderPlugin {
+            enabled: false,
+            ..Default::default()
+        })
         .add_client_event::<MoveEvent>(ChannelKind::Ordered)
         .add_client_event::<ActionEvent>(ChannelKind::Ordered)
         .add_mapped_client_event::<ClickTileEvent>(ChannelKind::Ordered)
-        .add_systems(Startup, (read_cli.map(Result::unwrap), ))
+        .add_systems(Startup, read_cli)
         .add_systems(
             Update,
-            (read_input, handle_connections.run_if(has_authority)),
+            (
+                read_input
+                    .run_if(in_state(GameState::InGame))
+                    .run_if(not(editing_sign))
+                    .run_if(not(local_player_sleeping)),
+                handle_connections.run_if(has_authority),
+            ),
         )
         .replicate_interpolated::<Transform>()
         .replicate::<Name>()
@@ -114,64 +307,27 @@ fn main() {
         .run();
 }
 
+/// Starts the server or connects to one, depending on the command line.
 fn read_cli(
     mut commands: Commands,
+    mut failures: EventWriter<NetworkSetupFailed>,
     cli: Res<Cli>,
     channels: Res<RepliconChannels>,
-) -> Result<(), Box<dyn Error>> {
-    match *cli {
-        Cli::Server { port } => {
-            let server_channels_config = channels.get_server_configs();
-            let client_channels_config = channels.get_client_configs();
-
-            let server = RenetServer::new(ConnectionConfig {
-                server_channels_config,
-                client_channels_config,
-                ..Default::default()
-            });
-
-            let current_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?;
-            let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, port))?;
-            let server_config = ServerConfig {
-                current_time,
-                max_clients: 10,
-                protocol_id: PROTOCOL_ID,
-                authentication: ServerAuthentication::Unsecure,
-                public_addresses: Default::default(),
-            };
-            let transport = NetcodeServerTransport::new(server_config, socket)?;
-
-            commands.insert_resource(server);
-            commands.insert_resource(transport);
-        }
-        Cli::Client { port, ip } => {
-            let server_channels_config = channels.get_server_configs();
-            let client_channels_config = channels.get_client_configs();
-
-            let client = RenetClient::new(ConnectionConfig {
-                server_channels_config,
-                client_channels_config,
-                ..Default::default()
-            });
-
-            let current_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?;
-            let client_id = current_time.as_millis() as u64;
-            let server_addr = SocketAddr::new(ip, port);
-            let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
-            let authentication = ClientAuthentication::Unsecure {
-                client_id,
-                protocol_id: PROTOCOL_ID,
-                server_addr,
-                user_data: None,
+) {
+    let result = match *cli {
+        Cli::Server { port, ref name, .. } => {
+            let settings = ServerInfo {
+                server_name: name.clone(),
+                max_players: MAX_CLIENTS,
             };
-            let transport = NetcodeClientTransport::new(current_time, authentication, socket)?;
-
-            commands.insert_resource(client);
-            commands.insert_resource(transport);
+            setup_server(port, settings, &mut commands, &channels)
         }
+        Cli::Client { port, ip, .. } => setup_client(SocketAddr::new(ip, port), &mut commands, &channels),
+        Cli::Status { .. } => unreachable!("status queries exit before the app is built"),
+    };
+    if let Err(err) = result {
+        failures.send(NetworkSetupFailed(err.to_string()));
     }
-
-    Ok(())
 }
 
 fn handle_connections(
@@ -201,8 +357,22 @@ fn read_input(
     input: Res<ButtonInput<KeyCode>>,
     mut move_ev: EventWriter<MoveEvent>,
     mut action_ev: EventWriter<ActionEvent>,
+    mut player_query: Query<(&NetworkOwner, &mut ActionCooldowns, Has<Respawning>)>,
+    client: Res<RepliconClient>,
+    cooldown_config: Res<ActionCooldownConfig>,
+    spectator: Res<Spectator>,
     time: Res<Time>,
+    mut move_clock: Local<MoveEventClock>,
 ) {
+    // dead players wait for their respawn
+    let respawning = client.id().is_some_and(|client_id| {
+        player_query
+            .iter()
+            .any(|(owner, _, respawning)| owner.0 == client_id.get() && respawning)
+    });
+    if respawning {
+        return;
+    }
     let mut direction = Vec2::ZERO;
 
     if input.pressed(KeyCode::KeyW) {
@@ -217,20 +387,72 @@ fn read_input(
     if input.pressed(KeyCode::KeyD) {
         direction.x += 1.0;
     }
-    if direction != Vec2::ZERO {
-        move_ev.send(MoveEvent { input: direction, timestamp: time.elapsed_seconds_wrapped() });
+    // the spectator camera flies with WASD while the player stays put
+    let moving = direction != Vec2::ZERO && !spectator.active;
+    for _ in 0..move_clock.due(time.delta_seconds(), moving) {
+        move_ev.send(MoveEvent {
+            input: direction,
+            timestamp: time.elapsed_seconds_wrapped(),
+            sprint: input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]),
+        });
     }
+    let mut local_cooldowns = client.id().and_then(|client_id| {
+        player_query
+            .iter_mut()
+            .find(|(owner, ..)| owner.0 == client_id.get())
+            .map(|(_, cooldowns, _)| cooldowns)
+    });
+    let now = time.elapsed_seconds();
     for key in input.get_just_pressed() {
+        let actions = GameAction::from_key(*key);
+        if let (Some(cooldowns), false) = (local_cooldowns.as_mut(), actions.is_empty()) {
+            let ready: Vec<GameAction> = actions
+                .iter()
+                .copied()
+                .filter(|action| cooldowns.ready(*action, now, &cooldown_config))
+                .collect();
+            // don't send actions the server would reject anyway
+            if ready.is_empty() {
+                continue;
+            }
+            for action in ready {
+                cooldowns.trigger(action, now);
+            }
+        }
         action_ev.send(ActionEvent {
             action: *key,
         });
     }
 }
 
+/// Paces the [`MoveEvent`]s of a client side system. Every event moves the player by the same
+/// distance, so they go out at [`MOVE_EVENT_RATE`] instead of once per rendered frame.
+#[derive(Debug, Default)]
+pub struct MoveEventClock {
+    pending: f32,
+}
+
+impl MoveEventClock {
+    /// Number of move events due this frame. The first frame of a movement always gets one.
+    pub fn due(&mut self, delta: f32, moving: bool) -> u32 {
+        let interval = 1.0 / MOVE_EVENT_RATE;
+        if !moving {
+            self.pending = interval;
+            return 0;
+        }
+        self.pending = (self.pending + delta).min(MAX_MOVE_CATCH_UP as f32 * interval);
+        let due = (self.pending / interval) as u32;
+        self.pending -= due as f32 * interval;
+        due
+    }
+}
+
 #[derive(Event, Serialize, Deserialize, Debug, Clone)]
 pub struct MoveEvent {
     input: Vec2,
     timestamp: f32,
+    /// Set while the sprint key is held, the server decides whether stamina allows it.
+    sprint: bool,
 }
 
 #[derive(Event, Serialize, Deserialize, Debug, Clone)]
@@ -241,12 +463,13 @@ struct ActionEvent {
 
 #[derive(Event, Serialize, Deserialize, Debug, Clone)]
 pub struct ClickTileEvent {
-    tile: Entity
+    chunk: Entity,
+    tile_pos: TilePos,
 }
 
 impl MapEntities for ClickTileEvent {
     fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
-        self.tile = entity_mapper.map_entity(self.tile);
+        self.chunk = entity_mapper.map_entity(self.chunk);
     }
 }
 
@@ -257,6 +480,42 @@ enum Cli {
     Server {
         #[arg(short, long, default_value_t = PORT)]
         port: u16,
+
+        /// Seed of a freshly created world, existing worlds keep theirs.
+        #[arg(long)]
+        seed: Option<u64>,
+
+        /// Replace the seed of an existing world with `--seed`.
+        #[arg(long)]
+        force_seed: bool,
+
+        #[arg(long, default_value = "Crafting Game Server")]
+        name: String,
+
+        /// Give every joining client creative mode.
+        #[arg(long)]
+        creative: bool,
+
+        /// World to run, a name for a world in the `worlds` folder or a path to a world folder.
+        /// Defaults to the `world` folder.
+        #[arg(long)]
+        world: Option<String>,
+
+        /// Name of the world, defaults to the stored name or the folder name for new worlds.
+        #[arg(long)]
+        world_name: Option<String>,
+
+        /// Log replication statistics and append them to this CSV file.
+        #[arg(long)]
+        net_profile: Option<PathBuf>,
+    },
+    /// Prints the status of a server without joining it.
+    Status {
+        #[arg(short, long, default_value_t = Ipv4Addr::LOCALHOST.into())]
+        ip: IpAddr,
+
+        #[arg(short, long, default_value_t = PORT)]
+        port: u16,
     },
     Client {
         #[arg(short, long, default_value_t = Ipv4Addr::LOCALHOST.into())]
@@ -264,11 +523,10 @@ enum Cli {
 
         #[arg(short, long, default_value_t = PORT)]
         port: u16,
+
+        /// Language of the UI, the name of a file in `assets/lang`.
+        #[arg(long, default_value = localization::DEFAULT_LANGUAGE)]
+        lang: String,
     },
 }
 
-impl Default for Cli {
-    fn default() -> Self {
-        Self::parse()
-    }
-}
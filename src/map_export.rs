@@ -0,0 +1,99 @@
+use std::path::Path;
+
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+    tasks::IoTaskPool,
+};
+use bevy_replicon::prelude::server_running;
+
+use crate::{
+    chunk::{read_saved_grounds, ChunkLoadError, TILES_PER_CHUNK},
+    console::ConsoleCommand,
+    minimap::{ground_color, COLOR_UNKNOWN},
+    world_meta::WorldPaths,
+};
+
+/// Largest radius accepted, keeps the image below 8k pixels per side.
+const MAX_EXPORT_RADIUS: u32 = 511;
+
+pub struct MapExportPlugin;
+
+impl Plugin for MapExportPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, export_map.run_if(server_running));
+    }
+}
+
+/// Starts the export on the IO pool, reading the chunk files keeps the frame waiting otherwise.
+fn export_map(mut console_commands: EventReader<ConsoleCommand>, paths: Res<WorldPaths>) {
+    for command in console_commands.read() {
+        let ConsoleCommand::ExportMap { radius } = *command else {
+            continue;
+        };
+        if radius > MAX_EXPORT_RADIUS {
+            warn!("Export radius {radius} is larger than {MAX_EXPORT_RADIUS}");
+            continue;
+        }
+        let path = paths.map_export(radius);
+        let paths = paths.clone();
+        IoTaskPool::get()
+            .spawn(async move {
+                match export_saved_map(radius as i32, &paths, &path) {
+                    Ok(()) => info!("Exported the map to {}", path.display()),
+                    Err(err) => error!("Cannot export the map to {}: {err:#}", path.display()),
+                }
+            })
+            .detach();
+    }
+}
+
+/// Renders one pixel per tile, reading one chunk file at a time so only the image stays in
+/// memory. Chunks without a readable save file stay [`COLOR_UNKNOWN`].
+fn export_saved_map(radius: i32, paths: &WorldPaths, path: &Path) -> anyhow::Result<()> {
+    let chunks = (2 * radius + 1) as u32;
+    let size = chunks * TILES_PER_CHUNK;
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &COLOR_UNKNOWN,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    for chunk_y in -radius..=radius {
+        for chunk_x in -radius..=radius {
+            let index = IVec2::new(chunk_x, chunk_y);
+            let grounds = match read_saved_grounds(&paths.chunk(index)) {
+                Ok(grounds) => grounds,
+                Err(ChunkLoadError::NotFound) => continue,
+                Err(err) => {
+                    warn!("Skipping chunk {index} in the map export: {err}");
+                    continue;
+                }
+            };
+            let origin = UVec2::new((chunk_x + radius) as u32, (radius - chunk_y) as u32) * TILES_PER_CHUNK;
+            for (tile, ground) in grounds {
+                if tile.x >= TILES_PER_CHUNK || tile.y >= TILES_PER_CHUNK {
+                    continue;
+                }
+                // image rows go down, tile rows go up
+                let x = origin.x + tile.x;
+                let y = origin.y + TILES_PER_CHUNK - 1 - tile.y;
+                let offset = ((y * size + x) * 4) as usize;
+                image.data[offset..offset + 4].copy_from_slice(&ground_color(&ground));
+            }
+        }
+    }
+    image
+        .try_into_dynamic()
+        .map_err(|err| anyhow::anyhow!("cannot convert the map: {err:?}"))?
+        .save(path)?;
+    Ok(())
+}
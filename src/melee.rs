@@ -0,0 +1,163 @@
+use bevy::{prelude::*, utils::HashSet};
+use bevy_rapier2d::prelude::*;
+use bevy_replicon::prelude::*;
+use bevy_replicon_snap::NetworkOwner;
+
+use crate::{
+    action::{ActionCooldownConfig, ActionCooldowns, GameAction},
+    health::{DamageEvent, Health},
+    equipment::Equipment,
+    item::Item,
+    item_registry::ItemRegistry,
+    player::{Facing, Player},
+    sleep::Sleeping,
+    world_object::ResourceNode,
+    ActionEvent,
+};
+
+pub struct MeleePlugin;
+
+impl Plugin for MeleePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<MeleeSettings>()
+            .init_resource::<MeleeSettings>()
+            .add_systems(
+                Update,
+                (spawn_hitboxes, apply_hitboxes)
+                    .chain()
+                    .run_if(server_running),
+            );
+    }
+}
+
+#[derive(Resource, Debug, Reflect)]
+#[reflect(Resource)]
+pub struct MeleeSettings {
+    pub range: f32,
+    /// Full opening angle of the swing arc in radians, centered on the player's facing.
+    pub arc: f32,
+    pub damage: f32,
+    /// Initial speed of hit targets in units per second.
+    pub knockback: f32,
+    /// Seconds the hitbox of a swing stays in the world.
+    pub hitbox_lifetime: f32,
+}
+
+impl Default for MeleeSettings {
+    fn default() -> Self {
+        Self {
+            range: 40.0,
+            arc: 120f32.to_radians(),
+            damage: 5.0,
+            knockback: 150.0,
+            hitbox_lifetime: 0.15,
+        }
+    }
+}
+
+/// Server side sensor of a swing. It covers the reach around the attacker, only targets inside
+/// the swing arc take damage, and each of them only once per swing.
+#[derive(Component, Debug)]
+struct MeleeHitbox {
+    attacker: Entity,
+    facing: Vec2,
+    /// Item the attacker held when swinging, decides which resource nodes it can harvest.
+    held: Option<Item>,
+    hit: HashSet<Entity>,
+    remaining: f32,
+}
+
+/// Spawns a hitbox for every accepted attack.
+fn spawn_hitboxes(
+    mut commands: Commands,
+    mut events: EventReader<FromClient<ActionEvent>>,
+    mut player_query: Query<
+        (Entity, &NetworkOwner, &Transform, &Facing, &Equipment, &mut ActionCooldowns),
+        Without<Sleeping>,
+    >,
+    cooldown_config: Res<ActionCooldownConfig>,
+    settings: Res<MeleeSettings>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds();
+    for FromClient { client_id, event } in events.read() {
+        if !GameAction::from_key(event.action).contains(&GameAction::Attack) {
+            continue;
+        }
+        let Some((attacker, _, transform, facing, equipment, mut cooldowns)) = player_query
+            .iter_mut()
+            .find(|(_, owner, ..)| owner.0 == client_id.get())
+        else {
+            continue;
+        };
+        if !cooldowns.ready(GameAction::Attack, now, &cooldown_config) {
+            continue;
+        }
+        cooldowns.trigger(GameAction::Attack, now);
+
+        commands.spawn((
+            Name::new("Melee Hitbox"),
+            MeleeHitbox {
+                attacker,
+                facing: facing.0,
+                held: equipment.hand.clone(),
+                hit: HashSet::default(),
+                remaining: settings.hitbox_lifetime,
+            },
+            TransformBundle::from_transform(Transform::from_translation(transform.translation)),
+            Collider::ball(settings.range),
+            Sensor,
+            // targets have no rigid body either, which rapier ignores by default
+            ActiveCollisionTypes::all(),
+        ));
+    }
+}
+
+/// Damages and knocks back the targets overlapping a hitbox. Resource nodes only take damage
+/// from attackers holding the right tool.
+fn apply_hitboxes(
+    mut commands: Commands,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut hitboxes: Query<(Entity, &Transform, &mut MeleeHitbox)>,
+    targets: Query<(&Transform, Option<&ResourceNode>), (With<Health>, Without<Player>)>,
+    items: Res<ItemRegistry>,
+    rapier_context: Res<RapierContext>,
+    settings: Res<MeleeSettings>,
+    time: Res<Time>,
+) {
+    let min_alignment = (settings.arc * 0.5).cos();
+    for (entity, transform, mut hitbox) in hitboxes.iter_mut() {
+        let origin = transform.translation.xy();
+        let overlapping: Vec<Entity> = rapier_context
+            .intersection_pairs_with(entity)
+            .filter(|(_, _, intersecting)| *intersecting)
+            .map(|(a, b, _)| if a == entity { b } else { a })
+            .collect();
+        for hit in overlapping {
+            if hit == hitbox.attacker || hitbox.hit.contains(&hit) {
+                continue;
+            }
+            let Ok((target_transform, node)) = targets.get(hit) else {
+                continue;
+            };
+            if node.is_some_and(|node| !node.can_harvest(hitbox.held.as_ref(), &items)) {
+                continue;
+            }
+            let offset = target_transform.translation.xy() - origin;
+            let in_arc = offset
+                .try_normalize()
+                .map_or(true, |direction| direction.dot(hitbox.facing) >= min_alignment);
+            if !in_arc {
+                continue;
+            }
+            hitbox.hit.insert(hit);
+            damage_events.send(
+                DamageEvent::new(hit, settings.damage).with_knockback(origin, settings.knockback),
+            );
+        }
+        hitbox.remaining -= time.delta_seconds();
+        if hitbox.remaining <= 0.0 {
+            commands.entity(entity).despawn();
+        }
+    }
+}
@@ -0,0 +1,317 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+    utils::HashMap,
+};
+use bevy_ecs_tilemap::tiles::TilePos;
+use bevy_replicon::{
+    client::ClientSet,
+    core::ClientId,
+    prelude::{client_connected, RepliconClient},
+};
+use bevy_replicon_snap::NetworkOwner;
+
+use crate::{
+    chunk::{Chunk, TILES_PER_CHUNK, TILE_LENGTH},
+    player::Player,
+    world::Ground,
+    ActionEvent,
+};
+
+/// Number of tiles shown along each side of the minimap.
+const MINIMAP_TILES: u32 = 128;
+const MINIMAP_SMALL_SIZE: f32 = 160.0;
+const MINIMAP_LARGE_SIZE: f32 = 512.0;
+const PLAYER_MARKER_RADIUS: i32 = 1;
+
+pub const COLOR_UNKNOWN: [u8; 4] = [0, 0, 0, 160];
+const COLOR_LOCAL_PLAYER: [u8; 4] = [255, 255, 255, 255];
+const COLOR_OTHER_PLAYER: [u8; 4] = [230, 40, 40, 255];
+
+pub struct MinimapPlugin;
+
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MinimapData>()
+            .add_systems(Startup, setup_minimap)
+            .add_systems(
+                Update,
+                (
+                    (track_chunks, track_tiles).after(ClientSet::Receive),
+                    redraw_minimap,
+                )
+                    .chain()
+                    .run_if(client_connected),
+            )
+            .add_systems(Update, toggle_minimap.run_if(client_connected));
+    }
+}
+
+/// Ground colors of every replicated tile known to this client, keyed by global tile coordinates.
+/// The image is only updated where something changed since it was last drawn.
+#[derive(Resource, Default)]
+struct MinimapData {
+    tiles: HashMap<IVec2, [u8; 4]>,
+    chunks: HashMap<Entity, IVec2>,
+    /// Tiles whose color changed since the last draw.
+    changed: Vec<IVec2>,
+    /// Center tile of the image, `None` until it was drawn once.
+    drawn_center: Option<IVec2>,
+    /// Player tiles the markers were drawn at, the local player first.
+    drawn_players: Vec<IVec2>,
+}
+
+#[derive(Component, Debug)]
+struct MinimapImage(Handle<Image>);
+
+#[derive(Component, Debug, Default)]
+struct MinimapExpanded(bool);
+
+fn setup_minimap(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    let image = Image::new_fill(
+        Extent3d {
+            width: MINIMAP_TILES,
+            height: MINIMAP_TILES,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &COLOR_UNKNOWN,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    let handle = images.add(image);
+
+    commands.spawn((
+        Name::new("Minimap"),
+        ImageBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(10.0),
+                right: Val::Px(10.0),
+                width: Val::Px(MINIMAP_SMALL_SIZE),
+                height: Val::Px(MINIMAP_SMALL_SIZE),
+                ..Default::default()
+            },
+            image: UiImage::new(handle.clone()),
+            ..Default::default()
+        },
+        MinimapImage(handle),
+        MinimapExpanded::default(),
+    ));
+}
+
+pub fn ground_color(ground: &Ground) -> [u8; 4] {
+    match ground {
+        Ground::Grass => [70, 140, 60, 255],
+        Ground::Dirt => [120, 85, 50, 255],
+        Ground::Stone => [130, 130, 130, 255],
+        Ground::Water => [50, 90, 190, 255],
+        Ground::DeepWater => [30, 55, 140, 255],
+    }
+}
+
+fn global_tile_index(chunk_index: IVec2, tile_pos: &TilePos) -> IVec2 {
+    chunk_index * TILES_PER_CHUNK as i32 + IVec2::new(tile_pos.x as i32, tile_pos.y as i32)
+}
+
+fn world_to_tile_index(pos: Vec2) -> IVec2 {
+    (pos / TILE_LENGTH).floor().as_ivec2()
+}
+
+fn track_chunks(
+    mut data: ResMut<MinimapData>,
+    chunk_query: Query<(Entity, &Chunk), Added<Chunk>>,
+    mut removed_chunks: RemovedComponents<Chunk>,
+) {
+    for (entity, chunk) in chunk_query.iter() {
+        data.chunks.insert(entity, chunk.chunk_index);
+    }
+    for entity in removed_chunks.read() {
+        let Some(chunk_index) = data.chunks.remove(&entity) else {
+            continue;
+        };
+        let origin = chunk_index * TILES_PER_CHUNK as i32;
+        for x in 0..TILES_PER_CHUNK as i32 {
+            for y in 0..TILES_PER_CHUNK as i32 {
+                let tile = origin + IVec2::new(x, y);
+                data.tiles.remove(&tile);
+                data.changed.push(tile);
+            }
+        }
+    }
+}
+
+fn track_tiles(
+    mut data: ResMut<MinimapData>,
+    chunk_query: Query<&Chunk>,
+    tile_query: Query<(&TilePos, &Ground, &Parent), Changed<Ground>>,
+) {
+    for (tile_pos, ground, parent) in tile_query.iter() {
+        let Ok(chunk) = chunk_query.get(parent.get()) else {
+            continue;
+        };
+        let index = global_tile_index(chunk.chunk_index, tile_pos);
+        data.tiles.insert(index, ground_color(ground));
+        data.changed.push(index);
+    }
+}
+
+/// Byte offset of a pixel in the image data, `None` outside the image.
+fn pixel_offset(pixel: IVec2) -> Option<usize> {
+    let size = MINIMAP_TILES as i32;
+    if pixel.x < 0 || pixel.y < 0 || pixel.x >= size || pixel.y >= size {
+        return None;
+    }
+    // image rows go top to bottom while world y goes up
+    Some(((size - 1 - pixel.y) * size + pixel.x) as usize * 4)
+}
+
+fn set_pixel(image: &mut [u8], pixel: IVec2, color: [u8; 4]) {
+    if let Some(offset) = pixel_offset(pixel) {
+        image[offset..offset + 4].copy_from_slice(&color);
+    }
+}
+
+/// Draws the ground color of a tile, if it is inside the image around `center`.
+fn draw_tile(image: &mut [u8], tiles: &HashMap<IVec2, [u8; 4]>, center: IVec2, tile: IVec2) {
+    let pixel = tile - center + IVec2::splat(MINIMAP_TILES as i32 / 2);
+    set_pixel(image, pixel, tiles.get(&tile).copied().unwrap_or(COLOR_UNKNOWN));
+}
+
+fn marker_tiles(tile: IVec2) -> impl Iterator<Item = IVec2> {
+    (-PLAYER_MARKER_RADIUS..=PLAYER_MARKER_RADIUS).flat_map(move |dx| {
+        (-PLAYER_MARKER_RADIUS..=PLAYER_MARKER_RADIUS).map(move |dy| tile + IVec2::new(dx, dy))
+    })
+}
+
+/// Moves the image content by `delta` tiles and draws the tiles that scrolled into view.
+fn scroll_image(image: &mut [u8], tiles: &HashMap<IVec2, [u8; 4]>, center: IVec2, delta: IVec2) {
+    let size = MINIMAP_TILES as i32;
+    let half = size / 2;
+    let previous = image.to_vec();
+    // columns whose content is still on the image after the move
+    let kept_start = (-delta.x).max(0);
+    let kept_end = (size - delta.x).min(size);
+    for y in 0..size {
+        let source_y = y + delta.y;
+        let row_kept = (0..size).contains(&source_y) && kept_start < kept_end;
+        if row_kept {
+            let (Some(target), Some(source)) = (
+                pixel_offset(IVec2::new(kept_start, y)),
+                pixel_offset(IVec2::new(kept_start + delta.x, source_y)),
+            ) else {
+                continue;
+            };
+            let length = (kept_end - kept_start) as usize * 4;
+            image[target..target + length].copy_from_slice(&previous[source..source + length]);
+        }
+        let new_columns = if row_kept {
+            (0..kept_start).chain(kept_end..size).collect::<Vec<_>>()
+        } else {
+            (0..size).collect()
+        };
+        for x in new_columns {
+            draw_tile(image, tiles, center, center + IVec2::new(x - half, y - half));
+        }
+    }
+}
+
+fn redraw_minimap(
+    mut data: ResMut<MinimapData>,
+    mut images: ResMut<Assets<Image>>,
+    minimap_query: Query<&MinimapImage>,
+    player_query: Query<(&Transform, &NetworkOwner), With<Player>>,
+    client: Res<RepliconClient>,
+) {
+    let Some(client_id) = client.id() else {
+        return;
+    };
+    let Some((local_transform, _)) = player_query
+        .iter()
+        .find(|(_, owner)| ClientId::new(owner.0) == client_id)
+    else {
+        return;
+    };
+    let center = world_to_tile_index(local_transform.translation.xy());
+    let half = MINIMAP_TILES as i32 / 2;
+    let players: Vec<IVec2> = std::iter::once(center)
+        .chain(
+            player_query
+                .iter()
+                .filter(|(_, owner)| ClientId::new(owner.0) != client_id)
+                .map(|(transform, _)| world_to_tile_index(transform.translation.xy()))
+                .filter(|tile| (*tile - center).abs().max_element() < half),
+        )
+        .collect();
+
+    if data.changed.is_empty() && data.drawn_center == Some(center) && data.drawn_players == players {
+        return;
+    }
+
+    let Ok(minimap) = minimap_query.get_single() else {
+        return;
+    };
+    let Some(image) = images.get_mut(&minimap.0) else {
+        return;
+    };
+    let data = &mut *data;
+    let image = image.data.as_mut_slice();
+
+    match data.drawn_center {
+        Some(drawn_center) if (center - drawn_center).abs().max_element() < MINIMAP_TILES as i32 => {
+            // markers are drawn over the ground, put it back before the image moves
+            for tile in data.drawn_players.iter().flat_map(|player| marker_tiles(*player)) {
+                draw_tile(image, &data.tiles, drawn_center, tile);
+            }
+            if center != drawn_center {
+                scroll_image(image, &data.tiles, center, center - drawn_center);
+            }
+            for tile in data.changed.iter() {
+                draw_tile(image, &data.tiles, center, *tile);
+            }
+        }
+        // first draw or teleported further than the image reaches
+        _ => {
+            for x in -half..MINIMAP_TILES as i32 - half {
+                for y in -half..MINIMAP_TILES as i32 - half {
+                    draw_tile(image, &data.tiles, center, center + IVec2::new(x, y));
+                }
+            }
+        }
+    }
+
+    for (i, player) in players.iter().enumerate().rev() {
+        let color = if i == 0 { COLOR_LOCAL_PLAYER } else { COLOR_OTHER_PLAYER };
+        for tile in marker_tiles(*player) {
+            set_pixel(image, tile - center + IVec2::splat(half), color);
+        }
+    }
+
+    data.changed.clear();
+    data.drawn_center = Some(center);
+    data.drawn_players = players;
+}
+
+fn toggle_minimap(
+    mut event_reader: EventReader<ActionEvent>,
+    mut minimap_query: Query<(&mut Style, &mut MinimapExpanded)>,
+) {
+    for event in event_reader.read() {
+        if event.action != KeyCode::KeyM {
+            continue;
+        }
+        for (mut style, mut expanded) in minimap_query.iter_mut() {
+            expanded.0 = !expanded.0;
+            let size = if expanded.0 {
+                MINIMAP_LARGE_SIZE
+            } else {
+                MINIMAP_SMALL_SIZE
+            };
+            style.width = Val::Px(size);
+            style.height = Val::Px(size);
+        }
+    }
+}
@@ -0,0 +1,381 @@
+use std::f32::consts::TAU;
+
+use bevy::{prelude::*, utils::HashMap};
+use bevy_ecs_tilemap::tiles::TilePos;
+use bevy_rand::prelude::{GlobalEntropy, WyRand};
+use bevy_rapier2d::prelude::{Collider, Sensor};
+use bevy_replicon::{client::ClientSet, core::Replicated, prelude::*};
+use bevy_replicon_snap::interpolation::Interpolated;
+use rand_core::RngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chunk::{Chunk, ChunkIndexMap, ChunkTiles, ParkedChunk, TILES_PER_CHUNK},
+    game_assets::GameAssets,
+    game_time::GameTime,
+    ground_registry::GroundRegistry,
+    health::{DamageEvent, DeathEvent, Health},
+    item::spawn_ground_item,
+    item_registry::ItemRegistry,
+    player::{Player, Respawning},
+    water::WaterSettings,
+    world::{chunk_index_at, global_tile_at, is_walkable, tile_center_world_pos},
+    world_object::TileOccupancy,
+};
+
+/// Columns of the player atlas, mobs use one of its rows.
+const ATLAS_COLUMNS: usize = 8;
+const WALK_FRAMES: usize = 4;
+const MOB_COLLIDER_RADIUS: f32 = 12.0;
+/// Item a killed mob leaves behind.
+const MOB_DROP: &str = "slime";
+
+pub struct MobPlugin;
+
+impl Plugin for MobPlugin {
+    fn build(&self, app: &mut App) {
+        app.replicate::<Mob>()
+            .register_type::<MobSettings>()
+            .init_resource::<MobSettings>()
+            .add_systems(PreUpdate, init_mob.after(ClientSet::Receive))
+            .add_systems(Update, animate_mobs.run_if(client_connected))
+            .add_systems(
+                Update,
+                (
+                    despawn_mobs,
+                    spawn_mobs,
+                    steer_mobs,
+                    contact_damage,
+                    kill_mobs,
+                )
+                    .chain()
+                    .run_if(server_running),
+            );
+    }
+}
+
+/// Hostile mob that roams at night. It wanders around until a player comes into aggro range and
+/// then chases the nearest one.
+#[derive(Component, Debug, Serialize, Deserialize)]
+pub struct Mob;
+
+/// Tuning of the hostile mobs.
+#[derive(Resource, Debug, Reflect)]
+#[reflect(Resource)]
+pub struct MobSettings {
+    /// Maximum number of mobs alive at once.
+    pub max_mobs: usize,
+    /// Maximum number of mobs standing in one chunk for another to spawn there.
+    pub max_per_chunk: usize,
+    /// Seconds between spawn attempts.
+    pub spawn_interval: f32,
+    /// Chance of a chunk below the cap to get a new mob per attempt.
+    pub spawn_chance: f32,
+    /// Mobs never spawn closer than this to any player, which keeps them out of view.
+    pub safety_radius: f32,
+    pub aggro_range: f32,
+    /// Walking speed in units per second.
+    pub speed: f32,
+    /// Speed while wandering without a target.
+    pub wander_speed: f32,
+    /// Longest stretch in seconds a mob wanders in one direction or idles.
+    pub wander_time: f32,
+    pub health: f32,
+    pub contact_range: f32,
+    pub contact_damage: f32,
+    /// Initial speed players are pushed away with on contact, in units per second.
+    pub contact_knockback: f32,
+    /// Seconds between two contact hits of the same mob.
+    pub damage_cooldown: f32,
+    pub atlas_row: usize,
+}
+
+impl Default for MobSettings {
+    fn default() -> Self {
+        Self {
+            max_mobs: 8,
+            max_per_chunk: 1,
+            spawn_interval: 5.0,
+            spawn_chance: 0.1,
+            safety_radius: 400.0,
+            aggro_range: 256.0,
+            speed: 45.0,
+            wander_speed: 20.0,
+            wander_time: 3.0,
+            health: 10.0,
+            contact_range: 20.0,
+            contact_damage: 10.0,
+            contact_knockback: 120.0,
+            damage_cooldown: 1.0,
+            atlas_row: 9,
+        }
+    }
+}
+
+/// Server side time of the last contact hit.
+#[derive(Component, Debug, Default)]
+struct ContactCooldown {
+    last_hit: Option<f32>,
+}
+
+/// Server side wandering of a mob without a target.
+#[derive(Component, Debug, Default)]
+struct Wander {
+    /// Zero while idling.
+    direction: Vec2,
+    /// Seconds until the mob picks a new direction.
+    remaining: f32,
+}
+
+#[derive(Component, Debug)]
+struct MobAnimation {
+    old_pos: Vec2,
+    frame: usize,
+    timer: Timer,
+}
+
+fn init_mob(
+    mut commands: Commands,
+    query: Query<(Entity, &Transform), (With<Mob>, Without<Sprite>)>,
+    settings: Res<MobSettings>,
+    game_assets: Res<GameAssets>,
+) {
+    for (entity, transform) in query.iter() {
+        commands.entity(entity).insert((
+            Sprite {
+                custom_size: Some(Vec2::new(32.0, 32.0)),
+                ..Default::default()
+            },
+            TextureAtlas {
+                layout: game_assets.player_layout.clone(),
+                index: settings.atlas_row * ATLAS_COLUMNS,
+            },
+            game_assets.player_texture.clone(),
+            VisibilityBundle::default(),
+            GlobalTransform::default(),
+            MobAnimation {
+                old_pos: transform.translation.xy(),
+                frame: 0,
+                timer: Timer::from_seconds(0.15, TimerMode::Repeating),
+            },
+        ));
+    }
+}
+
+fn animate_mobs(
+    mut query: Query<(&mut TextureAtlas, &mut Sprite, &Transform, &mut MobAnimation), With<Mob>>,
+    settings: Res<MobSettings>,
+    time: Res<Time>,
+) {
+    for (mut atlas, mut sprite, transform, mut animation) in query.iter_mut() {
+        if !animation.timer.tick(time.delta()).just_finished() {
+            continue;
+        }
+        let diff = transform.translation.xy() - animation.old_pos;
+        animation.old_pos = transform.translation.xy();
+        animation.frame = if diff == Vec2::ZERO {
+            0
+        } else {
+            (animation.frame + 1) % WALK_FRAMES
+        };
+        if diff.x != 0.0 {
+            sprite.flip_x = diff.x < 0.0;
+        }
+        atlas.index = settings.atlas_row * ATLAS_COLUMNS + animation.frame;
+    }
+}
+
+/// Mobs burn away at dawn and are dropped together with the chunk they stand in.
+fn despawn_mobs(
+    mut commands: Commands,
+    mobs: Query<(Entity, &Transform), With<Mob>>,
+    parked: Query<(), With<ParkedChunk>>,
+    chunk_map: Res<ChunkIndexMap>,
+    game_time: Res<GameTime>,
+) {
+    let is_night = game_time.is_night();
+    for (entity, transform) in mobs.iter() {
+        let chunk_loaded = chunk_map
+            .get(chunk_index_at(transform.translation.xy()))
+            .is_some_and(|chunk| !parked.contains(chunk));
+        if !is_night || !chunk_loaded {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Periodically adds mobs at night to random tiles of loaded chunks that are below the per chunk
+/// cap, more often in hostile biomes. Only grounds that allow hostile spawns are used, and never
+/// near a player.
+fn spawn_mobs(
+    mut commands: Commands,
+    mut glob: ResMut<GlobalEntropy<WyRand>>,
+    mut since_last_spawn: Local<f32>,
+    mobs: Query<&Transform, With<Mob>>,
+    players: Query<&Transform, With<Player>>,
+    chunks: Query<(&Chunk, &ChunkTiles), Without<ParkedChunk>>,
+    occupancy: Res<TileOccupancy>,
+    grounds: Res<GroundRegistry>,
+    settings: Res<MobSettings>,
+    game_time: Res<GameTime>,
+    time: Res<Time>,
+) {
+    *since_last_spawn += time.delta_seconds();
+    if *since_last_spawn < settings.spawn_interval {
+        return;
+    }
+    *since_last_spawn = 0.0;
+    if !game_time.is_night() {
+        return;
+    }
+
+    let mut population = HashMap::<IVec2, usize>::default();
+    for transform in mobs.iter() {
+        *population.entry(chunk_index_at(transform.translation.xy())).or_default() += 1;
+    }
+    let mut total = mobs.iter().count();
+    let player_positions: Vec<Vec2> = players.iter().map(|t| t.translation.xy()).collect();
+    for (chunk, chunk_tiles) in chunks.iter() {
+        if total >= settings.max_mobs {
+            return;
+        }
+        let count = population.get(&chunk.chunk_index).copied().unwrap_or_default();
+        let chance = settings.spawn_chance * chunk.biome.mob_spawn_factor();
+        if count >= settings.max_per_chunk || glob.next_u32() as f32 / u32::MAX as f32 >= chance {
+            continue;
+        }
+        let tile_pos = TilePos::new(
+            glob.next_u32() % TILES_PER_CHUNK,
+            glob.next_u32() % TILES_PER_CHUNK,
+        );
+        let allowed = chunk_tiles
+            .get(&tile_pos)
+            .and_then(|ground| grounds.get(ground))
+            .is_some_and(|def| def.hostile_spawns);
+        let position = tile_center_world_pos(chunk.chunk_index, &tile_pos);
+        let too_close = player_positions
+            .iter()
+            .any(|player| player.distance(position) < settings.safety_radius);
+        if !allowed || too_close || occupancy.is_occupied(global_tile_at(position)) {
+            continue;
+        }
+        total += 1;
+        spawn_mob(&mut commands, position, &settings);
+    }
+}
+
+fn spawn_mob(commands: &mut Commands, position: Vec2, settings: &MobSettings) {
+    commands.spawn((
+        Name::new("Mob"),
+        Mob,
+        Health::new(settings.health),
+        Transform::from_translation(position.extend(1.0)),
+        Collider::ball(MOB_COLLIDER_RADIUS),
+        Sensor,
+        ContactCooldown::default(),
+        Wander::default(),
+        Interpolated,
+        Replicated,
+    ));
+}
+
+/// Steers every mob straight at the nearest player in aggro range and lets the others wander.
+/// Both slide along obstacles on the tile grid.
+fn steer_mobs(
+    mut mobs: Query<(&mut Transform, &mut Wander), (With<Mob>, Without<Player>)>,
+    mut glob: ResMut<GlobalEntropy<WyRand>>,
+    players: Query<&Transform, (With<Player>, Without<Respawning>)>,
+    occupancy: Res<TileOccupancy>,
+    water: Res<WaterSettings>,
+    chunk_tiles: Query<&ChunkTiles>,
+    chunk_map: Res<ChunkIndexMap>,
+    settings: Res<MobSettings>,
+    time: Res<Time>,
+) {
+    let delta = time.delta_seconds();
+    for (mut transform, mut wander) in mobs.iter_mut() {
+        let position = transform.translation.xy();
+        let target = players
+            .iter()
+            .map(|t| t.translation.xy())
+            .filter(|player| player.distance(position) <= settings.aggro_range)
+            .min_by(|a, b| a.distance(position).total_cmp(&b.distance(position)));
+        let offset = match target {
+            Some(target) => (target - position).clamp_length_max(settings.speed * delta),
+            None => {
+                wander.remaining -= delta;
+                if wander.remaining <= 0.0 {
+                    let roll = glob.next_u32() as f32 / u32::MAX as f32;
+                    // idle for about a third of the time
+                    wander.direction = if roll < 1.0 / 3.0 {
+                        Vec2::ZERO
+                    } else {
+                        Vec2::from_angle(glob.next_u32() as f32 / u32::MAX as f32 * TAU)
+                    };
+                    wander.remaining = (0.2 + roll * 0.8) * settings.wander_time;
+                }
+                wander.direction * settings.wander_speed * delta
+            }
+        };
+        if offset == Vec2::ZERO {
+            continue;
+        }
+        let candidates = [offset, Vec2::new(offset.x, 0.0), Vec2::new(0.0, offset.y)];
+        match candidates
+            .into_iter()
+            .map(|offset| position + offset)
+            .find(|next| is_walkable(*next, &chunk_map, &chunk_tiles, &occupancy, &water))
+        {
+            Some(next) => transform.translation = next.extend(transform.translation.z),
+            // stuck, wander somewhere else next frame
+            None => wander.remaining = 0.0,
+        }
+    }
+}
+
+fn contact_damage(
+    mut mobs: Query<(&Transform, &mut ContactCooldown), With<Mob>>,
+    players: Query<(Entity, &Transform), (With<Player>, Without<Respawning>)>,
+    mut damage_events: EventWriter<DamageEvent>,
+    settings: Res<MobSettings>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds();
+    for (mob_transform, mut cooldown) in mobs.iter_mut() {
+        if cooldown
+            .last_hit
+            .is_some_and(|last| now - last < settings.damage_cooldown)
+        {
+            continue;
+        }
+        let position = mob_transform.translation.xy();
+        let Some((player, _)) = players.iter().find(|(_, t)| {
+            t.translation.xy().distance(position) <= settings.contact_range
+        }) else {
+            continue;
+        };
+        damage_events.send(
+            DamageEvent::new(player, settings.contact_damage)
+                .with_knockback(position, settings.contact_knockback),
+        );
+        cooldown.last_hit = Some(now);
+    }
+}
+
+fn kill_mobs(
+    mut commands: Commands,
+    mut death_events: EventReader<DeathEvent>,
+    mobs: Query<&Transform, With<Mob>>,
+    registry: Res<ItemRegistry>,
+) {
+    for DeathEvent { entity } in death_events.read() {
+        let Ok(transform) = mobs.get(*entity) else {
+            continue;
+        };
+        match registry.create(MOB_DROP) {
+            Some(drop) => spawn_ground_item(&mut commands, &drop, transform.translation.xy(), None),
+            None => warn!("Mob drop {MOB_DROP} is not a known item"),
+        }
+        commands.entity(*entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,184 @@
+use bevy::{ecs::event::Events, prelude::*, utils::HashMap};
+use bevy_replicon::prelude::*;
+
+use crate::{MoveEvent, MOVE_EVENT_RATE};
+
+/// Clients send [`MOVE_EVENT_RATE`] move events per second no matter their frame rate. Every event
+/// moves a fixed step, so the budget refills only slightly faster, jitter is left to the burst.
+const MOVE_RATE_TOLERANCE: f32 = 1.1;
+/// Move events a client may send in a burst, e.g. when a lag spike delivers several at once.
+const MAX_MOVE_BURST: f32 = 16.0;
+/// How far a client timestamp may run ahead of the server's view of the client clock, in seconds.
+const MAX_TIMESTAMP_LEAD: f32 = 1.0;
+/// How far a timestamp may go backwards before the event is considered out of order, in seconds.
+const MAX_TIMESTAMP_REGRESSION: f32 = 0.05;
+/// `Time::elapsed_seconds_wrapped` wraps around after this many seconds by default.
+const TIMESTAMP_WRAP_PERIOD: f32 = 3600.0;
+/// Violations within [`VIOLATION_WINDOW`] seconds after which the client gets disconnected.
+const MAX_VIOLATIONS: u32 = 200;
+const VIOLATION_WINDOW: f32 = 10.0;
+
+pub struct MoveValidationPlugin;
+
+impl Plugin for MoveValidationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MoveValidation>().add_systems(
+            PreUpdate,
+            validate_move_events
+                .run_if(server_running)
+                .after(ServerSet::Receive),
+        );
+    }
+}
+
+#[derive(Debug, Default)]
+struct ClientMoveStats {
+    /// Token bucket of accepted move events.
+    budget: f32,
+    /// Offset between server time and the client's timestamps, measured with the first event.
+    clock_offset: Option<f32>,
+    last_timestamp: Option<f32>,
+    violations: u32,
+    window_start: f32,
+}
+
+impl ClientMoveStats {
+    fn refill(&mut self, delta: f32) {
+        self.budget =
+            (self.budget + MOVE_EVENT_RATE * MOVE_RATE_TOLERANCE * delta).min(MAX_MOVE_BURST);
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+struct MoveValidation {
+    clients: HashMap<ClientId, ClientMoveStats>,
+}
+
+/// Filters incoming move events before they reach the prediction plugin: inputs are clamped to
+/// unit length, and events exceeding the rate limit or with implausible timestamps are dropped.
+pub fn validate_move_events(
+    mut events: ResMut<Events<FromClient<MoveEvent>>>,
+    mut validation: ResMut<MoveValidation>,
+    mut server_events: EventReader<ServerEvent>,
+    mut server: ResMut<RepliconServer>,
+    time: Res<Time>,
+) {
+    for event in server_events.read() {
+        if let ServerEvent::ClientDisconnected { client_id, .. } = event {
+            validation.clients.remove(client_id);
+        }
+    }
+
+    let now = time.elapsed_seconds();
+    for stats in validation.clients.values_mut() {
+        stats.refill(time.delta_seconds());
+    }
+
+    let mut accepted = Vec::new();
+    for FromClient {
+        client_id,
+        mut event,
+    } in events.drain()
+    {
+        let stats = validation
+            .clients
+            .entry(client_id)
+            .or_insert_with(|| ClientMoveStats {
+                budget: MAX_MOVE_BURST,
+                window_start: now,
+                ..Default::default()
+            });
+
+        if let Err(reason) = check_move_event(stats, &event, now) {
+            if now - stats.window_start > VIOLATION_WINDOW {
+                stats.window_start = now;
+                stats.violations = 0;
+            }
+            stats.violations += 1;
+            debug!("Dropped move event from {client_id:?}: {reason}");
+            if stats.violations > MAX_VIOLATIONS {
+                warn!("Disconnecting {client_id:?} for repeatedly sending invalid move events");
+                server.disconnect(client_id);
+                validation.clients.remove(&client_id);
+            }
+            continue;
+        }
+
+        event.input = event.input.clamp_length_max(1.0);
+        accepted.push(FromClient { client_id, event });
+    }
+    events.send_batch(accepted);
+}
+
+fn check_move_event(
+    stats: &mut ClientMoveStats,
+    event: &MoveEvent,
+    now: f32,
+) -> Result<(), &'static str> {
+    if !event.input.is_finite() || !event.timestamp.is_finite() {
+        return Err("non finite values");
+    }
+    if stats.budget < 1.0 {
+        return Err("rate limit exceeded");
+    }
+
+    let offset = *stats.clock_offset.get_or_insert(now - event.timestamp);
+    if let Some(last) = stats.last_timestamp {
+        let regression = last - event.timestamp;
+        let wrapped = regression > TIMESTAMP_WRAP_PERIOD * 0.5;
+        if wrapped {
+            stats.clock_offset = Some(now - event.timestamp);
+        } else if regression > MAX_TIMESTAMP_REGRESSION {
+            return Err("out of order timestamp");
+        } else if event.timestamp + offset > now + MAX_TIMESTAMP_LEAD {
+            return Err("timestamp from the future");
+        }
+    }
+
+    stats.budget -= 1.0;
+    stats.last_timestamp = Some(event.timestamp);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Events the validation accepts from a client sending `per_frame` events every 60 Hz frame
+    /// for ten seconds.
+    fn accepted_over_ten_seconds(per_frame: u32) -> u32 {
+        let delta = 1.0 / MOVE_EVENT_RATE;
+        let mut stats = ClientMoveStats {
+            budget: MAX_MOVE_BURST,
+            ..Default::default()
+        };
+        let mut accepted = 0;
+        for frame in 0..(10.0 * MOVE_EVENT_RATE) as u32 {
+            let now = frame as f32 * delta;
+            stats.refill(delta);
+            for _ in 0..per_frame {
+                let event = MoveEvent {
+                    input: Vec2::X,
+                    timestamp: now,
+                    sprint: false,
+                };
+                if check_move_event(&mut stats, &event, now).is_ok() {
+                    accepted += 1;
+                }
+            }
+        }
+        accepted
+    }
+
+    #[test]
+    fn steady_sender_is_not_throttled() {
+        assert_eq!(accepted_over_ten_seconds(1), 600);
+    }
+
+    #[test]
+    fn double_rate_sender_is_throttled() {
+        let accepted = accepted_over_ten_seconds(2) as f32;
+        assert!(accepted <= 10.0 * MOVE_EVENT_RATE * MOVE_RATE_TOLERANCE + MAX_MOVE_BURST);
+        assert!(accepted < 1.2 * 10.0 * MOVE_EVENT_RATE);
+    }
+}
@@ -0,0 +1,242 @@
+use std::{
+    collections::BTreeMap,
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use bevy::{prelude::*, utils::get_short_name};
+use bevy_replicon::{client::ClientSet, core::Replicated, prelude::*};
+use bevy_replicon_renet::renet::{RenetClient, RenetServer};
+
+use crate::{
+    chunk::ChunkTiles,
+    crafting::CraftQueue,
+    debug_overlay::DebugOverlayState,
+    health::Health,
+    item::Item,
+    item_container::ItemContainer,
+    player::{Facing, PlayerAction},
+    world_object::WorldObject,
+    Cli,
+};
+
+const CSV_HEADER: &str = "time,metric,per_second";
+
+pub struct NetProfilerPlugin;
+
+impl Plugin for NetProfilerPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<NetProfilerSettings>()
+            .init_resource::<NetProfilerSettings>()
+            .init_resource::<NetCounters>()
+            .add_systems(Startup, enable_from_cli)
+            .add_systems(
+                PostUpdate,
+                (count_spawns, count_despawns, report_server_profile)
+                    .chain()
+                    .run_if(server_running)
+                    .run_if(profiling_enabled),
+            )
+            .add_systems(
+                PreUpdate,
+                report_client_profile
+                    .after(ClientSet::Receive)
+                    .run_if(client_connected)
+                    .run_if(overlay_visible),
+            );
+        track_updates::<Transform>(app);
+        track_updates::<ChunkTiles>(app);
+        track_updates::<WorldObject>(app);
+        track_updates::<Item>(app);
+        track_updates::<ItemContainer>(app);
+        track_updates::<CraftQueue>(app);
+        track_updates::<Health>(app);
+        track_updates::<Facing>(app);
+        track_updates::<PlayerAction>(app);
+    }
+}
+
+/// Counts changes of a replicated component. On the server they are what replicon is about to
+/// send, on the client what it just received.
+fn track_updates<C: Component>(app: &mut App) {
+    app.add_systems(
+        PostUpdate,
+        count_updates::<C>
+            .before(report_server_profile)
+            .run_if(server_running)
+            .run_if(profiling_enabled),
+    )
+    .add_systems(
+        PreUpdate,
+        count_updates::<C>
+            .after(ClientSet::Receive)
+            .before(report_client_profile)
+            .run_if(client_connected)
+            .run_if(overlay_visible),
+    );
+}
+
+#[derive(Resource, Debug, Reflect)]
+#[reflect(Resource)]
+pub struct NetProfilerSettings {
+    /// Off by default, the counters cost nothing then. `--net-profile` turns it on.
+    pub enabled: bool,
+    /// Seconds between two summaries.
+    pub interval: f32,
+    /// File the summaries are appended to as CSV rows of `time,metric,per_second`.
+    #[reflect(ignore)]
+    pub csv_path: Option<PathBuf>,
+}
+
+impl Default for NetProfilerSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval: 5.0,
+            csv_path: None,
+        }
+    }
+}
+
+/// Replication counts since the last summary.
+#[derive(Resource, Debug, Default)]
+struct NetCounters {
+    spawns: u32,
+    despawns: u32,
+    /// Changed components by type name.
+    updates: BTreeMap<&'static str, u32>,
+    since: f32,
+}
+
+impl NetCounters {
+    /// Per second rates of everything counted, keyed by a stable metric name.
+    fn rates(&self, elapsed: f32) -> BTreeMap<String, f32> {
+        let per_second = |count: u32| count as f32 / elapsed.max(f32::EPSILON);
+        let mut rates = BTreeMap::new();
+        rates.insert("spawns".to_string(), per_second(self.spawns));
+        rates.insert("despawns".to_string(), per_second(self.despawns));
+        for (name, count) in &self.updates {
+            rates.insert(format!("updates.{}", get_short_name(name)), per_second(*count));
+        }
+        rates
+    }
+
+    fn reset(&mut self, now: f32) {
+        self.spawns = 0;
+        self.despawns = 0;
+        self.updates.values_mut().for_each(|count| *count = 0);
+        self.since = now;
+    }
+}
+
+fn profiling_enabled(settings: Res<NetProfilerSettings>) -> bool {
+    settings.enabled
+}
+
+fn overlay_visible(state: Res<DebugOverlayState>) -> bool {
+    state.visible
+}
+
+fn enable_from_cli(mut settings: ResMut<NetProfilerSettings>, cli: Res<Cli>) {
+    if let Cli::Server {
+        net_profile: Some(ref path),
+        ..
+    } = *cli
+    {
+        settings.enabled = true;
+        settings.csv_path = Some(path.clone());
+    }
+}
+
+fn count_updates<C: Component>(
+    mut counters: ResMut<NetCounters>,
+    changed: Query<(), (Changed<C>, With<Replicated>)>,
+) {
+    let count = changed.iter().count() as u32;
+    *counters.updates.entry(std::any::type_name::<C>()).or_default() += count;
+}
+
+fn count_spawns(mut counters: ResMut<NetCounters>, spawned: Query<(), Added<Replicated>>) {
+    counters.spawns += spawned.iter().count() as u32;
+}
+
+fn count_despawns(mut counters: ResMut<NetCounters>, mut despawned: RemovedComponents<Replicated>) {
+    counters.despawns += despawned.read().count() as u32;
+}
+
+/// Logs a summary every interval and appends it to the CSV file, if one was given.
+fn report_server_profile(
+    mut counters: ResMut<NetCounters>,
+    settings: Res<NetProfilerSettings>,
+    server: Option<Res<RenetServer>>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds();
+    let elapsed = now - counters.since;
+    if elapsed < settings.interval {
+        return;
+    }
+    let mut rates = counters.rates(elapsed);
+    if let Some(server) = server {
+        let (sent, received) = server
+            .clients_id()
+            .into_iter()
+            .filter_map(|client_id| server.network_info(client_id).ok())
+            .fold((0.0, 0.0), |(sent, received), info| {
+                (sent + info.bytes_sent_per_second, received + info.bytes_received_per_second)
+            });
+        rates.insert("bytes_sent".to_string(), sent as f32);
+        rates.insert("bytes_received".to_string(), received as f32);
+    }
+    let summary: Vec<String> = rates
+        .iter()
+        .filter(|(_, rate)| **rate > 0.0)
+        .map(|(metric, rate)| format!("{metric} {rate:.1}"))
+        .collect();
+    info!("Net profile (per second): {}", summary.join(", "));
+    if let Some(path) = &settings.csv_path {
+        if let Err(err) = append_csv(path, now, &rates) {
+            error!("Cannot write net profile to {}: {err}", path.display());
+        }
+    }
+    counters.reset(now);
+}
+
+fn append_csv(path: &Path, now: f32, rates: &BTreeMap<String, f32>) -> std::io::Result<()> {
+    let new_file = !path.exists();
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    if new_file {
+        writeln!(file, "{CSV_HEADER}")?;
+    }
+    for (metric, rate) in rates {
+        writeln!(file, "{now:.1},{metric},{rate:.2}")?;
+    }
+    Ok(())
+}
+
+/// Shows the received bytes and replicated updates per second in the debug overlay, refreshed
+/// once a second.
+fn report_client_profile(
+    mut counters: ResMut<NetCounters>,
+    mut overlay: ResMut<DebugOverlayState>,
+    client: Option<Res<RenetClient>>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds();
+    let elapsed = now - counters.since;
+    if elapsed < 1.0 {
+        return;
+    }
+    let updates: u32 = counters.updates.values().sum();
+    let received = client.map_or(0.0, |client| client.network_info().bytes_received_per_second);
+    overlay.set_line(
+        "04 net",
+        format!(
+            "Net in: {:.1} kB/s, {:.0} updates/s",
+            received / 1000.0,
+            updates as f32 / elapsed
+        ),
+    );
+    counters.reset(now);
+}
@@ -0,0 +1,143 @@
+use std::{
+    error::Error,
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+    time::SystemTime,
+};
+
+use bevy::prelude::*;
+use bevy_replicon::prelude::*;
+use bevy_replicon_renet::{
+    renet::{
+        transport::{
+            ClientAuthentication, NetcodeClientTransport, NetcodeServerTransport,
+            ServerAuthentication, ServerConfig,
+        },
+        ConnectionConfig, RenetClient, RenetServer,
+    },
+    RenetChannelsExt,
+};
+
+use crate::server_status::{ServerInfo, StatusSocket, STATUS_PORT_OFFSET};
+
+pub const PROTOCOL_ID: u64 = 0x1122334455667788;
+pub const MAX_CLIENTS: usize = 10;
+
+pub struct NetworkPlugin;
+
+impl Plugin for NetworkPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<NetworkSetupFailed>()
+            .register_type::<ServerSettings>()
+            .init_resource::<ServerSettings>()
+            .add_systems(Update, log_setup_failures);
+    }
+}
+
+#[derive(Resource, Debug, Reflect)]
+#[reflect(Resource)]
+pub struct ServerSettings {
+    /// New chunks revealed to each client per server tick, see [`crate::chunk_stream`].
+    pub chunks_per_tick: usize,
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        Self { chunks_per_tick: 4 }
+    }
+}
+
+/// Starting a server or connecting to one failed, e.g. because the port is already in use.
+/// Nothing was set up in that case, so the setup can simply be tried again.
+#[derive(Event, Debug, Clone)]
+pub struct NetworkSetupFailed(pub String);
+
+fn connection_config(channels: &RepliconChannels) -> ConnectionConfig {
+    ConnectionConfig {
+        server_channels_config: channels.get_server_configs(),
+        client_channels_config: channels.get_client_configs(),
+        ..Default::default()
+    }
+}
+
+/// Binds the game and status sockets on `port` and inserts the server resources. All sockets are
+/// bound before anything is inserted, so a failure leaves no half started server behind.
+pub fn setup_server(
+    port: u16,
+    settings: ServerInfo,
+    commands: &mut Commands,
+    channels: &RepliconChannels,
+) -> Result<(), Box<dyn Error>> {
+    let status_socket = StatusSocket::bind(SocketAddr::new(
+        Ipv4Addr::UNSPECIFIED.into(),
+        port + STATUS_PORT_OFFSET,
+    ))?;
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, port))?;
+    let current_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?;
+    let server_config = ServerConfig {
+        current_time,
+        max_clients: settings.max_players,
+        protocol_id: PROTOCOL_ID,
+        authentication: ServerAuthentication::Unsecure,
+        public_addresses: Default::default(),
+    };
+    let transport = NetcodeServerTransport::new(server_config, socket)?;
+
+    commands.insert_resource(settings);
+    commands.insert_resource(status_socket);
+    commands.insert_resource(RenetServer::new(connection_config(channels)));
+    commands.insert_resource(transport);
+    Ok(())
+}
+
+/// Connects to the server at `server_addr` with a client id derived from the current time.
+pub fn setup_client(
+    server_addr: SocketAddr,
+    commands: &mut Commands,
+    channels: &RepliconChannels,
+) -> Result<(), Box<dyn Error>> {
+    let current_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?;
+    let client_id = current_time.as_millis() as u64;
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+    let authentication = ClientAuthentication::Unsecure {
+        client_id,
+        protocol_id: PROTOCOL_ID,
+        server_addr,
+        user_data: None,
+    };
+    let transport = NetcodeClientTransport::new(current_time, authentication, socket)?;
+
+    commands.insert_resource(RenetClient::new(connection_config(channels)));
+    commands.insert_resource(transport);
+    Ok(())
+}
+
+/// Disconnects all clients and removes the server resources, closing its sockets.
+pub fn teardown_server(commands: &mut Commands) {
+    commands.add(|world: &mut World| {
+        if let Some(mut server) = world.remove_resource::<RenetServer>() {
+            if let Some(mut transport) = world.remove_resource::<NetcodeServerTransport>() {
+                transport.disconnect_all(&mut server);
+            }
+        }
+        world.remove_resource::<StatusSocket>();
+        world.remove_resource::<ServerInfo>();
+    });
+}
+
+/// Tells the server we leave and removes the client resources, closing its socket.
+pub fn teardown_client(commands: &mut Commands) {
+    commands.add(|world: &mut World| {
+        if let Some(mut transport) = world.remove_resource::<NetcodeClientTransport>() {
+            transport.disconnect();
+        }
+        if let Some(mut client) = world.remove_resource::<RenetClient>() {
+            client.disconnect();
+        }
+    });
+}
+
+fn log_setup_failures(mut events: EventReader<NetworkSetupFailed>) {
+    for NetworkSetupFailed(reason) in events.read() {
+        error!("Network setup failed: {reason}");
+    }
+}
@@ -1,4 +1,4 @@
-use bevy::prelude::*;
+use bevy::{ecs::event::Events, prelude::*};
 use bevy_replicon::prelude::*;
 use bevy_replicon_snap::{
     prediction::{AppPredictionExt, OwnerPredicted, Predict},
@@ -6,15 +6,87 @@ use bevy_replicon_snap::{
 };
 use serde::{Deserialize, Serialize};
 
-use crate::MoveEvent;
+use crate::{
+    action::{ActionCooldownConfig, ActionCooldowns, GameAction},
+    charge::{Charging, LocalCharge},
+    emote::{ActiveEmote, EMOTE_FRAMES},
+    equipment::{Equipment, HotbarSlot},
+    chunk::{ChunkIndexMap, ChunkTiles},
+    game_assets::GameAssets,
+    ground_registry::GroundRegistry,
+    health::{DeathEvent, Health},
+    stamina::{Stamina, PLAYER_STAMINA, SPRINT_MULTIPLIER},
+    hunger::{Hunger, PLAYER_HUNGER},
+    item::Item,
+    item_container::{ItemContainer, DEFAULT_CONTAINER_CAPACITY},
+    loot_bag::{spawn_loot_bag, BAG_LIFETIME},
+    move_validation::validate_move_events,
+    water::WaterSettings,
+    world::ground_at_world_pos,
+    ActionEvent, MoveEvent,
+};
+
+/// Replicated player positions are snapped to multiples of this.
+const POSITION_QUANTUM: f32 = 0.01;
+/// Move inputs shorter than this are treated as standing still.
+const MIN_INPUT_LENGTH: f32 = 0.001;
+const PLAYER_HEALTH: f32 = 100.0;
+/// Seconds a dead player waits before coming back.
+const RESPAWN_DELAY: f32 = 5.0;
+const STARTING_FENCES: u32 = 16;
+const STARTING_SIGNS: u32 = 4;
+const STARTING_BEDS: u32 = 1;
+const STARTING_COMPOSTERS: u32 = 1;
+/// First atlas indices of the tool swing rows.
+const TOOL_RIGHT_INDEX: usize = 16;
+const TOOL_LEFT_INDEX: usize = 24;
+const TOOL_FRAMES: usize = 4;
+/// First atlas index of the swimming row.
+const SWIM_INDEX: usize = 12;
+const SWIM_FRAMES: usize = 4;
+/// Length of one tool swing while charging, repeated until the charge ends.
+const CHARGE_SWING_TIME: f32 = 0.4;
 
 pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
         app.replicate::<Player>()
+            .replicate::<Facing>()
+            .replicate::<PlayerAction>()
+            .replicate::<Respawning>()
             .add_systems(PreUpdate, init_player.after(ClientSet::Receive))
-            .add_systems(Update, animate_player.run_if(client_connected))
+            .add_systems(
+                PreUpdate,
+                update_ground_speed
+                    .after(ClientSet::Receive)
+                    .after(ServerSet::Receive),
+            )
+            .add_systems(
+                Update,
+                (animate_local_actions, animate_remote_actions, animate_charging, animate_player)
+                    .chain()
+                    .run_if(client_connected),
+            )
+            .add_systems(Update, update_facing.run_if(server_running))
+            .add_systems(
+                PreUpdate,
+                drop_respawning_moves
+                    .run_if(server_running)
+                    .after(validate_move_events),
+            )
+            .add_systems(
+                Update,
+                (kill_players, respawn_players).chain().run_if(server_running),
+            )
+            .add_systems(Update, hide_respawning_players)
+            .add_systems(PostUpdate, broadcast_player_actions.run_if(server_running))
+            .add_systems(
+                PostUpdate,
+                quantize_player_transforms
+                    .run_if(server_running)
+                    .before(ServerSet::Send),
+            )
             .add_client_predicted_event::<MoveEvent>(ChannelKind::Ordered)
             .predict_event_for_component::<MoveEvent, Player, Transform>();
     }
@@ -28,17 +100,43 @@ pub struct PlayerBundle {
     predicted: OwnerPredicted,
     replicated: Replicated,
     name: Name,
+    cooldowns: ActionCooldowns,
+    facing: Facing,
+    health: Health,
+    hunger: Hunger,
+    stamina: Stamina,
+    action: PlayerAction,
+    inventory: ItemContainer,
+    equipment: Equipment,
+    hotbar_slot: HotbarSlot,
 }
 
 impl PlayerBundle {
     pub fn new(client_id: ClientId) -> Self {
         Self {
             owner: NetworkOwner(client_id.get()),
-            player: Player { speed: 100.0 },
+            player: Player::new(100.0),
             transform: Transform::from_xyz(0.0, 0.0, 1.0),
             replicated: Replicated::default(),
             predicted: OwnerPredicted,
             name: Name::new("Player"),
+            cooldowns: ActionCooldowns::default(),
+            facing: Facing::default(),
+            health: Health::new(PLAYER_HEALTH),
+            hunger: Hunger::new(PLAYER_HUNGER),
+            stamina: Stamina::new(PLAYER_STAMINA),
+            equipment: Equipment::default(),
+            hotbar_slot: HotbarSlot::default(),
+            action: PlayerAction::default(),
+            inventory: ItemContainer::new(
+                vec![
+                    Item::new("item.fence", "fence", 2).with_count(STARTING_FENCES),
+                    Item::new("item.sign", "sign", 3).with_count(STARTING_SIGNS),
+                    Item::new("item.bed", "bed", 4).with_count(STARTING_BEDS).with_max_stack(1),
+                    Item::new("item.composter", "composter", 5).with_count(STARTING_COMPOSTERS),
+                ],
+                DEFAULT_CONTAINER_CAPACITY,
+            ),
         }
     }
 }
@@ -46,19 +144,91 @@ impl PlayerBundle {
 #[derive(Component, Deserialize, Serialize)]
 pub struct Player {
     pub speed: f32,
+    /// Speed multiplier of the ground under the player, cached once per tick by
+    /// [`update_ground_speed`]. Not replicated: server and owning client look it up from their
+    /// own copy of the chunk tiles so prediction uses the same value as the server.
+    #[serde(skip, default = "default_ground_speed")]
+    pub ground_speed: f32,
+    /// Whether the player is in shallow water, cached together with the ground speed.
+    #[serde(skip)]
+    pub swimming: bool,
+    /// Whether the player ran out of stamina, cached from [`Stamina`] so prediction can tell if a
+    /// sprinting move is actually sprinted.
+    #[serde(skip)]
+    pub exhausted: bool,
+}
+
+/// Marks a dead player until it comes back at the world origin. Respawning players are hidden and
+/// neither move nor act.
+#[derive(Component, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Respawning {
+    /// Seconds left, counted down on the server only.
+    remaining: f32,
+}
+
+fn default_ground_speed() -> f32 {
+    1.0
+}
+
+impl Player {
+    pub fn new(speed: f32) -> Self {
+        Self {
+            speed,
+            ground_speed: default_ground_speed(),
+            swimming: false,
+            exhausted: false,
+        }
+    }
+
+    pub fn effective_speed(&self) -> f32 {
+        self.speed * self.ground_speed
+    }
+
+    /// Speed of a move, sprinting only speeds the player up while it has stamina left.
+    pub fn move_speed(&self, sprint: bool) -> f32 {
+        if sprint && !self.exhausted {
+            self.effective_speed() * SPRINT_MULTIPLIER
+        } else {
+            self.effective_speed()
+        }
+    }
+}
+
+/// Normalized direction the player last moved in.
+#[derive(Component, Debug, Clone, Copy, Deserialize, Serialize)]
+pub struct Facing(pub Vec2);
+
+impl Default for Facing {
+    fn default() -> Self {
+        Self(Vec2::NEG_Y)
+    }
+}
+
+/// Last action the server accepted from this player. The sequence changes with every action,
+/// so repeating the same action still counts as a change for replication.
+#[derive(Component, Debug, Default, Clone, Copy, Deserialize, Serialize)]
+pub struct PlayerAction {
+    pub action: Option<GameAction>,
+    pub sequence: u32,
 }
 
 #[derive(Debug, Component)]
 struct WalkAnimation {
     old_pos: Vec2,
     current_state: PlayerAnimationState,
+    /// State to return to once a tool swing is over.
+    interrupted: PlayerAnimationState,
+    tool_timer: Option<Timer>,
+    swim_frame: usize,
+    emote_frame: usize,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 enum PlayerAnimationState {
     StandStill(usize),
     WalkRight(usize),
     WalkLeft(usize),
+    UseTool { left: bool },
 }
 
 #[derive(Component, Deref, DerefMut)]
@@ -66,14 +236,14 @@ struct AnimationTimer(Timer);
 
 fn init_player(
     mut commands: Commands,
-    mut query: Query<(Entity, &Transform), (With<Player>, Without<GlobalTransform>)>,
-    asset_server: Res<AssetServer>,
-    mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+    mut query: Query<(Entity, &Transform, Has<ActionCooldowns>), (With<Player>, Without<GlobalTransform>)>,
+    game_assets: Res<GameAssets>,
 ) {
-    for (entity, transform) in query.iter_mut() {
-        let texture = asset_server.load::<Image>("player/global.png");
-        let layout = TextureAtlasLayout::from_grid(UVec2::new(32, 32), 8, 10, None, None);
-        let layout_handle = texture_atlas_layouts.add(layout);
+    for (entity, transform, has_cooldowns) in query.iter_mut() {
+        if !has_cooldowns {
+            // clients mirror the server side cooldowns locally
+            commands.entity(entity).insert(ActionCooldowns::default());
+        }
         commands.entity(entity).insert((
             GlobalTransform::default(),
             VisibilityBundle::default(),
@@ -82,13 +252,17 @@ fn init_player(
                 ..Default::default()
             },
             TextureAtlas {
-                layout: layout_handle,
+                layout: game_assets.player_layout.clone(),
                 index: 0,
             },
-            texture,
+            game_assets.player_texture.clone(),
             WalkAnimation {
                 old_pos: transform.translation.xy(),
                 current_state: PlayerAnimationState::WalkRight(0),
+                interrupted: PlayerAnimationState::WalkRight(0),
+                tool_timer: None,
+                swim_frame: 0,
+                emote_frame: 0,
             },
             AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating)),
         ));
@@ -102,15 +276,52 @@ fn animate_player(
             &Transform,
             &mut WalkAnimation,
             &mut AnimationTimer,
+            &Player,
+            Option<&ActiveEmote>,
         ),
-        With<Player>,
     >,
     time: Res<Time>,
 ) {
     const ANIMATION_RIGHT_INDEX : usize = 4;
     const ANIMATION_LEFT_INDEX : usize = 8;
-    for (mut atlas, transform, mut animation, mut timer) in query.iter_mut() {
+    for (mut atlas, transform, mut animation, mut timer, player, emote) in query.iter_mut() {
         timer.tick(time.delta());
+
+        // tool use overrides walking until its own timer ran out
+        if let PlayerAnimationState::UseTool { left } = animation.current_state {
+            let finished = animation
+                .tool_timer
+                .as_mut()
+                .map_or(true, |tool_timer| tool_timer.tick(time.delta()).finished());
+            if !finished {
+                let fraction = animation.tool_timer.as_ref().map_or(1.0, Timer::fraction);
+                let frame = ((fraction * TOOL_FRAMES as f32) as usize).min(TOOL_FRAMES - 1);
+                let row = if left { TOOL_LEFT_INDEX } else { TOOL_RIGHT_INDEX };
+                atlas.index = row + frame;
+                continue;
+            }
+            animation.current_state = animation.interrupted;
+            animation.tool_timer = None;
+        }
+
+        // an emote replaces idling, moving cancels it before the server's removal arrives
+        if let Some(emote) = emote.filter(|_| transform.translation.xy() == animation.old_pos) {
+            if timer.just_finished() {
+                animation.emote_frame = (animation.emote_frame + 1) % EMOTE_FRAMES;
+                atlas.index = emote.emote.atlas_index() + animation.emote_frame;
+            }
+            continue;
+        }
+
+        if player.swimming {
+            if timer.just_finished() {
+                animation.swim_frame = (animation.swim_frame + 1) % SWIM_FRAMES;
+                atlas.index = SWIM_INDEX + animation.swim_frame;
+                animation.old_pos = transform.translation.xy();
+            }
+            continue;
+        }
+
         if timer.just_finished() {
             let diff = transform.translation.x - animation.old_pos.x;
             let new_state = match animation.current_state {
@@ -135,22 +346,250 @@ fn animate_player(
                 PlayerAnimationState::WalkRight(old) => {
                     PlayerAnimationState::WalkRight((old + 1) % 4)
                 }
+                PlayerAnimationState::UseTool { .. } => unreachable!("tool use is handled above"),
             };
-            *animation = WalkAnimation {
-                old_pos: transform.translation.xy(),
-                current_state: new_state,
-            };
+            animation.old_pos = transform.translation.xy();
+            animation.current_state = new_state;
             atlas.index = match animation.current_state {
                 PlayerAnimationState::StandStill(x) => x,
                 PlayerAnimationState::WalkLeft(x) => x + ANIMATION_LEFT_INDEX,
                 PlayerAnimationState::WalkRight(x) => x + ANIMATION_RIGHT_INDEX,
+                PlayerAnimationState::UseTool { .. } => unreachable!("tool use is handled above"),
+            }
+        }
+    }
+}
+
+impl WalkAnimation {
+    /// Starts a tool swing lasting `duration` seconds. Ignored while a swing is still playing,
+    /// so spamming an action doesn't restart the animation.
+    fn use_tool(&mut self, duration: f32, left: bool) {
+        if matches!(self.current_state, PlayerAnimationState::UseTool { .. }) {
+            return;
+        }
+        self.interrupted = self.current_state;
+        self.current_state = PlayerAnimationState::UseTool { left };
+        self.tool_timer = Some(Timer::from_seconds(duration, TimerMode::Once));
+    }
+}
+
+/// Longest cooldown of the given actions, the swing lasts as long as the player has to wait.
+fn tool_use_duration(actions: impl IntoIterator<Item = GameAction>, config: &ActionCooldownConfig) -> f32 {
+    actions
+        .into_iter()
+        .map(|action| config.get(action))
+        .fold(0.0, f32::max)
+}
+
+/// Plays the swing on the owning client as soon as it sends an action.
+fn animate_local_actions(
+    mut events: EventReader<ActionEvent>,
+    mut player_query: Query<(&NetworkOwner, &Facing, &mut WalkAnimation)>,
+    client: Res<RepliconClient>,
+    cooldown_config: Res<ActionCooldownConfig>,
+) {
+    let actions: Vec<GameAction> = events
+        .read()
+        .flat_map(|event| GameAction::from_key(event.action).iter().copied())
+        .filter(|action| action.swings_tool())
+        .collect();
+    let Some(client_id) = client.id() else {
+        return;
+    };
+    if actions.is_empty() {
+        return;
+    }
+    if let Some((_, facing, mut animation)) = player_query
+        .iter_mut()
+        .find(|(owner, ..)| owner.0 == client_id.get())
+    {
+        animation.use_tool(tool_use_duration(actions, &cooldown_config), facing.0.x < 0.0);
+    }
+}
+
+/// Plays the swing of other players when the server reports an accepted action.
+fn animate_remote_actions(
+    mut player_query: Query<(&NetworkOwner, &Facing, &PlayerAction, &mut WalkAnimation), Changed<PlayerAction>>,
+    client: Res<RepliconClient>,
+    cooldown_config: Res<ActionCooldownConfig>,
+) {
+    let local_id = client.id().map(|client_id| client_id.get());
+    for (owner, facing, player_action, mut animation) in player_query.iter_mut() {
+        // the owning client already started the animation when sending the action
+        if Some(owner.0) == local_id {
+            continue;
+        }
+        let Some(action) = player_action.action.filter(|action| action.swings_tool()) else {
+            continue;
+        };
+        animation.use_tool(tool_use_duration([action], &cooldown_config), facing.0.x < 0.0);
+    }
+}
+
+/// Loops the tool swing while a player charges, predicted for the local player.
+fn animate_charging(
+    mut player_query: Query<(&NetworkOwner, &Facing, Has<Charging>, &mut WalkAnimation)>,
+    local_charge: Res<LocalCharge>,
+    client: Res<RepliconClient>,
+) {
+    let local_id = client.id().map(|client_id| client_id.get());
+    for (owner, facing, charging, mut animation) in player_query.iter_mut() {
+        let charging = if Some(owner.0) == local_id { local_charge.is_charging() } else { charging };
+        if charging {
+            animation.use_tool(CHARGE_SWING_TIME, facing.0.x < 0.0);
+        }
+    }
+}
+
+/// Publishes actions the server accepted this frame, so other clients can animate them.
+fn broadcast_player_actions(
+    mut player_query: Query<(&ActionCooldowns, &mut PlayerAction), Changed<ActionCooldowns>>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds();
+    for (cooldowns, mut player_action) in player_query.iter_mut() {
+        if let Some(action) = cooldowns.triggered_at(now).next() {
+            player_action.action = Some(action);
+            player_action.sequence = player_action.sequence.wrapping_add(1);
+        }
+    }
+}
+
+fn update_facing(
+    mut events: EventReader<FromClient<MoveEvent>>,
+    mut player_query: Query<(&NetworkOwner, &mut Facing)>,
+) {
+    for FromClient { client_id, event } in events.read() {
+        let Some(direction) = event.input.try_normalize() else {
+            continue;
+        };
+        if let Some((_, mut facing)) = player_query
+            .iter_mut()
+            .find(|(owner, _)| owner.0 == client_id.get())
+        {
+            facing.0 = direction;
+        }
+    }
+}
+
+/// Caches the ground speed multiplier for every player, shallow water in swimming mode uses the
+/// swim multiplier instead. Outside of loaded chunks the multiplier falls back to 1.0.
+///
+/// Near chunk borders the client may briefly use 1.0 while the server already has the next chunk
+/// loaded (or the other way round), so predicted positions can drift for a few ticks until the
+/// next server correction.
+fn update_ground_speed(
+    mut player_query: Query<(&Transform, &mut Player)>,
+    chunk_query: Query<&ChunkTiles>,
+    chunk_map: Res<ChunkIndexMap>,
+    registry: Res<GroundRegistry>,
+    water: Res<WaterSettings>,
+) {
+    for (transform, mut player) in player_query.iter_mut() {
+        let ground = ground_at_world_pos(transform.translation.xy(), &chunk_map, &chunk_query);
+        let swimming = ground.as_ref().is_some_and(|ground| water.swims(ground));
+        let ground_speed = match ground {
+            Some(_) if swimming => water.swim_speed_multiplier,
+            Some(ground) => registry.speed_multiplier(&ground),
+            None => 1.0,
+        };
+        if player.ground_speed != ground_speed || player.swimming != swimming {
+            // a local cache, changing it must not trigger replication of Player
+            let player = player.bypass_change_detection();
+            player.ground_speed = ground_speed;
+            player.swimming = swimming;
+        }
+    }
+}
+
+/// Drops the inventory of dead players as a loot bag and starts their respawn countdown.
+fn kill_players(
+    mut commands: Commands,
+    mut death_events: EventReader<DeathEvent>,
+    mut player_query: Query<(&Transform, &mut ItemContainer), (With<Player>, Without<Respawning>)>,
+) {
+    for DeathEvent { entity } in death_events.read() {
+        if let Ok((transform, mut inventory)) = player_query.get_mut(*entity) {
+            if !inventory.is_empty() {
+                let items = inventory.take_all();
+                spawn_loot_bag(&mut commands, items, transform.translation.xy(), BAG_LIFETIME);
             }
+            commands.entity(*entity).insert(Respawning {
+                remaining: RESPAWN_DELAY,
+            });
+        }
+    }
+}
+
+/// Brings players back at the world origin with full health and hunger once their countdown ran out.
+fn respawn_players(
+    mut commands: Commands,
+    mut player_query: Query<
+        (Entity, &mut Respawning, &mut Transform, &mut Health, &mut Hunger),
+        With<Player>,
+    >,
+    time: Res<Time>,
+) {
+    for (entity, mut respawning, mut transform, mut health, mut hunger) in player_query.iter_mut() {
+        // clients only need to know that the player is dead, not the countdown
+        let respawning = respawning.bypass_change_detection();
+        respawning.remaining -= time.delta_seconds();
+        if respawning.remaining > 0.0 {
+            continue;
+        }
+        transform.translation = Vec3::new(0.0, 0.0, transform.translation.z);
+        health.current = health.max;
+        *hunger = Hunger::new(hunger.max);
+        commands.entity(entity).remove::<Respawning>();
+    }
+}
+
+/// Drops move events of dead players before they reach the prediction.
+fn drop_respawning_moves(
+    mut events: ResMut<Events<FromClient<MoveEvent>>>,
+    respawning: Query<&NetworkOwner, (With<Player>, With<Respawning>)>,
+) {
+    if respawning.is_empty() {
+        return;
+    }
+    let accepted: Vec<_> = events
+        .drain()
+        .filter(|FromClient { client_id, .. }| {
+            !respawning.iter().any(|owner| owner.0 == client_id.get())
+        })
+        .collect();
+    events.send_batch(accepted);
+}
+
+fn hide_respawning_players(
+    mut player_query: Query<(&mut Visibility, Has<Respawning>), With<Player>>,
+) {
+    for (mut visibility, respawning) in player_query.iter_mut() {
+        let target = if respawning { Visibility::Hidden } else { Visibility::Inherited };
+        visibility.set_if_neq(target);
+    }
+}
+
+fn quantize(value: Vec3) -> Vec3 {
+    (value / POSITION_QUANTUM).round() * POSITION_QUANTUM
+}
+
+/// Snaps moved player positions to the replication quantum without flagging another change,
+/// so floating point drift never produces new values for a stationary player.
+fn quantize_player_transforms(mut query: Query<&mut Transform, (With<Player>, Changed<Transform>)>) {
+    for mut transform in query.iter_mut() {
+        let quantized = quantize(transform.translation);
+        if quantized != transform.translation {
+            transform.bypass_change_detection().translation = quantized;
         }
     }
 }
 
 impl Predict<MoveEvent, Player> for Transform {
     fn apply_event(&mut self, event: &MoveEvent, _delta_time: f32, context: &Player) {
-        self.translation += event.input.extend(0.0) * 0.005 * context.speed;
+        if event.input.length() < MIN_INPUT_LENGTH {
+            return;
+        }
+        self.translation += event.input.extend(0.0) * 0.005 * context.move_speed(event.sprint);
     }
 }
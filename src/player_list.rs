@@ -0,0 +1,134 @@
+use bevy::prelude::*;
+use bevy_mod_picking::picking_core::Pickable;
+use bevy_replicon::{core::ClientId, prelude::*};
+use bevy_replicon_renet::renet::{self, RenetServer};
+use bevy_replicon_snap::NetworkOwner;
+use serde::{Deserialize, Serialize};
+
+use crate::{localization::Localization, player::Player};
+
+const STATS_UPDATE_INTERVAL: f32 = 1.0;
+
+pub struct PlayerListPlugin;
+
+impl Plugin for PlayerListPlugin {
+    fn build(&self, app: &mut App) {
+        app.replicate::<PlayerStats>()
+            .insert_resource(PlayerStatsTimer(Timer::from_seconds(
+                STATS_UPDATE_INTERVAL,
+                TimerMode::Repeating,
+            )))
+            .add_systems(Startup, setup_player_list)
+            .add_systems(Update, update_player_stats.run_if(server_running))
+            .add_systems(Update, update_player_list);
+    }
+}
+
+/// Connection statistics of a player, updated by the server.
+#[derive(Component, Debug, Default, Serialize, Deserialize)]
+pub struct PlayerStats {
+    pub rtt_ms: f32,
+}
+
+#[derive(Resource, Deref, DerefMut)]
+struct PlayerStatsTimer(Timer);
+
+#[derive(Component, Debug)]
+struct PlayerListText;
+
+fn setup_player_list(mut commands: Commands) {
+    commands.spawn((
+        Name::new("Player List"),
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 18.0,
+                color: Color::WHITE,
+                ..Default::default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Percent(20.0),
+            left: Val::Percent(40.0),
+            padding: UiRect::all(Val::Px(8.0)),
+            ..Default::default()
+        })
+        .with_background_color(Color::srgba(0.0, 0.0, 0.0, 0.7)),
+        ZIndex::Global(i32::MAX - 1),
+        Visibility::Hidden,
+        Pickable::IGNORE,
+        PlayerListText,
+    ));
+}
+
+fn update_player_stats(
+    mut commands: Commands,
+    mut timer: ResMut<PlayerStatsTimer>,
+    mut player_query: Query<(Entity, &NetworkOwner, Option<&mut PlayerStats>), With<Player>>,
+    server: Option<Res<RenetServer>>,
+    time: Res<Time>,
+) {
+    if !timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    for (entity, owner, stats) in player_query.iter_mut() {
+        let rtt_ms = server
+            .as_ref()
+            .and_then(|server| server.network_info(renet::ClientId::from_raw(owner.0)).ok())
+            .map(|info| (info.rtt * 1000.0) as f32)
+            .unwrap_or_default();
+        match stats {
+            Some(mut stats) => stats.rtt_ms = rtt_ms,
+            None => {
+                commands.entity(entity).insert(PlayerStats { rtt_ms });
+            }
+        }
+    }
+}
+
+fn update_player_list(
+    input: Res<ButtonInput<KeyCode>>,
+    mut text_query: Query<(&mut Text, &mut Visibility), With<PlayerListText>>,
+    player_query: Query<(&NetworkOwner, &Transform, Option<&PlayerStats>), With<Player>>,
+    client: Option<Res<RepliconClient>>,
+    localization: Res<Localization>,
+) {
+    let Ok((mut text, mut visibility)) = text_query.get_single_mut() else {
+        return;
+    };
+    if !input.pressed(KeyCode::Tab) {
+        if *visibility != Visibility::Hidden {
+            *visibility = Visibility::Hidden;
+        }
+        return;
+    }
+    *visibility = Visibility::Visible;
+
+    let local_id = client.and_then(|client| client.id());
+    let local_pos = player_query
+        .iter()
+        .find(|(owner, ..)| Some(ClientId::new(owner.0)) == local_id)
+        .map(|(_, transform, _)| transform.translation.xy());
+
+    let mut players: Vec<_> = player_query.iter().collect();
+    players.sort_by_key(|(owner, ..)| owner.0);
+
+    let mut content = localization.tr_args("player_list.header", &[("count", players.len().to_string())]);
+    for (owner, transform, stats) in players {
+        content.push_str(&format!("\n{}", owner.0));
+        if Some(ClientId::new(owner.0)) == local_id {
+            content.push_str(&format!(" {}", localization.tr("player_list.you")));
+        }
+        if let Some(stats) = stats {
+            content.push_str(&format!("  {:.0} ms", stats.rtt_ms));
+        }
+        if let Some(local_pos) = local_pos {
+            let distance = local_pos.distance(transform.translation.xy());
+            content.push_str(&format!("  {distance:.0} units"));
+        }
+    }
+    if text.sections[0].value != content {
+        text.sections[0].value = content;
+    }
+}
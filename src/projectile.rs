@@ -0,0 +1,241 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use bevy_replicon::{client::ClientSet, prelude::*};
+use bevy_replicon_snap::{interpolation::Interpolated, NetworkOwner};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    action::{ActionCooldownConfig, ActionCooldowns, GameAction},
+    chunk::{ChunkIndexMap, ChunkTiles},
+    game_assets::GameAssets,
+    health::{DamageEvent, Health},
+    item_container::ItemContainer,
+    item_registry::ItemRegistry,
+    loading_screen::GameState,
+    player::{Facing, Player, Respawning},
+    sign::editing_sign,
+    sleep::{local_player_sleeping, Sleeping},
+    water::WaterSettings,
+    world::is_walkable,
+    world_object::{TileOccupancy, WorldObject},
+};
+
+const SHOOT_KEY: KeyCode = KeyCode::KeyZ;
+/// Item thrown by players, one is used up per throw.
+const AMMO_ID: &str = "stone";
+const PROJECTILE_SIZE: f32 = 10.0;
+/// Projectiles start this far in front of the shooter so they don't hit it right away.
+const SPAWN_DISTANCE: f32 = 12.0;
+
+pub struct ProjectilePlugin;
+
+impl Plugin for ProjectilePlugin {
+    fn build(&self, app: &mut App) {
+        app.replicate::<Projectile>()
+            .register_type::<ProjectileSettings>()
+            .init_resource::<ProjectileSettings>()
+            .add_client_event::<ShootEvent>(ChannelKind::Ordered)
+            .add_systems(PreUpdate, init_projectiles.after(ClientSet::Receive))
+            .add_systems(
+                Update,
+                (spawn_projectiles, move_projectiles).chain().run_if(server_running),
+            )
+            .add_systems(
+                Update,
+                send_shoot_events
+                    .run_if(client_connected)
+                    .run_if(in_state(GameState::InGame))
+                    .run_if(not(editing_sign))
+                    .run_if(not(local_player_sleeping)),
+            );
+    }
+}
+
+#[derive(Resource, Debug, Reflect)]
+#[reflect(Resource)]
+pub struct ProjectileSettings {
+    /// Units per second.
+    pub speed: f32,
+    /// Seconds a projectile flies before it drops out of the world.
+    pub lifetime: f32,
+    pub damage: f32,
+    /// Radius in which entities with health are hit.
+    pub hit_radius: f32,
+    /// Initial speed of hit targets in units per second.
+    pub knockback: f32,
+}
+
+impl Default for ProjectileSettings {
+    fn default() -> Self {
+        Self {
+            speed: 300.0,
+            lifetime: 1.5,
+            damage: 4.0,
+            hit_radius: 6.0,
+            knockback: 80.0,
+        }
+    }
+}
+
+/// Asks the server to throw a projectile in the direction the sender faces.
+#[derive(Event, Debug, Clone, Serialize, Deserialize)]
+pub struct ShootEvent;
+
+/// A flying projectile. Clients only get the look of it, the flight is simulated on the server
+/// and its Transform interpolated.
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+pub struct Projectile {
+    /// Index into the item atlas.
+    pub texture_index: usize,
+}
+
+/// Server side flight of a projectile.
+#[derive(Component, Debug)]
+struct Flight {
+    velocity: Vec2,
+    shooter: Entity,
+    remaining: f32,
+}
+
+fn send_shoot_events(
+    input: Res<ButtonInput<KeyCode>>,
+    mut shoot_events: EventWriter<ShootEvent>,
+    mut player_query: Query<(&NetworkOwner, &mut ActionCooldowns), (With<Player>, Without<Respawning>)>,
+    client: Res<RepliconClient>,
+    cooldown_config: Res<ActionCooldownConfig>,
+    time: Res<Time>,
+) {
+    if !input.just_pressed(SHOOT_KEY) {
+        return;
+    }
+    let now = time.elapsed_seconds();
+    let Some(mut cooldowns) = client.id().and_then(|client_id| {
+        player_query
+            .iter_mut()
+            .find(|(owner, _)| owner.0 == client_id.get())
+            .map(|(_, cooldowns)| cooldowns)
+    }) else {
+        return;
+    };
+    // don't send throws the server would reject anyway
+    if !cooldowns.ready(GameAction::Shoot, now, &cooldown_config) {
+        return;
+    }
+    cooldowns.trigger(GameAction::Shoot, now);
+    shoot_events.send(ShootEvent);
+}
+
+/// Throws a projectile for every accepted shot, using up one piece of ammo.
+fn spawn_projectiles(
+    mut commands: Commands,
+    mut events: EventReader<FromClient<ShootEvent>>,
+    mut player_query: Query<
+        (Entity, &NetworkOwner, &Transform, &Facing, &mut ItemContainer, &mut ActionCooldowns),
+        (With<Player>, Without<Respawning>, Without<Sleeping>),
+    >,
+    registry: Res<ItemRegistry>,
+    cooldown_config: Res<ActionCooldownConfig>,
+    settings: Res<ProjectileSettings>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds();
+    for FromClient { client_id, .. } in events.read() {
+        let Some((shooter, _, transform, facing, mut inventory, mut cooldowns)) = player_query
+            .iter_mut()
+            .find(|(_, owner, ..)| owner.0 == client_id.get())
+        else {
+            continue;
+        };
+        if !cooldowns.ready(GameAction::Shoot, now, &cooldown_config) {
+            continue;
+        }
+        let Some(texture_index) = registry.get(AMMO_ID).map(|def| def.texture_index) else {
+            warn!("Item {AMMO_ID} is not defined");
+            continue;
+        };
+        if inventory.remove(AMMO_ID, 1).is_none() {
+            continue;
+        }
+        cooldowns.trigger(GameAction::Shoot, now);
+
+        let position = transform.translation.xy() + facing.0 * SPAWN_DISTANCE;
+        commands.spawn((
+            Name::new("Projectile"),
+            Projectile { texture_index },
+            Flight {
+                velocity: facing.0 * settings.speed,
+                shooter,
+                remaining: settings.lifetime,
+            },
+            Transform::from_translation(position.extend(1.0)),
+            Interpolated,
+            Replicated,
+        ));
+    }
+}
+
+/// Moves projectiles along their velocity. They are gone once they hit an entity with health, a
+/// tile nothing could walk on or a world object, or once their lifetime ran out. World objects
+/// only stop them, resource nodes still need the right tool.
+fn move_projectiles(
+    mut commands: Commands,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut projectiles: Query<(Entity, &mut Transform, &mut Flight)>,
+    targets: Query<(), (With<Health>, Without<WorldObject>)>,
+    occupancy: Res<TileOccupancy>,
+    water: Res<WaterSettings>,
+    chunk_tiles: Query<&ChunkTiles>,
+    chunk_map: Res<ChunkIndexMap>,
+    rapier_context: Res<RapierContext>,
+    settings: Res<ProjectileSettings>,
+    time: Res<Time>,
+) {
+    let delta = time.delta_seconds();
+    let hit_shape = Collider::ball(settings.hit_radius);
+    for (entity, mut transform, mut flight) in projectiles.iter_mut() {
+        flight.remaining -= delta;
+        let previous = transform.translation.xy();
+        let next = previous + flight.velocity * delta;
+        transform.translation = next.extend(transform.translation.z);
+
+        let mut target = None;
+        rapier_context.intersections_with_shape(next, 0.0, &hit_shape, QueryFilter::default(), |hit| {
+            if hit == flight.shooter || !targets.contains(hit) {
+                return true;
+            }
+            target = Some(hit);
+            false
+        });
+        if let Some(target) = target {
+            damage_events.send(
+                DamageEvent::new(target, settings.damage).with_knockback(previous, settings.knockback),
+            );
+        }
+        let blocked = !is_walkable(next, &chunk_map, &chunk_tiles, &occupancy, &water);
+        if target.is_some() || blocked || flight.remaining <= 0.0 {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn init_projectiles(
+    mut commands: Commands,
+    query: Query<(Entity, &Projectile), Without<Sprite>>,
+    game_assets: Res<GameAssets>,
+) {
+    for (entity, projectile) in query.iter() {
+        commands.entity(entity).insert((
+            Sprite {
+                custom_size: Some(Vec2::splat(PROJECTILE_SIZE)),
+                ..Default::default()
+            },
+            TextureAtlas {
+                layout: game_assets.item_layout.clone(),
+                index: projectile.texture_index,
+            },
+            game_assets.item_texture.clone(),
+            VisibilityBundle::default(),
+            GlobalTransform::default(),
+        ));
+    }
+}
@@ -0,0 +1,150 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use bevy_replicon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chunk::TILE_LENGTH,
+    game_time::TimeSkipped,
+    world::split_global_tile,
+    world_object::{spawn_world_object, ObjectRegistry, TileOccupancy, WorldObject},
+};
+
+/// Size of the first stage relative to the full grown object.
+const MIN_GROWTH_SCALE: f32 = 0.4;
+
+pub struct SaplingPlugin;
+
+impl Plugin for SaplingPlugin {
+    fn build(&self, app: &mut App) {
+        app.replicate::<Growth>()
+            .add_systems(
+                Update,
+                (init_growth, grow_objects).chain().run_if(server_running),
+            )
+            .add_systems(Update, update_growth_visuals);
+    }
+}
+
+/// Registry entry of objects that grow into another object, like a sapling into a tree.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GrowthDefinition {
+    /// Object kind it turns into after the last stage.
+    pub grows_into: String,
+    pub stages: u32,
+    /// Seconds spent in each stage.
+    pub stage_time: f32,
+    /// First stage that blocks movement, the collider only exists from then on.
+    pub blocking_stage: u32,
+}
+
+impl GrowthDefinition {
+    /// Sprite scale of a stage, growing linearly up to the full size of the last stage.
+    fn scale(&self, stage: u32) -> f32 {
+        let progress = stage as f32 / self.stages.saturating_sub(1).max(1) as f32;
+        MIN_GROWTH_SCALE + (1.0 - MIN_GROWTH_SCALE) * progress.min(1.0)
+    }
+}
+
+/// Growth state of a growing world object, saved with its chunk.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Growth {
+    pub stage: u32,
+    /// Seconds left in the current stage. Counted down on the server only, clients just need
+    /// the stage.
+    pub remaining: f32,
+}
+
+/// Starts the growth of freshly planted objects. Loaded ones come with their state.
+fn init_growth(
+    mut commands: Commands,
+    objects: Query<(Entity, &WorldObject), (Added<WorldObject>, Without<Growth>)>,
+    registry: Res<ObjectRegistry>,
+) {
+    for (entity, object) in objects.iter() {
+        if let Some(growth) = registry.get(&object.kind).and_then(|def| def.growth.as_ref()) {
+            commands.entity(entity).insert(Growth {
+                stage: 0,
+                remaining: growth.stage_time,
+            });
+        }
+    }
+}
+
+/// Advances the growth stages and replaces objects past their last stage with what they grow
+/// into. Only loaded objects grow: an unloaded chunk keeps the saved countdown untouched, so growth
+/// pauses while nobody is around instead of catching up on load. A night skipped by sleeping
+/// counts as passed time and can advance several stages at once.
+fn grow_objects(
+    mut commands: Commands,
+    mut skipped: EventReader<TimeSkipped>,
+    mut objects: Query<(Entity, &WorldObject, &mut Growth)>,
+    occupancy: Res<TileOccupancy>,
+    registry: Res<ObjectRegistry>,
+    time: Res<Time>,
+) {
+    let elapsed = time.delta_seconds() + skipped.read().map(|skip| skip.seconds).sum::<f32>();
+    for (entity, object, mut growth) in objects.iter_mut() {
+        let Some(definition) = registry.get(&object.kind).and_then(|def| def.growth.as_ref()) else {
+            continue;
+        };
+        let remaining = &mut growth.bypass_change_detection().remaining;
+        *remaining -= elapsed;
+        if *remaining > 0.0 {
+            continue;
+        }
+        if growth.stage + 1 < definition.stages {
+            // time left over from a long skip goes on into the following stages
+            let mut stage = growth.stage;
+            let mut remaining = growth.remaining;
+            while remaining <= 0.0 && stage + 1 < definition.stages {
+                stage += 1;
+                remaining += definition.stage_time;
+            }
+            growth.stage = stage;
+            growth.remaining = remaining;
+            if remaining > 0.0 {
+                continue;
+            }
+        }
+        let footprint = registry.footprint(&definition.grows_into);
+        let blocked = WorldObject {
+            footprint,
+            ..object.clone()
+        }
+        .tiles()
+        .any(|tile| {
+            split_global_tile(tile).0 != object.chunk_index()
+                || occupancy.get(tile).is_some_and(|other| other != entity)
+        });
+        if blocked {
+            // try again after another stage, something may have been removed by then
+            growth.bypass_change_detection().remaining = definition.stage_time;
+            continue;
+        }
+        commands.entity(entity).despawn_recursive();
+        spawn_world_object(&mut commands, definition.grows_into.clone(), object.anchor, footprint);
+    }
+}
+
+/// Scales growing objects with their stage and adds the collider once they block movement.
+fn update_growth_visuals(
+    mut commands: Commands,
+    mut objects: Query<(Entity, &WorldObject, &Growth, &mut Sprite), Or<(Changed<Growth>, Added<Sprite>)>>,
+    registry: Res<ObjectRegistry>,
+) {
+    for (entity, object, growth, mut sprite) in objects.iter_mut() {
+        let Some(definition) = registry.get(&object.kind).and_then(|def| def.growth.as_ref()) else {
+            continue;
+        };
+        let size = object.footprint.as_vec2() * TILE_LENGTH * definition.scale(growth.stage);
+        sprite.custom_size = Some(size);
+        if growth.stage >= definition.blocking_stage {
+            commands
+                .entity(entity)
+                .insert(Collider::cuboid(size.x * 0.5, size.y * 0.5));
+        } else {
+            commands.entity(entity).remove::<Collider>();
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use std::{
+    fs::create_dir_all,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::{prelude::*, render::view::screenshot::ScreenshotManager, window::PrimaryWindow};
+use bevy_replicon::prelude::client_connected;
+
+use crate::ActionEvent;
+
+const SCREENSHOT_FOLDER: &str = "screenshots";
+
+pub struct ScreenshotPlugin;
+
+impl Plugin for ScreenshotPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, take_screenshot.run_if(client_connected));
+    }
+}
+
+/// Saves the current frame to `screenshots/screenshot-<millis>.png` on F12.
+fn take_screenshot(
+    mut event_reader: EventReader<ActionEvent>,
+    mut screenshots: ResMut<ScreenshotManager>,
+    window: Query<Entity, With<PrimaryWindow>>,
+) {
+    if !event_reader.read().any(|event| event.action == KeyCode::F12) {
+        return;
+    }
+    let Ok(window) = window.get_single() else {
+        return;
+    };
+    if let Err(err) = create_dir_all(SCREENSHOT_FOLDER) {
+        error!("Cannot create the screenshot folder: {err}");
+        return;
+    }
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_millis());
+    let path = format!("{SCREENSHOT_FOLDER}/screenshot-{millis}.png");
+    match screenshots.save_screenshot_to_disk(window, &path) {
+        Ok(()) => info!("Saved screenshot to {path}"),
+        Err(err) => error!("Cannot take a screenshot: {err}"),
+    }
+}
@@ -0,0 +1,144 @@
+use std::{
+    net::{SocketAddr, UdpSocket},
+    time::Duration,
+};
+
+use anyhow::{bail, Context};
+use bevy::prelude::*;
+use bevy_replicon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{player::Player, world_meta::WorldMeta};
+
+/// The status socket listens on the game port plus this offset.
+pub const STATUS_PORT_OFFSET: u16 = 1;
+pub const STATUS_TIMEOUT: Duration = Duration::from_millis(1500);
+const STATUS_MAGIC: [u8; 4] = *b"CGST";
+/// Bump whenever [`ServerStatus`] changes, older clients then report a mismatch instead of misparsing.
+const STATUS_VERSION: u8 = 1;
+const MAX_PACKET_SIZE: usize = 1024;
+
+pub struct ServerStatusPlugin;
+
+impl Plugin for ServerStatusPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            answer_status_requests
+                .run_if(server_running)
+                .run_if(resource_exists::<StatusSocket>)
+                .run_if(resource_exists::<WorldMeta>),
+        );
+    }
+}
+
+/// Publicly visible information about a running server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerStatus {
+    pub server_name: String,
+    pub players: usize,
+    pub max_players: usize,
+    pub world_name: String,
+    pub seed: u64,
+}
+
+/// Static part of the status, configured when the server starts. World details come from the
+/// [`WorldMeta`].
+#[derive(Resource, Debug, Clone)]
+pub struct ServerInfo {
+    pub server_name: String,
+    pub max_players: usize,
+}
+
+/// Non-blocking socket answering status requests next to the game socket.
+#[derive(Resource, Debug)]
+pub struct StatusSocket(UdpSocket);
+
+impl StatusSocket {
+    pub fn bind(addr: SocketAddr) -> std::io::Result<Self> {
+        let socket = UdpSocket::bind(addr)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self(socket))
+    }
+}
+
+fn header() -> [u8; 5] {
+    let mut header = [0; 5];
+    header[..4].copy_from_slice(&STATUS_MAGIC);
+    header[4] = STATUS_VERSION;
+    header
+}
+
+/// Splits off and checks the header of a status packet.
+fn parse_header(packet: &[u8]) -> anyhow::Result<&[u8]> {
+    if packet.len() < 5 || packet[..4] != STATUS_MAGIC {
+        bail!("Not a status packet");
+    }
+    if packet[4] != STATUS_VERSION {
+        bail!(
+            "Status protocol version {} is not supported, expected {STATUS_VERSION}",
+            packet[4]
+        );
+    }
+    Ok(&packet[5..])
+}
+
+fn answer_status_requests(
+    socket: Res<StatusSocket>,
+    info: Res<ServerInfo>,
+    world_meta: Res<WorldMeta>,
+    players: Query<(), With<Player>>,
+) {
+    let mut buffer = [0; MAX_PACKET_SIZE];
+    loop {
+        let (len, from) = match socket.0.recv_from(&mut buffer) {
+            Ok(received) => received,
+            Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => return,
+            Err(err) => {
+                warn!("Failed reading status request: {err}");
+                return;
+            }
+        };
+        if let Err(err) = parse_header(&buffer[..len]) {
+            debug!("Ignored status request from {from}: {err}");
+            continue;
+        }
+        let status = ServerStatus {
+            server_name: info.server_name.clone(),
+            players: players.iter().count(),
+            max_players: info.max_players,
+            world_name: world_meta.name.clone(),
+            seed: world_meta.seed,
+        };
+        let Ok(body) = ron::to_string(&status) else {
+            continue;
+        };
+        let mut response = header().to_vec();
+        response.extend_from_slice(body.as_bytes());
+        if let Err(err) = socket.0.send_to(&response, from) {
+            warn!("Failed answering status request from {from}: {err}");
+        }
+    }
+}
+
+/// Asks the server at the given game address for its status. Fails after [`STATUS_TIMEOUT`]
+/// if nobody answers.
+pub fn query_status(server_addr: SocketAddr) -> anyhow::Result<ServerStatus> {
+    let status_addr = SocketAddr::new(server_addr.ip(), server_addr.port() + STATUS_PORT_OFFSET);
+    let bind_addr: SocketAddr = if status_addr.is_ipv4() {
+        "0.0.0.0:0".parse()?
+    } else {
+        "[::]:0".parse()?
+    };
+    let socket = UdpSocket::bind(bind_addr)?;
+    socket.set_read_timeout(Some(STATUS_TIMEOUT))?;
+    socket.connect(status_addr)?;
+    socket.send(&header())?;
+
+    let mut buffer = [0; MAX_PACKET_SIZE];
+    let len = socket
+        .recv(&mut buffer)
+        .with_context(|| format!("No status answer from {status_addr}"))?;
+    let body = parse_header(&buffer[..len])?;
+    ron::de::from_bytes(body).context("Failed deserialising the server status")
+}
@@ -0,0 +1,333 @@
+use bevy::{
+    ecs::entity::MapEntities,
+    input::{
+        keyboard::{Key, KeyboardInput},
+        ButtonState,
+    },
+    prelude::*,
+};
+use bevy_replicon::{core::ClientId, prelude::*};
+use bevy_replicon_snap::NetworkOwner;
+use serde::{Deserialize, Serialize};
+use sickle_ui::prelude::*;
+
+use crate::{
+    camera::CursorWorldPos,
+    chunk::TILE_LENGTH,
+    interact::{InteractEvent, InteractKind, Interactable},
+    item_container::in_interaction_range,
+    localization::LocalizedText,
+    player::Player,
+};
+
+/// Longest text the server accepts for a sign, in characters.
+pub const MAX_SIGN_LENGTH: usize = 64;
+/// Characters shown above a sign while no player is close to it.
+const PREVIEW_LENGTH: usize = 12;
+const LABEL_OFFSET: f32 = TILE_LENGTH * 0.75;
+const EDITOR_BACKGROUND_COLOR: Color = Color::srgba(0.1, 0.1, 0.1, 0.9);
+
+pub struct SignPlugin;
+
+impl Plugin for SignPlugin {
+    fn build(&self, app: &mut App) {
+        app.replicate::<SignText>()
+            .init_resource::<SignEditor>()
+            .add_mapped_client_event::<SetSignTextEvent>(ChannelKind::Ordered)
+            .add_mapped_server_event::<EditSignEvent>(ChannelKind::Ordered)
+            .add_systems(Update, (edit_signs, set_sign_texts).run_if(has_authority))
+            .add_systems(Update, (init_sign_labels, update_sign_labels).chain())
+            .add_systems(
+                Update,
+                (open_sign_editor, type_sign_text, update_sign_editor)
+                    .chain()
+                    .run_if(client_connected),
+            );
+    }
+}
+
+/// Text written on a sign. Only the player who placed the sign can change it, as long as the sign
+/// is owned.
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+pub struct SignText {
+    pub text: String,
+    /// Client id of the player who placed the sign. Client ids change with every connection, so
+    /// the owner isn't saved and a sign loaded from disk can be edited by anyone close to it.
+    #[serde(skip)]
+    pub owner: Option<u64>,
+}
+
+impl SignText {
+    pub fn new(owner: ClientId) -> Self {
+        Self {
+            text: String::new(),
+            owner: Some(owner.get()),
+        }
+    }
+}
+
+/// Components that turn a world object into an editable sign.
+pub fn sign_components(sign: SignText) -> impl Bundle {
+    (sign, Interactable::new(InteractKind::Edit, "object.sign"))
+}
+
+/// Asks the server to replace the text of a sign.
+#[derive(Event, Debug, Clone, Serialize, Deserialize)]
+pub struct SetSignTextEvent {
+    pub sign: Entity,
+    pub text: String,
+}
+
+impl MapEntities for SetSignTextEvent {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.sign = entity_mapper.map_entity(self.sign);
+    }
+}
+
+/// Tells a client to open the text editor of a sign, after placing or interacting with it.
+#[derive(Event, Debug, Clone, Serialize, Deserialize)]
+pub struct EditSignEvent {
+    pub sign: Entity,
+}
+
+impl MapEntities for EditSignEvent {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.sign = entity_mapper.map_entity(self.sign);
+    }
+}
+
+/// Sign whose text the local player is currently typing.
+#[derive(Resource, Debug, Default)]
+pub struct SignEditor {
+    sign: Option<Entity>,
+    text: String,
+    root: Option<Entity>,
+}
+
+/// Keyboard input goes into the sign editor instead of moving the player.
+pub fn editing_sign(editor: Res<SignEditor>) -> bool {
+    editor.sign.is_some()
+}
+
+#[derive(Component, Debug)]
+struct SignLabel;
+
+#[derive(Component, Debug)]
+struct SignEditorText;
+
+/// Server side checks shared by opening the editor and submitting text.
+fn may_edit(client_id: ClientId, player_pos: Vec2, sign_pos: Vec2, sign: &SignText) -> bool {
+    sign.owner.map_or(true, |owner| owner == client_id.get())
+        && in_interaction_range(player_pos, sign_pos)
+}
+
+fn edit_signs(
+    mut events: EventReader<InteractEvent>,
+    mut edit_events: EventWriter<ToClients<EditSignEvent>>,
+    transforms: Query<&Transform>,
+    signs: Query<&SignText>,
+) {
+    for event in events.read() {
+        if event.kind != InteractKind::Edit {
+            continue;
+        }
+        let (Ok(player), Ok(sign_transform), Ok(sign)) = (
+            transforms.get(event.player),
+            transforms.get(event.target),
+            signs.get(event.target),
+        ) else {
+            continue;
+        };
+        if !may_edit(event.client_id, player.translation.xy(), sign_transform.translation.xy(), sign) {
+            continue;
+        }
+        edit_events.send(ToClients {
+            mode: SendMode::Direct(event.client_id),
+            event: EditSignEvent { sign: event.target },
+        });
+    }
+}
+
+fn set_sign_texts(
+    mut events: EventReader<FromClient<SetSignTextEvent>>,
+    players: Query<(&NetworkOwner, &Transform), With<Player>>,
+    mut signs: Query<(&Transform, &mut SignText)>,
+) {
+    for FromClient { client_id, event } in events.read() {
+        let Some((_, player)) = players.iter().find(|(owner, _)| owner.0 == client_id.get()) else {
+            continue;
+        };
+        let Ok((sign_transform, mut sign)) = signs.get_mut(event.sign) else {
+            continue;
+        };
+        if !may_edit(*client_id, player.translation.xy(), sign_transform.translation.xy(), &sign) {
+            debug!("{client_id:?} may not edit sign {:?}", event.sign);
+            continue;
+        }
+        if event.text.chars().count() > MAX_SIGN_LENGTH || event.text.chars().any(char::is_control) {
+            debug!("{client_id:?} sent an invalid sign text");
+            continue;
+        }
+        sign.text = event.text.trim().to_string();
+    }
+}
+
+fn init_sign_labels(mut commands: Commands, signs: Query<Entity, Added<SignText>>) {
+    for entity in signs.iter() {
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn((
+                Text2dBundle {
+                    text: Text::from_section(
+                        "",
+                        TextStyle {
+                            font_size: 10.0,
+                            color: Color::WHITE,
+                            ..Default::default()
+                        },
+                    ),
+                    transform: Transform::from_xyz(0.0, LABEL_OFFSET, 0.1),
+                    ..Default::default()
+                },
+                SignLabel,
+            ));
+        });
+    }
+}
+
+/// Shows a short preview of every sign, and the full text when the local player is close to it
+/// or the cursor hovers it.
+fn update_sign_labels(
+    signs: Query<(&SignText, &Transform, &Children)>,
+    mut labels: Query<&mut Text, With<SignLabel>>,
+    players: Query<(&NetworkOwner, &Transform), With<Player>>,
+    cursor: Res<CursorWorldPos>,
+    client: Option<Res<RepliconClient>>,
+) {
+    let local_id = client.and_then(|client| client.id());
+    let local_pos = players
+        .iter()
+        .find(|(owner, _)| Some(ClientId::new(owner.0)) == local_id)
+        .map(|(_, transform)| transform.translation.xy());
+
+    for (sign, transform, children) in signs.iter() {
+        let sign_pos = transform.translation.xy();
+        let hovered = cursor
+            .0
+            .is_some_and(|cursor| (cursor - sign_pos).abs().max_element() <= TILE_LENGTH * 0.5);
+        let close = local_pos.is_some_and(|pos| in_interaction_range(pos, sign_pos));
+        let content = if hovered || close || sign.text.chars().count() <= PREVIEW_LENGTH {
+            sign.text.clone()
+        } else {
+            format!("{}...", sign.text.chars().take(PREVIEW_LENGTH).collect::<String>())
+        };
+        for &child in children.iter() {
+            if let Ok(mut text) = labels.get_mut(child) {
+                if text.sections[0].value != content {
+                    text.sections[0].value = content.clone();
+                }
+            }
+        }
+    }
+}
+
+fn open_sign_editor(
+    mut commands: Commands,
+    mut events: EventReader<EditSignEvent>,
+    mut editor: ResMut<SignEditor>,
+    signs: Query<&SignText>,
+) {
+    let Some(EditSignEvent { sign }) = events.read().last().cloned() else {
+        return;
+    };
+    if let Some(root) = editor.root.take() {
+        commands.entity(root).despawn_recursive();
+    }
+    let root = commands
+        .ui_builder(UiRoot)
+        .container(
+            (
+                Name::new("Sign Editor"),
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        top: Val::Percent(40.0),
+                        left: Val::Percent(35.0),
+                        width: Val::Percent(30.0),
+                        flex_direction: FlexDirection::Column,
+                        padding: UiRect::all(Val::Px(8.0)),
+                        ..Default::default()
+                    },
+                    background_color: EDITOR_BACKGROUND_COLOR.into(),
+                    z_index: ZIndex::Global(i32::MAX - 1),
+                    ..Default::default()
+                },
+            ),
+            |window| {
+                window
+                    .label(LabelConfig::default())
+                    .insert(LocalizedText::new("sign.editor_hint"));
+                window.spawn((TextBundle::from_section("", TextStyle::default()), SignEditorText));
+            },
+        )
+        .id();
+    *editor = SignEditor {
+        sign: Some(sign),
+        text: signs.get(sign).map(|sign| sign.text.clone()).unwrap_or_default(),
+        root: Some(root),
+    };
+}
+
+fn type_sign_text(
+    mut commands: Commands,
+    mut keyboard_events: EventReader<KeyboardInput>,
+    mut set_events: EventWriter<SetSignTextEvent>,
+    mut editor: ResMut<SignEditor>,
+) {
+    let Some(sign) = editor.sign else {
+        keyboard_events.clear();
+        return;
+    };
+    let mut close = false;
+    for event in keyboard_events.read() {
+        if event.state != ButtonState::Pressed {
+            continue;
+        }
+        match &event.logical_key {
+            Key::Character(characters) => {
+                for character in characters.chars().filter(|c| !c.is_control()) {
+                    if editor.text.chars().count() < MAX_SIGN_LENGTH {
+                        editor.text.push(character);
+                    }
+                }
+            }
+            Key::Space if editor.text.chars().count() < MAX_SIGN_LENGTH => editor.text.push(' '),
+            Key::Backspace => {
+                editor.text.pop();
+            }
+            Key::Enter => {
+                set_events.send(SetSignTextEvent {
+                    sign,
+                    text: editor.text.clone(),
+                });
+                close = true;
+            }
+            Key::Escape => close = true,
+            _ => {}
+        }
+    }
+    if close {
+        if let Some(root) = editor.root.take() {
+            commands.entity(root).despawn_recursive();
+        }
+        *editor = SignEditor::default();
+    }
+}
+
+fn update_sign_editor(editor: Res<SignEditor>, mut texts: Query<&mut Text, With<SignEditorText>>) {
+    if !editor.is_changed() {
+        return;
+    }
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = format!("{}_", editor.text);
+    }
+}
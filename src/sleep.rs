@@ -0,0 +1,239 @@
+use bevy::{ecs::event::Events, prelude::*};
+use bevy_mod_picking::picking_core::Pickable;
+use bevy_replicon::{core::ClientId, prelude::*};
+use bevy_replicon_snap::NetworkOwner;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game_time::{GameTime, TimeSkipped},
+    health::Health,
+    interact::{InteractEvent, InteractKind, Interactable},
+    item_container::in_interaction_range,
+    loading_screen::GameState,
+    localization::Localization,
+    move_validation::validate_move_events,
+    player::Player,
+    world_object::{ObjectRegistry, WorldObject},
+    MoveEvent,
+};
+
+pub struct SleepPlugin;
+
+impl Plugin for SleepPlugin {
+    fn build(&self, app: &mut App) {
+        app.replicate::<Sleeping>()
+            .register_type::<SleepSettings>()
+            .init_resource::<SleepSettings>()
+            .add_client_event::<WakeUpEvent>(ChannelKind::Ordered)
+            .add_systems(Startup, setup_sleep_hud)
+            .add_systems(
+                Update,
+                (init_beds, go_to_sleep, wake_up, skip_night)
+                    .chain()
+                    .run_if(server_running),
+            )
+            .add_systems(
+                PreUpdate,
+                drop_sleeping_moves
+                    .run_if(server_running)
+                    .after(validate_move_events),
+            )
+            .add_systems(
+                Update,
+                send_wake_up
+                    .run_if(client_connected)
+                    .run_if(in_state(GameState::InGame)),
+            )
+            .add_systems(Update, update_sleep_hud);
+    }
+}
+
+/// Marks a player lying in a bed. The server ignores its movement and actions until it wakes up.
+#[derive(Component, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Sleeping;
+
+#[derive(Resource, Debug, Reflect)]
+#[reflect(Resource)]
+pub struct SleepSettings {
+    /// Whether sleeping through the night restores full health.
+    pub heal_on_wake: bool,
+}
+
+impl Default for SleepSettings {
+    fn default() -> Self {
+        Self { heal_on_wake: true }
+    }
+}
+
+/// Leaves the bed before the night is over.
+#[derive(Event, Debug, Clone, Serialize, Deserialize)]
+pub struct WakeUpEvent;
+
+#[derive(Component, Debug)]
+struct SleepHudText;
+
+/// Whether the local player is asleep, its movement input isn't sent then.
+pub fn local_player_sleeping(
+    players: Query<&NetworkOwner, (With<Player>, With<Sleeping>)>,
+    client: Option<Res<RepliconClient>>,
+) -> bool {
+    let Some(client_id) = client.and_then(|client| client.id()) else {
+        return false;
+    };
+    players.iter().any(|owner| ClientId::new(owner.0) == client_id)
+}
+
+/// Drops move events of sleeping players before they reach the prediction. Their clients don't
+/// send any, this keeps a modified one from walking around while its sleep counts for the skip.
+fn drop_sleeping_moves(
+    mut events: ResMut<Events<FromClient<MoveEvent>>>,
+    sleeping: Query<&NetworkOwner, (With<Player>, With<Sleeping>)>,
+) {
+    if sleeping.is_empty() {
+        return;
+    }
+    let accepted: Vec<_> = events
+        .drain()
+        .filter(|FromClient { client_id, .. }| {
+            !sleeping.iter().any(|owner| owner.0 == client_id.get())
+        })
+        .collect();
+    events.send_batch(accepted);
+}
+
+fn init_beds(
+    mut commands: Commands,
+    objects: Query<(Entity, &WorldObject), Added<WorldObject>>,
+    registry: Res<ObjectRegistry>,
+) {
+    for (entity, object) in objects.iter() {
+        if registry.get(&object.kind).is_some_and(|def| def.bed) {
+            commands.entity(entity).insert(Interactable::new(InteractKind::Sleep, "object.bed"));
+        }
+    }
+}
+
+fn go_to_sleep(
+    mut commands: Commands,
+    mut events: EventReader<InteractEvent>,
+    transforms: Query<&Transform>,
+    game_time: Res<GameTime>,
+) {
+    for event in events.read() {
+        if event.kind != InteractKind::Sleep {
+            continue;
+        }
+        if !game_time.is_night() {
+            debug!("{:?} tried to sleep during the day", event.client_id);
+            continue;
+        }
+        let (Ok(player), Ok(bed)) = (transforms.get(event.player), transforms.get(event.target)) else {
+            continue;
+        };
+        if in_interaction_range(player.translation.xy(), bed.translation.xy()) {
+            commands.entity(event.player).insert(Sleeping);
+        }
+    }
+}
+
+fn wake_up(
+    mut commands: Commands,
+    mut events: EventReader<FromClient<WakeUpEvent>>,
+    players: Query<(Entity, &NetworkOwner), (With<Player>, With<Sleeping>)>,
+    game_time: Res<GameTime>,
+) {
+    for FromClient { client_id, .. } in events.read() {
+        if let Some((entity, _)) = players.iter().find(|(_, owner)| owner.0 == client_id.get()) {
+            commands.entity(entity).remove::<Sleeping>();
+        }
+    }
+    // nobody keeps sleeping into the day, e.g. after the night ended without a skip
+    if !game_time.is_night() {
+        for (entity, _) in players.iter() {
+            commands.entity(entity).remove::<Sleeping>();
+        }
+    }
+}
+
+/// Skips to dawn once every connected player sleeps. Counting the current players every frame
+/// also covers players that disconnect while the others are asleep.
+fn skip_night(
+    mut commands: Commands,
+    mut skipped: EventWriter<TimeSkipped>,
+    mut game_time: ResMut<GameTime>,
+    mut players: Query<(Entity, Has<Sleeping>, &mut Health), With<Player>>,
+    settings: Res<SleepSettings>,
+) {
+    if players.is_empty() || !game_time.is_night() || players.iter().any(|(_, sleeping, _)| !sleeping) {
+        return;
+    }
+    let seconds = game_time.skip_to_dawn();
+    info!("Everyone is asleep, skipped {seconds:.0} seconds to dawn");
+    skipped.send(TimeSkipped { seconds });
+    for (entity, _, mut health) in players.iter_mut() {
+        commands.entity(entity).remove::<Sleeping>();
+        if settings.heal_on_wake {
+            health.current = health.max;
+        }
+    }
+}
+
+/// Any movement key or Escape gets the local player out of bed.
+fn send_wake_up(
+    input: Res<ButtonInput<KeyCode>>,
+    mut wake_events: EventWriter<WakeUpEvent>,
+    players: Query<&NetworkOwner, (With<Player>, With<Sleeping>)>,
+    client: Option<Res<RepliconClient>>,
+) {
+    let wake_keys = [KeyCode::KeyW, KeyCode::KeyA, KeyCode::KeyS, KeyCode::KeyD, KeyCode::Escape];
+    if input.any_just_pressed(wake_keys) && local_player_sleeping(players, client) {
+        wake_events.send(WakeUpEvent);
+    }
+}
+
+fn setup_sleep_hud(mut commands: Commands) {
+    commands.spawn((
+        Name::new("Sleep HUD"),
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 18.0,
+                color: Color::WHITE,
+                ..Default::default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.0),
+            left: Val::Percent(45.0),
+            ..Default::default()
+        }),
+        Visibility::Hidden,
+        Pickable::IGNORE,
+        SleepHudText,
+    ));
+}
+
+/// Shows how many players sleep while at least one does, from the replicated markers.
+fn update_sleep_hud(
+    mut hud: Query<(&mut Text, &mut Visibility), With<SleepHudText>>,
+    players: Query<Has<Sleeping>, With<Player>>,
+    localization: Res<Localization>,
+) {
+    let Ok((mut text, mut visibility)) = hud.get_single_mut() else {
+        return;
+    };
+    let sleeping = players.iter().filter(|sleeping| *sleeping).count();
+    if sleeping == 0 {
+        visibility.set_if_neq(Visibility::Hidden);
+        return;
+    }
+    visibility.set_if_neq(Visibility::Visible);
+    let content = localization.tr_args(
+        "sleep.hud",
+        &[("sleeping", sleeping.to_string()), ("total", players.iter().count().to_string())],
+    );
+    if text.sections[0].value != content {
+        text.sections[0].value = content;
+    }
+}
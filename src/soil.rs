@@ -0,0 +1,156 @@
+use bevy::prelude::*;
+use bevy_replicon::{core::ClientId, prelude::*};
+use bevy_replicon_snap::NetworkOwner;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    action::GameAction,
+    chunk::{ChunkIndexMap, ChunkTiles},
+    inventory_ui::SelectedSlot,
+    item_container::ItemContainer,
+    loading_screen::GameState,
+    player::Player,
+    world::{global_tile_at, split_global_tile, Ground},
+    ActionEvent,
+};
+
+/// Soil quality of untreated ground.
+pub const DEFAULT_SOIL_QUALITY: f32 = 1.0;
+/// Dirt at or above this quality uses the fertile texture variants of its ground definition.
+pub const FERTILE_SOIL_QUALITY: f32 = 1.5;
+const FERTILIZER_ID: &str = "fertilizer";
+
+pub struct SoilPlugin;
+
+impl Plugin for SoilPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<SoilSettings>()
+            .init_resource::<SoilSettings>()
+            .add_client_event::<FertilizeEvent>(ChannelKind::Ordered)
+            .add_systems(
+                Update,
+                send_fertilize_events
+                    .run_if(client_connected)
+                    .run_if(in_state(GameState::InGame)),
+            )
+            .add_systems(Update, fertilize.run_if(has_authority));
+    }
+}
+
+#[derive(Resource, Debug, Reflect)]
+#[reflect(Resource)]
+pub struct SoilSettings {
+    /// Quality added by one fertilizer.
+    pub fertilizer_bonus: f32,
+    pub max_quality: f32,
+}
+
+impl Default for SoilSettings {
+    fn default() -> Self {
+        Self {
+            fertilizer_bonus: 0.25,
+            max_quality: 2.0,
+        }
+    }
+}
+
+/// Local copy of the soil quality of a tile entity, kept in sync with [`ChunkTiles`] like its
+/// [`Ground`].
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct SoilQuality(pub f32);
+
+impl SoilQuality {
+    pub fn is_fertile(&self) -> bool {
+        self.0 >= FERTILE_SOIL_QUALITY
+    }
+}
+
+/// Uses the fertilizer in `slot` of the sender's inventory on `tile`.
+#[derive(Event, Debug, Clone, Serialize, Deserialize)]
+pub struct FertilizeEvent {
+    pub tile: IVec2,
+    pub slot: usize,
+}
+
+/// UseItem with a selected fertilizer fertilizes the tile under the player.
+fn send_fertilize_events(
+    mut action_events: EventReader<ActionEvent>,
+    mut fertilize_events: EventWriter<FertilizeEvent>,
+    players: Query<(Entity, &NetworkOwner, &Transform), With<Player>>,
+    containers: Query<&ItemContainer>,
+    selected: Res<SelectedSlot>,
+    client: Res<RepliconClient>,
+) {
+    let used = action_events
+        .read()
+        .any(|event| GameAction::from_key(event.action).contains(&GameAction::UseItem));
+    if !used {
+        return;
+    }
+    let Some((player, _, transform)) = client.id().and_then(|client_id| {
+        players
+            .iter()
+            .find(|(_, owner, _)| ClientId::new(owner.0) == client_id)
+    }) else {
+        return;
+    };
+    let Some((container, slot)) = selected.0.filter(|(container, _)| *container == player) else {
+        return;
+    };
+    let holds_fertilizer = containers
+        .get(container)
+        .ok()
+        .and_then(|container| container.get(slot))
+        .is_some_and(|item| item.id == FERTILIZER_ID);
+    if holds_fertilizer {
+        fertilize_events.send(FertilizeEvent {
+            tile: global_tile_at(transform.translation.xy()),
+            slot,
+        });
+    }
+}
+
+fn fertilize(
+    mut events: EventReader<FromClient<FertilizeEvent>>,
+    mut players: Query<(&NetworkOwner, &Transform, &mut ItemContainer), With<Player>>,
+    mut chunks: Query<&mut ChunkTiles>,
+    chunk_map: Res<ChunkIndexMap>,
+    settings: Res<SoilSettings>,
+) {
+    for FromClient { client_id, event } in events.read() {
+        let Some((_, transform, mut container)) = players
+            .iter_mut()
+            .find(|(owner, ..)| owner.0 == client_id.get())
+        else {
+            continue;
+        };
+        let player_tile = global_tile_at(transform.translation.xy());
+        if (event.tile - player_tile).abs().max_element() > 1 {
+            debug!("{client_id:?} tried to fertilize {} out of range", event.tile);
+            continue;
+        }
+        if container.get(event.slot).map_or(true, |item| item.id != FERTILIZER_ID) {
+            continue;
+        }
+        let (chunk_index, tile_pos) = split_global_tile(event.tile);
+        let Some(mut chunk_tiles) = chunk_map
+            .get(chunk_index)
+            .and_then(|chunk| chunks.get_mut(chunk).ok())
+        else {
+            continue;
+        };
+        if chunk_tiles.get(&tile_pos) != Some(&Ground::Dirt) {
+            debug!("{client_id:?} tried to fertilize untilled ground at {}", event.tile);
+            continue;
+        }
+        let quality = chunk_tiles.soil_quality(&tile_pos).unwrap_or(DEFAULT_SOIL_QUALITY);
+        if quality >= settings.max_quality {
+            continue;
+        }
+        chunk_tiles.set_soil_quality(
+            &tile_pos,
+            (quality + settings.fertilizer_bonus).min(settings.max_quality),
+        );
+        container.take_from_slot(event.slot, 1);
+    }
+}
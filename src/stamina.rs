@@ -0,0 +1,132 @@
+use bevy::{prelude::*, utils::HashSet};
+use bevy_replicon::prelude::*;
+use bevy_replicon_snap::NetworkOwner;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    player::{Player, Respawning},
+    MoveEvent,
+};
+
+pub const PLAYER_STAMINA: f32 = 100.0;
+/// Speed factor of sprinting moves.
+pub const SPRINT_MULTIPLIER: f32 = 1.6;
+
+pub struct StaminaPlugin;
+
+impl Plugin for StaminaPlugin {
+    fn build(&self, app: &mut App) {
+        app.replicate::<Stamina>()
+            .register_type::<StaminaSettings>()
+            .init_resource::<StaminaSettings>()
+            .add_systems(
+                PreUpdate,
+                update_exhaustion
+                    .after(ClientSet::Receive)
+                    .after(ServerSet::Receive),
+            )
+            .add_systems(Update, use_stamina.run_if(server_running));
+    }
+}
+
+#[derive(Resource, Debug, Reflect)]
+#[reflect(Resource)]
+pub struct StaminaSettings {
+    /// Stamina used by every sprinting move event. Moves are sent once per frame and each one
+    /// covers the same distance, so this is a cost per distance rather than per second.
+    pub sprint_cost: f32,
+    /// Stamina regained per second while not sprinting.
+    pub regeneration: f32,
+    /// Share of the maximum an exhausted player needs to regain before sprinting again.
+    pub recover_fraction: f32,
+}
+
+impl Default for StaminaSettings {
+    fn default() -> Self {
+        Self {
+            sprint_cost: 0.15,
+            regeneration: 12.0,
+            recover_fraction: 0.25,
+        }
+    }
+}
+
+/// Stamina used for sprinting, written by the server only.
+#[derive(Component, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Stamina {
+    pub current: f32,
+    pub max: f32,
+    /// Set once stamina runs out, cleared once enough of it came back.
+    pub exhausted: bool,
+}
+
+impl Stamina {
+    pub fn new(max: f32) -> Self {
+        Self {
+            current: max,
+            max,
+            exhausted: false,
+        }
+    }
+}
+
+/// Caches the exhaustion in [`Player`] on server and clients, the move prediction only sees that
+/// component.
+fn update_exhaustion(mut players: Query<(&Stamina, &mut Player), Changed<Stamina>>) {
+    for (stamina, mut player) in players.iter_mut() {
+        if player.exhausted != stamina.exhausted {
+            // a local cache, changing it must not trigger replication of Player
+            player.bypass_change_detection().exhausted = stamina.exhausted;
+        }
+    }
+}
+
+/// Drains stamina for sprinting moves and regenerates it for players that didn't sprint this frame.
+fn use_stamina(
+    mut events: EventReader<FromClient<MoveEvent>>,
+    mut players: Query<(&NetworkOwner, &mut Stamina), (With<Player>, Without<Respawning>)>,
+    settings: Res<StaminaSettings>,
+    time: Res<Time>,
+) {
+    let mut sprinting = HashSet::new();
+    for FromClient { client_id, event } in events.read() {
+        if !event.sprint || event.input == Vec2::ZERO {
+            continue;
+        }
+        let Some((_, mut stamina)) = players
+            .iter_mut()
+            .find(|(owner, _)| owner.0 == client_id.get())
+        else {
+            continue;
+        };
+        if stamina.exhausted {
+            continue;
+        }
+        sprinting.insert(client_id.get());
+        let before = stamina.current.ceil();
+        let state = stamina.bypass_change_detection();
+        state.current = (state.current - settings.sprint_cost).max(0.0);
+        state.exhausted = state.current <= 0.0;
+        if stamina.current.ceil() != before || stamina.exhausted {
+            stamina.set_changed();
+        }
+    }
+
+    let regained = settings.regeneration * time.delta_seconds();
+    for (owner, mut stamina) in players.iter_mut() {
+        if sprinting.contains(&owner.0) || stamina.current >= stamina.max {
+            continue;
+        }
+        // clients only need whole points for the bar
+        let before = stamina.current.ceil();
+        let was_exhausted = stamina.exhausted;
+        let state = stamina.bypass_change_detection();
+        state.current = (state.current + regained).min(state.max);
+        if state.exhausted && state.current >= state.max * settings.recover_fraction {
+            state.exhausted = false;
+        }
+        if stamina.current.ceil() != before || stamina.exhausted != was_exhausted {
+            stamina.set_changed();
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use std::sync::Arc;
+
+use bevy::{prelude::*, utils::HashMap};
+use bevy_rand::prelude::WyRand;
+use rand_core::{RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    biome::{biome_at, Biome},
+    chunk::{mix_seed, WorldSeed, TILES_PER_CHUNK},
+    ron_asset::RonAssetPlugin,
+    terrain::{ground_at, SPAWN_RADIUS},
+    world::Ground,
+};
+
+const STRUCTURES_PATH: &str = "data/structures.structure.ron";
+const STRUCTURE_SALT: u64 = 0x5743;
+/// Width and height in chunks of the regions that hold at most one structure each. Templates
+/// have to fit into a region, which keeps generating a chunk down to looking at its own region.
+const REGION_CHUNKS: i32 = 4;
+/// Chance of a region to hold a structure.
+const STRUCTURE_CHANCE: f32 = 0.4;
+/// Template character that keeps the generated ground.
+const KEEP_GROUND: char = '.';
+
+pub struct StructurePlugin;
+
+impl Plugin for StructurePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(RonAssetPlugin::<StructureDefinitions>::new(&["structure.ron"]))
+            .add_systems(PreStartup, load_structure_registry)
+            .add_systems(PreUpdate, update_structure_registry);
+    }
+}
+
+/// Prefab stamped into the world during chunk generation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StructureTemplate {
+    /// Relative chance of the template to be picked for a region.
+    pub weight: f32,
+    /// Biomes the template may be placed in, any if empty.
+    #[serde(default)]
+    pub biomes: Vec<Biome>,
+    /// Ground layout from the top row down, one character per tile looked up in `palette`.
+    pub grounds: Vec<String>,
+    #[serde(default)]
+    pub palette: HashMap<char, Ground>,
+    #[serde(default)]
+    pub objects: Vec<StructureObject>,
+}
+
+/// World object of a template, `offset` is its anchor relative to the lower left template tile.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StructureObject {
+    pub kind: String,
+    pub offset: IVec2,
+}
+
+impl StructureTemplate {
+    pub fn size(&self) -> IVec2 {
+        let width = self.grounds.iter().map(|row| row.chars().count()).max().unwrap_or_default();
+        IVec2::new(width as i32, self.grounds.len() as i32)
+    }
+
+    /// Ground the template puts at `offset` from its lower left tile, `None` keeps the generated one.
+    pub fn ground(&self, offset: IVec2) -> Option<Ground> {
+        let row = self.grounds.len().checked_sub(1 + usize::try_from(offset.y).ok()?)?;
+        let symbol = self.grounds[row].chars().nth(usize::try_from(offset.x).ok()?)?;
+        if symbol == KEEP_GROUND {
+            return None;
+        }
+        self.palette.get(&symbol).cloned()
+    }
+
+    fn allows(&self, biome: Biome) -> bool {
+        self.biomes.is_empty() || self.biomes.contains(&biome)
+    }
+}
+
+#[derive(Asset, TypePath, Debug, Serialize, Deserialize)]
+pub struct StructureDefinitions {
+    pub structures: HashMap<String, StructureTemplate>,
+}
+
+/// Templates from the structure RON asset. They are kept sorted by name behind an [`Arc`], so
+/// generation tasks get a cheap copy that picks the same template on every run.
+#[derive(Resource, Debug, Default)]
+pub struct StructureRegistry {
+    handle: Handle<StructureDefinitions>,
+    templates: Arc<Vec<(String, StructureTemplate)>>,
+    loaded: bool,
+}
+
+impl StructureRegistry {
+    pub fn templates(&self) -> Arc<Vec<(String, StructureTemplate)>> {
+        self.templates.clone()
+    }
+
+    /// Whether the structure file was read at least once.
+    pub fn is_loaded(&self) -> bool {
+        self.loaded
+    }
+}
+
+/// A template placed with its lower left tile on `origin`.
+#[derive(Debug, Clone, Copy)]
+pub struct Placement<'a> {
+    pub template: &'a StructureTemplate,
+    pub origin: IVec2,
+}
+
+impl Placement<'_> {
+    pub fn contains(&self, tile: IVec2) -> bool {
+        let offset = tile - self.origin;
+        offset.cmpge(IVec2::ZERO).all() && offset.cmplt(self.template.size()).all()
+    }
+
+    pub fn ground(&self, tile: IVec2) -> Option<Ground> {
+        self.template.ground(tile - self.origin)
+    }
+}
+
+/// Structure of the region the chunk lies in, if it has one. Only depends on the seed and the
+/// region, so every chunk the structure spans stamps its own part of the same placement.
+pub fn structure_at(
+    seed: WorldSeed,
+    chunk_index: IVec2,
+    templates: &[(String, StructureTemplate)],
+) -> Option<Placement<'_>> {
+    let region = chunk_index.div_euclid(IVec2::splat(REGION_CHUNKS));
+    let mut rng = WyRand::seed_from_u64(mix_seed(
+        mix_seed(seed.0, STRUCTURE_SALT),
+        (region.x as u32 as u64) | ((region.y as u32 as u64) << 32),
+    ));
+    let mut random = || rng.next_u32() as f32 / u32::MAX as f32;
+    let region_tiles = REGION_CHUNKS * TILES_PER_CHUNK as i32;
+    let corner = IVec2::new(
+        (random() * region_tiles as f32) as i32,
+        (random() * region_tiles as f32) as i32,
+    );
+    if random() >= STRUCTURE_CHANCE {
+        return None;
+    }
+    let biome = biome_at(seed, region * region_tiles + corner);
+    let candidates: Vec<_> = templates
+        .iter()
+        .map(|(_, template)| template)
+        .filter(|template| {
+            let size = template.size();
+            template.allows(biome)
+                && size.cmpgt(IVec2::ZERO).all()
+                && size.cmple(IVec2::splat(region_tiles)).all()
+        })
+        .collect();
+    let total: f32 = candidates.iter().map(|template| template.weight).sum();
+    let mut roll = random() * total;
+    let template = *candidates.iter().find(|template| {
+        roll -= template.weight;
+        roll < 0.0
+    })?;
+    // shift the template back into the region where it would stick out
+    let origin = region * region_tiles + corner.min(IVec2::splat(region_tiles) - template.size());
+    let center = origin + template.size() / 2;
+    let nearest_to_spawn = IVec2::ZERO.clamp(origin, origin + template.size() - IVec2::ONE);
+    if ground_at(seed, center).is_water() || nearest_to_spawn.as_vec2().length() < SPAWN_RADIUS {
+        return None;
+    }
+    Some(Placement { template, origin })
+}
+
+fn load_structure_registry(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(StructureRegistry {
+        handle: asset_server.load(STRUCTURES_PATH),
+        templates: Arc::default(),
+        loaded: false,
+    });
+}
+
+fn update_structure_registry(
+    mut events: EventReader<AssetEvent<StructureDefinitions>>,
+    mut registry: ResMut<StructureRegistry>,
+    definitions: Res<Assets<StructureDefinitions>>,
+) {
+    for event in events.read() {
+        if !event.is_loaded_with_dependencies(&registry.handle) && !event.is_modified(&registry.handle) {
+            continue;
+        }
+        if let Some(loaded) = definitions.get(&registry.handle) {
+            let mut templates: Vec<_> = loaded
+                .structures
+                .iter()
+                .map(|(name, template)| (name.clone(), template.clone()))
+                .collect();
+            templates.sort_by(|(a, _), (b, _)| a.cmp(b));
+            info!("Loaded {} structure templates", templates.len());
+            registry.templates = Arc::new(templates);
+            registry.loaded = true;
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use bevy::{prelude::*, utils::HashMap};
+use bevy_rand::prelude::WyRand;
+use rand_core::{RngCore, SeedableRng};
+
+use crate::{
+    biome::{biome_at, Biome},
+    chunk::{mix_seed, WorldSeed, TILES_PER_CHUNK},
+    world::Ground,
+    world_object::footprint_tiles,
+};
+
+/// Tiles per period of the coarsest elevation octave.
+const ELEVATION_SCALE: f32 = 48.0;
+const MOISTURE_SCALE: f32 = 64.0;
+const TEMPERATURE_SCALE: f32 = 128.0;
+const OCTAVES: u32 = 4;
+/// Salts that give every noise layer its own random stream.
+const ELEVATION_SALT: u64 = 0xe1e7;
+const MOISTURE_SALT: u64 = 0x3015;
+const TEMPERATURE_SALT: u64 = 0x7e39;
+const ORE_SALT: u64 = 0x04e5;
+const DECORATION_SALT: u64 = 0xdec0;
+/// Elevation thresholds between the grounds.
+const DEEP_WATER_LEVEL: f32 = 0.3;
+const STONE_LEVEL: f32 = 0.7;
+/// Below this moisture land is bare dirt.
+const DRY_LEVEL: f32 = 0.35;
+/// Tiles around the origin are raised to dry land so (re)spawning players never land in water.
+pub const SPAWN_RADIUS: f32 = 6.0;
+const SPAWN_ELEVATION: f32 = 0.5;
+/// Chance of a chunk to hold an ore vein, higher if the vein would start on stone.
+const VEIN_CHANCE: f32 = 0.15;
+const STONE_VEIN_CHANCE: f32 = 0.6;
+/// Steps of the random walk that lays out a vein.
+const MIN_VEIN_LENGTH: u32 = 3;
+const MAX_VEIN_LENGTH: u32 = 7;
+/// Veins starting above this elevation are iron, the rest copper.
+const IRON_LEVEL: f32 = 0.6;
+const IRON_VEIN: &str = "iron_vein";
+const COPPER_VEIN: &str = "copper_vein";
+/// The only decoration that grows on stone.
+const ROCK: &str = "rock";
+
+/// Climate of a tile, every value in `0.0..=1.0`.
+#[derive(Debug, Clone, Copy)]
+pub struct Climate {
+    pub elevation: f32,
+    pub moisture: f32,
+    pub temperature: f32,
+}
+
+pub fn climate_at(seed: WorldSeed, tile: IVec2) -> Climate {
+    let position = tile.as_vec2();
+    let mut elevation = fractal_noise(mix_seed(seed.0, ELEVATION_SALT), position / ELEVATION_SCALE);
+    let spawn_falloff = 1.0 - (position.length() / SPAWN_RADIUS).min(1.0);
+    elevation = elevation.max(SPAWN_ELEVATION * spawn_falloff.sqrt());
+    Climate {
+        elevation,
+        moisture: fractal_noise(mix_seed(seed.0, MOISTURE_SALT), position / MOISTURE_SCALE),
+        temperature: fractal_noise(mix_seed(seed.0, TEMPERATURE_SALT), position / TEMPERATURE_SCALE),
+    }
+}
+
+/// Ground of a freshly generated tile. It only depends on the seed and the global tile, so
+/// neighboring chunks line up no matter which one is generated first.
+pub fn ground_at(seed: WorldSeed, tile: IVec2) -> Ground {
+    let climate = climate_at(seed, tile);
+    let biome = Biome::from_climate(climate);
+    if climate.elevation < DEEP_WATER_LEVEL {
+        Ground::DeepWater
+    } else if climate.elevation < biome.water_level() {
+        Ground::Water
+    } else if climate.elevation > STONE_LEVEL {
+        Ground::Stone
+    } else if biome == Biome::Desert || (biome == Biome::Plains && climate.moisture < DRY_LEVEL) {
+        Ground::Dirt
+    } else {
+        Ground::Grass
+    }
+}
+
+/// Ore objects of a freshly generated chunk as object kinds and global anchor tiles. A vein is a
+/// short random walk that stays inside the chunk and skips water, so every object ends up in the
+/// chunk file it belongs to.
+pub fn ore_veins(seed: WorldSeed, chunk_index: IVec2) -> Vec<(&'static str, IVec2)> {
+    let mut rng = WyRand::seed_from_u64(mix_seed(seed.chunk_seed(chunk_index), ORE_SALT));
+    let mut random = || rng.next_u32() as f32 / u32::MAX as f32;
+    let tiles_per_chunk = TILES_PER_CHUNK as i32;
+    let origin = chunk_index * tiles_per_chunk;
+    let mut local = IVec2::new(
+        (random() * tiles_per_chunk as f32) as i32,
+        (random() * tiles_per_chunk as f32) as i32,
+    )
+    .min(IVec2::splat(tiles_per_chunk - 1));
+    let start = origin + local;
+    let chance = if ground_at(seed, start) == Ground::Stone { STONE_VEIN_CHANCE } else { VEIN_CHANCE };
+    if random() >= chance {
+        return Vec::new();
+    }
+    let kind = if climate_at(seed, start).elevation > IRON_LEVEL { IRON_VEIN } else { COPPER_VEIN };
+    let length = MIN_VEIN_LENGTH + (random() * (MAX_VEIN_LENGTH - MIN_VEIN_LENGTH + 1) as f32) as u32;
+    let mut tiles = Vec::new();
+    for _ in 0..length {
+        let tile = origin + local;
+        if !tiles.contains(&tile) && !ground_at(seed, tile).is_water() {
+            tiles.push(tile);
+        }
+        let step = match (random() * 4.0) as u32 {
+            0 => IVec2::X,
+            1 => IVec2::NEG_X,
+            2 => IVec2::Y,
+            _ => IVec2::NEG_Y,
+        };
+        local = (local + step).clamp(IVec2::ZERO, IVec2::splat(tiles_per_chunk - 1));
+    }
+    tiles.into_iter().map(|tile| (kind, tile)).collect()
+}
+
+/// Decorations of a freshly generated chunk as object kinds and global anchor tiles. Every tile
+/// rolls once against the decoration chances of its biome. Objects only go where their whole
+/// footprint is dry, inside the chunk and not in `occupied`, and never on the spawn area.
+pub fn decorations(
+    seed: WorldSeed,
+    chunk_index: IVec2,
+    footprints: &HashMap<String, UVec2>,
+    occupied: &mut Vec<IVec2>,
+) -> Vec<(&'static str, IVec2)> {
+    let mut rng = WyRand::seed_from_u64(mix_seed(seed.chunk_seed(chunk_index), DECORATION_SALT));
+    let tiles_per_chunk = TILES_PER_CHUNK as i32;
+    let origin = chunk_index * tiles_per_chunk;
+    let inside = |tile: IVec2| {
+        let local = tile - origin;
+        local.cmpge(IVec2::ZERO).all() && local.cmplt(IVec2::splat(tiles_per_chunk)).all()
+    };
+    let mut decorations = Vec::new();
+    for y in 0..tiles_per_chunk {
+        for x in 0..tiles_per_chunk {
+            let anchor = origin + IVec2::new(x, y);
+            // roll for every tile so skipped ones don't shift the rest of the chunk
+            let mut roll = rng.next_u32() as f32 / u32::MAX as f32;
+            if anchor.as_vec2().length() < SPAWN_RADIUS {
+                continue;
+            }
+            let Some(kind) = biome_at(seed, anchor).decorations().iter().find_map(|(kind, chance)| {
+                roll -= chance;
+                (roll < 0.0).then_some(*kind)
+            }) else {
+                continue;
+            };
+            let footprint = footprints.get(kind).copied().unwrap_or(UVec2::ONE);
+            let fits = footprint_tiles(anchor, footprint).all(|tile| {
+                let ground = ground_at(seed, tile);
+                inside(tile)
+                    && !occupied.contains(&tile)
+                    && !ground.is_water()
+                    && (kind == ROCK || ground != Ground::Stone)
+            });
+            if fits {
+                occupied.extend(footprint_tiles(anchor, footprint));
+                decorations.push((kind, anchor));
+            }
+        }
+    }
+    decorations
+}
+
+/// Sum of value noise octaves, each with double the frequency and half the weight of the last.
+fn fractal_noise(seed: u64, position: Vec2) -> f32 {
+    let mut sum = 0.0;
+    let mut weight = 1.0;
+    let mut total_weight = 0.0;
+    let mut frequency = 1.0;
+    for octave in 0..OCTAVES {
+        sum += value_noise(mix_seed(seed, octave as u64), position * frequency) * weight;
+        total_weight += weight;
+        weight *= 0.5;
+        frequency *= 2.0;
+    }
+    sum / total_weight
+}
+
+/// Smoothly interpolated random values on the integer lattice, in `0.0..=1.0`.
+fn value_noise(seed: u64, position: Vec2) -> f32 {
+    let cell = position.floor();
+    let local = position - cell;
+    let cell = cell.as_ivec2();
+    // smoothstep hides the lattice
+    let t = local * local * (Vec2::splat(3.0) - 2.0 * local);
+    let corner = |offset: IVec2| lattice_value(seed, cell + offset);
+    let bottom = lerp(corner(IVec2::ZERO), corner(IVec2::X), t.x);
+    let top = lerp(corner(IVec2::Y), corner(IVec2::ONE), t.x);
+    lerp(bottom, top, t.y)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+fn lattice_value(seed: u64, point: IVec2) -> f32 {
+    let hash = mix_seed(seed, (point.x as u32 as u64) | ((point.y as u32 as u64) << 32));
+    (hash >> 40) as f32 / (1u64 << 24) as f32
+}
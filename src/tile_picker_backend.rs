@@ -2,26 +2,23 @@
 
 use bevy::{
     app::{Plugin, PreUpdate}, ecs::{
-        entity::Entity, event::EventWriter, query::With, schedule::IntoSystemConfigs, system::Query,
-    }, math::{Vec2, Vec4}, prelude::*, render::{
+        entity::Entity, event::EventWriter, schedule::IntoSystemConfigs, system::Query,
+    }, prelude::*, render::{
         camera::{Camera, OrthographicProjection},
         view::ViewVisibility,
-    }, transform::components::GlobalTransform, window::PrimaryWindow
-};
-use bevy_ecs_tilemap::{
-    map::{TilemapGridSize, TilemapSize, TilemapType},
-    tiles::{TilePos, TileStorage, TileVisible},
+    }, transform::components::GlobalTransform
 };
+use bevy_ecs_tilemap::tiles::{TileStorage, TileVisible};
 use bevy_mod_picking::{
     backend::{HitData, PointerHits},
     picking_core::{PickSet, Pickable},
-    pointer::{PointerId, PointerLocation},
+    pointer::PointerId,
 };
 
 pub use bevy_ecs_tilemap;
 pub use bevy_mod_picking;
 
-use crate::{chunk::{Chunk, TILE_LENGTH}, world::ChunkPosExt};
+use crate::{camera::CursorTile, chunk::ChunkIndexMap};
 
 
 
@@ -36,77 +33,42 @@ impl Plugin for TilemapBackend {
     }
 }
 
-#[allow(clippy::type_complexity)]
+/// Picks the tile under the mouse cursor from [`CursorTile`], so picking agrees with every other
+/// system asking for the tile under the cursor.
 fn tile_picking(
-    pointers: Query<(&PointerId, &PointerLocation)>,
-    cameras: Query<(Entity, &Camera, &GlobalTransform, &OrthographicProjection)>,
-    primary_window: Query<Entity, With<PrimaryWindow>>,
-    tilemap_q: Query<(
-        &Chunk,
-        &TilemapSize,
-        &TilemapGridSize,
-        &TilemapType,
-        &TileStorage,
-        &GlobalTransform,
-        &ViewVisibility,
-    )>,
+    cameras: Query<(Entity, &Camera, &OrthographicProjection)>,
+    tilemap_q: Query<(&TileStorage, &GlobalTransform, &ViewVisibility)>,
     tile_q: Query<(&TileVisible, Option<&Pickable>)>,
+    cursor_tile: Res<CursorTile>,
+    chunk_map: Res<ChunkIndexMap>,
     mut output: EventWriter<PointerHits>,
 ) {
-    for (p_id, p_loc) in pointers
+    let Some((cam_entity, camera, cam_ortho)) = cameras
         .iter()
-        .filter_map(|(p_id, p_loc)| p_loc.location().map(|l| (p_id, l)))
-    {
-        let mut blocked = false;
-        let Some((cam_entity, camera, cam_transform, cam_ortho)) = cameras
-            .iter()
-            .filter(|(_, camera, _, _)| camera.is_active)
-            .find(|(_, camera, _, _)| {
-                camera
-                    .target
-                    .normalize(Some(match primary_window.get_single() {
-                        Ok(w) => w,
-                        Err(_) => return false,
-                    }))
-                    .unwrap()
-                    == p_loc.target
-            })
-        else {
-            continue;
-        };
-
-        let Some(cursor_pos_world) = camera.viewport_to_world_2d(cam_transform, p_loc.position)
-        else {
-            continue;
-        };
+        .find(|(_, camera, _)| camera.is_active)
+    else {
+        return;
+    };
 
-        let picks = tilemap_q
-            .iter()
-            .filter(|(.., vis)| vis.get())
-            .filter_map(|(_chunk, _t_s, _tgs, _tty, t_store, gt, ..)| {
-                if blocked {
-                    return None;
-                }
-                let in_map_pos: Vec2 = {
-                    let pos = Vec4::from((cursor_pos_world, 0., 1.));
-                    let in_map_pos = gt.compute_matrix().inverse() * pos;
-                    in_map_pos.xy() + Vec2::splat(TILE_LENGTH/2.0)
-                };
-                let picked: Entity = TilePos::from_in_chunk_pos(in_map_pos)
-                    .and_then(|tile_pos| t_store.get(&tile_pos))?;
-                let (vis, pck) = tile_q.get(picked).ok()?;
-                if !vis.0 {
-                    return None;
-                }
-                blocked = pck.is_some_and(|p| p.should_block_lower);
-                let depth = -cam_ortho.near - gt.translation().z;
-                Some((picked, HitData::new(cam_entity, depth, None, None)))
-            })
-            .collect();
+    let picks = cursor_tile
+        .and_then(|(chunk_index, tile_pos)| {
+            let (tile_storage, gt, vis) = tilemap_q.get(chunk_map.get(chunk_index)?).ok()?;
+            if !vis.get() {
+                return None;
+            }
+            let picked = tile_storage.get(&tile_pos)?;
+            let (tile_vis, _) = tile_q.get(picked).ok()?;
+            if !tile_vis.0 {
+                return None;
+            }
+            let depth = -cam_ortho.near - gt.translation().z;
+            Some((picked, HitData::new(cam_entity, depth, None, None)))
+        })
+        .into_iter()
+        .collect();
 
-        // f32 required by PointerHits
-        #[allow(clippy::cast_precision_loss)]
-        let order = camera.order as f32;
-        output.send(PointerHits::new(*p_id, picks, order));
-    }
-}
\ No newline at end of file
+    // f32 required by PointerHits
+    #[allow(clippy::cast_precision_loss)]
+    let order = camera.order as f32;
+    output.send(PointerHits::new(PointerId::Mouse, picks, order));
+}
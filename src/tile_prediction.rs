@@ -0,0 +1,186 @@
+use bevy::prelude::*;
+use bevy_ecs_tilemap::tiles::{TilePos, TileStorage};
+use bevy_replicon::{client::ClientSet, core::ClientId, prelude::*};
+use bevy_replicon_snap::NetworkOwner;
+
+use crate::{
+    action::{ActionCooldownConfig, ActionCooldowns, GameAction},
+    chunk::{Chunk, ChunkIndexMap, ChunkTiles},
+    ground_registry::GroundRegistry,
+    player::Player,
+    world::{global_tile, global_tile_at, tile_in_reach, world_pos_to_chunk_tile, Ground},
+    world_object::TileOccupancy,
+    read_input, ActionEvent, ClickTileEvent,
+};
+
+/// Seconds a prediction waits for the server before it counts as rejected. A rejected change
+/// doesn't replicate anything, so this is the only way to notice it.
+const PREDICTION_TIMEOUT: f32 = 1.0;
+
+pub struct TilePredictionPlugin;
+
+impl Plugin for TilePredictionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (predict_tile_clicks, predict_tilling.after(read_input)).run_if(client_connected),
+        )
+        .add_systems(PreUpdate, resolve_tile_predictions.after(ClientSet::Receive));
+    }
+}
+
+/// Tile entity whose Ground was changed locally ahead of the server. Only the tile entity is
+/// touched, the replicated [`ChunkTiles`] and with it the chunk saves keep the server's value.
+#[derive(Component, Debug)]
+pub struct PredictedTileChange {
+    pub expected: Ground,
+    pub predicted_at: f32,
+}
+
+/// Tills the tile entity locally if the server is expected to do the same, returns whether it did.
+fn predict_till(
+    commands: &mut Commands,
+    tile: Entity,
+    ground: &mut Ground,
+    tillable: bool,
+    occupied: bool,
+    now: f32,
+) -> bool {
+    if !tillable || occupied || *ground == Ground::Dirt {
+        return false;
+    }
+    *ground = Ground::Dirt;
+    commands.entity(tile).insert(PredictedTileChange {
+        expected: Ground::Dirt,
+        predicted_at: now,
+    });
+    true
+}
+
+fn predict_tile_clicks(
+    mut commands: Commands,
+    mut click_events: EventReader<ClickTileEvent>,
+    mut tiles: Query<&mut Ground>,
+    mut players: Query<(&NetworkOwner, &Transform, &mut ActionCooldowns), With<Player>>,
+    chunks: Query<(&Chunk, &ChunkTiles, &TileStorage)>,
+    registry: Res<GroundRegistry>,
+    occupancy: Res<TileOccupancy>,
+    cooldown_config: Res<ActionCooldownConfig>,
+    client: Res<RepliconClient>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds();
+    let Some((_, transform, mut cooldowns)) = client.id().and_then(|client_id| {
+        players
+            .iter_mut()
+            .find(|(owner, ..)| ClientId::new(owner.0) == client_id)
+    }) else {
+        click_events.clear();
+        return;
+    };
+    let position = transform.translation.xy();
+    for event in click_events.read() {
+        let Ok((chunk, chunk_tiles, storage)) = chunks.get(event.chunk) else {
+            continue;
+        };
+        let Some(tile) = storage.get(&event.tile_pos) else {
+            continue;
+        };
+        let Ok(mut ground) = tiles.get_mut(tile) else {
+            continue;
+        };
+        // the same checks as handle_tile_click, against the server's ground
+        if !tile_in_reach(position, chunk.chunk_index, &event.tile_pos)
+            || !cooldowns.ready(GameAction::Till, now, &cooldown_config)
+        {
+            continue;
+        }
+        let tillable = chunk_tiles
+            .get(&event.tile_pos)
+            .is_some_and(|ground| registry.is_tillable(ground));
+        let occupied = occupancy.is_occupied(global_tile(chunk.chunk_index, &event.tile_pos));
+        if predict_till(&mut commands, tile, &mut ground, tillable, occupied, now) {
+            cooldowns.trigger(GameAction::Till, now);
+        }
+    }
+}
+
+/// Predicts the Till action of the local player, if its cooldown let it through this frame.
+fn predict_tilling(
+    mut commands: Commands,
+    mut action_events: EventReader<ActionEvent>,
+    mut tiles: Query<&mut Ground>,
+    players: Query<(&NetworkOwner, &Transform, &ActionCooldowns), With<Player>>,
+    chunks: Query<(&ChunkTiles, &TileStorage)>,
+    chunk_map: Res<ChunkIndexMap>,
+    registry: Res<GroundRegistry>,
+    occupancy: Res<TileOccupancy>,
+    client: Res<RepliconClient>,
+    time: Res<Time>,
+) {
+    let tilled = action_events
+        .read()
+        .any(|event| GameAction::from_key(event.action).contains(&GameAction::Till));
+    if !tilled {
+        return;
+    }
+    let now = time.elapsed_seconds();
+    let Some((_, transform, cooldowns)) = client.id().and_then(|client_id| {
+        players
+            .iter()
+            .find(|(owner, ..)| ClientId::new(owner.0) == client_id)
+    }) else {
+        return;
+    };
+    if !cooldowns.triggered_at(now).any(|action| action == GameAction::Till) {
+        return;
+    }
+    let position = transform.translation.xy();
+    let Some((chunk_index, tile_pos)) = world_pos_to_chunk_tile(position) else {
+        return;
+    };
+    let Some((chunk_tiles, storage)) = chunk_map
+        .get(chunk_index)
+        .and_then(|chunk| chunks.get(chunk).ok())
+    else {
+        return;
+    };
+    let Some(tile) = storage.get(&tile_pos) else {
+        return;
+    };
+    let Ok(mut ground) = tiles.get_mut(tile) else {
+        return;
+    };
+    let tillable = chunk_tiles
+        .get(&tile_pos)
+        .is_some_and(|ground| registry.is_tillable(ground));
+    let occupied = occupancy.is_occupied(global_tile_at(position));
+    predict_till(&mut commands, tile, &mut ground, tillable, occupied, now);
+}
+
+/// Confirms predictions once the server's ground matches them and rolls back the ones the server
+/// didn't apply in time to its value.
+fn resolve_tile_predictions(
+    mut commands: Commands,
+    mut tiles: Query<(Entity, &mut Ground, &TilePos, &Parent, &PredictedTileChange)>,
+    chunks: Query<&ChunkTiles>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds();
+    for (entity, mut ground, tile_pos, parent, prediction) in tiles.iter_mut() {
+        let Some(server_ground) = chunks
+            .get(parent.get())
+            .ok()
+            .and_then(|chunk_tiles| chunk_tiles.get(tile_pos))
+        else {
+            continue;
+        };
+        if *server_ground == prediction.expected {
+            commands.entity(entity).remove::<PredictedTileChange>();
+        } else if now - prediction.predicted_at > PREDICTION_TIMEOUT {
+            debug!("Tile prediction at {tile_pos:?} was rejected, rolling back");
+            *ground = server_ground.clone();
+            commands.entity(entity).remove::<PredictedTileChange>();
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use bevy::prelude::*;
+use bevy_ecs_tilemap::tiles::{TilePos, TileStorage};
+use bevy_rand::prelude::{EntropyComponent, GlobalEntropy, WyRand};
+use bevy_replicon::prelude::*;
+use rand_core::RngCore;
+
+use crate::{
+    chunk::{Chunk, ChunkTiles, ParkedChunk, MAP_SIZE, TILES_PER_CHUNK},
+    game_time::TimeSkipped,
+    sapling::Growth,
+    soil::DEFAULT_SOIL_QUALITY,
+    world::{global_tile, Ground},
+    world_object::TileOccupancy,
+};
+
+pub struct TileTickPlugin;
+
+impl Plugin for TileTickPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<TileTickConfig>()
+            .init_resource::<TileTickConfig>()
+            .add_systems(Update, random_tile_tick.run_if(server_running));
+    }
+}
+
+/// Settings of the random tile tick. Every `interval` seconds `samples_per_chunk` random tiles
+/// of each loaded chunk get a chance to change their ground.
+#[derive(Resource, Debug, Reflect)]
+#[reflect(Resource)]
+pub struct TileTickConfig {
+    pub enabled: bool,
+    pub interval: f32,
+    pub samples_per_chunk: u32,
+    /// Chance of a sampled dirt tile without neighbouring grass to turn back into grass.
+    pub dirt_revert_chance: f32,
+    /// Chance of a sampled dirt tile next to grass to be overgrown.
+    pub grass_spread_chance: f32,
+    /// Soil quality a sampled dirt tile loses or regains toward the default.
+    pub soil_decay: f32,
+}
+
+impl Default for TileTickConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval: 5.0,
+            samples_per_chunk: 2,
+            dirt_revert_chance: 0.05,
+            grass_spread_chance: 0.25,
+            soil_decay: 0.05,
+        }
+    }
+}
+
+/// Upper bound of ticks run in a single frame when catching up on skipped time.
+const MAX_CATCH_UP_TICKS: u32 = 200;
+
+fn random_tile_tick(
+    mut skipped: EventReader<TimeSkipped>,
+    mut chunk_query: Query<(&Chunk, &mut ChunkTiles, &TileStorage), Without<ParkedChunk>>,
+    mut tile_query: Query<&mut EntropyComponent<WyRand>>,
+    growing: Query<(), With<Growth>>,
+    occupancy: Res<TileOccupancy>,
+    mut glob: ResMut<GlobalEntropy<WyRand>>,
+    mut since_last_tick: Local<f32>,
+    config: Res<TileTickConfig>,
+    time: Res<Time>,
+) {
+    if !config.enabled {
+        return;
+    }
+    // a night skipped by sleeping counts as passed time, so the world keeps changing
+    *since_last_tick += time.delta_seconds() + skipped.read().map(|skip| skip.seconds).sum::<f32>();
+    if *since_last_tick < config.interval {
+        return;
+    }
+    let ticks = ((*since_last_tick / config.interval) as u32).min(MAX_CATCH_UP_TICKS);
+    *since_last_tick = 0.0;
+
+    for (chunk, mut chunk_tiles, tile_storage) in chunk_query.iter_mut() {
+        for _ in 0..config.samples_per_chunk * ticks {
+            let tile_pos = TilePos::new(
+                glob.next_u32() % TILES_PER_CHUNK,
+                glob.next_u32() % TILES_PER_CHUNK,
+            );
+            if chunk_tiles.get(&tile_pos) != Some(&Ground::Dirt) {
+                continue;
+            }
+            decay_soil(&mut chunk_tiles, &tile_pos, config.soil_decay);
+            // grass doesn't take over dirt with a plant growing on it
+            let planted = occupancy
+                .get(global_tile(chunk.chunk_index, &tile_pos))
+                .is_some_and(|object| growing.contains(object));
+            if planted {
+                continue;
+            }
+            let Some(mut rng) = tile_storage
+                .get(&tile_pos)
+                .and_then(|tile| tile_query.get_mut(tile).ok())
+            else {
+                continue;
+            };
+
+            let chance = if next_to_grass(&chunk_tiles, &tile_pos) {
+                config.grass_spread_chance
+            } else {
+                config.dirt_revert_chance
+            };
+            let roll = rng.next_u32() as f32 / u32::MAX as f32;
+            if roll < chance {
+                chunk_tiles.set(&tile_pos, Ground::Grass);
+            }
+        }
+    }
+}
+
+/// Moves the soil quality of a tile a step back toward the default. Tiles already at the default
+/// are left alone, so sampling them doesn't trigger replication.
+fn decay_soil(chunk_tiles: &mut Mut<ChunkTiles>, tile_pos: &TilePos, step: f32) {
+    let Some(quality) = chunk_tiles.soil_quality(tile_pos) else {
+        return;
+    };
+    if quality == DEFAULT_SOIL_QUALITY {
+        return;
+    }
+    let offset = quality - DEFAULT_SOIL_QUALITY;
+    let decayed = DEFAULT_SOIL_QUALITY + offset.signum() * (offset.abs() - step).max(0.0);
+    chunk_tiles.set_soil_quality(tile_pos, decayed);
+}
+
+/// Only looks at neighbours inside the same chunk to keep the tick cheap.
+fn next_to_grass(chunk_tiles: &ChunkTiles, tile_pos: &TilePos) -> bool {
+    [(1, 0), (-1, 0), (0, 1), (0, -1)].into_iter().any(|(dx, dy)| {
+        TilePos::from_i32_pair(tile_pos.x as i32 + dx, tile_pos.y as i32 + dy, &MAP_SIZE)
+            .and_then(|neighbour| chunk_tiles.get(&neighbour))
+            == Some(&Ground::Grass)
+    })
+}
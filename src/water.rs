@@ -0,0 +1,141 @@
+use bevy::prelude::*;
+use bevy_ecs_tilemap::{map::TilemapGridSize, tiles::TilePos};
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    chunk::{Chunk, ChunkTiles, TILES_PER_CHUNK, TILE_LENGTH},
+    world::Ground,
+};
+
+pub struct WaterPlugin;
+
+impl Plugin for WaterPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<WaterSettings>()
+            .init_resource::<WaterSettings>()
+            .add_systems(PreUpdate, rebuild_water_colliders);
+    }
+}
+
+/// How water limits movement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum WaterMode {
+    /// All water is a wall.
+    Blocking,
+    /// Shallow water can be entered at [`WaterSettings::swim_speed_multiplier`], only deep water
+    /// is a wall.
+    Swimming,
+}
+
+/// Water rules of the server. Clients use the same defaults for predicting their own movement.
+#[derive(Resource, Debug, Reflect)]
+#[reflect(Resource)]
+pub struct WaterSettings {
+    pub mode: WaterMode,
+    /// Replaces the ground speed multiplier while swimming in shallow water.
+    pub swim_speed_multiplier: f32,
+}
+
+impl Default for WaterSettings {
+    fn default() -> Self {
+        Self {
+            mode: WaterMode::Blocking,
+            swim_speed_multiplier: 0.3,
+        }
+    }
+}
+
+impl WaterSettings {
+    /// Whether walkers can't enter the ground at all.
+    pub fn blocks(&self, ground: &Ground) -> bool {
+        match ground {
+            Ground::DeepWater => true,
+            Ground::Water => self.mode == WaterMode::Blocking,
+            _ => false,
+        }
+    }
+
+    /// Whether a walker on the ground is swimming.
+    pub fn swims(&self, ground: &Ground) -> bool {
+        *ground == Ground::Water && self.mode == WaterMode::Swimming
+    }
+}
+
+/// Child of a chunk holding one collider made of all its blocking water tiles.
+#[derive(Component, Debug)]
+struct WaterCollider;
+
+/// Greedily merges the set cells of a chunk sized grid into as few rectangles as possible,
+/// growing each rectangle first along x, then along y. Rectangles are in tile coordinates.
+pub fn merge_tiles(blocked: &[[bool; TILES_PER_CHUNK as usize]; TILES_PER_CHUNK as usize]) -> Vec<URect> {
+    let size = TILES_PER_CHUNK as usize;
+    let mut taken = [[false; TILES_PER_CHUNK as usize]; TILES_PER_CHUNK as usize];
+    let free = |taken: &[[bool; TILES_PER_CHUNK as usize]; TILES_PER_CHUNK as usize], x: usize, y: usize| {
+        blocked[y][x] && !taken[y][x]
+    };
+    let mut rects = Vec::new();
+    for y in 0..size {
+        for x in 0..size {
+            if !free(&taken, x, y) {
+                continue;
+            }
+            let mut width = 1;
+            while x + width < size && free(&taken, x + width, y) {
+                width += 1;
+            }
+            let mut height = 1;
+            while y + height < size && (x..x + width).all(|column| free(&taken, column, y + height)) {
+                height += 1;
+            }
+            for row in taken.iter_mut().skip(y).take(height) {
+                row[x..x + width].fill(true);
+            }
+            rects.push(URect::new(x as u32, y as u32, (x + width) as u32, (y + height) as u32));
+        }
+    }
+    rects
+}
+
+/// Rebuilds the merged water collider of every chunk whose tiles changed. The collider is a child
+/// of the chunk, so it goes away with it.
+fn rebuild_water_colliders(
+    mut commands: Commands,
+    chunks: Query<(Entity, Ref<ChunkTiles>, Option<&Children>, Ref<TilemapGridSize>), With<Chunk>>,
+    colliders: Query<(), With<WaterCollider>>,
+    settings: Res<WaterSettings>,
+) {
+    for (entity, chunk_tiles, children, grid) in chunks.iter() {
+        if !chunk_tiles.is_changed() && !grid.is_added() && !settings.is_changed() {
+            continue;
+        }
+        for &child in children.into_iter().flatten() {
+            if colliders.contains(child) {
+                commands.entity(child).despawn_recursive();
+            }
+        }
+
+        let mut blocked = [[false; TILES_PER_CHUNK as usize]; TILES_PER_CHUNK as usize];
+        for (TilePos { x, y }, ground) in chunk_tiles.iter() {
+            blocked[y as usize][x as usize] = settings.blocks(ground);
+        }
+        let shapes: Vec<(Vec2, f32, Collider)> = merge_tiles(&blocked)
+            .into_iter()
+            .map(|rect| {
+                let rect = rect.as_rect();
+                let half_size = rect.half_size() * TILE_LENGTH;
+                (rect.center() * TILE_LENGTH, 0.0, Collider::cuboid(half_size.x, half_size.y))
+            })
+            .collect();
+        if shapes.is_empty() {
+            continue;
+        }
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn((
+                Name::new("Water Collider"),
+                WaterCollider,
+                Collider::compound(shapes),
+                TransformBundle::default(),
+            ));
+        });
+    }
+}
@@ -1,141 +1,358 @@
-
 use bevy::{
-    color::palettes::css::{RED, YELLOW}, prelude::*
+    color::palettes::css::{RED, YELLOW},
+    ecs::query::{QueryData, QueryItem},
+    prelude::*,
 };
 use bevy_ecs_tilemap::{
-    map::{TilemapId, TilemapType},
     prelude::*,
     tiles::{TilePos, TileStorage},
 };
-use bevy_mod_picking::events::{Click, Pointer};
-use bevy_rand::prelude::*;
+use bevy_mod_picking::{
+    events::{Click, Pointer},
+    pointer::PointerButton,
+};
 use bevy_replicon::prelude::*;
 use bevy_replicon_snap::NetworkOwner;
-use rand_core::RngCore;
 use serde::{Deserialize, Serialize};
 
-use crate::{chunk::{Chunk, GRID_SIZE, MAP_SIZE, TILE_LENGTH}, ActionEvent, ClickTileEvent};
-
-
-
-
+use crate::{
+    action::{ActionCooldownConfig, ActionCooldowns, GameAction},
+    camera::CameraView,
+    chunk::{tile_seed, Chunk, ChunkIndexMap, ChunkTiles, GRID_SIZE, TILES_PER_CHUNK, TILE_LENGTH},
+    debug_inspector::inspector_pick_held,
+    debug_overlay::{debug_flag, DebugFlags},
+    ground_registry::{GroundRegistry, ERROR_TEXTURE_INDEX, ERROR_TILE_COLOR},
+    interact::INTERACT_RANGE,
+    sleep::Sleeping,
+    soil::SoilQuality,
+    water::WaterSettings,
+    world_object::TileOccupancy,
+    ActionEvent, ClickTileEvent,
+};
 
+/// Tint of every other chunk while the chunk checker debug flag is on.
+const CHECKER_TILE_COLOR: Color = Color::srgb(1.0, 0.7, 0.7);
 
+type GroundTextureData = (
+    &'static mut TileTextureIndex,
+    &'static mut TileColor,
+    &'static Ground,
+    Option<&'static SoilQuality>,
+    &'static TilePos,
+    &'static TilemapId,
+);
 
 fn update_ground_texture(
-    mut tile_query: Query<
-        (
-            &mut TileTextureIndex,
-            &Ground,
-            &mut EntropyComponent<WyRand>,
-        ),
-        Changed<Ground>,
-    >,
+    mut tile_queries: ParamSet<(
+        Query<GroundTextureData>,
+        Query<GroundTextureData, Or<(Changed<Ground>, Changed<SoilQuality>)>>,
+    )>,
+    chunk_query: Query<&Chunk>,
+    registry: Res<GroundRegistry>,
+    debug_flags: Res<DebugFlags>,
 ) {
-    for (mut texture_index, ground, mut rnd) in tile_query.iter_mut() {
-        match ground {
-            Ground::Grass => {
-                texture_index.0 = rnd.next_u32() % 32;
-            }
-            Ground::Dirt => {
-                texture_index.0 = 32;
-            }
-            _ => {
-                texture_index.0 = 4;
+    // a (re)loaded registry can change the textures of every tile, the checker their colors
+    if registry.is_changed() || debug_flags.is_changed() {
+        for tile in tile_queries.p0().iter_mut() {
+            apply_ground_texture(tile, &chunk_query, &registry, &debug_flags);
+        }
+    } else {
+        for tile in tile_queries.p1().iter_mut() {
+            apply_ground_texture(tile, &chunk_query, &registry, &debug_flags);
+        }
+    }
+}
+
+fn apply_ground_texture(
+    tile: QueryItem<'_, GroundTextureData>,
+    chunk_query: &Query<&Chunk>,
+    registry: &GroundRegistry,
+    debug_flags: &DebugFlags,
+) {
+    let (mut texture_index, mut color, ground, soil, tile_pos, tilemap_id) = tile;
+    let Ok(chunk) = chunk_query.get(tilemap_id.0) else {
+        return;
+    };
+    match registry.get(ground) {
+        Some(definition) => {
+            // derived from the chunk seed, so variants survive reloads and match on every instance
+            let fertile = soil.is_some_and(|soil| soil.is_fertile());
+            let index = definition.texture_index(tile_seed(chunk.seed, tile_pos) as u32, fertile);
+            // a refresh of every tile only touches the ones whose texture actually changed
+            texture_index.set_if_neq(TileTextureIndex(index));
+            let checker = debug_flags.chunk_checker
+                && (chunk.chunk_index.x + chunk.chunk_index.y).rem_euclid(2) == 1;
+            let target = if checker {
+                CHECKER_TILE_COLOR
+            } else {
+                chunk.biome.tint()
+            };
+            if color.0 != target {
+                color.0 = target;
             }
         }
+        None => {
+            texture_index.0 = ERROR_TEXTURE_INDEX;
+            *color = TileColor(ERROR_TILE_COLOR);
+        }
     }
 }
 
+/// Resolves the tile entity at a world position through the TileStorage of the chunk containing it.
+pub fn tile_at_world_pos(
+    world_pos: Vec2,
+    chunk_map: &ChunkIndexMap,
+    chunks: &Query<&TileStorage>,
+) -> Option<Entity> {
+    let (chunk_index, tile_pos) = world_pos_to_chunk_tile(world_pos)?;
+    let tile_storage = chunks.get(chunk_map.get(chunk_index)?).ok()?;
+    tile_storage.get(&tile_pos)
+}
+
+/// Ground of the tile at a world position according to the replicated ChunkTiles.
+pub fn ground_at_world_pos<D: QueryData<ReadOnly = &'static ChunkTiles>>(
+    world_pos: Vec2,
+    chunk_map: &ChunkIndexMap,
+    chunks: &Query<D>,
+) -> Option<Ground> {
+    let (chunk_index, tile_pos) = world_pos_to_chunk_tile(world_pos)?;
+    let chunk_tiles = chunks.get(chunk_map.get(chunk_index)?).ok()?;
+    chunk_tiles.get(&tile_pos).cloned()
+}
+
+/// Whether ground walkers may enter the position: it has to be on a loaded tile that is neither
+/// blocking water nor covered by a world object.
+pub fn is_walkable(
+    world_pos: Vec2,
+    chunk_map: &ChunkIndexMap,
+    chunks: &Query<&ChunkTiles>,
+    occupancy: &TileOccupancy,
+    water: &WaterSettings,
+) -> bool {
+    !occupancy.is_occupied(global_tile_at(world_pos))
+        && ground_at_world_pos(world_pos, chunk_map, chunks)
+            .is_some_and(|ground| !water.blocks(&ground))
+}
 
+/// Sets the ground of the tile at a world position in the replicated ChunkTiles of its chunk.
+/// Returns false if the position is not inside a loaded chunk.
+pub fn set_ground_at_world_pos(
+    world_pos: Vec2,
+    ground: Ground,
+    chunk_map: &ChunkIndexMap,
+    chunks: &mut Query<&mut ChunkTiles>,
+) -> bool {
+    let Some((chunk_index, tile_pos)) = world_pos_to_chunk_tile(world_pos) else {
+        return false;
+    };
+    let Some(mut chunk_tiles) = chunk_map
+        .get(chunk_index)
+        .and_then(|chunk| chunks.get_mut(chunk).ok())
+    else {
+        return false;
+    };
+    chunk_tiles.set(&tile_pos, ground);
+    true
+}
 
 fn apply_action(
-    mut tile_query: Query<(&TilePos, &mut Ground)>,
-    player_query: Query<(&NetworkOwner, &Transform)>,
+    mut chunk_query: Query<&mut ChunkTiles>,
+    chunk_map: Res<ChunkIndexMap>,
+    registry: Res<GroundRegistry>,
+    occupancy: Res<TileOccupancy>,
+    mut player_query: Query<(&NetworkOwner, &Transform, &mut ActionCooldowns), Without<Sleeping>>,
     mut events: EventReader<FromClient<ActionEvent>>,
-) -> Option<()> {
+    cooldown_config: Res<ActionCooldownConfig>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds();
     for FromClient { client_id, event } in events.read() {
-        if event.action != KeyCode::Space {
+        if !GameAction::from_key(event.action).contains(&GameAction::Till) {
             continue;
         }
-        if let Some((_, t)) = player_query.iter().find(|p| p.0 .0 == client_id.get()) {
-            let tile_pos = TilePos::from_world_pos(
-                &t.translation.xy(),
-                &MAP_SIZE,
-                &GRID_SIZE,
-                &TilemapType::Square,
-            )?;
-            let (_pos, mut ground) = tile_query.iter_mut().find(|(pos, _)| pos == &&tile_pos)?;
-            *ground = Ground::Dirt;
+        let Some((_, t, mut cooldowns)) =
+            player_query.iter_mut().find(|p| p.0 .0 == client_id.get())
+        else {
+            continue;
+        };
+        if !cooldowns.ready(GameAction::Till, now, &cooldown_config) {
+            continue;
+        }
+        let tillable = ground_at_world_pos(t.translation.xy(), &chunk_map, &chunk_query)
+            .is_some_and(|ground| registry.is_tillable(&ground));
+        if !tillable || occupancy.is_occupied(global_tile_at(t.translation.xy())) {
+            continue;
+        }
+        if set_ground_at_world_pos(
+            t.translation.xy(),
+            Ground::Dirt,
+            &chunk_map,
+            &mut chunk_query,
+        ) {
+            cooldowns.trigger(GameAction::Till, now);
+        } else {
+            debug!("No loaded tile under player of client {:?}", client_id);
         }
     }
-    Some(())
 }
 
 fn detect_tile_click(
     mut click_events: EventReader<Pointer<Click>>,
-    tiles: Query<&TilePos>,
+    tiles: Query<(&TilePos, &Parent)>,
     mut writer: EventWriter<ClickTileEvent>,
+    flags: Res<DebugFlags>,
+    input: Res<ButtonInput<KeyCode>>,
 ) {
     for click in click_events.read() {
-        let Some(_tile_pos) = tiles.get(click.target).ok() else {
+        if click.button != PointerButton::Primary || inspector_pick_held(&flags, &input) {
+            continue;
+        }
+        let Some((tile_pos, parent)) = tiles.get(click.target).ok() else {
             continue;
         };
-        writer.send(ClickTileEvent { tile: click.target });
+        writer.send(ClickTileEvent {
+            chunk: parent.get(),
+            tile_pos: *tile_pos,
+        });
     }
 }
 
 fn handle_tile_click(
     mut reader: EventReader<FromClient<ClickTileEvent>>,
-    mut tiles: Query<&mut Ground, With<TilePos>>,
+    mut chunks: Query<(&Chunk, &mut ChunkTiles)>,
+    mut players: Query<(&NetworkOwner, &Transform, &mut ActionCooldowns), Without<Sleeping>>,
+    registry: Res<GroundRegistry>,
+    occupancy: Res<TileOccupancy>,
+    cooldown_config: Res<ActionCooldownConfig>,
+    time: Res<Time>,
 ) {
+    let now = time.elapsed_seconds();
     for FromClient {
-        client_id: _,
-        event: ClickTileEvent { tile },
+        client_id,
+        event: ClickTileEvent { chunk, tile_pos },
     } in reader.read()
     {
-        match tiles.get_mut(*tile) {
-            Ok(mut ground) => *ground = Ground::Dirt,
-            Err(_) => {}
+        let Some((_, transform, mut cooldowns)) = players
+            .iter_mut()
+            .find(|(owner, ..)| owner.0 == client_id.get())
+        else {
+            continue;
+        };
+        let Ok((chunk, mut chunk_tiles)) = chunks.get_mut(*chunk) else {
+            continue;
+        };
+        if !tile_in_reach(transform.translation.xy(), chunk.chunk_index, tile_pos)
+            || !cooldowns.ready(GameAction::Till, now, &cooldown_config)
+            || occupancy.is_occupied(global_tile(chunk.chunk_index, tile_pos))
+        {
+            continue;
+        }
+        if chunk_tiles
+            .get(tile_pos)
+            .is_some_and(|ground| registry.is_tillable(ground))
+        {
+            chunk_tiles.set(tile_pos, Ground::Dirt);
+            cooldowns.trigger(GameAction::Till, now);
         }
     }
 }
 
-fn debug_draw_chunk_borders(chunk_query: Query<&Chunk>, mut gizmos: Gizmos) {
-    for chunk in chunk_query.iter() {
-        let pos = chunk.get_world_coords();
-        gizmos.circle_2d(pos, 1.0, RED);
-        gizmos.rect_2d(
-            chunk.get_world_coords() + chunk.get_size() * 0.5,
-            0.0,
-            chunk.get_size(),
-            RED,
-        );
+/// Whether a player standing at `player_pos` can reach the tile to till it by clicking.
+pub fn tile_in_reach(player_pos: Vec2, chunk_index: IVec2, tile_pos: &TilePos) -> bool {
+    player_pos.distance(tile_center_world_pos(chunk_index, tile_pos)) <= INTERACT_RANGE
+}
+
+/// Upper bound of debug gizmos drawn per frame, anything beyond is skipped.
+const MAX_DEBUG_GIZMOS: usize = 4096;
+
+/// Logs that a debug draw ran out of gizmos, once until it fits into the budget again.
+fn warn_gizmo_budget(warned: &mut bool, what: &str) {
+    if !*warned {
+        warn!("Debug {what} exceed the budget of {MAX_DEBUG_GIZMOS} gizmos, skipping the rest");
+        *warned = true;
     }
 }
 
-fn debug_draw_tile_borders(
+fn chunk_rect(chunk: &Chunk) -> Rect {
+    let min = chunk.get_world_coords();
+    Rect::from_corners(min, min + chunk.get_size())
+}
+
+fn debug_draw_chunk_borders(
     chunk_query: Query<&Chunk>,
-    tile_query: Query<(&TilePos, &Parent)>,
+    camera_view: Res<CameraView>,
+    mut warned: Local<bool>,
     mut gizmos: Gizmos,
 ) {
-    for (tile, parent) in tile_query.iter() {
-        let chunk = chunk_query.get(**parent).unwrap();
-        let tile_pos = Vec2::new(tile.x as f32 * TILE_LENGTH, tile.y as f32 * TILE_LENGTH);
-        let pos = chunk.get_world_coords() + tile_pos;
-        let tile_size: Vec2 = GRID_SIZE.into();
-        gizmos.rect_2d(pos + tile_size * 0.5, 0.0, tile_size, YELLOW);
+    let mut budget = MAX_DEBUG_GIZMOS;
+    for chunk in chunk_query.iter() {
+        let rect = chunk_rect(chunk);
+        if rect.intersect(camera_view.0).is_empty() {
+            continue;
+        }
+        if budget == 0 {
+            warn_gizmo_budget(&mut warned, "chunk borders");
+            return;
+        }
+        budget -= 1;
+        gizmos.circle_2d(rect.min, 1.0, RED);
+        gizmos.rect_2d(rect.center(), 0.0, rect.size(), RED);
     }
+    *warned = false;
 }
 
+/// Draws the tiles of on screen chunks only. Tiles are looked up through the TileStorage of their
+/// chunk, so a chunk that is being despawned is simply skipped.
+fn debug_draw_tile_borders(
+    chunk_query: Query<(&Chunk, &TileStorage)>,
+    camera_view: Res<CameraView>,
+    mut warned: Local<bool>,
+    mut gizmos: Gizmos,
+) {
+    let mut budget = MAX_DEBUG_GIZMOS;
+    let tile_size: Vec2 = GRID_SIZE.into();
+    for (chunk, tile_storage) in chunk_query.iter() {
+        if chunk_rect(chunk).intersect(camera_view.0).is_empty() {
+            continue;
+        }
+        for x in 0..TILES_PER_CHUNK {
+            for y in 0..TILES_PER_CHUNK {
+                let tile = TilePos::new(x, y);
+                if tile_storage.get(&tile).is_none() {
+                    continue;
+                }
+                let pos = chunk.get_world_coords() + tile.get_in_chunk_pos();
+                let tile_rect = Rect::from_corners(pos, pos + tile_size);
+                if tile_rect.intersect(camera_view.0).is_empty() {
+                    continue;
+                }
+                if budget == 0 {
+                    warn_gizmo_budget(&mut warned, "tile borders");
+                    return;
+                }
+                budget -= 1;
+                gizmos.rect_2d(tile_rect.center(), 0.0, tile_size, YELLOW);
+            }
+        }
+    }
+    *warned = false;
+}
 
-#[derive(Component, Debug, Reflect, Serialize, Deserialize, Clone)]
+#[derive(Component, Debug, Reflect, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 #[reflect(Component)]
 pub enum Ground {
     Dirt,
     Grass,
     Stone,
     Water,
+    /// Water too deep to swim in, never enterable.
+    DeepWater,
+}
+
+impl Ground {
+    pub fn is_water(&self) -> bool {
+        matches!(self, Ground::Water | Ground::DeepWater)
+    }
 }
 
 pub struct WorldPlugin;
@@ -143,52 +360,135 @@ pub struct WorldPlugin;
 impl Plugin for WorldPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(TilemapPlugin)
-            
-            .replicate_mapped::<TilemapId>()
-            .replicate_mapped::<TileStorage>()
-            .replicate::<TilePos>()
-            .replicate::<Ground>()
             .replicate::<Chunk>()
-            .replicate::<TileVisible>()
-            .replicate::<TileFlip>()
-            .replicate::<TileTextureIndex>()
-            .replicate::<TileColor>()
-            .replicate::<TilePosOld>()
+            .replicate::<ChunkTiles>()
             .register_type::<Chunk>()
             .register_type::<Ground>()
-
-
             .add_systems(
                 Update,
                 (
-                    debug_draw_chunk_borders,
-                    debug_draw_tile_borders,
+                    debug_draw_chunk_borders.run_if(debug_flag(|f| f.chunk_borders)),
+                    debug_draw_tile_borders.run_if(debug_flag(|f| f.tile_borders)),
                     detect_tile_click.run_if(client_connected),
                     handle_tile_click.run_if(has_authority),
                 ),
             )
             .add_systems(
                 Update,
-                (
-                    apply_action.map(Option::unwrap).run_if(has_authority),
-                    update_ground_texture,
-                ),
+                (apply_action.run_if(has_authority), update_ground_texture),
             );
     }
 }
 
 pub trait ChunkPosExt {
-    fn from_in_chunk_pos(pos: Vec2) -> Option<Self>
-    where
-        Self: Sized;
-
+    /// Position of the tile's lower left corner relative to the chunk origin.
+    fn get_in_chunk_pos(&self) -> Vec2;
 }
 
 impl ChunkPosExt for TilePos {
-    fn from_in_chunk_pos(pos: Vec2) -> Option<Self> {
-        let tile_pos = (pos / TILE_LENGTH).trunc();
-        let tile_pos = TilePos::from_i32_pair(tile_pos.x as i32, tile_pos.y as i32, &MAP_SIZE);
-        //dbg!(tile_pos);
-        return tile_pos;
+    fn get_in_chunk_pos(&self) -> Vec2 {
+        Vec2::new(self.x as f32, self.y as f32) * TILE_LENGTH
+    }
+}
+
+/// Index of the chunk containing the given world position.
+pub fn chunk_index_at(world_pos: Vec2) -> IVec2 {
+    (world_pos / (TILES_PER_CHUNK as f32 * TILE_LENGTH))
+        .floor()
+        .as_ivec2()
+}
+
+/// Converts a world position into the index of its chunk and the tile position inside that chunk.
+pub fn world_pos_to_chunk_tile(world_pos: Vec2) -> Option<(IVec2, TilePos)> {
+    // going through the global tile avoids subtracting the chunk origin, which rounds tiny
+    // negative offsets up to a full chunk
+    world_pos
+        .is_finite()
+        .then(|| split_global_tile(global_tile_at(world_pos)))
+}
+
+/// Global coordinates of the tile containing a world position, independent of chunks.
+pub fn global_tile_at(world_pos: Vec2) -> IVec2 {
+    (world_pos / TILE_LENGTH).floor().as_ivec2()
+}
+
+/// Global coordinates of a tile inside a chunk.
+pub fn global_tile(chunk_index: IVec2, tile_pos: &TilePos) -> IVec2 {
+    chunk_index * TILES_PER_CHUNK as i32 + IVec2::new(tile_pos.x as i32, tile_pos.y as i32)
+}
+
+/// Splits global tile coordinates into the chunk index and the tile position inside that chunk.
+pub fn split_global_tile(tile: IVec2) -> (IVec2, TilePos) {
+    let tiles_per_chunk = TILES_PER_CHUNK as i32;
+    let chunk_index = tile.div_euclid(IVec2::splat(tiles_per_chunk));
+    let in_chunk = tile.rem_euclid(IVec2::splat(tiles_per_chunk));
+    (
+        chunk_index,
+        TilePos::new(in_chunk.x as u32, in_chunk.y as u32),
+    )
+}
+
+/// World position of the center of a tile.
+pub fn tile_center_world_pos(chunk_index: IVec2, tile_pos: &TilePos) -> Vec2 {
+    chunk_index.as_vec2() * TILES_PER_CHUNK as f32 * TILE_LENGTH
+        + tile_pos.get_in_chunk_pos()
+        + Vec2::splat(TILE_LENGTH * 0.5)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHUNK_LENGTH: f32 = TILES_PER_CHUNK as f32 * TILE_LENGTH;
+
+    fn chunk_tile(world_pos: Vec2) -> (IVec2, UVec2) {
+        let (chunk_index, tile_pos) = world_pos_to_chunk_tile(world_pos).unwrap();
+        (chunk_index, UVec2::new(tile_pos.x, tile_pos.y))
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn negative_positions_belong_to_negative_chunks() {
+        let last = UVec2::splat(TILES_PER_CHUNK - 1);
+        assert_eq!(
+            chunk_tile(Vec2::new(-0.1, -0.1)),
+            (IVec2::new(-1, -1), last)
+        );
+        assert_eq!(
+            chunk_tile(Vec2::new(-1e-6, -1e-6)),
+            (IVec2::new(-1, -1), last)
+        );
+        assert_eq!(
+            chunk_tile(Vec2::new(0.1, -0.1)),
+            (IVec2::new(0, -1), UVec2::new(0, last.y))
+        );
+    }
+
+    #[test]
+    fn chunk_borders_start_the_next_chunk() {
+        assert_eq!(chunk_tile(Vec2::ZERO), (IVec2::ZERO, UVec2::ZERO));
+        assert_eq!(
+            chunk_tile(Vec2::splat(CHUNK_LENGTH)),
+            (IVec2::ONE, UVec2::ZERO)
+        );
+        assert_eq!(
+            chunk_tile(Vec2::splat(-CHUNK_LENGTH)),
+            (IVec2::NEG_ONE, UVec2::ZERO)
+        );
+        assert_eq!(
+            chunk_tile(Vec2::new(3.0 * CHUNK_LENGTH, -2.0 * CHUNK_LENGTH)),
+            (IVec2::new(3, -2), UVec2::ZERO)
+        );
+    }
+
+    #[test]
+    fn tile_centers_round_trip() {
+        for chunk_index in [IVec2::ZERO, IVec2::new(-1, -1), IVec2::new(5, -7)] {
+            for x in 0..TILES_PER_CHUNK {
+                for y in 0..TILES_PER_CHUNK {
+                    let center = tile_center_world_pos(chunk_index, &TilePos::new(x, y));
+                    assert_eq!(chunk_tile(center), (chunk_index, UVec2::new(x, y)));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,297 @@
+use std::{
+    fs::{create_dir_all, File},
+    io::Write,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Context};
+use bevy::prelude::*;
+use bevy_replicon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chunk::{CHUNK_FORMAT_VERSION, DEFAULT_WORLD_SEED},
+    game_time::GameTime,
+};
+
+/// Folder of the world used when `--world` isn't given.
+const WORLD_FOLDER: &str = "world";
+/// Folder that holds the worlds selected by name with `--world`.
+const WORLDS_FOLDER: &str = "worlds";
+const META_FILE: &str = "world.ron";
+/// Seconds between autosaves of the metadata and the loaded chunks.
+pub const AUTOSAVE_INTERVAL: f32 = 60.0;
+
+pub struct WorldMetaPlugin;
+
+impl Plugin for WorldMetaPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Startup,
+            restore_game_time.run_if(resource_exists::<WorldMeta>),
+        )
+        .add_systems(
+            Update,
+            autosave_world_meta
+                .run_if(server_running)
+                .run_if(resource_exists::<WorldMeta>),
+        )
+        .add_systems(
+            Last,
+            save_world_meta_on_exit
+                .run_if(server_running)
+                .run_if(resource_exists::<WorldMeta>),
+        );
+    }
+}
+
+/// Locations of the files of the world the server runs. Every save file is found through it.
+#[derive(Resource, Debug, Clone)]
+pub struct WorldPaths {
+    root: PathBuf,
+}
+
+impl WorldPaths {
+    /// Resolves `--world`: nothing picks the default `world` folder, a bare name a folder in
+    /// `worlds` and anything else is used as a path.
+    pub fn resolve(world: Option<&str>) -> Self {
+        let root = match world.map(Path::new) {
+            None => PathBuf::from(WORLD_FOLDER),
+            Some(path) if path.is_relative() && path.components().count() == 1 => {
+                Path::new(WORLDS_FOLDER).join(path)
+            }
+            Some(path) => path.to_path_buf(),
+        };
+        Self { root }
+    }
+
+    pub fn meta(&self) -> PathBuf {
+        self.root.join(META_FILE)
+    }
+
+    pub fn chunk(&self, index: IVec2) -> PathBuf {
+        self.root.join(format!("{}_{}.ron", index.x, index.y))
+    }
+
+    pub fn map_export(&self, radius: u32) -> PathBuf {
+        self.root.join(format!("map_{radius}.png"))
+    }
+
+    /// Name of a freshly created world, the name of its folder.
+    fn default_name(&self) -> String {
+        self.root
+            .file_name()
+            .map_or_else(|| WORLD_FOLDER.into(), |name| name.to_string_lossy().into_owned())
+    }
+
+    /// Creates the world folder if it doesn't exist yet.
+    pub fn create_folder(&self) -> anyhow::Result<()> {
+        create_dir_all(&self.root)
+            .with_context(|| format!("Unable to create world folder {}", self.root.display()))?;
+        if !self.root.is_dir() {
+            bail!("{} is not a folder", self.root.display());
+        }
+        Ok(())
+    }
+}
+
+/// Identity of a world folder, stored in `world.ron` next to the chunk files.
+#[derive(Resource, Debug, Clone, Serialize, Deserialize)]
+pub struct WorldMeta {
+    pub name: String,
+    pub seed: u64,
+    /// Unix time in seconds the world was created at, 0 for worlds older than this field.
+    #[serde(default)]
+    pub created: u64,
+    /// Seconds the server ran this world in total.
+    pub playtime: f64,
+    /// [`GameTime::elapsed`] when the world was last saved.
+    pub game_time: f32,
+    /// [`CHUNK_FORMAT_VERSION`] of the chunk files in the folder.
+    pub format_version: u32,
+    /// Version of the game that last wrote the world.
+    pub game_version: String,
+    /// Seeds the world used before they were replaced with `--force-seed`, oldest first. Chunks
+    /// saved under them won't line up with newly generated ones.
+    #[serde(default)]
+    pub previous_seeds: Vec<u64>,
+}
+
+impl WorldMeta {
+    fn new(name: String, seed: u64) -> Self {
+        Self {
+            name,
+            seed,
+            created: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_secs()),
+            playtime: 0.0,
+            game_time: 0.0,
+            format_version: CHUNK_FORMAT_VERSION,
+            game_version: env!("CARGO_PKG_VERSION").into(),
+            previous_seeds: Vec::new(),
+        }
+    }
+
+    /// Reads the metadata of a world folder.
+    pub fn read(paths: &WorldPaths) -> anyhow::Result<Self> {
+        let path = paths.meta();
+        let file = File::open(&path).with_context(|| format!("Failed opening {}", path.display()))?;
+        ron::de::from_reader(file).with_context(|| format!("Failed deserialising {}", path.display()))
+    }
+
+    /// Opens the world at `paths`, creating the folder and its metadata if it doesn't exist yet.
+    /// `seed` only applies to fresh worlds unless `force_seed` is set, `name` renames existing
+    /// ones. Worlds written by a newer, incompatible format version are refused. Runs before
+    /// logging is set up, so it prints.
+    pub fn open_or_create(
+        paths: &WorldPaths,
+        name: Option<String>,
+        seed: Option<u64>,
+        force_seed: bool,
+    ) -> anyhow::Result<Self> {
+        paths.create_folder()?;
+        if !paths.meta().exists() {
+            let seed = seed.unwrap_or(DEFAULT_WORLD_SEED);
+            let meta = Self::new(name.unwrap_or_else(|| paths.default_name()), seed);
+            meta.write(paths)?;
+            println!("Created world {} with seed {seed}", meta.name);
+            return Ok(meta);
+        }
+
+        let mut meta = Self::read(paths)?;
+        if meta.format_version > CHUNK_FORMAT_VERSION {
+            bail!(
+                "World {} was written by game version {} with format version {}, \
+                 this version only supports format version {CHUNK_FORMAT_VERSION} or older",
+                meta.name,
+                meta.game_version,
+                meta.format_version
+            );
+        }
+        match seed.filter(|seed| *seed != meta.seed) {
+            Some(seed) if force_seed => {
+                eprintln!(
+                    "Forcing seed {seed} on world {} generated with seed {}, new chunks won't \
+                     line up with saved ones. Use the regen command to fix seams",
+                    meta.name, meta.seed
+                );
+                meta.previous_seeds.push(meta.seed);
+                meta.seed = seed;
+            }
+            Some(seed) => eprintln!(
+                "Ignoring seed {seed}, world {} was generated with seed {}. \
+                 Pass --force-seed to override it",
+                meta.name, meta.seed
+            ),
+            None => {}
+        }
+        if let Some(name) = name {
+            meta.name = name;
+        }
+        meta.format_version = CHUNK_FORMAT_VERSION;
+        meta.game_version = env!("CARGO_PKG_VERSION").into();
+        meta.write(paths)?;
+        println!("Opened world {} with seed {}", meta.name, meta.seed);
+        Ok(meta)
+    }
+
+    pub fn write(&self, paths: &WorldPaths) -> anyhow::Result<()> {
+        let serialized = ron::ser::to_string_pretty(self, Default::default())
+            .context("Failed serialisation")?;
+        File::create(paths.meta())
+            .and_then(|mut file| file.write_all(serialized.as_bytes()))
+            .context("Error while writing world metadata")
+    }
+}
+
+fn restore_game_time(meta: Res<WorldMeta>, mut game_time: ResMut<GameTime>) {
+    game_time.elapsed = meta.game_time;
+}
+
+fn autosave_world_meta(
+    mut meta: ResMut<WorldMeta>,
+    mut since_last_save: Local<f32>,
+    paths: Res<WorldPaths>,
+    game_time: Res<GameTime>,
+    time: Res<Time>,
+) {
+    meta.playtime += time.delta_seconds_f64();
+    *since_last_save += time.delta_seconds();
+    if *since_last_save < AUTOSAVE_INTERVAL {
+        return;
+    }
+    *since_last_save = 0.0;
+    meta.game_time = game_time.elapsed;
+    if let Err(err) = meta.write(&paths) {
+        error!("{err:#}");
+    }
+}
+
+fn save_world_meta_on_exit(
+    mut exit_events: EventReader<AppExit>,
+    mut meta: ResMut<WorldMeta>,
+    paths: Res<WorldPaths>,
+    game_time: Res<GameTime>,
+) {
+    if exit_events.is_empty() {
+        return;
+    }
+    exit_events.clear();
+    meta.game_time = game_time.elapsed;
+    if let Err(err) = meta.write(&paths) {
+        error!("{err:#}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Empty world folder in the system temp folder, unique per test and test run.
+    fn temp_world(name: &str) -> WorldPaths {
+        let root = std::env::temp_dir().join(format!("crafting_game_meta_{}_{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        WorldPaths::resolve(root.to_str())
+    }
+
+    #[test]
+    fn fresh_world_uses_given_seed() {
+        let paths = temp_world("fresh");
+        let meta = WorldMeta::open_or_create(&paths, None, Some(42), false).unwrap();
+        assert_eq!(meta.seed, 42);
+        assert!(meta.previous_seeds.is_empty());
+        assert_eq!(WorldMeta::read(&paths).unwrap().seed, 42);
+    }
+
+    #[test]
+    fn matching_seed_keeps_world() {
+        let paths = temp_world("matching");
+        WorldMeta::open_or_create(&paths, None, Some(42), false).unwrap();
+        let meta = WorldMeta::open_or_create(&paths, None, Some(42), false).unwrap();
+        assert_eq!(meta.seed, 42);
+        assert!(meta.previous_seeds.is_empty());
+    }
+
+    #[test]
+    fn mismatching_seed_is_ignored() {
+        let paths = temp_world("mismatching");
+        WorldMeta::open_or_create(&paths, None, Some(42), false).unwrap();
+        let meta = WorldMeta::open_or_create(&paths, None, Some(7), false).unwrap();
+        assert_eq!(meta.seed, 42);
+        assert!(meta.previous_seeds.is_empty());
+    }
+
+    #[test]
+    fn forced_seed_replaces_stored_one() {
+        let paths = temp_world("forced");
+        WorldMeta::open_or_create(&paths, None, Some(42), false).unwrap();
+        let meta = WorldMeta::open_or_create(&paths, None, Some(7), true).unwrap();
+        assert_eq!(meta.seed, 7);
+        assert_eq!(meta.previous_seeds, vec![42]);
+        let stored = WorldMeta::read(&paths).unwrap();
+        assert_eq!(stored.seed, 7);
+        assert_eq!(stored.previous_seeds, vec![42]);
+    }
+}
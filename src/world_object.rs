@@ -1,9 +1,34 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::HashMap};
+use bevy_rand::prelude::{GlobalEntropy, WyRand};
 use bevy_rapier2d::prelude::*;
-use bevy_replicon::{client::ClientSet, core::Replicated, prelude::AppRuleExt};
+use bevy_replicon::{
+    client::ClientSet,
+    core::Replicated,
+    prelude::{server_running, AppRuleExt},
+};
+use rand_core::RngCore;
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    autotile::NeighborUpdates,
+    composter::ComposterData,
+    fence::ConnectedShape,
+    furnace::FurnaceData,
+    chunk::{ChunkIndexMap, ChunkTiles, TILE_LENGTH},
+    game_assets::GameAssets,
+    ground_registry::GroundRegistry,
+    health::{DeathEvent, Health},
+    item::{spawn_ground_item, Item},
+    item_container::ItemContainer,
+    item_registry::{ItemRegistry, ToolKind},
+    ron_asset::RonAssetPlugin,
+    sapling::{Growth, GrowthDefinition},
+    sign::SignText,
+    world::{split_global_tile, Ground},
+};
 
+const WORLD_OBJECT_HEALTH: f32 = 20.0;
+const OBJECTS_PATH: &str = "data/objects.object.ron";
 
 
 
@@ -11,54 +36,521 @@ pub struct WorldObjectPlugin;
 
 impl Plugin for WorldObjectPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
-        app
+        app.add_plugins(RonAssetPlugin::<ObjectDefinitions>::new(&["object.ron"]))
             .replicate::<WorldObject>()
-            .add_systems(Update, init_world_objets.after(ClientSet::Receive));
+            .init_resource::<TileOccupancy>()
+            .init_resource::<NodeRespawns>()
+            .observe(occupy_tiles)
+            .observe(free_tiles)
+            .add_systems(PreStartup, load_object_registry)
+            .add_systems(PreUpdate, update_object_registry)
+            .add_systems(
+                Update,
+                (init_world_objets, refresh_world_objects)
+                    .chain()
+                    .after(ClientSet::Receive),
+            )
+            .add_systems(
+                Update,
+                (init_resource_nodes, destroy_world_objects, respawn_resource_nodes)
+                    .chain()
+                    .run_if(server_running),
+            );
     }
 }
 
 
-#[derive(Debug, Component, Serialize, Deserialize)]
-struct WorldObject;
+/// A static object placed on the tile grid, covering `footprint` tiles from its lower left
+/// `anchor` tile. The footprint never crosses a chunk border, so the object belongs to the chunk
+/// of its anchor.
+#[derive(Debug, Clone, Component, Serialize, Deserialize)]
+pub struct WorldObject {
+    pub kind: String,
+    /// Global tile of the lower left corner.
+    pub anchor: IVec2,
+    pub footprint: UVec2,
+}
+
+impl WorldObject {
+    /// Global tiles covered by the object.
+    pub fn tiles(&self) -> impl Iterator<Item = IVec2> {
+        footprint_tiles(self.anchor, self.footprint)
+    }
+
+    pub fn chunk_index(&self) -> IVec2 {
+        split_global_tile(self.anchor).0
+    }
+
+    /// World position of the center of the footprint.
+    pub fn center(&self) -> Vec2 {
+        (self.anchor.as_vec2() + self.footprint.as_vec2() * 0.5) * TILE_LENGTH
+    }
+}
+
+pub fn footprint_tiles(anchor: IVec2, footprint: UVec2) -> impl Iterator<Item = IVec2> {
+    let size = footprint.as_ivec2();
+    (0..size.y).flat_map(move |y| (0..size.x).map(move |x| anchor + IVec2::new(x, y)))
+}
+
+/// Save format of a world object inside its chunk file. The footprint comes from the registry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorldObjectData {
+    pub kind: String,
+    pub anchor: IVec2,
+    #[serde(default)]
+    pub sign: Option<SignText>,
+    #[serde(default)]
+    pub composter: Option<ComposterData>,
+    #[serde(default)]
+    pub growth: Option<Growth>,
+    #[serde(default)]
+    pub furnace: Option<FurnaceData>,
+    /// Slots and contents of a chest.
+    #[serde(default)]
+    pub chest: Option<ItemContainer>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObjectDefinition {
+    /// Index into the props atlas.
+    pub atlas_index: usize,
+    /// Width and height in tiles.
+    pub footprint: UVec2,
+    /// Connects to neighbors of the same kind instead of using the atlas sprite, see [`crate::fence`].
+    #[serde(default)]
+    pub connected: bool,
+    /// Item that places the object and is given back when a player removes it. Objects without
+    /// one can't be built or removed by players.
+    #[serde(default)]
+    pub item: Option<Item>,
+    /// Carries a text the placing player writes, see [`crate::sign`].
+    #[serde(default)]
+    pub writable: bool,
+    /// Players can sleep in it at night, see [`crate::sleep`].
+    #[serde(default)]
+    pub bed: bool,
+    /// Turns compostable items into fertilizer, see [`crate::composter`].
+    #[serde(default)]
+    pub composter: bool,
+    /// Smelts items using fuel, see [`crate::furnace`].
+    #[serde(default)]
+    pub furnace: bool,
+    /// Stores items, see [`crate::chest`].
+    #[serde(default)]
+    pub chest: bool,
+    /// Can be harvested for its drops and grows back afterwards.
+    #[serde(default)]
+    pub resource: Option<ResourceNodeDefinition>,
+    /// Grows through stages into another object, see [`crate::sapling`].
+    #[serde(default)]
+    pub growth: Option<GrowthDefinition>,
+    /// Grounds it can be placed on, any buildable ground if empty.
+    #[serde(default)]
+    pub placeable_on: Vec<Ground>,
+    /// Items that may drop when the object is destroyed.
+    #[serde(default)]
+    pub drops: Vec<ObjectDrop>,
+}
+
+impl ObjectDefinition {
+    pub fn allows_ground(&self, ground: &Ground) -> bool {
+        self.placeable_on.is_empty() || self.placeable_on.contains(ground)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourceNodeDefinition {
+    pub health: f32,
+    /// Tool the attacker needs to hold to damage the node, any attack works without one.
+    #[serde(default)]
+    pub tool: Option<ToolKind>,
+    /// Seconds until a harvested node grows back in the same place.
+    pub respawn_time: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObjectDrop {
+    pub item: String,
+    /// Probability between 0 and 1.
+    pub chance: f32,
+}
+
+#[derive(Asset, TypePath, Debug, Serialize, Deserialize)]
+pub struct ObjectDefinitions {
+    pub objects: HashMap<String, ObjectDefinition>,
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct ObjectRegistry {
+    handle: Handle<ObjectDefinitions>,
+    objects: HashMap<String, ObjectDefinition>,
+    loaded: bool,
+}
+
+impl ObjectRegistry {
+    pub fn get(&self, kind: &str) -> Option<&ObjectDefinition> {
+        self.objects.get(kind)
+    }
+
+    /// Kind of object placed by the item with the given id.
+    pub fn kind_for_item(&self, item_id: &str) -> Option<&str> {
+        self.objects
+            .iter()
+            .find(|(_, def)| def.item.as_ref().is_some_and(|item| item.id == item_id))
+            .map(|(kind, _)| kind.as_str())
+    }
+
+    /// Whether the object file was read at least once.
+    pub fn is_loaded(&self) -> bool {
+        self.loaded
+    }
+
+    /// Footprint of a kind, unknown kinds cover a single tile.
+    pub fn footprint(&self, kind: &str) -> UVec2 {
+        self.get(kind).map_or(UVec2::ONE, |def| def.footprint)
+    }
+}
+
+/// Server side marker of a harvestable world object, see [`ResourceNodeDefinition`].
+#[derive(Component, Debug, Clone, Copy)]
+pub struct ResourceNode {
+    pub tool: Option<ToolKind>,
+    pub respawn_time: f32,
+}
+
+impl ResourceNode {
+    /// Whether an attacker holding the item may damage the node.
+    pub fn can_harvest(&self, held: Option<&Item>, items: &ItemRegistry) -> bool {
+        self.tool
+            .map_or(true, |tool| held.is_some_and(|item| items.tool(&item.id) == Some(tool)))
+    }
+}
+
+/// A harvested resource node waiting to grow back.
+#[derive(Debug)]
+struct NodeRespawn {
+    kind: String,
+    anchor: IVec2,
+    footprint: UVec2,
+    remaining: f32,
+}
+
+/// Only kept in memory, nodes harvested before a restart don't come back.
+#[derive(Resource, Debug, Default)]
+struct NodeRespawns(Vec<NodeRespawn>);
+
+/// Which world object covers a global tile, kept in sync with every object's footprint.
+#[derive(Resource, Debug, Default)]
+pub struct TileOccupancy {
+    tiles: HashMap<IVec2, Entity>,
+}
+
+impl TileOccupancy {
+    pub fn get(&self, tile: IVec2) -> Option<Entity> {
+        self.tiles.get(&tile).copied()
+    }
+
+    pub fn is_occupied(&self, tile: IVec2) -> bool {
+        self.tiles.contains_key(&tile)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlacementError {
+    CrossesChunkBorder,
+    NotLoaded,
+    NotBuildable,
+    Occupied,
+}
 
+/// Checks whether an object with the footprint can be placed at the anchor tile. Footprints
+/// straddling a chunk border are rejected so every object is saved with exactly one chunk.
+pub fn validate_placement(
+    anchor: IVec2,
+    footprint: UVec2,
+    occupancy: &TileOccupancy,
+    chunk_map: &ChunkIndexMap,
+    chunks: &Query<&ChunkTiles>,
+    grounds: &GroundRegistry,
+) -> Result<(), PlacementError> {
+    let anchor_chunk = split_global_tile(anchor).0;
+    if footprint_tiles(anchor, footprint).any(|tile| split_global_tile(tile).0 != anchor_chunk) {
+        return Err(PlacementError::CrossesChunkBorder);
+    }
+    for tile in footprint_tiles(anchor, footprint) {
+        let (chunk_index, tile_pos) = split_global_tile(tile);
+        let ground = chunk_map
+            .get(chunk_index)
+            .and_then(|chunk| chunks.get(chunk).ok())
+            .and_then(|chunk_tiles| chunk_tiles.get(&tile_pos))
+            .ok_or(PlacementError::NotLoaded)?;
+        if !grounds.is_buildable(ground) {
+            return Err(PlacementError::NotBuildable);
+        }
+        if occupancy.is_occupied(tile) {
+            return Err(PlacementError::Occupied);
+        }
+    }
+    Ok(())
+}
 
 pub fn spawn_world_object(
     commands: &mut Commands,
-    position: Vec2,
-) {
+    kind: String,
+    anchor: IVec2,
+    footprint: UVec2,
+) -> Entity {
+    let object = WorldObject { kind, anchor, footprint };
     commands.spawn((
         Name::new("World_Object"),
-        Transform::from_translation(position.extend(1.0)),
-        WorldObject,
+        Transform::from_translation(object.center().extend(1.0)),
+        object,
+        Health::new(WORLD_OBJECT_HEALTH),
         Replicated,
-    ));
+    )).id()
+}
+
+fn occupy_tiles(
+    trigger: Trigger<OnAdd, WorldObject>,
+    objects: Query<&WorldObject>,
+    mut occupancy: ResMut<TileOccupancy>,
+) {
+    if let Ok(object) = objects.get(trigger.entity()) {
+        for tile in object.tiles() {
+            occupancy.tiles.insert(tile, trigger.entity());
+        }
+    }
+}
+
+fn free_tiles(
+    trigger: Trigger<OnRemove, WorldObject>,
+    objects: Query<&WorldObject>,
+    mut occupancy: ResMut<TileOccupancy>,
+) {
+    if let Ok(object) = objects.get(trigger.entity()) {
+        for tile in object.tiles() {
+            if occupancy.get(tile) == Some(trigger.entity()) {
+                occupancy.tiles.remove(&tile);
+            }
+        }
+    }
+}
+
+fn load_object_registry(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(ObjectRegistry {
+        handle: asset_server.load(OBJECTS_PATH),
+        objects: HashMap::default(),
+        loaded: false,
+    });
+}
+
+fn update_object_registry(
+    mut events: EventReader<AssetEvent<ObjectDefinitions>>,
+    mut registry: ResMut<ObjectRegistry>,
+    definitions: Res<Assets<ObjectDefinitions>>,
+) {
+    for event in events.read() {
+        if !event.is_loaded_with_dependencies(&registry.handle) && !event.is_modified(&registry.handle) {
+            continue;
+        }
+        if let Some(loaded) = definitions.get(&registry.handle) {
+            registry.objects = loaded.objects.clone();
+            registry.loaded = true;
+        }
+    }
+}
+
+/// Gives new resource nodes their health from the registry.
+fn init_resource_nodes(
+    mut commands: Commands,
+    objects: Query<(Entity, &WorldObject), Added<WorldObject>>,
+    registry: Res<ObjectRegistry>,
+) {
+    for (entity, object) in objects.iter() {
+        let Some(resource) = registry.get(&object.kind).and_then(|def| def.resource.as_ref()) else {
+            continue;
+        };
+        commands.entity(entity).insert((
+            ResourceNode {
+                tool: resource.tool,
+                respawn_time: resource.respawn_time,
+            },
+            Health::new(resource.health),
+        ));
+    }
 }
 
+fn destroy_world_objects(
+    mut commands: Commands,
+    mut death_events: EventReader<DeathEvent>,
+    mut rng: ResMut<GlobalEntropy<WyRand>>,
+    mut respawns: ResMut<NodeRespawns>,
+    objects: Query<(&WorldObject, Option<&ResourceNode>)>,
+    registry: Res<ObjectRegistry>,
+    items: Res<ItemRegistry>,
+) {
+    for DeathEvent { entity } in death_events.read() {
+        let Ok((object, node)) = objects.get(*entity) else {
+            continue;
+        };
+        if let Some(node) = node {
+            respawns.0.push(NodeRespawn {
+                kind: object.kind.clone(),
+                anchor: object.anchor,
+                footprint: object.footprint,
+                remaining: node.respawn_time,
+            });
+        }
+        let drops = registry.get(&object.kind).map_or(&[][..], |def| &def.drops[..]);
+        for drop in drops {
+            if (rng.next_u32() as f32 / u32::MAX as f32) >= drop.chance {
+                continue;
+            }
+            match items.create(&drop.item) {
+                Some(item) => spawn_ground_item(&mut commands, &item, object.center(), None),
+                None => warn!("Item {} dropped by {} is not defined", drop.item, object.kind),
+            }
+        }
+        commands.entity(*entity).despawn_recursive();
+    }
+}
+
+/// Puts harvested nodes back once their time is up. A node waits while its chunk isn't loaded or
+/// something else stands on its tiles.
+fn respawn_resource_nodes(
+    mut commands: Commands,
+    mut respawns: ResMut<NodeRespawns>,
+    occupancy: Res<TileOccupancy>,
+    chunk_map: Res<ChunkIndexMap>,
+    time: Res<Time>,
+) {
+    let delta = time.delta_seconds();
+    respawns.0.retain_mut(|respawn| {
+        respawn.remaining -= delta;
+        if respawn.remaining > 0.0 {
+            return true;
+        }
+        let loaded = chunk_map.get(split_global_tile(respawn.anchor).0).is_some();
+        if !loaded || footprint_tiles(respawn.anchor, respawn.footprint).any(|tile| occupancy.is_occupied(tile)) {
+            return true;
+        }
+        spawn_world_object(&mut commands, respawn.kind.clone(), respawn.anchor, respawn.footprint);
+        false
+    });
+}
 
 fn init_world_objets(
     mut commands: Commands,
-    asset_server: Res<AssetServer>,
+    game_assets: Res<GameAssets>,
+    registry: Res<ObjectRegistry>,
     query: Query<(Entity, &WorldObject), Without<Sprite>>,
-    mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
 ) {
-    let texture = asset_server.load::<Image>("TX Props.png");
-    let mut layout = TextureAtlasLayout::new_empty(UVec2::new(512, 512));
-    layout.add_texture(URect::new(162, 16, 190, 64));
-    let texture_atlas_layout = texture_atlas_layouts.add(layout);
-    for (entity, _item ) in query.iter() {
+    for (entity, object) in query.iter() {
+        let definition = registry.get(&object.kind);
+        if definition.is_some_and(|def| def.connected) {
+            // shape and collider depend on the neighbors and are set up by the fence plugin
+            commands.entity(entity).insert((
+                Sprite::default(),
+                VisibilityBundle::default(),
+                GlobalTransform::default(),
+            ));
+            continue;
+        }
+        let half_size = object.footprint.as_vec2() * TILE_LENGTH * 0.5;
         commands.entity(entity).insert((
             Sprite::default(),
             TextureAtlas {
-                layout: texture_atlas_layout.clone(),
-                index: 0,
+                layout: game_assets.props_layout.clone(),
+                index: definition.map_or(0, |def| def.atlas_index),
             },
-            texture.clone(),
+            game_assets.props_texture.clone(),
             VisibilityBundle::default(),
             GlobalTransform::default(),
-            Collider::ball(50.0),
             Restitution::coefficient(0.7),
+            ActiveEvents::COLLISION_EVENTS,
         ));
+        // growing objects get their collider from the sapling plugin once they are big enough
+        if !definition.is_some_and(|def| def.growth.is_some()) {
+            commands.entity(entity).insert(Collider::cuboid(half_size.x, half_size.y));
+        }
     }
 }
 
+/// Applies a (re)loaded registry to objects that were already set up, so edits to the object
+/// file show up in a running game. Only local components are touched, nothing is replicated or
+/// saved because of it. Footprint changes are ignored: collider, occupancy and placement all use
+/// the footprint stored in [`WorldObject`], which picks up the new one when its chunk reloads.
+fn refresh_world_objects(
+    mut commands: Commands,
+    mut objects: Query<(Entity, &WorldObject, Option<&mut TextureAtlas>), With<Sprite>>,
+    mut updates: ResMut<NeighborUpdates>,
+    registry: Res<ObjectRegistry>,
+) {
+    if !registry.is_changed() || registry.is_added() {
+        return;
+    }
+    for (entity, object, atlas) in objects.iter_mut() {
+        let Some(definition) = registry.get(&object.kind) else {
+            continue;
+        };
+        if definition.connected {
+            // rebuilt by the fence plugin once its shape is unknown
+            commands.entity(entity).remove::<ConnectedShape>();
+            object.tiles().for_each(|tile| updates.notify(tile));
+            continue;
+        }
+        if let Some(mut atlas) = atlas {
+            if atlas.index != definition.atlas_index {
+                atlas.index = definition.atlas_index;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+    use crate::chunk::TILES_PER_CHUNK;
+
+    fn placement(anchor: IVec2, footprint: UVec2) -> Result<(), PlacementError> {
+        World::new().run_system_once(move |chunks: Query<&ChunkTiles>| {
+            validate_placement(
+                anchor,
+                footprint,
+                &TileOccupancy::default(),
+                &ChunkIndexMap::default(),
+                &chunks,
+                &GroundRegistry::default(),
+            )
+        })
+    }
 
+    #[test]
+    fn footprint_covers_tiles_from_anchor() {
+        let mut tiles: Vec<_> = footprint_tiles(IVec2::new(3, -2), UVec2::new(2, 3)).collect();
+        tiles.sort_by_key(|tile| (tile.x, tile.y));
+        let expected = [(3, -2), (3, -1), (3, 0), (4, -2), (4, -1), (4, 0)].map(IVec2::from);
+        assert_eq!(tiles, expected);
+    }
+
+    #[test]
+    fn footprint_over_chunk_border_is_rejected() {
+        let tiles_per_chunk = TILES_PER_CHUNK as i32;
+        for chunk_index in [IVec2::new(0, 0), IVec2::new(-1, -1), IVec2::new(-1, 0), IVec2::new(2, -3)] {
+            let anchor = chunk_index * tiles_per_chunk + IVec2::new(7, 7);
+            assert_eq!(
+                placement(anchor, UVec2::new(2, 2)),
+                Err(PlacementError::CrossesChunkBorder),
+                "2x2 footprint at local (7, 7) of chunk {chunk_index}",
+            );
+        }
+    }
+
+    #[test]
+    fn footprint_inside_chunk_is_not_rejected_for_the_border() {
+        let anchor = IVec2::new(-1, -1) * TILES_PER_CHUNK as i32 + IVec2::new(6, 6);
+        assert_eq!(placement(anchor, UVec2::new(2, 2)), Err(PlacementError::NotLoaded));
+    }
+}